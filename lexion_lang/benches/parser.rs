@@ -0,0 +1,194 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use lexion_lang::parser::ParserLexion;
+use lexion_lib::grammar::Grammar;
+use lexion_lib::parsers::{GrammarParserLR, ParseEventKind, ParseTableAction, ParseTableLR};
+use lexion_lib::tokenizer::tokens::{EOF, EPSILON};
+use lexion_lib::tokenizer::Tokenizer;
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::sync::Arc;
+
+/// Parses with a copy of the table of the Lexion parser, to compare it with the compressed one.
+struct TableParser(ParseTableLR);
+
+impl GrammarParserLR for TableParser {
+    fn get_parse_table(&self) -> &ParseTableLR {
+        &self.0
+    }
+}
+
+/// Ways of looking up the action of a state on a symbol: the action of each symbol by state, as
+/// the table was stored before it was indexed, the indexed rows without default reductions, and
+/// the parse table itself.
+enum Lookup<'a> {
+    Map(HashMap<String, HashMap<usize, ParseTableAction>>),
+    Rows(&'a ParseTableLR, Vec<Vec<ParseTableAction>>),
+    Table(&'a ParseTableLR),
+}
+
+impl<'a> Lookup<'a> {
+    fn map(table: &ParseTableLR) -> Self {
+        let mut map: HashMap<String, HashMap<usize, ParseTableAction>> = HashMap::new();
+        for (state, symbol, action) in table.entries() {
+            map.entry(symbol.to_string())
+                .or_default()
+                .insert(state, action.clone());
+        }
+        Lookup::Map(map)
+    }
+
+    fn rows(table: &'a ParseTableLR) -> Self {
+        let mut rows = vec![vec![]; table.num_states()];
+        for (state, symbol, action) in table.entries() {
+            let id = table.symbol_id(symbol).unwrap();
+            let row: &mut Vec<ParseTableAction> = &mut rows[state];
+            if row.len() <= id {
+                row.resize(id + 1, ParseTableAction::Reject);
+            }
+            row[id] = action.clone();
+        }
+        Lookup::Rows(table, rows)
+    }
+
+    fn symbol_id(&self, symbol: &str) -> Option<usize> {
+        match self {
+            Lookup::Map(_) => None,
+            Lookup::Rows(table, _) | Lookup::Table(table) => table.symbol_id(symbol),
+        }
+    }
+
+    fn action(&self, state: usize, symbol: &str, symbol_id: Option<usize>) -> &ParseTableAction {
+        const REJECT: &ParseTableAction = &ParseTableAction::Reject;
+        let action = match self {
+            Lookup::Map(map) => map.get(symbol).and_then(|states| states.get(&state)),
+            Lookup::Rows(_, rows) => symbol_id.and_then(|id| rows[state].get(id)),
+            Lookup::Table(table) => Some(table.action(state, symbol_id)),
+        };
+        match action {
+            Some(ParseTableAction::Conflict(actions)) => actions.last().unwrap(),
+            Some(action) => action,
+            None => REJECT,
+        }
+    }
+
+    fn goto(&self, state: usize, symbol: &str) -> &ParseTableAction {
+        self.action(state, symbol, self.symbol_id(symbol))
+    }
+}
+
+/// Runs the LR automaton on `tokens` without building a derivation, so that the table lookups
+/// make up most of the time. Returns the number of steps.
+fn recognize(grammar: &Grammar, lookup: &Lookup, tokens: &[String]) -> usize {
+    let mut states = vec![0];
+    let mut tokens = tokens.iter();
+    let mut token = tokens.next().unwrap();
+    let mut token_id = lookup.symbol_id(token);
+    let mut steps = 0;
+    loop {
+        steps += 1;
+        let state = *states.last().unwrap();
+        match lookup.action(state, token, token_id) {
+            ParseTableAction::Shift(next) => {
+                states.push(*next);
+                token = tokens.next().unwrap();
+                token_id = lookup.symbol_id(token);
+            }
+            ParseTableAction::Reduce(rule_index) => {
+                let rule = grammar.get_rule(*rule_index);
+                if rule.right != [EPSILON] {
+                    states.truncate(states.len() - rule.right.len());
+                }
+                let state = *states.last().unwrap();
+                let ParseTableAction::Goto(next) = lookup.goto(state, &rule.left) else {
+                    panic!("no goto on {} in state {state}", rule.left);
+                };
+                states.push(*next);
+            }
+            ParseTableAction::Accept => return steps,
+            action => panic!("unexpected action '{action}' on {token} in state {state}"),
+        }
+    }
+}
+
+fn fixtures() -> Vec<(String, Arc<String>)> {
+    let root = env!("CARGO_MANIFEST_DIR");
+    let mut paths = ["tests", "tests/fixtures"]
+        .into_iter()
+        .flat_map(|dir| std::fs::read_dir(format!("{root}/{dir}")).unwrap())
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lex"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().to_string();
+            (name, Arc::new(std::fs::read_to_string(path).unwrap()))
+        })
+        .collect()
+}
+
+/// Symbols of the tokens the Lexion parser reads from `source`.
+fn tokens(source: &Arc<String>) -> Vec<String> {
+    let grammar = ParserLexion::GRAMMAR;
+    let tokenizer = Tokenizer::from_string(source.clone(), grammar.get_token_types());
+    let mut tokens = vec![];
+    ParserLexion::PARSER
+        .parse_observe(grammar, tokenizer, &mut |event| {
+            if let ParseEventKind::Shift(_) = event.kind {
+                tokens.push(event.lookahead.token.clone());
+            }
+        })
+        .unwrap();
+    tokens.push(EOF.to_string());
+    tokens
+}
+
+fn lookup(c: &mut Criterion) {
+    let grammar = ParserLexion::GRAMMAR;
+    let table = ParserLexion::PARSER.get_parse_table();
+    let lookups = [
+        ("map", Lookup::map(table)),
+        ("rows", Lookup::rows(table)),
+        ("table", Lookup::Table(table)),
+    ];
+
+    let mut group = c.benchmark_group("lookup");
+    for (name, source) in fixtures() {
+        let tokens = tokens(&source);
+        for (label, lookup) in lookups.iter() {
+            group.bench_with_input(BenchmarkId::new(*label, &name), &tokens, |b, tokens| {
+                b.iter(|| black_box(recognize(grammar, lookup, tokens)))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn parse(c: &mut Criterion) {
+    let grammar = ParserLexion::GRAMMAR;
+    let table = ParserLexion::PARSER.get_parse_table().clone();
+    let token_types = grammar.get_token_types();
+    let mut compressed = table.clone();
+    compressed.eliminate_unit_rules(grammar);
+    let parsers = [
+        ("table", TableParser(table)),
+        ("compressed", TableParser(compressed)),
+    ];
+
+    let mut group = c.benchmark_group("parse");
+    for (name, source) in fixtures() {
+        for (label, parser) in parsers.iter() {
+            group.bench_with_input(BenchmarkId::new(*label, &name), &source, |b, source| {
+                b.iter(|| {
+                    let tokenizer = Tokenizer::from_string(source.clone(), token_types);
+                    black_box(parser.parse(grammar, tokenizer).unwrap())
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, lookup, parse);
+criterion_main!(benches);
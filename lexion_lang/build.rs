@@ -1,6 +1,4 @@
-use lexion_lib::grammar::serialize::RuleData;
 use lexion_lib::tabled::builder::Builder;
-use lexion_lib::tokenizer::tokens::EPSILON;
 use lexion_lib::Parser;
 use lexion_parsers::grm::ParserGRM;
 use std::io::BufWriter;
@@ -14,19 +12,9 @@ fn main() {
         panic!("{err:?}\n{table}");
     }
     let mut data = data.unwrap();
-    data.rules = data
-        .rules
-        .into_iter()
-        .map(|r| RuleData {
-            left: r.left,
-            right: if r.right.is_empty() {
-                vec![String::from(EPSILON)]
-            } else {
-                r.right
-            },
-            reduction: r.reduction,
-        })
-        .collect::<Vec<_>>();
+    for rule in data.rules.iter_mut() {
+        rule.right = rule.right_or_epsilon();
+    }
     let result = std::fs::OpenOptions::new()
         .create(true)
         .write(true)
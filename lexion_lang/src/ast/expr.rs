@@ -31,6 +31,7 @@ pub enum Expr {
     CallExpr(CallExpr),
     IdentExpr(IdentExpr),
     LitExpr(LitExpr),
+    ArrayExpr(ArrayExpr),
 }
 
 #[derive(Debug)]
@@ -80,6 +81,11 @@ pub struct LitExpr {
     pub lit: Lit,
 }
 
+#[derive(Debug)]
+pub struct ArrayExpr {
+    pub elements: Vec<SourcedExpr>,
+}
+
 #[derive(Debug, Clone)]
 pub enum Lit {
     Integer(isize),
@@ -104,6 +110,8 @@ pub enum Type {
     Path(PathType),
     Reference(ReferenceType),
     Tuple(TupleType),
+    Array(ArrayType),
+    Slice(SliceType),
 }
 
 #[derive(Debug)]
@@ -125,3 +133,14 @@ pub struct ReferenceType {
 pub struct TupleType {
     pub types: Vec<Sourced<Type>>,
 }
+
+#[derive(Debug)]
+pub struct ArrayType {
+    pub ty: Box<Sourced<Type>>,
+    pub len: Sourced<usize>,
+}
+
+#[derive(Debug)]
+pub struct SliceType {
+    pub ty: Box<Sourced<Type>>,
+}
@@ -31,6 +31,19 @@ pub struct RefType {
     pub to: Index,
 }
 
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ArrayType {
+    pub ty: Index,
+    pub len: usize,
+}
+
+/// Elements of an unknown number, only used behind a reference, which holds the address of the
+/// first element and the length.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SliceType {
+    pub ty: Index,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct FunctionType {
     pub params: Vec<Index>,
@@ -56,6 +69,7 @@ pub enum PrimitiveType {
 
 pub struct PrimitiveTypeLayout {
     primitive: PrimitiveType,
+    #[allow(dead_code)]
     bitness: Bitness,
 }
 
@@ -89,6 +103,8 @@ pub enum Type {
     TupleType(TupleType),
     StructType(StructType),
     RefType(RefType),
+    ArrayType(ArrayType),
+    SliceType(SliceType),
     FunctionType(FunctionType),
     TypeDefType(TypeDefType),
     PrimitiveType(PrimitiveType),
@@ -115,6 +131,7 @@ lazy_static! {
     pub static ref TYPE_STR: Type = Type::PrimitiveType(PrimitiveType::STR);
 }
 
+#[derive(Clone)]
 pub struct TypeCollection {
     pub arena: Arena<Type>,
     pub type_strings: HashMap<String, Index>,
@@ -177,7 +194,7 @@ impl TypeCollection {
         types.join(", ")
     }
 
-    pub fn to_string_index(&self, ty: Index) -> Cow<str> {
+    pub fn to_string_index(&self, ty: Index) -> Cow<'_, str> {
         match self.arena.get(ty) {
             Some(ty) => self.to_string_type(ty),
             None => "".into(),
@@ -191,6 +208,10 @@ impl TypeCollection {
             }
             Type::StructType(StructType { ident, .. }) => ident.to_string().into(),
             Type::RefType(RefType { to }) => format!("&{}", self.to_string_index(*to)).into(),
+            Type::ArrayType(ArrayType { ty, len }) => {
+                format!("[{}; {len}]", self.to_string_index(*ty)).into()
+            }
+            Type::SliceType(SliceType { ty }) => format!("[{}]", self.to_string_index(*ty)).into(),
             Type::FunctionType(FunctionType {
                 params,
                 return_type,
@@ -219,6 +240,33 @@ impl TypeCollection {
         self.insert(&ty)
     }
 
+    /// Reference type to `to`, if one was created already.
+    pub fn reference_of(&self, to: Index) -> Option<Index> {
+        let ty = Type::RefType(RefType { to });
+        self.type_strings
+            .get(self.to_string_type(&ty).as_ref())
+            .copied()
+    }
+
+    pub fn array(&mut self, of: Index, len: usize) -> Index {
+        self.insert(&Type::ArrayType(ArrayType { ty: of, len }))
+    }
+
+    /// Reference to a slice of `of`.
+    pub fn slice_ref(&mut self, of: Index) -> Index {
+        let slice = self.insert(&Type::SliceType(SliceType { ty: of }));
+        self.reference(slice)
+    }
+
+    /// Element type of an array or a slice, and the length of an array.
+    pub fn element(&self, ty: Index) -> Option<(Index, Option<usize>)> {
+        match &self.arena[self.canonicalize(ty)] {
+            Type::ArrayType(ArrayType { ty, len }) => Some((*ty, Some(*len))),
+            Type::SliceType(SliceType { ty }) => Some((*ty, None)),
+            _ => None,
+        }
+    }
+
     pub fn dereference(&self, from: Index) -> Option<Index> {
         if let Type::RefType(RefType { to }) = &self.arena[from] {
             Some(*to)
@@ -265,7 +313,13 @@ impl TypeCollection {
                 self.type_strings.get(&ident).cloned()
             }
             AstType::Reference(ty) => {
-                let to = self.insert_ast_type(&ty.to.value)?;
+                let to = match &ty.to.value {
+                    AstType::Slice(slice) => {
+                        let of = self.insert_ast_type(&slice.ty.value)?;
+                        self.insert(&Type::SliceType(SliceType { ty: of }))
+                    }
+                    to => self.insert_ast_type(to)?,
+                };
                 Some(self.reference(to))
             }
             AstType::Tuple(ty) => {
@@ -284,6 +338,12 @@ impl TypeCollection {
                     Some(self.insert(&Type::TupleType(TupleType { types })))
                 }
             }
+            AstType::Array(ty) => {
+                let of = self.insert_ast_type(&ty.ty.value)?;
+                Some(self.array(of, ty.len.value))
+            }
+            // Slices have no size of their own and are only valid behind a reference.
+            AstType::Slice(_) => None,
         }
     }
 
@@ -356,13 +416,26 @@ impl TypeCollection {
                 &struct_ty.members,
                 |m| m.ty,
             ),
-            Type::RefType(ref_ty) => {
-                if arena[ref_ty.to] == Type::PrimitiveType(PrimitiveType::STR) {
-                    SizeAlign::slice(bitness)
-                } else {
-                    SizeAlign::ptr(bitness)
+            Type::RefType(ref_ty) => match arena[ref_ty.to] {
+                Type::PrimitiveType(PrimitiveType::STR) => SizeAlign::slice(bitness),
+                Type::SliceType(_) => {
+                    // The address of the first element, then the length.
+                    let mut builder = Builder::new();
+                    let SizeAlign { size, align } = SizeAlign::ptr(bitness);
+                    builder.member(size, align);
+                    builder.member(size, align);
+                    let layout = builder.build();
+                    let result = layout.size_align();
+                    layouts.insert(ty, layout);
+                    result
                 }
-            }
+                _ => SizeAlign::ptr(bitness),
+            },
+            Type::ArrayType(array_ty) => Self::array_layout(
+                Self::layout::<Builder>(arena, layouts, bitness, array_ty.ty),
+                array_ty.len,
+            ),
+            Type::SliceType(_) => SizeAlign::none(),
             Type::FunctionType(_) => SizeAlign::ptr(bitness),
             Type::TypeDefType(typedef_ty) => {
                 Self::layout::<Builder>(arena, layouts, bitness, typedef_ty.ty)
@@ -372,9 +445,16 @@ impl TypeCollection {
         }
     }
 
+    fn array_layout(element: SizeAlign, len: usize) -> SizeAlign {
+        SizeAlign {
+            size: element.align.align(element.size) * len,
+            align: element.align,
+        }
+    }
+
     pub fn compute_memory_layouts<Builder: MemoryLayoutBuilder>(&mut self, bitness: Bitness) {
-        for (ty, _) in self.arena.iter() {
-            if self.memory_layouts.contains_key(&ty) {
+        for (ty, kind) in self.arena.iter() {
+            if self.memory_layouts.contains_key(&ty) || *kind == Type::Unknown {
                 continue;
             }
             Self::layout::<Builder>(&self.arena, &mut self.memory_layouts, bitness, ty);
@@ -389,17 +469,55 @@ impl TypeCollection {
                 .get(&ty)
                 .map(|l| l.size_align())
                 .unwrap_or(SizeAlign::none()),
+            Type::RefType(_) if self.memory_layouts.contains_key(&ty) => {
+                self.memory_layouts[&ty].size_align()
+            }
             Type::RefType(_) | Type::FunctionType(_) => SizeAlign::ptr(bitness),
+            Type::ArrayType(array_ty) => {
+                Self::array_layout(self.size_align(array_ty.ty, bitness), array_ty.len)
+            }
             Type::PrimitiveType(primitive_ty) => primitive_ty.layout(bitness).size_align(),
-            Type::TypeDefType(_) | Type::Unknown => SizeAlign::none(),
+            Type::SliceType(_) | Type::TypeDefType(_) | Type::Unknown => SizeAlign::none(),
         }
     }
 
+    /// Type and offset of member `name` of a struct, of a tuple element by its index, or of the
+    /// `len` of a slice within the reference to it. Requires the memory layouts to be computed.
+    pub fn member(&self, ty: Index, name: &str) -> Option<(Index, usize)> {
+        let ty = self.canonicalize(ty);
+        let (index, member_ty) = match &self.arena[ty] {
+            Type::StructType(struct_ty) => struct_ty
+                .members
+                .iter()
+                .enumerate()
+                .find(|(_, member)| member.name == name)
+                .map(|(index, member)| (index, member.ty))?,
+            Type::TupleType(tuple_ty) => {
+                let index = name.parse::<usize>().ok()?;
+                (index, *tuple_ty.types.get(index)?)
+            }
+            Type::SliceType(_) if name == "len" => {
+                let reference = self.reference_of(ty)?;
+                let layout = self.memory_layouts.get(&reference)?.members().get(1)?;
+                return Some((self.i32(), layout.offset));
+            }
+            _ => return None,
+        };
+        let layout = self.memory_layouts.get(&ty)?.members().get(index)?;
+        Some((member_ty, layout.offset))
+    }
+
     pub fn kind(&self, ty: Index) -> TypeKind {
         let ty = self.canonicalize(ty);
         match &self.arena[ty] {
-            Type::TupleType(_) | Type::StructType(_) => TypeKind::Memory,
+            Type::TupleType(_) | Type::StructType(_) | Type::ArrayType(_) => TypeKind::Memory,
+            Type::RefType(ref_ty)
+                if matches!(self.arena[self.canonicalize(ref_ty.to)], Type::SliceType(_)) =>
+            {
+                TypeKind::Memory
+            }
             Type::RefType(_) | Type::FunctionType(_) => TypeKind::Integer,
+            Type::SliceType(_) => TypeKind::Unknown,
             Type::TypeDefType(_) => unreachable!(),
             Type::PrimitiveType(primitive_ty) => match primitive_ty {
                 PrimitiveType::U32
@@ -412,6 +530,14 @@ impl TypeCollection {
             Type::Unknown => TypeKind::Unknown,
         }
     }
+
+    pub fn is_signed(&self, ty: Index) -> bool {
+        let ty = self.canonicalize(ty);
+        matches!(
+            &self.arena[ty],
+            Type::PrimitiveType(PrimitiveType::I32 | PrimitiveType::F32)
+        )
+    }
 }
 
 impl TypeCollection {
@@ -1,7 +1,7 @@
 use crate::ast::{
-    Ast, BlockExpr, CallExpr, Expr, ExprStmt, FuncDeclStmt, IfExpr, IndexExpr, MemberExpr,
-    OperatorExpr, ReturnStmt, Sourced, SourcedExpr, SourcedStmt, Stmt, StructDeclStmt, TypedExpr,
-    VarDeclStmt, WhileStmt,
+    ArrayExpr, Ast, BlockExpr, CallExpr, Expr, ExprStmt, FuncDeclStmt, IfExpr, IndexExpr,
+    MemberExpr, OperatorExpr, ReturnStmt, Sourced, SourcedExpr, SourcedStmt, Stmt, StructDeclStmt,
+    TypedExpr, VarDeclStmt, WhileStmt,
 };
 
 pub struct AstVisitor {
@@ -253,7 +253,7 @@ impl AstVisitor {
                 visitor,
             );
         }
-        if let Some(expr) = return_expr {
+        if let Some(expr) = return_expr.as_ref().filter(|_| self.block_end_exprs) {
             self.visit_expr(
                 expr,
                 if !include_block && ty == NodeType::Child {
@@ -321,7 +321,7 @@ impl AstVisitor {
                 visitor,
             );
         }
-        if let Some(expr) = return_expr {
+        if let Some(expr) = return_expr.as_mut().filter(|_| self.block_end_exprs) {
             self.visit_expr_mut(
                 expr,
                 if !include_block && ty == NodeType::Child {
@@ -403,6 +403,19 @@ impl AstVisitor {
                     self.visit_expr(arg, iter.peek().into(), visitor);
                 }
             }
+            Sourced {
+                value:
+                    TypedExpr {
+                        expr: Expr::ArrayExpr(ArrayExpr { elements }),
+                        ..
+                    },
+                ..
+            } => {
+                let mut iter = elements.iter().peekable();
+                while let Some(element) = iter.next() {
+                    self.visit_expr(element, iter.peek().into(), visitor);
+                }
+            }
             Sourced {
                 value:
                     TypedExpr {
@@ -510,6 +523,19 @@ impl AstVisitor {
                     self.visit_expr_mut(arg, iter.peek().into(), visitor);
                 }
             }
+            Sourced {
+                value:
+                    TypedExpr {
+                        expr: Expr::ArrayExpr(ArrayExpr { elements }),
+                        ..
+                    },
+                ..
+            } => {
+                let mut iter = elements.iter_mut().peekable();
+                while let Some(element) = iter.next() {
+                    self.visit_expr_mut(element, iter.peek().into(), visitor);
+                }
+            }
             Sourced {
                 value:
                     TypedExpr {
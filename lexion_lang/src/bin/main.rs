@@ -1,12 +1,19 @@
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use enumflags2::BitFlag;
-use lexion_lang::compiler::{LexionCompiler, LexionCompilerOptions};
+use lexion_lang::compiler::{LexionCompiler, LexionCompilerOptions, RunError};
+use lexion_lang::diagnostic::set_report_hook;
+use lexion_lang::generators::tac::opt::OptLevel;
+use lexion_lang::generators::x86::AsmSyntax;
 use lexion_lang::{CompilationError, Dump, DumpFlags};
-use lexion_lib::miette;
 use lexion_lib::miette::{NamedSource, Report};
 
+/// Exit status of `--run` when the program fails while it runs, which sets it apart from the
+/// failure to compile it.
+const RUNTIME_ERROR_EXIT_CODE: i32 = 101;
+
 #[derive(Parser, Debug)]
 #[command(long_about = None)]
 struct Args {
@@ -15,44 +22,114 @@ struct Args {
     dump: DumpFlags,
     #[arg(long, default_value_t = String::from("dump"))]
     dump_dir: String,
+    /// Optimization level: 0 runs no passes, 1 runs the cheap ones once and 2 runs all of them
+    /// until the program stops changing
+    #[arg(short = 'O', default_value_t = OptLevel::O0)]
+    opt_level: OptLevel,
+    /// What to write; defaults to an executable when an output path is given
+    #[arg(long, value_enum)]
+    emit: Option<Emit>,
+    /// Syntax of the assembler source written by `--emit asm`
+    #[arg(long, value_enum, default_value_t = AsmSyntax::Gas)]
+    asm_syntax: AsmSyntax,
+    /// Path of the emitted file; defaults to the source file with the matching extension
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+    /// Interpret the program instead of compiling it and exit with the value `main` returns
+    #[arg(long, conflicts_with_all = ["emit", "output"])]
+    run: bool,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Emit {
+    /// Executable linked with the system C compiler
+    Exe,
+    /// ELF64 relocatable object file
+    Obj,
+    /// Assembler source
+    Asm,
 }
 
 impl Args {
-    fn split(self) -> (String, LexionCompilerOptions) {
+    fn split(
+        self,
+    ) -> (
+        String,
+        Option<(Emit, PathBuf)>,
+        AsmSyntax,
+        LexionCompilerOptions,
+    ) {
+        let emit = self
+            .emit
+            .or(self.output.is_some().then_some(Emit::Exe))
+            .map(|emit| {
+                let output = self.output.unwrap_or_else(|| {
+                    Path::new(&self.filename).with_extension(match emit {
+                        Emit::Exe => "",
+                        Emit::Obj => "o",
+                        Emit::Asm => self.asm_syntax.extension(),
+                    })
+                });
+                (emit, output)
+            });
         (
             self.filename,
+            emit,
+            self.asm_syntax,
             LexionCompilerOptions {
                 dump_flags: self.dump,
                 dump_dir: self.dump_dir.into(),
+                opt_level: self.opt_level,
             },
         )
     }
 }
 
 fn main() -> Result<(), CompilationError> {
-    miette::set_hook(Box::new(|_| {
-        Box::new(
-            miette::MietteHandlerOpts::new()
-                .force_graphical(true)
-                .terminal_links(true)
-                .context_lines(2)
-                .color(true)
-                .unicode(true)
-                .break_words(true)
-                .build(),
-        )
-    }))
-    .expect("failed to initialize logging hook");
-    let (filename, options) = Args::parse().split();
+    set_report_hook();
+    let args = Args::parse();
+    let run = args.run;
+    let (filename, emit, syntax, options) = args.split();
     let source_code =
         Arc::new(std::fs::read_to_string(filename.as_str()).map_err(CompilationError::IO)?);
     let source = NamedSource::new(filename.as_str(), source_code);
-    match LexionCompiler::new(options).exec(source) {
-        Ok(list) => {
+    let mut compiler = LexionCompiler::new(options);
+    if run {
+        return match compiler.run(source, &mut std::io::stdout()) {
+            Ok((exit_code, list)) => {
+                if !list.is_empty() {
+                    println!("{:?}", Report::new(list));
+                }
+                std::process::exit(exit_code as i32)
+            }
+            Err(RunError::Compilation(list)) => {
+                if !list.is_empty() {
+                    println!("{:?}", Report::new(list));
+                }
+                Err(CompilationError::CompilationFailed)
+            }
+            Err(RunError::Runtime(list, errors)) => {
+                if !list.is_empty() {
+                    println!("{:?}", Report::new(list));
+                }
+                println!("{:?}", Report::new(errors));
+                std::process::exit(RUNTIME_ERROR_EXIT_CODE)
+            }
+        };
+    }
+    match compiler.compile(source.clone()) {
+        Ok((module, list)) => {
             if !list.is_empty() {
                 println!("{:?}", Report::new(list));
             }
-            Ok(())
+            match emit {
+                Some((Emit::Exe, output)) => compiler.link(&module, &output),
+                Some((Emit::Obj, output)) => compiler.write_object(&module, &output),
+                Some((Emit::Asm, output)) => {
+                    compiler.write_assembly(source, &module, syntax, &output)
+                }
+                None => Ok(()),
+            }
         }
         Err(list) => {
             if !list.is_empty() {
@@ -0,0 +1,78 @@
+use std::io::{BufRead, Write};
+
+use lexion_lang::compiler::RunError;
+use lexion_lang::diagnostic::{set_report_hook, LexionDiagnosticList};
+use lexion_lang::repl::Repl;
+use lexion_lib::miette::Report;
+
+const HELP: &str = "\
+:type <expr>  show the type of an expression without running it
+:ast <input>  show the syntax tree of an input
+:help         show this message
+:quit         exit";
+
+/// Reads one input, continuing on further lines while braces are left open.
+fn read_input(lines: &mut impl Iterator<Item = std::io::Result<String>>) -> Option<String> {
+    let mut input = String::new();
+    let mut depth = 0;
+    loop {
+        print!("{}", if input.is_empty() { "> " } else { "| " });
+        let _ = std::io::stdout().flush();
+        let line = lines.next()?.ok()?;
+        depth += line.matches('{').count() as isize - line.matches('}').count() as isize;
+        input.push_str(&line);
+        input.push('\n');
+        if depth <= 0 {
+            return Some(input);
+        }
+    }
+}
+
+fn report(list: LexionDiagnosticList) {
+    if !list.is_empty() {
+        println!("{:?}", Report::new(list));
+    }
+}
+
+fn main() {
+    set_report_hook();
+    let mut repl = Repl::new();
+    let mut lines = std::io::stdin().lock().lines();
+    while let Some(input) = read_input(&mut lines) {
+        let (command, argument) = match input.trim().split_once(char::is_whitespace) {
+            Some((command, argument)) if command.starts_with(':') => (command, argument),
+            _ => (input.trim(), ""),
+        };
+        match command {
+            ":quit" | ":q" => break,
+            ":help" => println!("{HELP}"),
+            ":type" => match repl.type_of(argument) {
+                Ok((ty, list)) => {
+                    report(list);
+                    println!("{ty}");
+                }
+                Err(list) => report(list),
+            },
+            ":ast" => match repl.ast(argument) {
+                Ok(ast) => print!("{ast}"),
+                Err(list) => report(list),
+            },
+            command if command.starts_with(':') => {
+                println!("unknown command '{command}', see :help");
+            }
+            _ => match repl.eval(&input, &mut std::io::stdout()) {
+                Ok((value, list)) => {
+                    report(list);
+                    if let Some(value) = value {
+                        println!("{value}");
+                    }
+                }
+                Err(RunError::Compilation(list)) => report(list),
+                Err(RunError::Runtime(list, errors)) => {
+                    report(list);
+                    println!("{:?}", Report::new(errors));
+                }
+            },
+        }
+    }
+}
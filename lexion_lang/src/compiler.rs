@@ -1,15 +1,26 @@
 use crate::ast::types::TypeCollection;
 use crate::ast::visitor::{AstNode, AstVisitor, AstVisitorAction, TraversalType};
 use crate::ast::{Ast, AstView};
-use crate::diagnostic::{DiagnosticConsumer, LexionDiagnosticInfo, LexionDiagnosticList};
+use crate::diagnostic::{
+    DiagnosticConsumer, LexionDiagnostic, LexionDiagnosticInfo, LexionDiagnosticList,
+    LexionRuntimeErrorList,
+};
+use crate::generators::elf::ElfObjectWriter;
 use crate::generators::tac::instructions::{ControlFlowGraph, FunctionRange, LivenessInterval};
+use crate::generators::tac::interpreter::{InterpreterState, TacInterpreter};
+use crate::generators::tac::opt::{OptLevel, PassManager};
+use crate::generators::tac::ssa::{SsaBuilder, SsaDestructor, SsaGraph};
 use crate::generators::tac::CodeGeneratorTac;
-use crate::generators::x86::{AssignedLivenessInterval, LinearRegisterAllocator};
+use crate::generators::x86::system_v::SystemV64;
+use crate::generators::x86::{
+    AsmSyntax, AsmWriter, AssembledModule, AssignedLivenessInterval, CallingConvention,
+    CodeGeneratorX86, LinearRegisterAllocator, SCRATCH_REGISTERS,
+};
 use crate::parser::ParserLexion;
 use crate::pipeline::PipelineStage;
 use crate::symbol_table::{SymbolTableGenerator, SymbolTableGraph};
 use crate::type_checker::TypeChecker;
-use crate::{Dump, DumpFlags};
+use crate::{CompilationError, Dump, DumpFlags};
 use iced_x86::Register;
 use lexion_lib::miette::{NamedSource, Report};
 use lexion_lib::parsers::GrammarParserLR;
@@ -18,19 +29,56 @@ use lexion_lib::tabled::Table;
 use std::collections::HashMap;
 use std::fs::OpenOptions;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::sync::Arc;
 
+/// The program in SSA form, with the symbols and types its values refer to.
+struct SsaProgram {
+    ssa: SsaGraph,
+    symbols: SymbolTableGraph,
+    types: TypeCollection,
+}
+
+/// The output of the front end, which both code generation and the interpreter start from.
+struct LoweredProgram {
+    cfg: ControlFlowGraph,
+    intervals: HashMap<FunctionRange, Vec<LivenessInterval>>,
+    symbols: SymbolTableGraph,
+    types: TypeCollection,
+}
+
 #[derive(Clone)]
 pub struct LexionCompilerOptions {
     pub dump_dir: PathBuf,
     pub dump_flags: DumpFlags,
+    pub opt_level: OptLevel,
 }
 
 pub struct LexionCompiler {
     options: LexionCompilerOptions,
 }
 
+/// Why [`LexionCompiler::run`] did not return a value.
+#[derive(Debug)]
+pub enum RunError {
+    /// The program did not compile.
+    Compilation(LexionDiagnosticList),
+    /// The program compiled with the diagnostics given, but failed while it ran, after writing
+    /// the output before the failure.
+    Runtime(LexionDiagnosticList, LexionRuntimeErrorList),
+}
+
+impl RunError {
+    /// Errors that stopped the program, whether it did not compile or failed while it ran.
+    pub fn errors(&self) -> &[LexionDiagnostic] {
+        match self {
+            RunError::Compilation(diagnostics) => &diagnostics.list,
+            RunError::Runtime(_, errors) => &errors.list,
+        }
+    }
+}
+
 impl LexionCompiler {
     pub fn new(options: LexionCompilerOptions) -> Self {
         Self { options }
@@ -40,50 +88,202 @@ impl LexionCompiler {
         &mut self,
         source: NamedSource<Arc<String>>,
     ) -> Result<LexionDiagnosticList, LexionDiagnosticList> {
+        self.compile(source).map(|(_, diagnostics)| diagnostics)
+    }
+
+    pub fn compile(
+        &mut self,
+        source: NamedSource<Arc<String>>,
+    ) -> Result<(AssembledModule, LexionDiagnosticList), LexionDiagnosticList> {
         let mut diagnostics = LexionDiagnosticList::default();
-        let Some((mut ast, mut types, trace)) = self.parse_source(&mut diagnostics, source.clone())
-        else {
+        let Some(program) = self.lower(&mut diagnostics, source.clone()) else {
             return Err(diagnostics);
         };
 
-        let Some(mut symbols) =
-            self.generate_symbols(&mut diagnostics, source.clone(), &ast, &mut types, &trace)
+        let Some(assigned) =
+            self.assign_registers(&mut diagnostics, &program.cfg, program.intervals)
         else {
             return Err(diagnostics);
         };
 
-        let Some(_) = self.type_check(
-            &mut diagnostics,
-            source.clone(),
-            &mut ast,
-            &mut symbols,
-            &mut types,
-        ) else {
+        let Some(module) =
+            CodeGeneratorX86::new((source, &program.cfg, &program.types, &program.symbols))
+                .exec(&mut diagnostics, assigned)
+        else {
             return Err(diagnostics);
         };
 
-        let Some((cfg, intervals)) =
-            self.generate_ir(&mut diagnostics, source, &ast, &mut symbols, &types)
-        else {
-            return Err(diagnostics);
+        Ok((module, diagnostics))
+    }
+
+    /// Runs the program in the TAC interpreter, writing its output to `stdout`, and returns the
+    /// value `main` returned.
+    pub fn run(
+        &mut self,
+        source: NamedSource<Arc<String>>,
+        stdout: &mut dyn Write,
+    ) -> Result<(i64, LexionDiagnosticList), RunError> {
+        let mut diagnostics = LexionDiagnosticList::default();
+        let Some(program) = self.lower(&mut diagnostics, source.clone()) else {
+            return Err(RunError::Compilation(diagnostics));
         };
 
-        let Some(assigned) = self.assign_registers(&mut diagnostics, &cfg, intervals) else {
-            return Err(diagnostics);
+        let mut errors = LexionDiagnosticList::default();
+        let Some(exit_code) = TacInterpreter::new((
+            source,
+            &program.cfg,
+            &program.types,
+            &program.symbols,
+            &mut InterpreterState::default(),
+            stdout,
+        ))
+        .exec(&mut errors, "main") else {
+            return Err(RunError::Runtime(
+                diagnostics,
+                LexionRuntimeErrorList { list: errors.list },
+            ));
         };
+        diagnostics.list.extend(errors.list);
 
-        println!("{assigned:#?}");
+        Ok((exit_code, diagnostics))
+    }
 
-        Ok(diagnostics)
+    /// Lowers `source` to three-address code in SSA form, optimized at the level of the options.
+    pub fn ssa(
+        &mut self,
+        source: NamedSource<Arc<String>>,
+    ) -> Result<(SsaGraph, LexionDiagnosticList), LexionDiagnosticList> {
+        let mut diagnostics = LexionDiagnosticList::default();
+        match self.construct_ssa(&mut diagnostics, source) {
+            Some(program) => Ok((program.ssa, diagnostics)),
+            None => Err(diagnostics),
+        }
+    }
+
+    /// Writes `module` as an ELF64 relocatable object file.
+    pub fn write_object(
+        &self,
+        module: &AssembledModule,
+        output: &Path,
+    ) -> Result<(), CompilationError> {
+        let object = ElfObjectWriter::new(module)
+            .exec(&mut LexionDiagnosticList::default(), ())
+            .ok_or(CompilationError::CompilationFailed)?;
+        std::fs::write(output, object).map_err(CompilationError::IO)
+    }
+
+    /// Writes `module` as assembler source, commented with the lines of `source` it came from.
+    pub fn write_assembly(
+        &self,
+        source: NamedSource<Arc<String>>,
+        module: &AssembledModule,
+        syntax: AsmSyntax,
+        output: &Path,
+    ) -> Result<(), CompilationError> {
+        let assembly = AsmWriter::new((source, module))
+            .exec(&mut LexionDiagnosticList::default(), syntax)
+            .ok_or(CompilationError::CompilationFailed)?;
+        std::fs::write(output, assembly).map_err(CompilationError::IO)
+    }
+
+    /// Writes `module` as an object file and links it into the executable `output` with the
+    /// system C compiler, which also pulls in the C runtime and libc for `extern fn`s.
+    pub fn link(&self, module: &AssembledModule, output: &Path) -> Result<(), CompilationError> {
+        let object_path = std::env::temp_dir().join(format!(
+            "lexion-{}-{}.o",
+            std::process::id(),
+            output
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default()
+        ));
+        self.write_object(module, &object_path)?;
+        let result = Command::new("cc")
+            .arg(&object_path)
+            .arg("-o")
+            .arg(output)
+            .output();
+        let _ = std::fs::remove_file(&object_path);
+        let result = result.map_err(CompilationError::IO)?;
+        if result.status.success() {
+            Ok(())
+        } else {
+            Err(CompilationError::LinkFailed(
+                output.display().to_string(),
+                String::from_utf8_lossy(&result.stderr).trim().to_string(),
+            ))
+        }
     }
 }
 
 impl LexionCompiler {
-    fn dump_file(
+    /// Parses, checks and lowers `source` to three-address code, which goes through SSA form
+    /// on the way.
+    fn lower(
         &self,
-        name: &'static str,
-        content: impl AsRef<[u8]>,
-    ) -> Result<(), std::io::Error> {
+        diagnostics: &mut LexionDiagnosticList,
+        source: NamedSource<Arc<String>>,
+    ) -> Option<LoweredProgram> {
+        let SsaProgram {
+            ssa,
+            mut symbols,
+            types,
+        } = self.construct_ssa(diagnostics, source)?;
+        let mut cfg = SsaDestructor::new((ssa, &mut symbols)).exec(diagnostics, ())?;
+        let intervals = cfg.liveness_analysis();
+        Some(LoweredProgram {
+            cfg,
+            intervals,
+            symbols,
+            types,
+        })
+    }
+
+    /// Parses, checks and lowers `source` to three-address code in SSA form, and runs the
+    /// optimization passes over it.
+    fn construct_ssa(
+        &self,
+        diagnostics: &mut LexionDiagnosticList,
+        source: NamedSource<Arc<String>>,
+    ) -> Option<SsaProgram> {
+        let (mut ast, mut types, trace) = self.parse_source(diagnostics, source.clone())?;
+        let mut symbols =
+            self.generate_symbols(diagnostics, source.clone(), &ast, &mut types, &trace)?;
+        self.type_check(
+            diagnostics,
+            source.clone(),
+            &mut ast,
+            &mut symbols,
+            &mut types,
+        )?;
+        let cfg = self.generate_ir(diagnostics, source, &ast, &mut symbols, &mut types)?;
+        let ssa = SsaBuilder::new((cfg, &symbols)).exec(diagnostics, ())?;
+
+        if self
+            .options
+            .dump_flags
+            .contains(Dump::StaticSingleAssignment)
+        {
+            self.dump_file("ssa.tac", ssa.to_string()).unwrap();
+            self.dump_file("ssa.dot", format!("{:?}", Dot::new(&ssa.cfg.graph)))
+                .unwrap();
+        }
+
+        let (ssa, dumps) = PassManager::new((ssa, &types)).exec(
+            diagnostics,
+            (self.options.opt_level, self.options.dump_flags),
+        )?;
+        for dump in dumps {
+            self.dump_file(&dump.name, dump.listing).unwrap();
+        }
+        Some(SsaProgram {
+            ssa,
+            symbols,
+            types,
+        })
+    }
+
+    fn dump_file(&self, name: &str, content: impl AsRef<[u8]>) -> Result<(), std::io::Error> {
         std::fs::create_dir_all(self.options.dump_dir.as_path())?;
         let path = self.options.dump_dir.join(name);
         println!("{path:?}");
@@ -157,7 +357,10 @@ impl LexionCompiler {
                 .unwrap();
         }
 
-        TypeChecker::new((source.clone(), symbols, types)).exec(diagnostics, ast)
+        TypeChecker::new((source.clone(), symbols, types)).exec(diagnostics, ast)?;
+        // The checker reports every error it finds rather than stopping at the first one, so
+        // nothing past it may run on an ill-typed program.
+        (!diagnostics.has_errors()).then_some(())
     }
 
     fn generate_ir(
@@ -166,11 +369,8 @@ impl LexionCompiler {
         source: NamedSource<Arc<String>>,
         ast: &Ast,
         symbols: &mut SymbolTableGraph,
-        types: &TypeCollection,
-    ) -> Option<(
-        ControlFlowGraph,
-        HashMap<FunctionRange, Vec<LivenessInterval>>,
-    )> {
+        types: &mut TypeCollection,
+    ) -> Option<ControlFlowGraph> {
         if self.options.dump_flags.contains(Dump::Types) {
             let mut type_list = LexionDiagnosticList::default();
             AstVisitor::new().visit(ast, |ty, node, _| {
@@ -187,20 +387,14 @@ impl LexionCompiler {
                 .unwrap();
         }
 
-        CodeGeneratorTac::new((ast, symbols, types)).exec(diagnostics, ())
-    }
+        let mut cfg = CodeGeneratorTac::new((ast, symbols, types)).exec(diagnostics, ())?;
 
-    fn assign_registers(
-        &self,
-        diagnostics: &mut LexionDiagnosticList,
-        cfg: &ControlFlowGraph,
-        intervals: HashMap<FunctionRange, Vec<LivenessInterval>>,
-    ) -> Option<HashMap<FunctionRange, Vec<AssignedLivenessInterval>>> {
         if self
             .options
             .dump_flags
             .contains(Dump::IntermediateRepresentation)
         {
+            cfg.liveness_analysis();
             let mut ir = String::with_capacity(4096);
             for block in cfg.node_weights() {
                 ir.push_str(&block.table().to_string());
@@ -210,21 +404,26 @@ impl LexionCompiler {
             self.dump_file("ir.dot", format!("{:?}", Dot::new(&cfg.graph)))
                 .unwrap();
         }
-        // SystemV64::callee_saved();
-        LinearRegisterAllocator::new((
-            cfg,
-            Vec::from_iter([
-                Register::RAX,
-                Register::RCX,
-                Register::RDX,
-                Register::RSI,
-                Register::RDI,
-                Register::R8,
-                Register::R9,
-                Register::R10,
-                Register::R11,
-            ]),
-        ))
-        .exec(diagnostics, intervals)
+        Some(cfg)
+    }
+
+    fn assign_registers(
+        &self,
+        diagnostics: &mut LexionDiagnosticList,
+        cfg: &ControlFlowGraph,
+        intervals: HashMap<FunctionRange, Vec<LivenessInterval>>,
+    ) -> Option<HashMap<FunctionRange, Vec<AssignedLivenessInterval>>> {
+        // Callee-saved registers come first so values survive calls without being saved around
+        // each one. RAX carries return values and the scratch registers are left to codegen.
+        let registers = SystemV64
+            .callee_saved()
+            .iter()
+            .chain(SystemV64.caller_saved())
+            .filter(|reg| {
+                !matches!(reg, Register::RBP | Register::RAX) && !SCRATCH_REGISTERS.contains(reg)
+            })
+            .cloned()
+            .collect();
+        LinearRegisterAllocator::new((cfg, registers)).exec(diagnostics, intervals)
     }
 }
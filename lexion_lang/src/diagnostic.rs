@@ -55,7 +55,12 @@ impl From<(NamedSource<Arc<String>>, ParseError)> for LexionDiagnosticError {
     fn from((src, value): (NamedSource<Arc<String>>, ParseError)) -> Self {
         match value {
             ParseError::Syntax(err) => err.into(),
-            ParseError::Io(err) => LexionDiagnosticError {
+            ParseError::Production(err) => LexionDiagnosticError {
+                src,
+                span: err.span,
+                message: err.to_string(),
+            },
+            err => LexionDiagnosticError {
                 src,
                 span: SourceSpan::from(0),
                 message: err.to_string(),
@@ -83,18 +88,34 @@ impl From<SyntaxError> for LexionDiagnostic {
     }
 }
 
+/// Diagnostics of compiling a program, headed as errors only when one of them is an error.
 #[derive(Debug, Default, Error, Diagnostic)]
-#[error("Compilation errors:")]
+#[error("{}", if self.has_errors() { "Compilation errors:" } else { "Compilation diagnostics:" })]
 #[diagnostic()]
 pub struct LexionDiagnosticList {
     #[related]
     pub list: Vec<LexionDiagnostic>,
 }
 
+/// Errors that stopped a program the interpreter ran.
+#[derive(Debug, Default, Error, Diagnostic)]
+#[error("Runtime errors:")]
+#[diagnostic()]
+pub struct LexionRuntimeErrorList {
+    #[related]
+    pub list: Vec<LexionDiagnostic>,
+}
+
 impl LexionDiagnosticList {
     pub fn is_empty(&self) -> bool {
         self.list.is_empty()
     }
+
+    pub fn has_errors(&self) -> bool {
+        self.list
+            .iter()
+            .any(|diagnostic| matches!(diagnostic, LexionDiagnostic::Error(_)))
+    }
 }
 
 pub trait DiagnosticConsumer {
@@ -131,6 +152,23 @@ impl DiagnosticConsumer for LexionDiagnosticList {
     }
 }
 
+/// Renders reports graphically, with a few lines of source around each label.
+pub fn set_report_hook() {
+    miette::set_hook(Box::new(|_| {
+        Box::new(
+            miette::MietteHandlerOpts::new()
+                .force_graphical(true)
+                .terminal_links(true)
+                .context_lines(2)
+                .color(true)
+                .unicode(true)
+                .break_words(true)
+                .build(),
+        )
+    }))
+    .expect("failed to initialize logging hook");
+}
+
 #[derive(Default)]
 pub struct DiagnosticPrinterStdout;
 
@@ -0,0 +1,324 @@
+use crate::diagnostic::DiagnosticConsumer;
+use crate::generators::x86::{AssembledModule, DataSymbol, RelocationKind};
+use crate::pipeline::PipelineStage;
+use std::collections::HashMap;
+
+const EHDR_SIZE: u16 = 64;
+const SHDR_SIZE: u16 = 64;
+const SYM_SIZE: u64 = 24;
+const RELA_SIZE: u64 = 24;
+
+const ET_REL: u16 = 1;
+const EM_X86_64: u16 = 62;
+
+const SHT_PROGBITS: u32 = 1;
+const SHT_SYMTAB: u32 = 2;
+const SHT_STRTAB: u32 = 3;
+const SHT_RELA: u32 = 4;
+const SHT_NOBITS: u32 = 8;
+
+const SHF_WRITE: u64 = 0x1;
+const SHF_ALLOC: u64 = 0x2;
+const SHF_EXECINSTR: u64 = 0x4;
+const SHF_INFO_LINK: u64 = 0x40;
+
+const STB_LOCAL: u8 = 0;
+const STB_GLOBAL: u8 = 1;
+const STT_NOTYPE: u8 = 0;
+const STT_OBJECT: u8 = 1;
+const STT_FUNC: u8 = 2;
+const SHN_UNDEF: u16 = 0;
+
+const R_X86_64_PC32: u32 = 2;
+const R_X86_64_PLT32: u32 = 4;
+
+/// Section header indices, in the order the sections are written.
+const TEXT: u16 = 1;
+const RODATA: u16 = 2;
+const DATA: u16 = 3;
+const BSS: u16 = 4;
+const SYMTAB: u16 = 6;
+const STRTAB: u16 = 7;
+const SHSTRTAB: u16 = 8;
+
+struct Section {
+    name: &'static str,
+    ty: u32,
+    flags: u64,
+    bytes: Vec<u8>,
+    /// Size of a `SHT_NOBITS` section, which takes no space in the file.
+    size: Option<u64>,
+    link: u32,
+    info: u32,
+    align: u64,
+    entry_size: u64,
+}
+
+impl Section {
+    fn new(name: &'static str, ty: u32, flags: u64, bytes: Vec<u8>, align: u64) -> Self {
+        Self {
+            name,
+            ty,
+            flags,
+            bytes,
+            size: None,
+            link: 0,
+            info: 0,
+            align,
+            entry_size: 0,
+        }
+    }
+}
+
+struct Symbol {
+    name: String,
+    bind: u8,
+    ty: u8,
+    section: u16,
+    value: u64,
+    size: u64,
+}
+
+struct StringTable {
+    bytes: Vec<u8>,
+    offsets: HashMap<String, u32>,
+}
+
+impl StringTable {
+    fn new() -> Self {
+        Self {
+            bytes: vec![0],
+            offsets: HashMap::from([(String::new(), 0)]),
+        }
+    }
+
+    fn insert(&mut self, name: &str) -> u32 {
+        if let Some(offset) = self.offsets.get(name) {
+            return *offset;
+        }
+        let offset = self.bytes.len() as u32;
+        self.bytes.extend_from_slice(name.as_bytes());
+        self.bytes.push(0);
+        self.offsets.insert(name.to_string(), offset);
+        offset
+    }
+}
+
+/// Writes an [`AssembledModule`] as an ELF64 relocatable object file for x86-64 System V.
+pub struct ElfObjectWriter<'a> {
+    module: &'a AssembledModule,
+    symbols: Vec<Symbol>,
+}
+
+impl<'a> PipelineStage for ElfObjectWriter<'a> {
+    type Input = &'a AssembledModule;
+    type Options = ();
+    type Output = Vec<u8>;
+
+    fn new(module: Self::Input) -> Self {
+        Self {
+            module,
+            symbols: Default::default(),
+        }
+    }
+
+    fn exec(
+        mut self,
+        _diag: &mut dyn DiagnosticConsumer,
+        _: Self::Options,
+    ) -> Option<Self::Output> {
+        let module = self.module;
+        self.symbols.push(Symbol {
+            name: String::new(),
+            bind: STB_LOCAL,
+            ty: STT_NOTYPE,
+            section: SHN_UNDEF,
+            value: 0,
+            size: 0,
+        });
+        let rodata = self.data_symbols(&module.rodata, RODATA, 1);
+        let data = self.data_symbols(&module.data, DATA, 8);
+        let bss = self.data_symbols(&module.bss, BSS, 8);
+        let first_global = self.symbols.len();
+        for function in &module.functions {
+            self.symbols.push(Symbol {
+                name: function.name.clone(),
+                bind: STB_GLOBAL,
+                ty: STT_FUNC,
+                section: TEXT,
+                value: function.offset as u64,
+                size: function.size as u64,
+            });
+        }
+        for name in &module.externs {
+            self.undefined(name);
+        }
+
+        let mut rela_text = Vec::new();
+        for relocation in &module.relocations {
+            let symbol = self.undefined(&relocation.symbol);
+            let ty = match relocation.kind {
+                RelocationKind::Call => R_X86_64_PLT32,
+                RelocationKind::Data => R_X86_64_PC32,
+            };
+            put_u64(&mut rela_text, relocation.offset as u64);
+            put_u64(&mut rela_text, ((symbol as u64) << 32) | ty as u64);
+            put_u64(&mut rela_text, relocation.addend as u64);
+        }
+
+        let mut strtab = StringTable::new();
+        let mut symtab = Vec::new();
+        for symbol in &self.symbols {
+            put_u32(&mut symtab, strtab.insert(&symbol.name));
+            symtab.push((symbol.bind << 4) | symbol.ty);
+            symtab.push(0);
+            put_u16(&mut symtab, symbol.section);
+            put_u64(&mut symtab, symbol.value);
+            put_u64(&mut symtab, symbol.size);
+        }
+
+        let mut sections = vec![
+            Section::new("", 0, 0, Vec::new(), 0),
+            Section::new(
+                ".text",
+                SHT_PROGBITS,
+                SHF_ALLOC | SHF_EXECINSTR,
+                module.code.clone(),
+                16,
+            ),
+            Section::new(".rodata", SHT_PROGBITS, SHF_ALLOC, rodata, 1),
+            Section::new(".data", SHT_PROGBITS, SHF_ALLOC | SHF_WRITE, data, 8),
+            Section {
+                size: Some(bss.len() as u64),
+                ..Section::new(".bss", SHT_NOBITS, SHF_ALLOC | SHF_WRITE, Vec::new(), 8)
+            },
+            Section {
+                link: SYMTAB as u32,
+                info: TEXT as u32,
+                entry_size: RELA_SIZE,
+                ..Section::new(".rela.text", SHT_RELA, SHF_INFO_LINK, rela_text, 8)
+            },
+            Section {
+                link: STRTAB as u32,
+                info: first_global as u32,
+                entry_size: SYM_SIZE,
+                ..Section::new(".symtab", SHT_SYMTAB, 0, symtab, 8)
+            },
+            Section::new(".strtab", SHT_STRTAB, 0, strtab.bytes, 1),
+            Section::new(".shstrtab", SHT_STRTAB, 0, Vec::new(), 1),
+            // Marks the stack as non-executable.
+            Section::new(".note.GNU-stack", SHT_PROGBITS, 0, Vec::new(), 1),
+        ];
+
+        let mut shstrtab = StringTable::new();
+        let names = sections
+            .iter()
+            .map(|section| shstrtab.insert(section.name))
+            .collect::<Vec<_>>();
+        sections[SHSTRTAB as usize].bytes = shstrtab.bytes;
+
+        Some(Self::write(&sections, &names))
+    }
+}
+
+impl<'a> ElfObjectWriter<'a> {
+    /// Adds a local symbol for each entry and returns the contents of their section.
+    fn data_symbols(&mut self, entries: &[DataSymbol], section: u16, align: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for entry in entries {
+            bytes.resize(bytes.len().next_multiple_of(align), 0);
+            self.symbols.push(Symbol {
+                name: entry.name.clone(),
+                bind: STB_LOCAL,
+                ty: STT_OBJECT,
+                section,
+                value: bytes.len() as u64,
+                size: entry.bytes.len() as u64,
+            });
+            bytes.extend_from_slice(&entry.bytes);
+        }
+        bytes
+    }
+
+    /// Index of the symbol called `name`, declaring it as undefined if the module has none.
+    fn undefined(&mut self, name: &str) -> usize {
+        if let Some(index) = self.symbols.iter().position(|s| s.name == name) {
+            return index;
+        }
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            bind: STB_GLOBAL,
+            ty: STT_NOTYPE,
+            section: SHN_UNDEF,
+            value: 0,
+            size: 0,
+        });
+        self.symbols.len() - 1
+    }
+
+    fn write(sections: &[Section], names: &[u32]) -> Vec<u8> {
+        let mut file = vec![0; EHDR_SIZE as usize];
+        let mut offsets = Vec::with_capacity(sections.len());
+        for section in sections {
+            if section.align > 1 {
+                file.resize(file.len().next_multiple_of(section.align as usize), 0);
+            }
+            offsets.push(file.len() as u64);
+            file.extend_from_slice(&section.bytes);
+        }
+        file.resize(file.len().next_multiple_of(8), 0);
+        let section_headers = file.len() as u64;
+
+        for ((section, name), offset) in sections.iter().zip(names).zip(offsets) {
+            if section.ty == 0 {
+                file.extend_from_slice(&[0; SHDR_SIZE as usize]);
+                continue;
+            }
+            put_u32(&mut file, *name);
+            put_u32(&mut file, section.ty);
+            put_u64(&mut file, section.flags);
+            put_u64(&mut file, 0);
+            put_u64(&mut file, offset);
+            put_u64(
+                &mut file,
+                section.size.unwrap_or(section.bytes.len() as u64),
+            );
+            put_u32(&mut file, section.link);
+            put_u32(&mut file, section.info);
+            put_u64(&mut file, section.align);
+            put_u64(&mut file, section.entry_size);
+        }
+
+        let mut header = Vec::with_capacity(EHDR_SIZE as usize);
+        // ELFCLASS64, ELFDATA2LSB, EV_CURRENT, ELFOSABI_SYSV
+        header.extend_from_slice(&[0x7F, b'E', b'L', b'F', 2, 1, 1, 0]);
+        header.extend_from_slice(&[0; 8]);
+        put_u16(&mut header, ET_REL);
+        put_u16(&mut header, EM_X86_64);
+        put_u32(&mut header, 1);
+        put_u64(&mut header, 0);
+        put_u64(&mut header, 0);
+        put_u64(&mut header, section_headers);
+        put_u32(&mut header, 0);
+        put_u16(&mut header, EHDR_SIZE);
+        put_u16(&mut header, 0);
+        put_u16(&mut header, 0);
+        put_u16(&mut header, SHDR_SIZE);
+        put_u16(&mut header, sections.len() as u16);
+        put_u16(&mut header, SHSTRTAB);
+        file[..EHDR_SIZE as usize].copy_from_slice(&header);
+        file
+    }
+}
+
+fn put_u16(bytes: &mut Vec<u8>, value: u16) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
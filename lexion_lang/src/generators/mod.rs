@@ -1,3 +1,4 @@
+pub mod elf;
 mod label;
 pub mod tac;
 pub mod x86;
@@ -1,20 +1,35 @@
 use crate::ast::Lit;
 use crate::generators::label::Label;
+use crate::generators::x86::SizeAlign;
 use derived_deref::{Deref, DerefMut};
 use enum_dispatch::enum_dispatch;
 use lexion_lib::itertools::Itertools;
+use lexion_lib::miette::SourceSpan;
 use lexion_lib::petgraph::graph::NodeIndex;
-use lexion_lib::petgraph::Graph;
+use lexion_lib::petgraph::{Direction, Graph};
 use lexion_lib::tabled::builder::Builder;
 use lexion_lib::tabled::settings::Style;
 use lexion_lib::tabled::Table;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Display, Formatter};
 
+/// Value in SSA form, which is assigned exactly once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ValueId(pub usize);
+
+impl Display for ValueId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "%{}", self.0)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Operand {
     Variable(String),
+    /// Variable declared at the top level, which lives in memory instead of a register.
+    Global(String),
     Temporary(Label),
+    Value(ValueId),
     Label(String),
     Literal(Lit),
     Placeholder,
@@ -29,8 +44,23 @@ impl Operand {
         matches!(self, Operand::Literal(_))
     }
 
+    pub fn is_placeholder(&self) -> bool {
+        matches!(self, Operand::Placeholder)
+    }
+
+    pub fn is_global(&self) -> bool {
+        matches!(self, Operand::Global(_))
+    }
+
+    pub fn value(&self) -> Option<ValueId> {
+        match self {
+            Operand::Value(value) => Some(*value),
+            _ => None,
+        }
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = String> {
-        if self.is_literal() {
+        if self.is_literal() || self.is_placeholder() || self.is_global() {
             None.into_iter()
         } else {
             Some(self.to_string()).into_iter()
@@ -42,8 +72,10 @@ impl Display for Operand {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
             Operand::Variable(inner) => write!(f, "{inner}"),
+            Operand::Global(inner) => write!(f, "{inner}"),
             Operand::Label(inner) => write!(f, "{inner}"),
             Operand::Temporary(inner) => write!(f, "{inner}"),
+            Operand::Value(inner) => write!(f, "{inner}"),
             Operand::Literal(inner) => write!(f, "{inner}"),
             Operand::Placeholder => write!(f, "_"),
         }
@@ -83,7 +115,13 @@ impl BaseInstruction for AssignmentInstruction {
         }
     }
     fn variables_written(&self) -> HashSet<String> {
-        HashSet::from_iter([self.target.to_string()])
+        HashSet::from_iter(self.target.iter())
+    }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        self.left.iter_mut().chain([&mut self.right]).collect()
+    }
+    fn operands_written_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.target]
     }
 }
 
@@ -103,7 +141,13 @@ impl BaseInstruction for CopyInstruction {
         HashSet::from_iter(self.src.iter())
     }
     fn variables_written(&self) -> HashSet<String> {
-        HashSet::from_iter([self.dst.to_string()])
+        HashSet::from_iter(self.dst.iter())
+    }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.src]
+    }
+    fn operands_written_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.dst]
     }
 }
 
@@ -139,6 +183,9 @@ impl BaseInstruction for ConditionalJumpInstruction {
                 .chain(self.left.iter().flat_map(|src| src.iter())),
         )
     }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        self.left.iter_mut().chain([&mut self.right]).collect()
+    }
 }
 
 pub struct JumpInstruction {
@@ -167,6 +214,9 @@ impl BaseInstruction for ParameterInstruction {
     fn variables_read(&self) -> HashSet<String> {
         HashSet::from_iter(self.param.iter())
     }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.param]
+    }
 }
 
 pub struct FunctionCallInstruction {
@@ -193,6 +243,9 @@ impl BaseInstruction for FunctionCallInstruction {
     fn variables_written(&self) -> HashSet<String> {
         HashSet::from_iter(self.return_target.iter().map(|target| target.to_string()))
     }
+    fn operands_written_mut(&mut self) -> Vec<&mut Operand> {
+        self.return_target.iter_mut().collect()
+    }
 }
 
 pub struct ReturnInstruction {
@@ -203,7 +256,7 @@ impl Display for ReturnInstruction {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{}",
+            "return {}",
             self.value
                 .as_ref()
                 .map(|s| s.to_string())
@@ -216,19 +269,30 @@ impl BaseInstruction for ReturnInstruction {
     fn variables_read(&self) -> HashSet<String> {
         HashSet::from_iter(self.value.iter().flat_map(|src| src.iter()))
     }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        self.value.iter_mut().collect()
+    }
 }
 
 pub struct FunctionInstruction {
     pub label: String,
+    pub params: Vec<Operand>,
 }
 
 impl Display for FunctionInstruction {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "fn {}", self.label)
+        write!(f, "fn {}({})", self.label, self.params.iter().join(", "))
     }
 }
 
-impl BaseInstruction for FunctionInstruction {}
+impl BaseInstruction for FunctionInstruction {
+    fn variables_written(&self) -> HashSet<String> {
+        HashSet::from_iter(self.params.iter().flat_map(|param| param.iter()))
+    }
+    fn operands_written_mut(&mut self) -> Vec<&mut Operand> {
+        self.params.iter_mut().collect()
+    }
+}
 
 pub struct EndFunctionInstruction {
     pub label: String,
@@ -254,6 +318,209 @@ impl Display for ExternInstruction {
 
 impl BaseInstruction for ExternInstruction {}
 
+/// Reserves `size` bytes of stack memory aligned to `align` for the rest of the function, and
+/// assigns their address to `target`. Structs and variables whose address is taken live there.
+pub struct AllocaInstruction {
+    pub target: Operand,
+    pub size: usize,
+    pub align: usize,
+}
+
+impl Display for AllocaInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = alloca {}, {}", self.target, self.size, self.align)
+    }
+}
+
+impl BaseInstruction for AllocaInstruction {
+    fn variables_written(&self) -> HashSet<String> {
+        HashSet::from_iter(self.target.iter())
+    }
+    fn operands_written_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.target]
+    }
+}
+
+/// Computes the address `base + index * scale + offset`, of a member or an element.
+pub struct AddressInstruction {
+    pub target: Operand,
+    pub base: Operand,
+    pub index: Option<Operand>,
+    pub scale: usize,
+    pub offset: usize,
+}
+
+impl Display for AddressInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = &[{}", self.target, self.base)?;
+        if let Some(index) = &self.index {
+            write!(f, " + {index} * {}", self.scale)?;
+        }
+        write!(f, " + {}]", self.offset)
+    }
+}
+
+impl BaseInstruction for AddressInstruction {
+    fn variables_read(&self) -> HashSet<String> {
+        HashSet::from_iter(
+            self.base
+                .iter()
+                .chain(self.index.iter().flat_map(|index| index.iter())),
+        )
+    }
+    fn variables_written(&self) -> HashSet<String> {
+        HashSet::from_iter(self.target.iter())
+    }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        [&mut self.base]
+            .into_iter()
+            .chain(self.index.iter_mut())
+            .collect()
+    }
+    fn operands_written_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.target]
+    }
+}
+
+/// Reads `size` bytes at `address + offset` into `target`.
+pub struct LoadInstruction {
+    pub target: Operand,
+    pub address: Operand,
+    pub offset: usize,
+    pub size: usize,
+}
+
+impl Display for LoadInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} = load {} [{} + {}]",
+            self.target, self.size, self.address, self.offset
+        )
+    }
+}
+
+impl BaseInstruction for LoadInstruction {
+    fn variables_read(&self) -> HashSet<String> {
+        HashSet::from_iter(self.address.iter())
+    }
+    fn variables_written(&self) -> HashSet<String> {
+        HashSet::from_iter(self.target.iter())
+    }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.address]
+    }
+    fn operands_written_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.target]
+    }
+}
+
+/// Writes the low `size` bytes of `value` to `address + offset`.
+pub struct StoreInstruction {
+    pub address: Operand,
+    pub offset: usize,
+    pub value: Operand,
+    pub size: usize,
+}
+
+impl Display for StoreInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "store {} [{} + {}] = {}",
+            self.size, self.address, self.offset, self.value
+        )
+    }
+}
+
+impl BaseInstruction for StoreInstruction {
+    fn variables_read(&self) -> HashSet<String> {
+        HashSet::from_iter(self.address.iter().chain(self.value.iter()))
+    }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.address, &mut self.value]
+    }
+}
+
+/// Copies `size` bytes from address `src` to address `dst`, which is how structs are assigned.
+pub struct MemoryCopyInstruction {
+    pub dst: Operand,
+    pub src: Operand,
+    pub size: usize,
+}
+
+impl Display for MemoryCopyInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "memcpy {} [{}] = [{}]", self.size, self.dst, self.src)
+    }
+}
+
+impl BaseInstruction for MemoryCopyInstruction {
+    fn variables_read(&self) -> HashSet<String> {
+        HashSet::from_iter(self.dst.iter().chain(self.src.iter()))
+    }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.dst, &mut self.src]
+    }
+}
+
+/// Stops the program unless `index` is below `length`, guarding an element access.
+pub struct BoundsCheckInstruction {
+    pub index: Operand,
+    pub length: Operand,
+}
+
+impl Display for BoundsCheckInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "check {} < {}", self.index, self.length)
+    }
+}
+
+impl BaseInstruction for BoundsCheckInstruction {
+    fn variables_read(&self) -> HashSet<String> {
+        HashSet::from_iter(self.index.iter().chain(self.length.iter()))
+    }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.index, &mut self.length]
+    }
+}
+
+/// Merges the values of a variable coming from different predecessors at the start of a block.
+/// Only control flow graphs in SSA form contain phis.
+pub struct PhiInstruction {
+    pub target: Operand,
+    pub sources: Vec<(NodeIndex, Operand)>,
+}
+
+impl Display for PhiInstruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} = phi({})",
+            self.target,
+            self.sources
+                .iter()
+                .map(|(block, source)| format!("{source}: b{}", block.index()))
+                .join(", ")
+        )
+    }
+}
+
+impl BaseInstruction for PhiInstruction {
+    fn variables_read(&self) -> HashSet<String> {
+        HashSet::from_iter(self.sources.iter().flat_map(|(_, source)| source.iter()))
+    }
+    fn variables_written(&self) -> HashSet<String> {
+        HashSet::from_iter(self.target.iter())
+    }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        self.sources.iter_mut().map(|(_, source)| source).collect()
+    }
+    fn operands_written_mut(&mut self) -> Vec<&mut Operand> {
+        vec![&mut self.target]
+    }
+}
+
 #[enum_dispatch(BaseInstruction)]
 pub enum Instruction {
     Assignment(AssignmentInstruction),
@@ -266,6 +533,13 @@ pub enum Instruction {
     Function(FunctionInstruction),
     EndFunction(EndFunctionInstruction),
     Extern(ExternInstruction),
+    Phi(PhiInstruction),
+    Alloca(AllocaInstruction),
+    Address(AddressInstruction),
+    Load(LoadInstruction),
+    Store(StoreInstruction),
+    MemoryCopy(MemoryCopyInstruction),
+    BoundsCheck(BoundsCheckInstruction),
 }
 
 impl Display for Instruction {
@@ -281,6 +555,13 @@ impl Display for Instruction {
             Instruction::Function(function) => function.fmt(f),
             Instruction::EndFunction(end_function) => end_function.fmt(f),
             Instruction::Extern(external) => external.fmt(f),
+            Instruction::Phi(phi) => phi.fmt(f),
+            Instruction::Alloca(alloca) => alloca.fmt(f),
+            Instruction::Address(address) => address.fmt(f),
+            Instruction::Load(load) => load.fmt(f),
+            Instruction::Store(store) => store.fmt(f),
+            Instruction::MemoryCopy(copy) => copy.fmt(f),
+            Instruction::BoundsCheck(check) => check.fmt(f),
         }
     }
 }
@@ -293,6 +574,12 @@ pub trait BaseInstruction {
     fn variables_written(&self) -> HashSet<String> {
         Default::default()
     }
+    fn operands_read_mut(&mut self) -> Vec<&mut Operand> {
+        Vec::new()
+    }
+    fn operands_written_mut(&mut self) -> Vec<&mut Operand> {
+        Vec::new()
+    }
 }
 
 #[derive(Default)]
@@ -314,6 +601,8 @@ impl Display for LiveSets {
 pub struct InstructionInstance {
     pub instruction: Instruction,
     pub live: LiveSets,
+    /// Statement or expression the instruction was lowered from.
+    pub span: Option<SourceSpan>,
 }
 
 impl Display for InstructionInstance {
@@ -393,9 +682,19 @@ impl FunctionRange {
     }
 }
 
+pub struct GlobalVariable {
+    pub name: String,
+    /// Literal the variable starts out with, or a placeholder for a non-constant initializer.
+    pub init: Option<Operand>,
+    /// Memory of a global whose type lives in memory or whose address is taken. Its operand then
+    /// stands for the address of that memory, like the variables of structs do.
+    pub memory: Option<SizeAlign>,
+}
+
 #[derive(Deref, DerefMut)]
 pub struct ControlFlowGraph {
     pub functions: Vec<FunctionRange>,
+    pub globals: Vec<GlobalVariable>,
     #[target]
     pub graph: Graph<InstructionBlock, ()>,
 }
@@ -410,6 +709,7 @@ impl ControlFlowGraph {
     pub fn new() -> Self {
         Self {
             functions: Default::default(),
+            globals: Default::default(),
             graph: Default::default(),
         }
     }
@@ -444,9 +744,67 @@ impl ControlFlowGraph {
     pub fn function_nodes(&self, func: &FunctionRange) -> impl Iterator<Item = NodeIndex> {
         (func.start.index()..=func.end.index()).map(NodeIndex::new)
     }
+
+    /// Labels `node` jumps to, and whether it falls through to the next block. Instructions after
+    /// an unconditional jump or a return are never reached.
+    pub fn exits(&self, node: NodeIndex) -> (Vec<String>, bool) {
+        let mut targets = Vec::new();
+        for inst in &self.graph[node].instructions {
+            match &inst.instruction {
+                Instruction::ConditionalJump(jump) => targets.push(jump.target.to_string()),
+                Instruction::Jump(jump) => {
+                    targets.push(jump.target.to_string());
+                    return (targets, false);
+                }
+                Instruction::Return(_) | Instruction::EndFunction(_) => return (targets, false),
+                _ => {}
+            }
+        }
+        (targets, true)
+    }
+
+    /// Replaces the edges of the graph with the ones its jumps and fallthroughs take, for passes
+    /// that add blocks or change where blocks jump to.
+    pub fn relink(&mut self) {
+        self.graph.clear_edges();
+        let blocks = self
+            .graph
+            .node_indices()
+            .map(|node| (self.graph[node].label.clone(), node))
+            .collect::<HashMap<_, _>>();
+        for function in self.functions.clone() {
+            for node in self.function_nodes(&function) {
+                let (targets, falls_through) = self.exits(node);
+                let mut successors = targets
+                    .iter()
+                    .filter_map(|target| blocks.get(target).copied())
+                    .collect::<Vec<_>>();
+                if falls_through && node != function.end {
+                    successors.push(NodeIndex::new(node.index() + 1));
+                }
+                for successor in successors.into_iter().unique() {
+                    self.link(node, successor);
+                }
+            }
+        }
+    }
+
+    /// Successors of `node`, each listed once.
+    pub fn successors(&self, node: NodeIndex) -> Vec<NodeIndex> {
+        self.graph.neighbors(node).unique().sorted().collect()
+    }
+
+    /// Predecessors of `node`, each listed once.
+    pub fn predecessors(&self, node: NodeIndex) -> Vec<NodeIndex> {
+        self.graph
+            .neighbors_directed(node, Direction::Incoming)
+            .unique()
+            .sorted()
+            .collect()
+    }
 }
 
-#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct CodeLocation {
     pub block: NodeIndex,
     pub instruction: usize,
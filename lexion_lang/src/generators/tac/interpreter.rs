@@ -0,0 +1,1040 @@
+use crate::ast::types::{TypeCollection, TypeKind};
+use crate::ast::Lit;
+use crate::diagnostic::{DiagnosticConsumer, LexionDiagnosticError};
+use crate::generators::tac::instructions::{
+    AssignmentInstruction, CodeLocation, ConditionalJumpInstruction, ControlFlowGraph,
+    FunctionCallInstruction, GlobalVariable, Instruction, Operand,
+};
+use crate::generators::x86::{unescape_string_literal, Bitness};
+use crate::operators;
+use crate::pipeline::PipelineStage;
+use crate::symbol_table::SymbolTableGraph;
+use generational_arena::Index;
+use lexion_lib::miette::{NamedSource, SourceSpan};
+use lexion_lib::petgraph::graph::NodeIndex;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Calls nested deeper than this are reported instead of exhausting memory.
+const MAX_CALL_DEPTH: usize = 100_000;
+
+/// Memory of `alloca` instructions starts at this address, apart from the memory of `malloc` and
+/// string literals, so that it can be reused once the frame that allocated it returns.
+const STACK_BASE: usize = 1 << 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    /// Integers, booleans and addresses into the interpreter's memory.
+    Integer(i64),
+    Float(f64),
+}
+
+impl Value {
+    pub fn as_integer(&self) -> i64 {
+        match self {
+            Value::Integer(value) => *value,
+            Value::Float(value) => *value as i64,
+        }
+    }
+
+    pub fn as_float(&self) -> f64 {
+        match self {
+            Value::Integer(value) => *value as f64,
+            Value::Float(value) => *value,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(value) => write!(f, "{value}"),
+            Value::Float(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+enum RuntimeError {
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("division overflow")]
+    DivisionOverflow,
+    #[error("call to undefined function '{0}'")]
+    UndefinedFunction(String),
+    #[error("invalid memory access at address {0}")]
+    InvalidAddress(i64),
+    #[error("index {0} out of bounds for length {1}")]
+    IndexOutOfBounds(i64, i64),
+    #[error("stack overflow")]
+    StackOverflow,
+    #[error("failed to write program output: {0}")]
+    Output(#[from] std::io::Error),
+    #[error("{0} is not supported by the interpreter")]
+    Unsupported(String),
+}
+
+type RuntimeResult<T> = Result<T, RuntimeError>;
+
+enum Step {
+    Next,
+    Jump(NodeIndex),
+    Call(NodeIndex, Option<Operand>),
+    Return(Option<Value>),
+}
+
+/// Global variables and memory, which outlive a single run so that later runs can build on
+/// them. Address zero stays null.
+#[derive(Default)]
+pub struct InterpreterState {
+    globals: HashMap<String, Value>,
+    /// Size of the memory of the globals that live in memory, whose value is its address.
+    global_memory: HashMap<String, usize>,
+    memory: Vec<u8>,
+    /// Memory of `alloca` instructions. Like a native stack, the memory of a frame that returned
+    /// stays readable until it is allocated again, which lets callers copy the structs returned
+    /// to them.
+    stack: Vec<u8>,
+    /// End of the memory of the frames that are still running.
+    stack_top: usize,
+    /// Addresses of the string literals already placed in memory.
+    strings: HashMap<String, i64>,
+}
+
+struct Frame {
+    variables: HashMap<String, Value>,
+    types: HashMap<String, Index>,
+    /// Arguments of the call that created the frame, first argument first.
+    args: Vec<Value>,
+    /// Where the caller continues and which of its operands receives the return value.
+    return_to: Option<(CodeLocation, Option<Operand>)>,
+    /// Top of the stack when the frame was entered, which it is reset to on return.
+    stack_top: usize,
+    /// Memory of each `alloca` instruction of the function, reserved on entry like the slots of a
+    /// native stack frame, so that running one again reuses its memory.
+    allocas: HashMap<CodeLocation, i64>,
+}
+
+/// Executes a [`ControlFlowGraph`] directly, with `printf`, `puts`, `putchar`, `malloc` and
+/// `free` standing in for the C library. Returns the value the entry function returns.
+/// Globals that `state` already holds keep their value instead of being initialized again.
+pub struct TacInterpreter<'a> {
+    src: NamedSource<Arc<String>>,
+    cfg: &'a ControlFlowGraph,
+    types: &'a TypeCollection,
+    symbols: &'a SymbolTableGraph,
+    stdout: &'a mut dyn Write,
+    blocks: HashMap<&'a str, NodeIndex>,
+    functions: HashSet<&'a str>,
+    state: &'a mut InterpreterState,
+    frames: Vec<Frame>,
+    args: Vec<Value>,
+    /// Byte widths of the pending `args`, which `printf` reads unsigned conversions at.
+    arg_sizes: Vec<usize>,
+}
+
+impl<'a> PipelineStage for TacInterpreter<'a> {
+    type Input = (
+        NamedSource<Arc<String>>,
+        &'a ControlFlowGraph,
+        &'a TypeCollection,
+        &'a SymbolTableGraph,
+        &'a mut InterpreterState,
+        &'a mut dyn Write,
+    );
+    type Options = &'a str;
+    type Output = i64;
+
+    fn new((src, cfg, types, symbols, state, stdout): Self::Input) -> Self {
+        Self {
+            src,
+            cfg,
+            types,
+            symbols,
+            stdout,
+            blocks: Default::default(),
+            functions: Default::default(),
+            state,
+            frames: Default::default(),
+            args: Default::default(),
+            arg_sizes: Default::default(),
+        }
+    }
+
+    fn exec(mut self, diag: &mut dyn DiagnosticConsumer, entry: Self::Options) -> Option<i64> {
+        let cfg = self.cfg;
+        for node in cfg.node_indices() {
+            let block = &cfg[node];
+            self.blocks.insert(block.label.as_str(), node);
+            if let Some(Instruction::Function(function)) =
+                block.instructions.first().map(|i| &i.instruction)
+            {
+                self.functions.insert(function.label.as_str());
+            }
+        }
+        for global in &cfg.globals {
+            if let Err(err) = self.global(global) {
+                self.error(diag, None, err);
+                return None;
+            }
+        }
+
+        if !self.functions.contains(entry) {
+            self.error(
+                diag,
+                None,
+                RuntimeError::UndefinedFunction(entry.to_string()),
+            );
+            return None;
+        }
+        // Frames of a run that failed are never returned from, so nothing is kept between runs.
+        self.state.stack = Vec::new();
+        self.state.stack_top = 0;
+        let _ = self.enter(entry, None);
+        let mut location = CodeLocation::new(self.blocks[entry], 0);
+        loop {
+            let Some(block) = cfg.node_weight(location.block) else {
+                return Some(0);
+            };
+            let Some(instance) = block.instructions.get(location.instruction) else {
+                // Blocks are laid out in order, so the end of one falls through to the next.
+                location = CodeLocation::new(NodeIndex::new(location.block.index() + 1), 0);
+                continue;
+            };
+            let step = match self.step(location, &instance.instruction) {
+                Ok(step) => step,
+                Err(err) => {
+                    let _ = self.stdout.flush();
+                    self.error(diag, instance.span, err);
+                    return None;
+                }
+            };
+            match step {
+                Step::Next => location.instruction += 1,
+                Step::Jump(block) => location = CodeLocation::new(block, 0),
+                Step::Call(block, return_target) => {
+                    let next = CodeLocation::new(location.block, location.instruction + 1);
+                    let function = cfg[block].label.as_str();
+                    if let Err(err) = self.enter(function, Some((next, return_target))) {
+                        self.error(diag, instance.span, err);
+                        return None;
+                    }
+                    location = CodeLocation::new(block, 0);
+                }
+                Step::Return(value) => {
+                    let frame = self.frames.pop().expect("no frame to return from");
+                    self.state.stack_top = frame.stack_top;
+                    let Some((next, return_target)) = frame.return_to else {
+                        let _ = self.stdout.flush();
+                        return Some(value.map(|v| v.as_integer()).unwrap_or(0));
+                    };
+                    if let (Some(target), Some(value)) = (return_target, value) {
+                        self.assign(&target, value);
+                    }
+                    location = next;
+                }
+            }
+        }
+    }
+}
+
+impl<'a> TacInterpreter<'a> {
+    fn error(
+        &self,
+        diag: &mut dyn DiagnosticConsumer,
+        span: Option<SourceSpan>,
+        err: RuntimeError,
+    ) {
+        diag.error(LexionDiagnosticError {
+            src: self.src.clone(),
+            span: span.unwrap_or(SourceSpan::from(0)),
+            message: err.to_string(),
+        });
+    }
+
+    fn enter(
+        &mut self,
+        function: &str,
+        return_to: Option<(CodeLocation, Option<Operand>)>,
+    ) -> RuntimeResult<()> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::StackOverflow);
+        }
+        // `param` instructions come last argument first.
+        let mut args = std::mem::take(&mut self.args);
+        args.reverse();
+        self.arg_sizes.clear();
+        let stack_top = self.state.stack_top;
+        let mut allocas = HashMap::new();
+        let start = self.blocks[function];
+        if let Some(func) = self.cfg.functions.iter().find(|func| func.start == start) {
+            for block in self.cfg.function_nodes(func) {
+                for (index, inst) in self.cfg[block].instructions.iter().enumerate() {
+                    if let Instruction::Alloca(alloca) = &inst.instruction {
+                        let address = self.state.alloca(alloca.size as i64);
+                        allocas.insert(CodeLocation::new(block, index), address);
+                    }
+                }
+            }
+        }
+        self.frames.push(Frame {
+            variables: Default::default(),
+            types: self.symbols.variable_types(function),
+            args,
+            return_to,
+            stack_top,
+            allocas,
+        });
+        Ok(())
+    }
+
+    /// Initializes `global`, unless an earlier run already did. A global that moved to memory,
+    /// e.g. because a later input takes its address, keeps its value there.
+    fn global(&mut self, global: &GlobalVariable) -> RuntimeResult<()> {
+        let size = global.memory.map(|memory| memory.size);
+        let current = self.state.globals.get(&global.name).copied();
+        let had_memory = self.state.global_memory.get(&global.name).copied();
+        if current.is_some() && had_memory == size {
+            return Ok(());
+        }
+        let value = match (&global.init, current) {
+            (Some(init), _) => self.value(init)?,
+            (None, Some(value)) if had_memory.is_none() => value,
+            _ => Value::Integer(0),
+        };
+        let Some(size) = size else {
+            self.state.global_memory.remove(&global.name);
+            self.state.globals.insert(global.name.clone(), value);
+            return Ok(());
+        };
+        let address = self.state.allocate(size as i64);
+        let bits = match value {
+            Value::Float(value) => (value as f32).to_bits() as i64,
+            Value::Integer(value) => value,
+        };
+        self.state
+            .write(address, &bits.to_le_bytes()[..size.min(8)])?;
+        self.state.global_memory.insert(global.name.clone(), size);
+        self.state
+            .globals
+            .insert(global.name.clone(), Value::Integer(address));
+        Ok(())
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("no active frame")
+    }
+
+    fn step(&mut self, location: CodeLocation, instruction: &Instruction) -> RuntimeResult<Step> {
+        match instruction {
+            Instruction::Function(function) => {
+                let frame = self.frame();
+                let args = std::mem::take(&mut frame.args);
+                for (param, value) in function.params.iter().zip(args) {
+                    self.assign(param, value);
+                }
+            }
+            Instruction::EndFunction(_) => return Ok(Step::Return(None)),
+            Instruction::Extern(_) => {}
+            Instruction::Phi(_) => {
+                return Err(RuntimeError::Unsupported(String::from(
+                    "phi instructions outside of SSA form",
+                )))
+            }
+            Instruction::Alloca(alloca) => {
+                let address = self.frame().allocas[&location];
+                self.state.write(address, &vec![0; alloca.size])?;
+                self.assign(&alloca.target, Value::Integer(address));
+            }
+            Instruction::Address(address) => {
+                let base = self.value(&address.base)?.as_integer();
+                let index = match &address.index {
+                    Some(index) => self.value(index)?.as_integer(),
+                    None => 0,
+                };
+                let value = base + index * address.scale as i64 + address.offset as i64;
+                self.assign(&address.target, Value::Integer(value));
+            }
+            Instruction::Load(load) => {
+                let address = self.value(&load.address)?.as_integer() + load.offset as i64;
+                let mut buffer = [0; 8];
+                buffer[..load.size].copy_from_slice(self.state.read(address, load.size)?);
+                let bits = i64::from_le_bytes(buffer);
+                let value = match self.variable_type(&load.target.to_string()) {
+                    Some(ty) if matches!(self.types.kind(ty), TypeKind::Float) => {
+                        Value::Float(f32::from_bits(bits as u32) as f64)
+                    }
+                    _ => Value::Integer(bits),
+                };
+                self.assign(&load.target, value);
+            }
+            Instruction::Store(store) => {
+                let address = self.value(&store.address)?.as_integer() + store.offset as i64;
+                let bits = match self.value(&store.value)? {
+                    Value::Float(value) => (value as f32).to_bits() as i64,
+                    Value::Integer(value) => value,
+                };
+                self.state
+                    .write(address, &bits.to_le_bytes()[..store.size])?;
+            }
+            Instruction::MemoryCopy(copy) => {
+                let dst = self.value(&copy.dst)?.as_integer();
+                let src = self.value(&copy.src)?.as_integer();
+                let bytes = self.state.read(src, copy.size)?.to_vec();
+                self.state.write(dst, &bytes)?;
+            }
+            Instruction::BoundsCheck(check) => {
+                let index = self.value(&check.index)?.as_integer();
+                let length = self.value(&check.length)?.as_integer();
+                if !(0..length).contains(&index) {
+                    return Err(RuntimeError::IndexOutOfBounds(index, length));
+                }
+            }
+            Instruction::Assignment(assignment) => {
+                let value = self.assignment(assignment)?;
+                self.assign(&assignment.target, value);
+            }
+            Instruction::Copy(copy) => {
+                let value = self.value(&copy.src)?;
+                self.assign(&copy.dst, value);
+            }
+            Instruction::ConditionalJump(jump) => return self.conditional_jump(jump),
+            Instruction::Jump(jump) => return Ok(Step::Jump(self.block(&jump.target)?)),
+            Instruction::Parameter(param) => {
+                let value = self.value(&param.param)?;
+                self.args.push(value);
+                // Untyped operands are literals, which are `i32`.
+                self.arg_sizes.push(self.size(&param.param).unwrap_or(4));
+            }
+            Instruction::FunctionCall(call) => return self.call(call),
+            Instruction::Return(ret) => {
+                let value = ret.value.as_ref().map(|v| self.value(v)).transpose()?;
+                return Ok(Step::Return(value));
+            }
+        }
+        Ok(Step::Next)
+    }
+
+    fn block(&self, target: &Operand) -> RuntimeResult<NodeIndex> {
+        match target {
+            Operand::Label(label) if self.blocks.contains_key(label.as_str()) => {
+                Ok(self.blocks[label.as_str()])
+            }
+            target => Err(RuntimeError::Unsupported(format!("jump to '{target}'"))),
+        }
+    }
+
+    fn conditional_jump(&mut self, jump: &ConditionalJumpInstruction) -> RuntimeResult<Step> {
+        let right = self.value(&jump.right)?;
+        let taken = match &jump.left {
+            Some(left) => {
+                let size = self.size(left).unwrap_or(8);
+                let signed = self.is_signed(left, &jump.right);
+                let left = self.value(left)?;
+                binary(jump.operator, left, right, size, signed)?.as_integer() != 0
+            }
+            None => unary(jump.operator, right)?.as_integer() != 0,
+        };
+        if taken {
+            Ok(Step::Jump(self.block(&jump.target)?))
+        } else {
+            Ok(Step::Next)
+        }
+    }
+
+    fn assignment(&mut self, assignment: &AssignmentInstruction) -> RuntimeResult<Value> {
+        let right = self.value(&assignment.right)?;
+        match &assignment.left {
+            Some(left) => {
+                let size = self.size(&assignment.target).unwrap_or(8);
+                let signed = self.is_signed(left, &assignment.right);
+                let left = self.value(left)?;
+                binary(assignment.operator, left, right, size, signed)
+            }
+            None => unary(assignment.operator, right),
+        }
+    }
+
+    fn call(&mut self, call: &FunctionCallInstruction) -> RuntimeResult<Step> {
+        if self.functions.contains(call.function.as_str()) {
+            let block = self.blocks[call.function.as_str()];
+            return Ok(Step::Call(block, call.return_target.clone()));
+        }
+        let mut args = std::mem::take(&mut self.args);
+        args.reverse();
+        let mut sizes = std::mem::take(&mut self.arg_sizes);
+        sizes.reverse();
+        let result = match call.function.as_str() {
+            "printf" => {
+                let format = args.first().copied().unwrap_or(Value::Integer(0));
+                let rest = 1.min(args.len());
+                let output = self.format(format, &args[rest..], &sizes[rest..])?;
+                self.stdout.write_all(&output)?;
+                output.len() as i64
+            }
+            "puts" => {
+                let string = self
+                    .state
+                    .string(args.first().copied().unwrap_or(Value::Integer(0)))?;
+                self.stdout.write_all(&string)?;
+                self.stdout.write_all(b"\n")?;
+                0
+            }
+            "putchar" => {
+                let c = args.first().map(|c| c.as_integer()).unwrap_or(0);
+                self.stdout.write_all(&[c as u8])?;
+                c
+            }
+            "malloc" => self
+                .state
+                .allocate(args.first().map(|n| n.as_integer()).unwrap_or(0)),
+            "free" => 0,
+            function => return Err(RuntimeError::UndefinedFunction(function.to_string())),
+        };
+        if let Some(target) = &call.return_target {
+            self.assign(target, Value::Integer(result));
+        }
+        Ok(Step::Next)
+    }
+}
+
+impl<'a> TacInterpreter<'a> {
+    fn value(&mut self, operand: &Operand) -> RuntimeResult<Value> {
+        Ok(match operand {
+            Operand::Variable(_) | Operand::Temporary(_) | Operand::Value(_) => {
+                let name = operand.to_string();
+                self.frame()
+                    .variables
+                    .get(&name)
+                    .copied()
+                    .unwrap_or(Value::Integer(0))
+            }
+            Operand::Global(name) => self
+                .state
+                .globals
+                .get(name)
+                .copied()
+                .unwrap_or(Value::Integer(0)),
+            Operand::Literal(Lit::Integer(value)) => Value::Integer(*value as i64),
+            Operand::Literal(Lit::Boolean(value)) => Value::Integer(*value as i64),
+            Operand::Literal(Lit::Float(value)) => Value::Float(*value),
+            Operand::Literal(Lit::String(value)) => {
+                Value::Integer(self.state.string_literal(value))
+            }
+            Operand::Label(label) => {
+                return Err(RuntimeError::Unsupported(format!(
+                    "taking the address of '{label}'"
+                )))
+            }
+            Operand::Placeholder => {
+                return Err(RuntimeError::Unsupported(String::from(
+                    "an expression that is not lowered to TAC",
+                )))
+            }
+        })
+    }
+
+    fn assign(&mut self, target: &Operand, value: Value) {
+        let name = match target {
+            Operand::Variable(_)
+            | Operand::Temporary(_)
+            | Operand::Value(_)
+            | Operand::Global(_) => target.to_string(),
+            _ => return,
+        };
+        let value = self.convert(&name, value);
+        if target.is_global() {
+            self.state.globals.insert(name, value);
+        } else {
+            self.frame().variables.insert(name, value);
+        }
+    }
+
+    fn variable_type(&self, name: &str) -> Option<Index> {
+        self.frames.last()?.types.get(name).copied()
+    }
+
+    fn size(&self, operand: &Operand) -> Option<usize> {
+        let ty = self.variable_type(&operand.to_string())?;
+        Some(self.types.size_align(ty, Bitness::_64).size.clamp(1, 8))
+    }
+
+    /// Whether an operation on `left` and `right` is signed, decided by the type of the first
+    /// typed operand like the x86-64 backend does.
+    fn is_signed(&self, left: &Operand, right: &Operand) -> bool {
+        self.variable_type(&left.to_string())
+            .or_else(|| self.variable_type(&right.to_string()))
+            .map(|ty| self.types.is_signed(ty))
+            .unwrap_or(true)
+    }
+
+    /// Wraps `value` to the width and signedness of the variable it is stored in.
+    fn convert(&self, name: &str, value: Value) -> Value {
+        match self.variable_type(name) {
+            Some(ty) => convert(self.types, ty, value),
+            None => value,
+        }
+    }
+}
+
+impl InterpreterState {
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.globals.get(name).copied()
+    }
+
+    /// Renders `value` as a literal of type `ty`, reading strings from memory.
+    pub fn format_value(&self, value: Value, ty: Index, types: &TypeCollection) -> String {
+        if types.eq(ty, types.bool()) {
+            (value.as_integer() != 0).to_string()
+        } else if types.eq(ty, types.char()) {
+            format!("{:?}", value.as_integer() as u8 as char)
+        } else if types.eq(ty, types.f32()) {
+            format!("{:?}", value.as_float() as f32)
+        } else if types
+            .dereference(ty)
+            .is_some_and(|to| types.eq(to, types.str()))
+        {
+            match self.string(value) {
+                Ok(bytes) => format!("{:?}", String::from_utf8_lossy(&bytes)),
+                Err(_) => format!("0x{:x}", value.as_integer()),
+            }
+        } else if types.dereference(ty).is_some() {
+            format!("0x{:x}", value.as_integer())
+        } else {
+            value.to_string()
+        }
+    }
+
+    fn allocate(&mut self, size: i64) -> i64 {
+        let address = self.memory.len().max(8).next_multiple_of(8);
+        self.memory.resize(address + size.max(0) as usize, 0);
+        address as i64
+    }
+
+    fn alloca(&mut self, size: i64) -> i64 {
+        let offset = self.stack_top.next_multiple_of(8);
+        self.stack_top = offset + size.max(0) as usize;
+        if self.stack.len() < self.stack_top {
+            self.stack.resize(self.stack_top, 0);
+        }
+        (STACK_BASE + offset) as i64
+    }
+
+    /// Memory that `address` points into, and the offset of the address in it.
+    fn region(&self, address: i64) -> RuntimeResult<(&[u8], usize)> {
+        match usize::try_from(address).ok().filter(|start| *start != 0) {
+            Some(start) if start >= STACK_BASE => Ok((&self.stack, start - STACK_BASE)),
+            Some(start) => Ok((&self.memory, start)),
+            None => Err(RuntimeError::InvalidAddress(address)),
+        }
+    }
+
+    fn read(&self, address: i64, size: usize) -> RuntimeResult<&[u8]> {
+        let (memory, start) = self.region(address)?;
+        memory
+            .get(start..start + size)
+            .ok_or(RuntimeError::InvalidAddress(address))
+    }
+
+    fn write(&mut self, address: i64, bytes: &[u8]) -> RuntimeResult<()> {
+        let (memory, start) = match usize::try_from(address).ok().filter(|start| *start != 0) {
+            Some(start) if start >= STACK_BASE => (&mut self.stack, start - STACK_BASE),
+            Some(start) => (&mut self.memory, start),
+            None => return Err(RuntimeError::InvalidAddress(address)),
+        };
+        memory
+            .get_mut(start..start + bytes.len())
+            .ok_or(RuntimeError::InvalidAddress(address))?
+            .copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn string_literal(&mut self, literal: &str) -> i64 {
+        if let Some(address) = self.strings.get(literal) {
+            return *address;
+        }
+        let bytes = unescape_string_literal(literal);
+        let address = self.allocate(bytes.len() as i64);
+        self.memory[address as usize..address as usize + bytes.len()].copy_from_slice(&bytes);
+        self.strings.insert(literal.to_string(), address);
+        address
+    }
+
+    /// Bytes of the NUL-terminated string at `address`, without the terminator.
+    fn string(&self, address: Value) -> RuntimeResult<Vec<u8>> {
+        let address = address.as_integer();
+        let (memory, start) = self.region(address)?;
+        let bytes = memory
+            .get(start..)
+            .ok_or(RuntimeError::InvalidAddress(address))?;
+        let length = bytes
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or(RuntimeError::InvalidAddress(address))?;
+        Ok(bytes[..length].to_vec())
+    }
+}
+
+impl<'a> TacInterpreter<'a> {
+    /// Expands a `printf` format string.
+    fn format(&self, format: Value, args: &[Value], sizes: &[usize]) -> RuntimeResult<Vec<u8>> {
+        let format = self.state.string(format)?;
+        let mut args = args.iter().copied().zip(sizes.iter().copied());
+        let mut output = Vec::new();
+        let mut bytes = format.iter().copied().peekable();
+        while let Some(byte) = bytes.next() {
+            if byte != b'%' {
+                output.push(byte);
+                continue;
+            }
+            let mut spec = FormatSpec::default();
+            while let Some(flag) = bytes.next_if(|b| b"-+ 0#".contains(b)) {
+                match flag {
+                    b'-' => spec.left = true,
+                    b'+' => spec.plus = true,
+                    b' ' => spec.space = true,
+                    b'0' => spec.zero = true,
+                    _ => spec.alternate = true,
+                }
+            }
+            spec.width = number(&mut bytes, &mut args);
+            if bytes.next_if_eq(&b'.').is_some() {
+                spec.precision = Some(number(&mut bytes, &mut args).unwrap_or(0));
+            }
+            while bytes.next_if(|b| b"hlLqjzt".contains(b)).is_some() {}
+            let Some(conversion) = bytes.next() else {
+                output.push(b'%');
+                break;
+            };
+            let mut arg = || args.next().unwrap_or((Value::Integer(0), 4));
+            let (sign, body) = match conversion {
+                b'%' => {
+                    output.push(b'%');
+                    continue;
+                }
+                b'd' | b'i' => {
+                    let value = arg().0.as_integer();
+                    (
+                        sign(value < 0, &spec),
+                        spec.digits(value.unsigned_abs().to_string()),
+                    )
+                }
+                b'u' | b'x' | b'X' | b'o' => {
+                    let (value, size) = arg();
+                    let value = unsigned(value.as_integer(), size);
+                    let digits = match conversion {
+                        b'u' => value.to_string(),
+                        b'x' => format!("{value:x}"),
+                        b'X' => format!("{value:X}"),
+                        _ => format!("{value:o}"),
+                    };
+                    (String::new(), spec.digits(digits))
+                }
+                b'p' => (String::new(), format!("0x{:x}", arg().0.as_integer())),
+                b'c' => (
+                    String::new(),
+                    (arg().0.as_integer() as u8 as char).to_string(),
+                ),
+                b's' => {
+                    let mut string =
+                        String::from_utf8_lossy(&self.state.string(arg().0)?).into_owned();
+                    if let Some(precision) = spec.precision {
+                        string = string.chars().take(precision).collect();
+                    }
+                    (String::new(), string)
+                }
+                b'f' | b'F' | b'e' | b'E' | b'g' | b'G' => {
+                    let value = arg().0.as_float();
+                    let body = float(value.abs(), conversion, &spec);
+                    (sign(value.is_sign_negative(), &spec), body)
+                }
+                other => {
+                    return Err(RuntimeError::Unsupported(format!(
+                        "the printf conversion '%{}'",
+                        other as char
+                    )))
+                }
+            };
+            output.extend(spec.pad(sign, body).into_bytes());
+        }
+        Ok(output)
+    }
+}
+
+#[derive(Default)]
+struct FormatSpec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    zero: bool,
+    alternate: bool,
+    width: Option<usize>,
+    precision: Option<usize>,
+}
+
+impl FormatSpec {
+    /// Applies the precision of an integer conversion, its minimum number of digits.
+    fn digits(&self, digits: String) -> String {
+        match self.precision {
+            Some(0) if digits == "0" => String::new(),
+            Some(precision) => format!("{digits:0>precision$}"),
+            None => digits,
+        }
+    }
+
+    fn pad(&self, sign: String, body: String) -> String {
+        let width = self.width.unwrap_or(0);
+        let length = sign.len() + body.len();
+        if length >= width {
+            sign + &body
+        } else if self.left {
+            format!("{sign}{body}{}", " ".repeat(width - length))
+        } else if self.zero && (self.precision.is_none() || body.contains('.')) {
+            format!("{sign}{}{body}", "0".repeat(width - length))
+        } else {
+            format!("{}{sign}{body}", " ".repeat(width - length))
+        }
+    }
+}
+
+fn sign(negative: bool, spec: &FormatSpec) -> String {
+    if negative {
+        String::from("-")
+    } else if spec.plus {
+        String::from("+")
+    } else if spec.space {
+        String::from(" ")
+    } else {
+        String::new()
+    }
+}
+
+/// Reads a width or precision, which is either digits or `*` for the next argument.
+fn number(
+    bytes: &mut std::iter::Peekable<impl Iterator<Item = u8>>,
+    args: &mut impl Iterator<Item = (Value, usize)>,
+) -> Option<usize> {
+    if bytes.next_if_eq(&b'*').is_some() {
+        return args.next().map(|(arg, _)| arg.as_integer().max(0) as usize);
+    }
+    let mut result = None;
+    while let Some(digit) = bytes.next_if(u8::is_ascii_digit) {
+        result = Some(result.unwrap_or(0) * 10 + (digit - b'0') as usize);
+    }
+    result
+}
+
+/// `value` as an unsigned integer of `size` bytes, the way C reads the argument of `%u`.
+fn unsigned(value: i64, size: usize) -> u64 {
+    match size {
+        1 => value as u8 as u64,
+        2 => value as u16 as u64,
+        4 => value as u32 as u64,
+        _ => value as u64,
+    }
+}
+
+/// Formats a non-negative float like C's `%f`, `%e` and `%g`.
+fn float(value: f64, conversion: u8, spec: &FormatSpec) -> String {
+    if !value.is_finite() {
+        let text = if value.is_nan() { "nan" } else { "inf" };
+        return if conversion.is_ascii_uppercase() {
+            text.to_uppercase()
+        } else {
+            text.to_string()
+        };
+    }
+    let precision = spec.precision.unwrap_or(6);
+    let text = match conversion.to_ascii_lowercase() {
+        b'f' => format!("{value:.precision$}"),
+        b'e' => exponential(value, precision),
+        _ => {
+            let precision = precision.max(1);
+            let exponent = if value == 0.0 {
+                0
+            } else {
+                exponential(value, precision - 1)
+                    .split_once('e')
+                    .and_then(|(_, exponent)| exponent.parse::<i32>().ok())
+                    .unwrap_or(0)
+            };
+            let text = if -4 <= exponent && exponent < precision as i32 {
+                let decimals = (precision as i32 - 1 - exponent) as usize;
+                format!("{value:.decimals$}")
+            } else {
+                exponential(value, precision - 1)
+            };
+            if spec.alternate {
+                text
+            } else {
+                strip_zeros(&text)
+            }
+        }
+    };
+    if conversion.is_ascii_uppercase() {
+        text.to_uppercase()
+    } else {
+        text
+    }
+}
+
+/// `value` as `d.ddde±xx`, with at least two exponent digits like C.
+fn exponential(value: f64, precision: usize) -> String {
+    let text = format!("{value:.precision$e}");
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let exponent = exponent.parse::<i32>().unwrap_or(0);
+    let sign = if exponent < 0 { '-' } else { '+' };
+    format!("{mantissa}e{sign}{:02}", exponent.abs())
+}
+
+/// Drops trailing zeros of the fraction, and the point if nothing is left after it.
+fn strip_zeros(text: &str) -> String {
+    let (number, exponent) = match text.find('e') {
+        Some(index) => text.split_at(index),
+        None => (text, ""),
+    };
+    let number = if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    };
+    format!("{number}{exponent}")
+}
+
+/// Wraps `value` to the width and signedness of `ty`.
+pub(crate) fn convert(types: &TypeCollection, ty: Index, value: Value) -> Value {
+    match types.kind(ty) {
+        TypeKind::Float | TypeKind::Double => Value::Float(value.as_float()),
+        TypeKind::Integer => {
+            let value = value.as_integer();
+            let signed = types.is_signed(ty);
+            Value::Integer(match types.size_align(ty, Bitness::_64).size {
+                1 if signed => value as i8 as i64,
+                1 => value as u8 as i64,
+                2 if signed => value as i16 as i64,
+                2 => value as u16 as i64,
+                4 if signed => value as i32 as i64,
+                4 => value as u32 as i64,
+                _ => value,
+            })
+        }
+        _ => value,
+    }
+}
+
+/// Applies `operator` to operands of `size` bytes the way the interpreter does, or returns `None`
+/// if that would fail, as a division by zero does. The operands are untyped literals, so they are
+/// signed; unsigned values are zero-extended and give the same result.
+pub(crate) fn evaluate(
+    operator: &str,
+    left: Option<Value>,
+    right: Value,
+    size: usize,
+) -> Option<Value> {
+    match left {
+        Some(left) => binary(operator, left, right, size, true),
+        None => unary(operator, right),
+    }
+    .ok()
+}
+
+fn unary(operator: &str, right: Value) -> RuntimeResult<Value> {
+    Ok(match (operator, right) {
+        (operators::UNARY_PLUS, value) => value,
+        (operators::UNARY_MINUS, Value::Integer(value)) => Value::Integer(value.wrapping_neg()),
+        (operators::UNARY_MINUS, Value::Float(value)) => Value::Float(-value),
+        (operators::BITWISE_NOT, Value::Integer(value)) => Value::Integer(!value),
+        (operators::LOGICAL_NOT, value) => Value::Integer((value.as_float() == 0.0) as i64),
+        (operator, _) => {
+            return Err(RuntimeError::Unsupported(format!(
+                "unary operator '{operator}'"
+            )))
+        }
+    })
+}
+
+/// Applies a binary operator, treating integers as unsigned unless `signed`. Values are kept
+/// extended to 64 bits from the width of their type, so unsigned ones compare and shift as `u64`.
+///
+/// Integer operands of `size` bytes are shifted and divided as x86 does with registers of at least
+/// 32 bits: shift counts are masked to the register width, and dividing the smallest value by -1
+/// overflows.
+fn binary(
+    operator: &str,
+    left: Value,
+    right: Value,
+    size: usize,
+    signed: bool,
+) -> RuntimeResult<Value> {
+    if let Some(result) = compare(operator, left, right, signed) {
+        return Ok(Value::Integer(result as i64));
+    }
+    if let (Value::Integer(left), Value::Integer(right)) = (left, right) {
+        let bits = if size > 4 { 64 } else { 32 };
+        return Ok(Value::Integer(match operator {
+            operators::PLUS => left.wrapping_add(right),
+            operators::MINUS => left.wrapping_sub(right),
+            operators::MULTIPLY => left.wrapping_mul(right),
+            operators::DIVIDE | operators::REMAINDER if right == 0 => {
+                return Err(RuntimeError::DivisionByZero)
+            }
+            operators::DIVIDE if !signed => (left as u64 / right as u64) as i64,
+            operators::REMAINDER if !signed => (left as u64 % right as u64) as i64,
+            operators::DIVIDE | operators::REMAINDER
+                if right == -1 && left == i64::MIN >> (64 - bits) =>
+            {
+                return Err(RuntimeError::DivisionOverflow)
+            }
+            operators::DIVIDE => left.wrapping_div(right),
+            operators::REMAINDER => left.wrapping_rem(right),
+            operators::BITWISE_AND | operators::LOGICAL_AND => left & right,
+            operators::BITWISE_OR | operators::LOGICAL_OR => left | right,
+            operators::BITWISE_XOR => left ^ right,
+            operators::SHIFT_LEFT => left.wrapping_shl(right as u32 % bits),
+            operators::SHIFT_RIGHT if !signed => {
+                (left as u64).wrapping_shr(right as u32 % bits) as i64
+            }
+            operators::SHIFT_RIGHT => left.wrapping_shr(right as u32 % bits),
+            operator => {
+                return Err(RuntimeError::Unsupported(format!(
+                    "binary operator '{operator}'"
+                )))
+            }
+        }));
+    }
+    let (left, right) = (left.as_float(), right.as_float());
+    Ok(Value::Float(match operator {
+        operators::PLUS => left + right,
+        operators::MINUS => left - right,
+        operators::MULTIPLY => left * right,
+        operators::DIVIDE => left / right,
+        operators::REMAINDER => left % right,
+        operator => {
+            return Err(RuntimeError::Unsupported(format!(
+                "binary operator '{operator}' on floats"
+            )))
+        }
+    }))
+}
+
+fn compare(operator: &str, left: Value, right: Value, signed: bool) -> Option<bool> {
+    let ordering = match (left, right) {
+        (Value::Integer(left), Value::Integer(right)) if !signed => {
+            Some((left as u64).cmp(&(right as u64)))
+        }
+        (Value::Integer(left), Value::Integer(right)) => Some(left.cmp(&right)),
+        (left, right) => left.as_float().partial_cmp(&right.as_float()),
+    };
+    Some(match operator {
+        operators::EQUALS => ordering.is_some_and(|o| o.is_eq()),
+        operators::NOT_EQUALS => !ordering.is_some_and(|o| o.is_eq()),
+        operators::LESS => ordering.is_some_and(|o| o.is_lt()),
+        operators::LESS_EQUALS => ordering.is_some_and(|o| o.is_le()),
+        operators::GREATER => ordering.is_some_and(|o| o.is_gt()),
+        operators::GREATER_EQUALS => ordering.is_some_and(|o| o.is_ge()),
+        _ => return None,
+    })
+}
@@ -0,0 +1,158 @@
+use crate::generators::tac::instructions::{
+    BaseInstruction, CodeLocation, CodeSpan, ControlFlowGraph, FunctionRange, LivenessInterval,
+};
+use lexion_lib::petgraph::Direction;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+impl ControlFlowGraph {
+    /// Computes the live sets of every block and instruction, and the interval each variable
+    /// is live in per function.
+    pub fn liveness_analysis(&mut self) -> HashMap<FunctionRange, Vec<LivenessInterval>> {
+        let mut intervals: HashMap<FunctionRange, Vec<LivenessInterval>> = Default::default();
+        for interval in self.live_sets() {
+            if let Some(func) = self.functions.iter().find(|f| f.contains(interval.span)) {
+                let func_intervals = intervals.entry(*func).or_default();
+                // A variable keeps one location for its whole function, so intervals separated
+                // by a lifetime hole (e.g. a value only used in an else branch) are merged.
+                if let Some(existing) = func_intervals
+                    .iter_mut()
+                    .find(|i| i.variable == interval.variable)
+                {
+                    existing.span.start = existing.span.start.min(interval.span.start);
+                    existing.span.end = existing.span.end.max(interval.span.end);
+                    existing.uses.extend(interval.uses);
+                } else {
+                    func_intervals.push(interval);
+                }
+            }
+        }
+        intervals
+    }
+
+    fn liveness_read_written(&mut self) {
+        for block in self.node_weights_mut() {
+            // Passes change the code between analyses, so nothing carries over from the last one.
+            block.live.input.clear();
+            block.live.output.clear();
+            let mut written_so_far = HashSet::new();
+            let mut block_use = HashSet::new();
+            let mut block_def = HashSet::new();
+            for inst in block.instructions.iter_mut() {
+                inst.live.read = inst.instruction.variables_read();
+                inst.live.written = inst.instruction.variables_written();
+                for var in inst.live.read.iter() {
+                    if !written_so_far.contains(var) {
+                        block_use.insert(var.clone());
+                    }
+                }
+                for var in inst.live.written.iter() {
+                    written_so_far.insert(var.clone());
+                    block_def.insert(var.clone());
+                }
+            }
+            block.live.read = block_use;
+            block.live.written = block_def;
+        }
+    }
+
+    fn liveness_per_instruction(&mut self) {
+        for block in self.node_weights_mut() {
+            let mut output = block.live.output.clone();
+            for inst in block.instructions.iter_mut().rev() {
+                inst.live.output = output;
+                let diff = inst
+                    .live
+                    .output
+                    .difference(&inst.live.written)
+                    .cloned()
+                    .collect();
+                output = inst.live.read.union(&diff).cloned().collect();
+                inst.live.input = output.clone();
+            }
+        }
+    }
+
+    fn live_sets(&mut self) -> Vec<LivenessInterval> {
+        self.liveness_read_written();
+
+        // Functions are not connected to each other, so every block seeds the worklist.
+        let mut worklist = self.node_indices().rev().collect::<VecDeque<_>>();
+        let mut in_worklist = worklist.iter().cloned().collect::<HashSet<_>>();
+
+        while let Some(block_idx) = worklist.pop_front() {
+            in_worklist.remove(&block_idx);
+
+            let old_input = self[block_idx].live.input.clone();
+            let old_output = self[block_idx].live.output.clone();
+
+            let output = self
+                .neighbors(block_idx)
+                .flat_map(|block| self[block].live.input.iter().cloned())
+                .collect::<HashSet<_>>();
+
+            let diff = output
+                .difference(&self[block_idx].live.written)
+                .cloned()
+                .collect();
+            let input = self[block_idx].live.read.union(&diff).cloned().collect();
+
+            if old_input != input || old_output != output {
+                for pred in self.neighbors_directed(block_idx, Direction::Incoming) {
+                    if !in_worklist.contains(&pred) {
+                        worklist.push_back(pred);
+                        in_worklist.insert(pred);
+                    }
+                }
+            }
+
+            self[block_idx].live.input = input;
+            self[block_idx].live.output = output;
+        }
+
+        self.liveness_per_instruction();
+        self.liveness_intervals()
+    }
+
+    pub fn liveness_intervals(&self) -> Vec<LivenessInterval> {
+        let mut result: Vec<LivenessInterval> = Default::default();
+        let mut active_intervals: HashMap<String, LivenessInterval> = HashMap::new();
+        for node in self.node_indices() {
+            // Split edges come after `endfn` and jump back into their function, so variables
+            // live out of them must not run on into the next function's ones of the same name.
+            if self.functions.iter().any(|f| f.start == node) {
+                result.extend(active_intervals.drain().map(|(_, interval)| interval));
+            }
+            let block = &self[node];
+            for (inst_idx, inst) in block.instructions.iter().enumerate() {
+                let loc = CodeLocation::new(node, inst_idx);
+                for live_var in &inst.live.input {
+                    active_intervals
+                        .entry(live_var.clone())
+                        .or_insert_with(|| LivenessInterval {
+                            variable: live_var.clone(),
+                            span: CodeSpan::from_location(loc),
+                            uses: Vec::new(),
+                        });
+                }
+                for read_var in &inst.live.read {
+                    if let Some(interval) = active_intervals.get_mut(read_var) {
+                        interval.uses.push(loc);
+                    }
+                }
+                let (mut still_live, now_dead) = active_intervals
+                    .drain()
+                    .partition::<HashMap<_, _>, _>(|(v, _)| inst.live.output.contains(v));
+                for (_, interval) in still_live.iter_mut() {
+                    interval.span.end = loc;
+                }
+                active_intervals = still_live;
+                for (_, mut interval) in now_dead {
+                    interval.span.end = CodeLocation::new(node, inst_idx + 1);
+                    result.push(interval);
+                }
+            }
+        }
+        result.extend(active_intervals.into_values());
+        result
+    }
+}
@@ -2,3 +2,7 @@
 mod tac;
 pub use self::tac::*;
 pub mod instructions;
+pub mod interpreter;
+mod liveness;
+pub mod opt;
+pub mod ssa;
@@ -0,0 +1,186 @@
+use crate::ast::types::TypeCollection;
+use crate::ast::Lit;
+use crate::generators::tac::instructions::{
+    AssignmentInstruction, ConditionalJumpInstruction, CopyInstruction, Instruction,
+    JumpInstruction, Operand, ValueId,
+};
+use crate::generators::tac::interpreter::{convert, evaluate, Value};
+use crate::generators::tac::opt::{replace_values, Pass};
+use crate::generators::tac::ssa::SsaGraph;
+use crate::generators::x86::Bitness;
+use crate::Dump;
+use generational_arena::Index;
+use std::collections::HashMap;
+
+/// Computes instructions whose operands are all literals, and replaces the values they assign
+/// with the result. Conditional jumps on literals become jumps, or are removed if they are never
+/// taken, and bounds checks of literal indices that are in bounds are removed. Results are wrapped
+/// to the type of the value as the interpreter does, and operations that would fail at run time,
+/// such as a division by zero, are left in place.
+pub struct ConstantPropagation<'a> {
+    types: &'a TypeCollection,
+}
+
+impl<'a> ConstantPropagation<'a> {
+    pub fn new(types: &'a TypeCollection) -> Self {
+        Self { types }
+    }
+
+    /// Literal `value` is stored as in a value of type `ty`.
+    fn literal(&self, value: Value, ty: Index) -> Lit {
+        match convert(self.types, ty, value) {
+            Value::Float(value) => Lit::Float(value),
+            Value::Integer(value) if self.types.eq(ty, self.types.bool()) => {
+                Lit::Boolean(value != 0)
+            }
+            Value::Integer(value) => Lit::Integer(value as isize),
+        }
+    }
+
+    /// Result of `assignment` if its operands are literals.
+    fn fold(&self, ssa: &SsaGraph, assignment: &AssignmentInstruction) -> Option<(ValueId, Lit)> {
+        let target = assignment.target.value()?;
+        let ty = ssa.ty(target)?;
+        let left = match &assignment.left {
+            Some(left) => Some(constant(left)?),
+            None => None,
+        };
+        let size = self.types.size_align(ty, Bitness::_64).size;
+        let value = evaluate(
+            assignment.operator,
+            left,
+            constant(&assignment.right)?,
+            size,
+        )?;
+        Some((target, self.literal(value, ty)))
+    }
+
+    /// Whether `jump` is taken, if its operands are literals.
+    fn taken(jump: &ConditionalJumpInstruction) -> Option<bool> {
+        let left = match &jump.left {
+            Some(left) => Some(constant(left)?),
+            None => None,
+        };
+        // Jumps compare their operands, which does not depend on their size.
+        let value = evaluate(jump.operator, left, constant(&jump.right)?, 8)?;
+        Some(value.as_integer() != 0)
+    }
+}
+
+impl<'a> Pass for ConstantPropagation<'a> {
+    fn dump(&self) -> Dump {
+        Dump::ConstantPropagation
+    }
+
+    fn run(&mut self, ssa: &mut SsaGraph) -> bool {
+        let mut constants: HashMap<ValueId, Lit> = HashMap::new();
+        let mut changed = false;
+        let mut jumps_changed = false;
+        loop {
+            let mut progress = replace_values(ssa, |value| {
+                constants.get(&value).cloned().map(Operand::Literal)
+            });
+            for node in ssa.cfg.node_indices() {
+                let instructions = std::mem::take(&mut ssa.cfg[node].instructions);
+                let mut kept = Vec::with_capacity(instructions.len());
+                for mut inst in instructions {
+                    match &inst.instruction {
+                        Instruction::Assignment(assignment) => {
+                            if let Some((target, lit)) = self.fold(ssa, assignment) {
+                                inst.instruction = Instruction::Copy(CopyInstruction {
+                                    src: Operand::Literal(lit.clone()),
+                                    dst: Operand::Value(target),
+                                });
+                                constants.insert(target, lit);
+                                progress = true;
+                            }
+                        }
+                        Instruction::Copy(CopyInstruction {
+                            src: Operand::Literal(lit),
+                            dst: Operand::Value(target),
+                        }) if !matches!(lit, Lit::String(_)) => {
+                            progress |= constants.insert(*target, lit.clone()).is_none();
+                        }
+                        Instruction::Phi(phi) => {
+                            // A phi choosing between the same literal on every edge, or keeping
+                            // its own value around a loop, is that literal.
+                            let target = phi.target.value();
+                            let mut sources = phi
+                                .sources
+                                .iter()
+                                .map(|(_, source)| source)
+                                .filter(|source| source.value().is_none_or(|v| Some(v) != target));
+                            if let (Some(target), Some(Operand::Literal(first))) =
+                                (target, sources.next())
+                            {
+                                let is_constant = !matches!(first, Lit::String(_))
+                                    && sources.all(|source| match source {
+                                        Operand::Literal(lit) => same(lit, first),
+                                        _ => false,
+                                    });
+                                if is_constant {
+                                    progress |= constants.insert(target, first.clone()).is_none();
+                                }
+                            }
+                        }
+                        Instruction::ConditionalJump(jump) => match Self::taken(jump) {
+                            Some(true) => {
+                                inst.instruction = Instruction::Jump(JumpInstruction {
+                                    target: jump.target.clone(),
+                                });
+                                progress = true;
+                                jumps_changed = true;
+                            }
+                            Some(false) => {
+                                progress = true;
+                                jumps_changed = true;
+                                continue;
+                            }
+                            None => {}
+                        },
+                        Instruction::BoundsCheck(check) => {
+                            if let (Some(index), Some(length)) =
+                                (constant(&check.index), constant(&check.length))
+                            {
+                                if (0..length.as_integer()).contains(&index.as_integer()) {
+                                    progress = true;
+                                    continue;
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                    kept.push(inst);
+                }
+                ssa.cfg[node].instructions = kept;
+            }
+            if !progress {
+                break;
+            }
+            changed = true;
+        }
+        if jumps_changed {
+            ssa.relink();
+        }
+        changed
+    }
+}
+
+/// Value of `operand` if it is a literal the interpreter computes with directly.
+fn constant(operand: &Operand) -> Option<Value> {
+    match operand {
+        Operand::Literal(Lit::Integer(value)) => Some(Value::Integer(*value as i64)),
+        Operand::Literal(Lit::Boolean(value)) => Some(Value::Integer(*value as i64)),
+        Operand::Literal(Lit::Float(value)) => Some(Value::Float(*value)),
+        _ => None,
+    }
+}
+
+fn same(a: &Lit, b: &Lit) -> bool {
+    match (a, b) {
+        (Lit::Integer(a), Lit::Integer(b)) => a == b,
+        (Lit::Float(a), Lit::Float(b)) => a == b,
+        (Lit::Boolean(a), Lit::Boolean(b)) => a == b,
+        _ => false,
+    }
+}
@@ -0,0 +1,70 @@
+use crate::generators::tac::instructions::{CopyInstruction, Instruction, Operand, ValueId};
+use crate::generators::tac::opt::{replace_values, Pass};
+use crate::generators::tac::ssa::SsaGraph;
+use crate::Dump;
+use std::collections::HashMap;
+
+/// Replaces values that are copies of another value with the original. A phi that chooses the
+/// same value on every edge, apart from its own value around a loop, counts as a copy. The copies
+/// themselves are left for [`DeadCodeElimination`](super::DeadCodeElimination).
+pub struct CopyPropagation;
+
+impl Pass for CopyPropagation {
+    fn dump(&self) -> Dump {
+        Dump::CopyPropagation
+    }
+
+    fn run(&mut self, ssa: &mut SsaGraph) -> bool {
+        let mut copies: HashMap<ValueId, ValueId> = HashMap::new();
+        for block in ssa.cfg.node_weights() {
+            for inst in &block.instructions {
+                match &inst.instruction {
+                    Instruction::Copy(CopyInstruction {
+                        src: Operand::Value(source),
+                        dst: Operand::Value(target),
+                    }) => {
+                        copies.insert(*target, *source);
+                    }
+                    Instruction::Phi(phi) => {
+                        let Some(target) = phi.target.value() else {
+                            continue;
+                        };
+                        let mut sources = phi
+                            .sources
+                            .iter()
+                            .map(|(_, source)| source.value())
+                            .filter(|source| *source != Some(target));
+                        let Some(Some(first)) = sources.next() else {
+                            continue;
+                        };
+                        if sources.all(|source| source == Some(first)) {
+                            copies.insert(target, first);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // Chains of copies lead to the value they all started from. Phis copying each other in a
+        // cycle have no such value, and are left alone.
+        let mut originals = HashMap::new();
+        for &value in copies.keys() {
+            let mut original = value;
+            let mut steps = 0;
+            while let Some(&next) = copies.get(&original) {
+                original = next;
+                steps += 1;
+                if steps > copies.len() {
+                    break;
+                }
+            }
+            if steps <= copies.len() {
+                originals.insert(value, original);
+            }
+        }
+        replace_values(ssa, |value| {
+            originals.get(&value).copied().map(Operand::Value)
+        })
+    }
+}
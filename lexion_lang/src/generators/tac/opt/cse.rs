@@ -0,0 +1,104 @@
+use crate::ast::Lit;
+use crate::generators::tac::instructions::{
+    AssignmentInstruction, CopyInstruction, Instruction, Operand, ValueId,
+};
+use crate::generators::tac::opt::Pass;
+use crate::generators::tac::ssa::{DominatorTree, SsaGraph};
+use crate::operators;
+use crate::Dump;
+use generational_arena::Index;
+use lexion_lib::petgraph::graph::NodeIndex;
+use std::collections::HashMap;
+
+/// Operator, operands and result type of an assignment.
+type Expression = (&'static str, Option<String>, String, Option<Index>);
+
+/// Replaces an assignment that computes the same expression as an assignment in a dominating
+/// block, or earlier in the same block, with a copy of the value computed there. Expressions
+/// reading memory or globals, which may change in between, are computed again.
+pub struct CommonSubexpressionElimination;
+
+impl Pass for CommonSubexpressionElimination {
+    fn dump(&self) -> Dump {
+        Dump::CommonSubexpressionElimination
+    }
+
+    fn run(&mut self, ssa: &mut SsaGraph) -> bool {
+        enum Visit {
+            Enter(NodeIndex),
+            Exit(Vec<Expression>),
+        }
+
+        let mut changed = false;
+        for function in ssa.cfg.functions.clone() {
+            let tree = DominatorTree::new(&ssa.cfg, &function);
+            let mut available: HashMap<Expression, ValueId> = HashMap::new();
+            let mut visits = vec![Visit::Enter(tree.root())];
+            while let Some(visit) = visits.pop() {
+                match visit {
+                    Visit::Enter(node) => {
+                        let mut computed = Vec::new();
+                        let mut instructions = std::mem::take(&mut ssa.cfg[node].instructions);
+                        for inst in instructions.iter_mut() {
+                            let Instruction::Assignment(assignment) = &inst.instruction else {
+                                continue;
+                            };
+                            let Some((target, expression)) = expression(ssa, assignment) else {
+                                continue;
+                            };
+                            if let Some(&value) = available.get(&expression) {
+                                inst.instruction = Instruction::Copy(CopyInstruction {
+                                    src: Operand::Value(value),
+                                    dst: Operand::Value(target),
+                                });
+                                changed = true;
+                            } else {
+                                available.insert(expression.clone(), target);
+                                computed.push(expression);
+                            }
+                        }
+                        ssa.cfg[node].instructions = instructions;
+                        visits.push(Visit::Exit(computed));
+                        visits.extend(tree.children(node).iter().rev().map(|c| Visit::Enter(*c)));
+                    }
+                    Visit::Exit(computed) => {
+                        for expression in computed {
+                            available.remove(&expression);
+                        }
+                    }
+                }
+            }
+        }
+        changed
+    }
+}
+
+/// The value `assignment` assigns and the expression it computes, if computing the expression
+/// again always gives the same result.
+fn expression(ssa: &SsaGraph, assignment: &AssignmentInstruction) -> Option<(ValueId, Expression)> {
+    let target = assignment.target.value()?;
+    let operands = assignment.left.iter().chain([&assignment.right]);
+    for operand in operands {
+        match operand {
+            Operand::Value(_) => {}
+            Operand::Literal(lit) if !matches!(lit, Lit::String(_)) => {}
+            _ => return None,
+        }
+    }
+    let mut left = assignment.left.as_ref().map(|left| left.to_string());
+    let mut right = assignment.right.to_string();
+    let commutative = matches!(
+        assignment.operator,
+        operators::PLUS
+            | operators::MULTIPLY
+            | operators::BITWISE_AND
+            | operators::BITWISE_OR
+            | operators::BITWISE_XOR
+            | operators::EQUALS
+            | operators::NOT_EQUALS
+    );
+    if let Some(left) = left.as_mut().filter(|left| commutative && **left > right) {
+        std::mem::swap(left, &mut right);
+    }
+    Some((target, (assignment.operator, left, right, ssa.ty(target))))
+}
@@ -0,0 +1,104 @@
+use crate::ast::Lit;
+use crate::generators::tac::instructions::{
+    AssignmentInstruction, BaseInstruction, Instruction, Operand, ValueId,
+};
+use crate::generators::tac::opt::Pass;
+use crate::generators::tac::ssa::SsaGraph;
+use crate::operators;
+use crate::Dump;
+
+/// Removes instructions that assign a value which is never read. Values are counted by their
+/// [`ValueId`], so removing an instruction only updates the counts of the values it read instead
+/// of running a liveness analysis again. Only instructions without side effects are removed; a
+/// call keeps running but no longer stores its result.
+pub struct DeadCodeElimination;
+
+impl Pass for DeadCodeElimination {
+    fn dump(&self) -> Dump {
+        Dump::DeadCodeElimination
+    }
+
+    fn run(&mut self, ssa: &mut SsaGraph) -> bool {
+        let mut uses = vec![0usize; ssa.values.len()];
+        // The values read by the removable instruction defining each value.
+        let mut definitions: Vec<Option<Vec<ValueId>>> = vec![None; ssa.values.len()];
+        for block in ssa.cfg.node_weights_mut() {
+            for inst in &mut block.instructions {
+                let reads = values(inst.instruction.operands_read_mut());
+                for value in &reads {
+                    uses[value.0] += 1;
+                }
+                if let Some(target) = removable_target(&inst.instruction) {
+                    definitions[target.0] = Some(reads);
+                }
+            }
+        }
+
+        // Removing an instruction can make the values it read dead as well.
+        let mut dead = vec![false; ssa.values.len()];
+        let mut worklist: Vec<ValueId> = (0..uses.len())
+            .filter(|&index| uses[index] == 0 && definitions[index].is_some())
+            .map(ValueId)
+            .collect();
+        while let Some(value) = worklist.pop() {
+            dead[value.0] = true;
+            for read in definitions[value.0].take().into_iter().flatten() {
+                uses[read.0] -= 1;
+                if uses[read.0] == 0 && definitions[read.0].is_some() {
+                    worklist.push(read);
+                }
+            }
+        }
+
+        let mut changed = false;
+        for block in ssa.cfg.node_weights_mut() {
+            block.instructions.retain_mut(|inst| {
+                if let Some(target) = removable_target(&inst.instruction) {
+                    changed |= dead[target.0];
+                    return !dead[target.0];
+                }
+                if let Instruction::FunctionCall(call) = &mut inst.instruction {
+                    if call
+                        .return_target
+                        .as_ref()
+                        .and_then(Operand::value)
+                        .is_some_and(|value| uses[value.0] == 0)
+                    {
+                        call.return_target = None;
+                        changed = true;
+                    }
+                }
+                true
+            });
+        }
+        changed
+    }
+}
+
+fn values(operands: Vec<&mut Operand>) -> Vec<ValueId> {
+    operands
+        .into_iter()
+        .filter_map(|operand| operand.value())
+        .collect()
+}
+
+/// The value assigned by `instruction`, if the instruction can be removed once it is unused.
+fn removable_target(instruction: &Instruction) -> Option<ValueId> {
+    match instruction {
+        Instruction::Assignment(assignment) if is_pure(assignment) => assignment.target.value(),
+        Instruction::Copy(copy) => copy.dst.value(),
+        Instruction::Phi(phi) => phi.target.value(),
+        _ => None,
+    }
+}
+
+/// Whether removing `assignment` cannot change what the program does. A division is only removed
+/// if it cannot divide by zero, or overflow by dividing the smallest signed integer by -1.
+fn is_pure(assignment: &AssignmentInstruction) -> bool {
+    match assignment.operator {
+        operators::DIVIDE | operators::REMAINDER if assignment.left.is_some() => {
+            matches!(&assignment.right, Operand::Literal(Lit::Integer(value)) if *value != 0 && *value != -1)
+        }
+        _ => true,
+    }
+}
@@ -0,0 +1,102 @@
+use crate::generators::tac::instructions::{
+    ConditionalJumpInstruction, Instruction, JumpInstruction, Operand,
+};
+use crate::generators::tac::opt::Pass;
+use crate::generators::tac::ssa::{phi_count, SsaGraph};
+use crate::Dump;
+use lexion_lib::petgraph::graph::NodeIndex;
+use std::collections::{HashMap, HashSet};
+
+/// Makes jumps to a block that does nothing but jump on go straight to where that block jumps,
+/// and removes jumps to the block right after them. Jumps are not threaded into a block with
+/// phis, which would need an operand for the new edge.
+pub struct JumpThreading;
+
+impl Pass for JumpThreading {
+    fn dump(&self) -> Dump {
+        Dump::JumpThreading
+    }
+
+    fn run(&mut self, ssa: &mut SsaGraph) -> bool {
+        let blocks = ssa
+            .cfg
+            .node_indices()
+            .map(|node| (ssa.cfg[node].label.clone(), node))
+            .collect::<HashMap<_, _>>();
+        let forwards = ssa
+            .cfg
+            .node_weights()
+            .filter_map(|block| match block.instructions.as_slice() {
+                [inst] => match &inst.instruction {
+                    Instruction::Jump(jump) => Some((block.label.clone(), jump.target.to_string())),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+        let has_phis = |label: &str| {
+            blocks
+                .get(label)
+                .is_some_and(|node| phi_count(&ssa.cfg[*node]) > 0)
+        };
+        let thread = |label: &str| {
+            let mut visited = HashSet::from([label.to_string()]);
+            let mut result = label.to_string();
+            while let Some(next) = forwards.get(&result) {
+                if has_phis(next) || !visited.insert(next.clone()) {
+                    break;
+                }
+                result = next.clone();
+            }
+            result
+        };
+
+        let mut threaded = Vec::new();
+        for node in ssa.cfg.node_indices() {
+            for (index, inst) in ssa.cfg[node].instructions.iter().enumerate() {
+                if let Instruction::ConditionalJump(ConditionalJumpInstruction { target, .. })
+                | Instruction::Jump(JumpInstruction { target }) = &inst.instruction
+                {
+                    let label = target.to_string();
+                    let new = thread(&label);
+                    if new != label {
+                        threaded.push((node, index, new));
+                    }
+                }
+            }
+        }
+        let mut changed = !threaded.is_empty();
+        for (node, index, label) in threaded {
+            if let Instruction::ConditionalJump(ConditionalJumpInstruction { target, .. })
+            | Instruction::Jump(JumpInstruction { target }) =
+                &mut ssa.cfg[node].instructions[index].instruction
+            {
+                *target = Operand::Label(label);
+            }
+        }
+
+        // A jump to the next block of the same function is where control goes anyway.
+        for function in ssa.cfg.functions.clone() {
+            for node in ssa.cfg.function_nodes(&function) {
+                if node == function.end {
+                    continue;
+                }
+                let next = ssa.cfg[NodeIndex::new(node.index() + 1)].label.clone();
+                let block = &mut ssa.cfg[node];
+                if let Some(Instruction::Jump(jump)) =
+                    block.instructions.last().map(|inst| &inst.instruction)
+                {
+                    if jump.target.to_string() == next {
+                        block.instructions.pop();
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        if changed {
+            ssa.relink();
+        }
+        changed
+    }
+}
@@ -0,0 +1,156 @@
+mod constant;
+mod copy;
+mod cse;
+mod dce;
+mod jump_threading;
+mod unreachable;
+
+pub use self::constant::ConstantPropagation;
+pub use self::copy::CopyPropagation;
+pub use self::cse::CommonSubexpressionElimination;
+pub use self::dce::DeadCodeElimination;
+pub use self::jump_threading::JumpThreading;
+pub use self::unreachable::UnreachableBlockRemoval;
+
+use crate::ast::types::TypeCollection;
+use crate::diagnostic::DiagnosticConsumer;
+use crate::generators::tac::instructions::{BaseInstruction, Operand, ValueId};
+use crate::generators::tac::ssa::SsaGraph;
+use crate::pipeline::PipelineStage;
+use crate::{Dump, DumpFlags};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// Rounds of `-O2` after which the passes stop even if they still find something to do.
+const MAX_ROUNDS: usize = 8;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OptLevel {
+    /// No optimization.
+    #[default]
+    O0,
+    /// One round of constant and copy propagation, unreachable block removal and dead code
+    /// elimination.
+    O1,
+    /// All passes, repeated until none of them changes the program.
+    O2,
+}
+
+impl FromStr for OptLevel {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "0" => Ok(OptLevel::O0),
+            "1" => Ok(OptLevel::O1),
+            "2" => Ok(OptLevel::O2),
+            level => Err(format!("invalid optimization level: {level}")),
+        }
+    }
+}
+
+impl Display for OptLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptLevel::O0 => write!(f, "0"),
+            OptLevel::O1 => write!(f, "1"),
+            OptLevel::O2 => write!(f, "2"),
+        }
+    }
+}
+
+/// Transformation of a program in SSA form.
+pub trait Pass {
+    /// Flag that dumps the program after each run of the pass.
+    fn dump(&self) -> Dump;
+    /// Runs the pass, and returns whether it changed anything.
+    fn run(&mut self, ssa: &mut SsaGraph) -> bool;
+}
+
+/// Program after one run of a pass.
+pub struct PassDump {
+    /// File name, numbered in the order the passes ran.
+    pub name: String,
+    pub listing: String,
+}
+
+/// Runs the passes of an [`OptLevel`] over an [`SsaGraph`], and keeps a listing of the program
+/// after each pass whose [`Pass::dump`] flag is set.
+pub struct PassManager<'a> {
+    ssa: SsaGraph,
+    types: &'a TypeCollection,
+}
+
+impl<'a> PipelineStage for PassManager<'a> {
+    type Input = (SsaGraph, &'a TypeCollection);
+    type Options = (OptLevel, DumpFlags);
+    type Output = (SsaGraph, Vec<PassDump>);
+
+    fn new((ssa, types): Self::Input) -> Self {
+        Self { ssa, types }
+    }
+
+    fn exec(
+        mut self,
+        _diag: &mut dyn DiagnosticConsumer,
+        (level, dump_flags): Self::Options,
+    ) -> Option<Self::Output> {
+        let (mut passes, rounds): (Vec<Box<dyn Pass + 'a>>, _) = match level {
+            OptLevel::O0 => (Vec::new(), 0),
+            OptLevel::O1 => (
+                vec![
+                    Box::new(ConstantPropagation::new(self.types)),
+                    Box::new(CopyPropagation),
+                    Box::new(UnreachableBlockRemoval),
+                    Box::new(DeadCodeElimination),
+                ],
+                1,
+            ),
+            OptLevel::O2 => (
+                vec![
+                    Box::new(ConstantPropagation::new(self.types)),
+                    Box::new(CopyPropagation),
+                    Box::new(CommonSubexpressionElimination),
+                    Box::new(JumpThreading),
+                    Box::new(UnreachableBlockRemoval),
+                    Box::new(DeadCodeElimination),
+                ],
+                MAX_ROUNDS,
+            ),
+        };
+
+        let mut dumps = Vec::new();
+        for _ in 0..rounds {
+            let mut changed = false;
+            for pass in passes.iter_mut() {
+                changed |= pass.run(&mut self.ssa);
+                if dump_flags.contains(pass.dump()) {
+                    dumps.push(PassDump {
+                        name: format!("opt.{:02}.{}.tac", dumps.len() + 1, pass.dump().as_str()),
+                        listing: self.ssa.to_string(),
+                    });
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        Some((self.ssa, dumps))
+    }
+}
+
+/// Replaces every value read in the program by the operand `replacement` returns for it, if any.
+/// Returns whether anything was replaced.
+fn replace_values(ssa: &mut SsaGraph, replacement: impl Fn(ValueId) -> Option<Operand>) -> bool {
+    let mut changed = false;
+    for block in ssa.cfg.node_weights_mut() {
+        for inst in block.instructions.iter_mut() {
+            for operand in inst.instruction.operands_read_mut() {
+                if let Some(new) = operand.value().and_then(&replacement) {
+                    *operand = new;
+                    changed = true;
+                }
+            }
+        }
+    }
+    changed
+}
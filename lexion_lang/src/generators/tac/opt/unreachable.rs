@@ -0,0 +1,74 @@
+use crate::generators::tac::instructions::Instruction;
+use crate::generators::tac::opt::Pass;
+use crate::generators::tac::ssa::SsaGraph;
+use crate::Dump;
+use std::collections::HashSet;
+
+/// Removes instructions after a jump or return, and blocks no path from the entry of their
+/// function reaches. The block ending a function keeps its `endfn`, which code generation puts
+/// the epilogue at.
+pub struct UnreachableBlockRemoval;
+
+impl Pass for UnreachableBlockRemoval {
+    fn dump(&self) -> Dump {
+        Dump::UnreachableBlocks
+    }
+
+    fn run(&mut self, ssa: &mut SsaGraph) -> bool {
+        let mut changed = false;
+        for block in ssa.cfg.node_weights_mut() {
+            let Some(end) = block.instructions.iter().position(|inst| {
+                matches!(
+                    inst.instruction,
+                    Instruction::Jump(_) | Instruction::Return(_)
+                )
+            }) else {
+                continue;
+            };
+            let mut index = 0;
+            block.instructions.retain(|inst| {
+                index += 1;
+                index <= end + 1 || matches!(inst.instruction, Instruction::EndFunction(_))
+            });
+            changed |= index != block.instructions.len();
+        }
+        if changed {
+            ssa.relink();
+        }
+
+        let mut removed = HashSet::new();
+        for function in ssa.cfg.functions.clone() {
+            let mut reachable = HashSet::from([function.start]);
+            let mut worklist = vec![function.start];
+            while let Some(node) = worklist.pop() {
+                for successor in ssa.cfg.successors(node) {
+                    if reachable.insert(successor) {
+                        worklist.push(successor);
+                    }
+                }
+            }
+            for node in ssa.cfg.function_nodes(&function) {
+                if reachable.contains(&node) {
+                    continue;
+                }
+                let block = &mut ssa.cfg[node];
+                let before = block.instructions.len();
+                block
+                    .instructions
+                    .retain(|inst| matches!(inst.instruction, Instruction::EndFunction(_)));
+                if block.instructions.is_empty() {
+                    removed.insert(node);
+                } else {
+                    changed |= before != block.instructions.len();
+                }
+            }
+        }
+        if !removed.is_empty() {
+            ssa.remove_blocks(&removed);
+            changed = true;
+        } else if changed {
+            ssa.relink();
+        }
+        changed
+    }
+}
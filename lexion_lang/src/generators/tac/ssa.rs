@@ -0,0 +1,697 @@
+use crate::diagnostic::DiagnosticConsumer;
+use crate::generators::label::LabelGenerator;
+use crate::generators::tac::instructions::{
+    BaseInstruction, ConditionalJumpInstruction, ControlFlowGraph, CopyInstruction, FunctionRange,
+    Instruction, InstructionBlock, InstructionInstance, JumpInstruction, Operand, PhiInstruction,
+    ValueId,
+};
+use crate::operators;
+use crate::pipeline::PipelineStage;
+use crate::symbol_table::{SymbolTableEntryType, SymbolTableGraph};
+use generational_arena::Index;
+use lexion_lib::petgraph::algo::dominators::{simple_fast, Dominators};
+use lexion_lib::petgraph::graph::NodeIndex;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+/// Dominator tree and dominance frontiers of the blocks of one function.
+pub struct DominatorTree {
+    dominators: Dominators<NodeIndex>,
+    children: HashMap<NodeIndex, Vec<NodeIndex>>,
+    frontiers: HashMap<NodeIndex, BTreeSet<NodeIndex>>,
+}
+
+impl DominatorTree {
+    pub fn new(cfg: &ControlFlowGraph, function: &FunctionRange) -> Self {
+        let dominators = simple_fast(&cfg.graph, function.start);
+        let mut tree = Self {
+            dominators,
+            children: Default::default(),
+            frontiers: Default::default(),
+        };
+        for node in cfg.function_nodes(function) {
+            let Some(idom) = tree.immediate_dominator(node) else {
+                continue;
+            };
+            tree.children.entry(idom).or_default().push(node);
+
+            // A join point is in the frontier of every block on the way up from a predecessor
+            // to its immediate dominator.
+            let predecessors = cfg
+                .predecessors(node)
+                .into_iter()
+                .filter(|pred| tree.is_reachable(*pred))
+                .collect::<Vec<_>>();
+            if predecessors.len() < 2 {
+                continue;
+            }
+            for mut runner in predecessors {
+                while runner != idom {
+                    tree.frontiers.entry(runner).or_default().insert(node);
+                    let Some(next) = tree.immediate_dominator(runner) else {
+                        break;
+                    };
+                    runner = next;
+                }
+            }
+        }
+        tree
+    }
+
+    pub fn root(&self) -> NodeIndex {
+        self.dominators.root()
+    }
+
+    pub fn is_reachable(&self, node: NodeIndex) -> bool {
+        node == self.root() || self.immediate_dominator(node).is_some()
+    }
+
+    /// Closest block every path from the entry to `node` passes through, or `None` for the
+    /// entry and unreachable blocks.
+    pub fn immediate_dominator(&self, node: NodeIndex) -> Option<NodeIndex> {
+        self.dominators.immediate_dominator(node)
+    }
+
+    pub fn dominates(&self, dominator: NodeIndex, node: NodeIndex) -> bool {
+        self.dominators
+            .dominators(node)
+            .is_some_and(|mut dominators| dominators.any(|d| d == dominator))
+    }
+
+    /// Blocks `node` is the immediate dominator of, in layout order.
+    pub fn children(&self, node: NodeIndex) -> &[NodeIndex] {
+        self.children.get(&node).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Blocks where the dominance of `node` ends, which are the ones a value assigned in `node`
+    /// has to be merged in.
+    pub fn frontier(&self, node: NodeIndex) -> impl Iterator<Item = NodeIndex> + '_ {
+        self.frontiers.get(&node).into_iter().flatten().copied()
+    }
+
+    /// Reachable blocks, each before the blocks it dominates.
+    pub fn preorder(&self) -> Vec<NodeIndex> {
+        let mut result = Vec::new();
+        let mut stack = vec![self.root()];
+        while let Some(node) = stack.pop() {
+            result.push(node);
+            stack.extend(self.children(node).iter().rev());
+        }
+        result
+    }
+}
+
+/// Version of a variable or temporary of the TAC.
+pub struct SsaValue {
+    /// Variable or temporary the value is a version of.
+    pub variable: String,
+    /// Type the variable is declared with, if the symbol table knows it.
+    pub ty: Option<Index>,
+}
+
+/// A [`ControlFlowGraph`] in SSA form. The variables and temporaries of each function are
+/// replaced by values that are assigned exactly once, and phis at the start of blocks choose
+/// between the values reaching them from different predecessors. Globals stay as they are.
+pub struct SsaGraph {
+    pub cfg: ControlFlowGraph,
+    pub values: Vec<SsaValue>,
+}
+
+impl SsaGraph {
+    pub fn new_value(&mut self, variable: String, ty: Option<Index>) -> ValueId {
+        new_value(&mut self.values, variable, ty)
+    }
+
+    pub fn variable(&self, value: ValueId) -> &str {
+        &self.values[value.0].variable
+    }
+
+    pub fn ty(&self, value: ValueId) -> Option<Index> {
+        self.values[value.0].ty
+    }
+
+    /// Rebuilds the edges after the jumps changed, and drops the phi operands of edges that are
+    /// gone.
+    pub fn relink(&mut self) {
+        self.cfg.relink();
+        for node in self.cfg.node_indices() {
+            let predecessors = self.cfg.predecessors(node);
+            for inst in self.cfg[node].instructions.iter_mut() {
+                let Instruction::Phi(phi) = &mut inst.instruction else {
+                    break;
+                };
+                phi.sources
+                    .retain(|(pred, _)| predecessors.binary_search(pred).is_ok());
+            }
+        }
+    }
+
+    /// Removes `blocks` from the graph. The remaining blocks keep their order and are renumbered,
+    /// so the entry of a function must not be removed.
+    pub fn remove_blocks(&mut self, blocks: &HashSet<NodeIndex>) {
+        let mut result = ControlFlowGraph::new();
+        result.globals = std::mem::take(&mut self.cfg.globals);
+        let mut renumbered = HashMap::new();
+        for function in self.cfg.functions.clone() {
+            for node in self.cfg.function_nodes(&function) {
+                if blocks.contains(&node) {
+                    continue;
+                }
+                let block = &mut self.cfg[node];
+                let new = result.block(block.label.clone(), node == function.start);
+                result[new].instructions = std::mem::take(&mut block.instructions);
+                renumbered.insert(node, new);
+            }
+        }
+        for node in result.node_indices() {
+            for inst in result[node].instructions.iter_mut() {
+                let Instruction::Phi(phi) = &mut inst.instruction else {
+                    break;
+                };
+                phi.sources = std::mem::take(&mut phi.sources)
+                    .into_iter()
+                    .filter_map(|(pred, source)| Some((*renumbered.get(&pred)?, source)))
+                    .collect();
+            }
+        }
+        self.cfg = result;
+        self.relink();
+    }
+}
+
+impl Display for SsaGraph {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for node in self.cfg.node_indices() {
+            write!(f, "b{} {:?}\n\n", node.index(), self.cfg[node])?;
+        }
+        Ok(())
+    }
+}
+
+fn new_value(values: &mut Vec<SsaValue>, variable: String, ty: Option<Index>) -> ValueId {
+    values.push(SsaValue { variable, ty });
+    ValueId(values.len() - 1)
+}
+
+fn instance(instruction: Instruction) -> InstructionInstance {
+    InstructionInstance {
+        instruction,
+        live: Default::default(),
+        span: None,
+    }
+}
+
+/// Number of phis a block starts with.
+pub(crate) fn phi_count(block: &InstructionBlock) -> usize {
+    block
+        .instructions
+        .iter()
+        .take_while(|inst| matches!(inst.instruction, Instruction::Phi(_)))
+        .count()
+}
+
+/// Converts a [`ControlFlowGraph`] into SSA form. Phis go into the dominance frontiers of the
+/// blocks assigning a variable, and a walk of the dominator tree then replaces every variable
+/// with the value that reaches it. Variables whose address is taken are left in place.
+///
+/// The values leaving a block are the ones it ends with, so a conditional jump has to end its
+/// block, as it does in the output of [`CodeGeneratorTac`](crate::generators::tac::CodeGeneratorTac).
+pub struct SsaBuilder<'a> {
+    cfg: ControlFlowGraph,
+    symbols: &'a SymbolTableGraph,
+    values: Vec<SsaValue>,
+    /// Types of the variables of the function being renamed.
+    types: HashMap<String, Index>,
+    /// Values of each variable on the path from the entry to the block being renamed.
+    stacks: HashMap<String, Vec<ValueId>>,
+    /// Value a variable has before its first assignment, per function.
+    undefined: HashMap<String, ValueId>,
+    address_taken: HashSet<String>,
+}
+
+impl<'a> PipelineStage for SsaBuilder<'a> {
+    type Input = (ControlFlowGraph, &'a SymbolTableGraph);
+    type Options = ();
+    type Output = SsaGraph;
+
+    fn new((cfg, symbols): Self::Input) -> Self {
+        Self {
+            cfg,
+            symbols,
+            values: Default::default(),
+            types: Default::default(),
+            stacks: Default::default(),
+            undefined: Default::default(),
+            address_taken: Default::default(),
+        }
+    }
+
+    fn exec(
+        mut self,
+        _diag: &mut dyn DiagnosticConsumer,
+        _: Self::Options,
+    ) -> Option<Self::Output> {
+        self.cfg.relink();
+        for function in self.cfg.functions.clone() {
+            let is_defined = matches!(
+                self.cfg[function.start].instructions.first(),
+                Some(InstructionInstance {
+                    instruction: Instruction::Function(_),
+                    ..
+                })
+            );
+            if !is_defined {
+                continue;
+            }
+            self.stacks.clear();
+            self.undefined.clear();
+            self.types = self.symbols.variable_types(&self.cfg[function.start].label);
+            self.address_taken = self.address_taken(&function);
+            let tree = DominatorTree::new(&self.cfg, &function);
+            self.insert_phis(&function, &tree);
+            self.rename(&function, &tree);
+        }
+        Some(SsaGraph {
+            cfg: self.cfg,
+            values: self.values,
+        })
+    }
+}
+
+impl<'a> SsaBuilder<'a> {
+    fn address_taken(&self, function: &FunctionRange) -> HashSet<String> {
+        self.cfg
+            .function_nodes(function)
+            .flat_map(|node| self.cfg[node].instructions.iter())
+            .filter_map(|inst| match &inst.instruction {
+                Instruction::Assignment(assignment)
+                    if assignment.left.is_none()
+                        && assignment.operator == operators::ADDRESS_OF =>
+                {
+                    Some(assignment.right.to_string())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Name of the variable `operand` refers to, if it is renamed to values.
+    fn renamed(&self, operand: &Operand) -> Option<String> {
+        match operand {
+            Operand::Variable(_) | Operand::Temporary(_) => {
+                let name = operand.to_string();
+                (!self.address_taken.contains(&name)).then_some(name)
+            }
+            _ => None,
+        }
+    }
+
+    fn insert_phis(&mut self, function: &FunctionRange, tree: &DominatorTree) {
+        // Variables only used in the block that assigns them never need a phi.
+        let mut assignments: BTreeMap<String, BTreeSet<NodeIndex>> = BTreeMap::new();
+        let mut live_across = HashSet::new();
+        for node in self.cfg.function_nodes(function) {
+            if !tree.is_reachable(node) {
+                continue;
+            }
+            let mut written = HashSet::new();
+            for inst in &self.cfg[node].instructions {
+                for variable in inst.instruction.variables_read() {
+                    if !written.contains(&variable) {
+                        live_across.insert(variable);
+                    }
+                }
+                for variable in inst.instruction.variables_written() {
+                    written.insert(variable.clone());
+                    assignments.entry(variable).or_default().insert(node);
+                }
+            }
+        }
+
+        let mut phis: BTreeMap<NodeIndex, Vec<String>> = BTreeMap::new();
+        for (variable, blocks) in assignments {
+            if !live_across.contains(&variable) || self.address_taken.contains(&variable) {
+                continue;
+            }
+            let mut has_phi = HashSet::new();
+            let mut assigned = blocks.clone();
+            let mut worklist = blocks.into_iter().collect::<Vec<_>>();
+            while let Some(node) = worklist.pop() {
+                for frontier in tree.frontier(node) {
+                    if !has_phi.insert(frontier) {
+                        continue;
+                    }
+                    phis.entry(frontier).or_default().push(variable.clone());
+                    if assigned.insert(frontier) {
+                        worklist.push(frontier);
+                    }
+                }
+            }
+        }
+
+        for (node, variables) in phis {
+            let block = &mut self.cfg[node];
+            block.instructions.splice(
+                0..0,
+                variables.into_iter().map(|variable| {
+                    instance(Instruction::Phi(PhiInstruction {
+                        target: Operand::Variable(variable),
+                        sources: Vec::new(),
+                    }))
+                }),
+            );
+        }
+    }
+
+    fn rename(&mut self, function: &FunctionRange, tree: &DominatorTree) {
+        enum Visit {
+            Enter(NodeIndex),
+            Exit(Vec<String>),
+        }
+
+        let mut visits = vec![Visit::Enter(tree.root())];
+        while let Some(visit) = visits.pop() {
+            match visit {
+                Visit::Enter(node) => {
+                    let assigned = self.rename_block(node);
+                    visits.push(Visit::Exit(assigned));
+                    visits.extend(tree.children(node).iter().rev().map(|c| Visit::Enter(*c)));
+                }
+                Visit::Exit(assigned) => {
+                    for variable in assigned {
+                        if let Some(stack) = self.stacks.get_mut(&variable) {
+                            stack.pop();
+                        }
+                    }
+                }
+            }
+        }
+
+        // Unreachable blocks never run, so they only see their own assignments.
+        for node in self.cfg.function_nodes(function) {
+            if !tree.is_reachable(node) {
+                let stacks = std::mem::take(&mut self.stacks);
+                self.rename_block(node);
+                self.stacks = stacks;
+            }
+        }
+    }
+
+    /// Renames the operands of `node` and fills in its operand of the phis of its successors.
+    /// Returns the variables assigned in the block.
+    fn rename_block(&mut self, node: NodeIndex) -> Vec<String> {
+        let mut assigned = Vec::new();
+        let mut instructions = std::mem::take(&mut self.cfg[node].instructions);
+        for inst in instructions.iter_mut() {
+            if !matches!(inst.instruction, Instruction::Phi(_)) {
+                for operand in inst.instruction.operands_read_mut() {
+                    if let Some(variable) = self.renamed(operand) {
+                        *operand = Operand::Value(self.current(&variable));
+                    }
+                }
+            }
+            for operand in inst.instruction.operands_written_mut() {
+                if let Some(variable) = self.renamed(operand) {
+                    let ty = self.types.get(&variable).copied();
+                    let value = new_value(&mut self.values, variable.clone(), ty);
+                    self.stacks.entry(variable.clone()).or_default().push(value);
+                    assigned.push(variable);
+                    *operand = Operand::Value(value);
+                }
+            }
+        }
+        self.cfg[node].instructions = instructions;
+
+        for successor in self.cfg.successors(node) {
+            let mut instructions = std::mem::take(&mut self.cfg[successor].instructions);
+            for inst in instructions.iter_mut() {
+                let Instruction::Phi(phi) = &mut inst.instruction else {
+                    break;
+                };
+                let variable = match &phi.target {
+                    Operand::Value(value) => self.values[value.0].variable.clone(),
+                    target => target.to_string(),
+                };
+                phi.sources
+                    .push((node, Operand::Value(self.current(&variable))));
+            }
+            self.cfg[successor].instructions = instructions;
+        }
+        assigned
+    }
+
+    /// Value of `variable` at the current point of the renaming walk.
+    fn current(&mut self, variable: &str) -> ValueId {
+        if let Some(value) = self.stacks.get(variable).and_then(|stack| stack.last()) {
+            return *value;
+        }
+        let ty = self.types.get(variable).copied();
+        *self
+            .undefined
+            .entry(variable.to_string())
+            .or_insert_with(|| new_value(&mut self.values, variable.to_string(), ty))
+    }
+}
+
+/// Translates an [`SsaGraph`] back into a [`ControlFlowGraph`] for register allocation. Phis
+/// become copies at the end of their predecessors, or in a block of their own on edges from a
+/// block that branches, and values become variables again. The first value of a variable keeps
+/// its name and later ones are numbered after it.
+pub struct SsaDestructor<'a> {
+    cfg: ControlFlowGraph,
+    values: Vec<SsaValue>,
+    symbols: &'a mut SymbolTableGraph,
+    labels: LabelGenerator,
+}
+
+impl<'a> PipelineStage for SsaDestructor<'a> {
+    type Input = (SsaGraph, &'a mut SymbolTableGraph);
+    type Options = ();
+    type Output = ControlFlowGraph;
+
+    fn new((ssa, symbols): Self::Input) -> Self {
+        Self {
+            cfg: ssa.cfg,
+            values: ssa.values,
+            symbols,
+            labels: LabelGenerator::new("$split_", None),
+        }
+    }
+
+    fn exec(
+        mut self,
+        _diag: &mut dyn DiagnosticConsumer,
+        _: Self::Options,
+    ) -> Option<Self::Output> {
+        self.cfg.relink();
+        let mut result = ControlFlowGraph::new();
+        result.globals = std::mem::take(&mut self.cfg.globals);
+        for function in self.cfg.functions.clone() {
+            let mut splits = self.lower_phis(&function);
+            self.rename(&function, &mut splits);
+
+            // Split edges go after the end of the function, where nothing falls through to them.
+            for node in self.cfg.function_nodes(&function) {
+                let block = &mut self.cfg[node];
+                let new = result.block(block.label.clone(), node == function.start);
+                result[new].instructions = std::mem::take(&mut block.instructions);
+            }
+            for split in splits {
+                let new = result.block(split.label, false);
+                result[new].instructions = split.instructions;
+            }
+        }
+        result.relink();
+        Some(result)
+    }
+}
+
+impl<'a> SsaDestructor<'a> {
+    /// Replaces the phis of `function` with copies, and returns the blocks made for edges that
+    /// need copies of their own.
+    fn lower_phis(&mut self, function: &FunctionRange) -> Vec<InstructionBlock> {
+        let mut edges: BTreeMap<(NodeIndex, NodeIndex), Vec<(ValueId, Operand)>> = BTreeMap::new();
+        for node in self.cfg.function_nodes(function) {
+            let count = phi_count(&self.cfg[node]);
+            for inst in self.cfg[node].instructions.drain(..count) {
+                let Instruction::Phi(phi) = inst.instruction else {
+                    unreachable!()
+                };
+                let Some(target) = phi.target.value() else {
+                    continue;
+                };
+                for (pred, source) in phi.sources {
+                    edges
+                        .entry((pred, node))
+                        .or_default()
+                        .push((target, source));
+                }
+            }
+        }
+
+        let mut splits = Vec::new();
+        for ((pred, successor), copies) in edges {
+            let copies = self.sequentialize(copies);
+            if copies.is_empty() {
+                continue;
+            }
+            let (targets, falls_through) = self.cfg.exits(pred);
+            if targets.len() + falls_through as usize <= 1 {
+                // The copies go before the jump, if the block ends in one.
+                let block = &mut self.cfg[pred];
+                let position = block
+                    .instructions
+                    .iter()
+                    .position(|inst| matches!(inst.instruction, Instruction::Jump(_)))
+                    .unwrap_or(block.instructions.len());
+                block.instructions.splice(position..position, copies);
+                continue;
+            }
+
+            // The copies must only run when control goes to `successor`.
+            let label = self.labels.next().to_string();
+            let successor_label = self.cfg[successor].label.clone();
+            for inst in self.cfg[pred].instructions.iter_mut() {
+                if let Instruction::ConditionalJump(ConditionalJumpInstruction { target, .. })
+                | Instruction::Jump(JumpInstruction { target }) = &mut inst.instruction
+                {
+                    if target.to_string() == successor_label {
+                        *target = Operand::Label(label.clone());
+                    }
+                }
+            }
+            if falls_through && successor.index() == pred.index() + 1 {
+                self.cfg[pred]
+                    .instructions
+                    .push(instance(Instruction::Jump(JumpInstruction {
+                        target: Operand::Label(label.clone()),
+                    })));
+            }
+            let mut block = InstructionBlock::new(label);
+            block.instructions = copies;
+            block
+                .instructions
+                .push(instance(Instruction::Jump(JumpInstruction {
+                    target: Operand::Label(successor_label),
+                })));
+            splits.push(block);
+        }
+        splits
+    }
+
+    /// Orders the copies of one edge, which all happen at once, so that no copy overwrites a
+    /// value another one still reads.
+    fn sequentialize(&mut self, mut copies: Vec<(ValueId, Operand)>) -> Vec<InstructionInstance> {
+        copies
+            .retain(|(target, source)| source.value() != Some(*target) && !source.is_placeholder());
+        let mut result = Vec::new();
+        while !copies.is_empty() {
+            let ready = copies.iter().position(|(target, _)| {
+                copies
+                    .iter()
+                    .all(|(_, source)| source.value() != Some(*target))
+            });
+            if let Some(index) = ready {
+                let (target, source) = copies.remove(index);
+                result.push(instance(Instruction::Copy(CopyInstruction {
+                    src: source,
+                    dst: Operand::Value(target),
+                })));
+                continue;
+            }
+            // Every copy left is part of a cycle, which is broken by saving one of the values.
+            let (target, _) = copies[0];
+            let variable = self.values[target.0].variable.clone();
+            let ty = self.values[target.0].ty;
+            let saved = new_value(&mut self.values, variable, ty);
+            result.push(instance(Instruction::Copy(CopyInstruction {
+                src: Operand::Value(target),
+                dst: Operand::Value(saved),
+            })));
+            for (_, source) in copies.iter_mut() {
+                if source.value() == Some(target) {
+                    *source = Operand::Value(saved);
+                }
+            }
+        }
+        result
+    }
+
+    /// Turns the values of `function` into variables, declaring the new ones next to the
+    /// variable they are a version of.
+    fn rename(&mut self, function: &FunctionRange, splits: &mut [InstructionBlock]) {
+        let scope = self
+            .symbols
+            .lookup(self.symbols.root, &self.cfg[function.start].label)
+            .and_then(|(_, _, entry)| entry.table);
+        let mut names: HashMap<ValueId, String> = HashMap::new();
+        let mut versions: HashMap<String, usize> = HashMap::new();
+        let mut blocks = self
+            .cfg
+            .function_nodes(function)
+            .map(|node| std::mem::take(&mut self.cfg[node].instructions))
+            .collect::<Vec<_>>();
+        let instructions = blocks
+            .iter_mut()
+            .chain(splits.iter_mut().map(|split| &mut split.instructions))
+            .flatten();
+        for inst in instructions {
+            for is_written in [false, true] {
+                let operands = if is_written {
+                    inst.instruction.operands_written_mut()
+                } else {
+                    inst.instruction.operands_read_mut()
+                };
+                for operand in operands {
+                    let Some(value) = operand.value() else {
+                        continue;
+                    };
+                    let name = match names.get(&value) {
+                        Some(name) => name.clone(),
+                        None => {
+                            let name = self.version(scope, &mut versions, value);
+                            names.insert(value, name.clone());
+                            name
+                        }
+                    };
+                    *operand = Operand::Variable(name);
+                }
+            }
+        }
+        for (node, instructions) in self.cfg.function_nodes(function).zip(blocks) {
+            self.cfg[node].instructions = instructions;
+        }
+    }
+
+    /// Name of the next version of the variable `value` belongs to.
+    fn version(
+        &mut self,
+        scope: Option<NodeIndex>,
+        versions: &mut HashMap<String, usize>,
+        value: ValueId,
+    ) -> String {
+        let variable = self.values[value.0].variable.clone();
+        let version = versions.entry(variable.clone()).or_default();
+        *version += 1;
+        if *version == 1 {
+            return variable;
+        }
+        let name = format!("{variable}.{}", *version - 1);
+        let Some(scope) = scope else {
+            return name;
+        };
+        let entry = self
+            .symbols
+            .nested_scopes(scope)
+            .into_iter()
+            .flat_map(|scope| self.symbols.graph[scope].entries.iter())
+            .find(|entry| entry.name == variable)
+            .cloned();
+        if let Some(mut entry) = entry {
+            entry.name = name.clone();
+            entry.ty = SymbolTableEntryType::Temporary;
+            let _ = self.symbols.insert_entry(scope, entry);
+        }
+        name
+    }
+}
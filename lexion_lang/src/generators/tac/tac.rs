@@ -1,49 +1,70 @@
-use crate::ast::types::TypeCollection;
+use crate::ast::types::{TypeCollection, TypeKind};
 use crate::ast::visitor::{AstNode, AstVisitor, AstVisitorAction, NodeType, TraversalType};
 use crate::ast::{
-    Ast, BlockExpr, CallExpr, Expr, ExprStmt, FuncDeclStmt, IdentExpr, Lit, LitExpr, Sourced,
-    SourcedExpr, Stmt, TypedExpr, VarDeclStmt, WhileStmt,
+    ArrayExpr, Ast, BlockExpr, CallExpr, Expr, ExprStmt, FuncDeclStmt, IdentExpr, Lit, LitExpr,
+    OperatorExpr, ReturnStmt, Sourced, SourcedExpr, Stmt, TypedExpr, VarDeclStmt, WhileStmt,
 };
 use crate::diagnostic::DiagnosticConsumer;
 use crate::generators::label::{Label, LabelGenerator};
 use crate::generators::tac::instructions::{
-    AssignmentInstruction, BaseInstruction, CodeLocation, CodeSpan, ConditionalJumpInstruction,
-    ControlFlowGraph, CopyInstruction, EndFunctionInstruction, ExternInstruction,
-    FunctionCallInstruction, FunctionInstruction, FunctionRange, Instruction, InstructionBlock,
-    InstructionInstance, JumpInstruction, LivenessInterval, Operand, ParameterInstruction,
-    ReturnInstruction,
+    AddressInstruction, AllocaInstruction, AssignmentInstruction, BoundsCheckInstruction,
+    CodeLocation, ConditionalJumpInstruction, ControlFlowGraph, CopyInstruction,
+    EndFunctionInstruction, ExternInstruction, FunctionCallInstruction, FunctionInstruction,
+    GlobalVariable, Instruction, InstructionBlock, InstructionInstance, JumpInstruction,
+    LoadInstruction, MemoryCopyInstruction, Operand, ParameterInstruction, ReturnInstruction,
+    StoreInstruction,
 };
+use crate::generators::x86::{Bitness, CMemoryLayoutBuilder, SizeAlign};
 use crate::operators;
 use crate::pipeline::PipelineStage;
 use crate::symbol_table::{SymbolTableEntry, SymbolTableEntryType, SymbolTableGraph};
 use generational_arena::Index;
 use lexion_lib::miette::SourceSpan;
 use lexion_lib::petgraph::prelude::NodeIndex;
-use lexion_lib::petgraph::visit::{Dfs, Reversed, Walker};
-use lexion_lib::petgraph::Direction;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashMap, HashSet};
 
 struct LabelGenerators {
     temp: LabelGenerator,
     loop_start: LabelGenerator,
+    loop_body: LabelGenerator,
     loop_end: LabelGenerator,
     cond_then: LabelGenerator,
     cond_else: LabelGenerator,
     cond_end: LabelGenerator,
+    short_circuit: LabelGenerator,
 }
 
 struct PartialLoop {
-    jump_instruction: CodeLocation,
+    /// Jumps leaving the loop when its condition is false.
+    exits: Vec<CodeLocation>,
     start_label: Label,
+    start_block: NodeIndex,
+}
+
+/// Memory `offset` bytes past the address `base` holds.
+struct Place {
+    base: Operand,
+    offset: usize,
 }
 
 pub struct CodeGeneratorTac<'a> {
     cfg: ControlFlowGraph,
     current_block: Option<NodeIndex>,
     scope: NodeIndex,
+    /// Temporaries standing for the locals that share their name with a variable declared before
+    /// them in the same function, keyed by their scope and name.
+    shadowing: HashMap<(NodeIndex, String), Operand>,
+    /// Operands holding the address of the variables that live in memory: structs, and variables
+    /// whose address is taken. Keyed by the scope declaring them and their name, so that
+    /// variables of the same name in different scopes get memory of their own.
+    addresses: HashMap<(NodeIndex, String), Operand>,
+    /// Globals that live in memory, whose operand stands for the address of their memory.
+    memory_globals: HashSet<String>,
+    /// Source of the statement being lowered, attached to each instruction emitted for it.
+    span: Option<SourceSpan>,
     labels: LabelGenerators,
     ast: &'a Ast,
-    types: &'a TypeCollection,
+    types: &'a mut TypeCollection,
     symbols: &'a mut SymbolTableGraph,
     loop_stack: Vec<PartialLoop>,
 }
@@ -70,9 +91,14 @@ impl<'a> CodeGeneratorTac<'a> {
         new_block_idx
     }
 
-    fn instruction(&mut self, instruction: InstructionInstance) -> CodeLocation {
+    fn instruction(&mut self, instruction: Instruction) -> CodeLocation {
+        let span = self.span;
         let block = self.current_block_mut().unwrap();
-        block.instructions.push(instruction);
+        block.instructions.push(InstructionInstance {
+            instruction,
+            live: Default::default(),
+            span,
+        });
         let instruction = block.instructions.len() - 1;
         CodeLocation::new(self.current_block.unwrap(), instruction)
     }
@@ -84,22 +110,16 @@ impl<'a> CodeGeneratorTac<'a> {
         right: Operand,
         left: Option<Operand>,
     ) -> CodeLocation {
-        self.instruction(InstructionInstance {
-            live: Default::default(),
-            instruction: Instruction::Assignment(AssignmentInstruction {
-                target,
-                operator,
-                right,
-                left,
-            }),
-        })
+        self.instruction(Instruction::Assignment(AssignmentInstruction {
+            target,
+            operator,
+            right,
+            left,
+        }))
     }
 
     fn copy(&mut self, dst: Operand, src: Operand) -> CodeLocation {
-        self.instruction(InstructionInstance {
-            live: Default::default(),
-            instruction: Instruction::Copy(CopyInstruction { dst, src }),
-        })
+        self.instruction(Instruction::Copy(CopyInstruction { dst, src }))
     }
 
     fn conditional_jump(
@@ -109,67 +129,98 @@ impl<'a> CodeGeneratorTac<'a> {
         right: Operand,
         left: Option<Operand>,
     ) -> CodeLocation {
-        self.instruction(InstructionInstance {
-            live: Default::default(),
-            instruction: Instruction::ConditionalJump(ConditionalJumpInstruction {
-                target,
-                operator,
-                right,
-                left,
-            }),
-        })
+        self.instruction(Instruction::ConditionalJump(ConditionalJumpInstruction {
+            target,
+            operator,
+            right,
+            left,
+        }))
     }
 
     fn jump(&mut self, target: Operand) -> CodeLocation {
-        self.instruction(InstructionInstance {
-            live: Default::default(),
-            instruction: Instruction::Jump(JumpInstruction { target }),
-        })
+        self.instruction(Instruction::Jump(JumpInstruction { target }))
     }
 
     fn param(&mut self, param: Operand) -> CodeLocation {
-        self.instruction(InstructionInstance {
-            live: Default::default(),
-            instruction: Instruction::Parameter(ParameterInstruction { param }),
-        })
+        self.instruction(Instruction::Parameter(ParameterInstruction { param }))
     }
 
     fn call(&mut self, function: String, return_target: Option<Operand>) -> CodeLocation {
-        self.instruction(InstructionInstance {
-            live: Default::default(),
-            instruction: Instruction::FunctionCall(FunctionCallInstruction {
-                function,
-                return_target,
-            }),
-        })
+        self.instruction(Instruction::FunctionCall(FunctionCallInstruction {
+            function,
+            return_target,
+        }))
     }
 
     fn _return(&mut self, value: Option<Operand>) -> CodeLocation {
-        self.instruction(InstructionInstance {
-            live: Default::default(),
-            instruction: Instruction::Return(ReturnInstruction { value }),
-        })
+        self.instruction(Instruction::Return(ReturnInstruction { value }))
     }
 
-    fn function(&mut self, label: String) -> CodeLocation {
-        self.instruction(InstructionInstance {
-            live: Default::default(),
-            instruction: Instruction::Function(FunctionInstruction { label }),
-        })
+    fn function(&mut self, label: String, params: Vec<Operand>) -> CodeLocation {
+        self.instruction(Instruction::Function(FunctionInstruction { label, params }))
     }
 
     fn end_function(&mut self, label: String) -> CodeLocation {
-        self.instruction(InstructionInstance {
-            live: Default::default(),
-            instruction: Instruction::EndFunction(EndFunctionInstruction { label }),
-        })
+        self.instruction(Instruction::EndFunction(EndFunctionInstruction { label }))
     }
 
     fn extern_(&mut self, label: String) -> CodeLocation {
-        self.instruction(InstructionInstance {
-            live: Default::default(),
-            instruction: Instruction::Extern(ExternInstruction { label }),
-        })
+        self.instruction(Instruction::Extern(ExternInstruction { label }))
+    }
+
+    fn alloca(&mut self, target: Operand, ty: Index) -> CodeLocation {
+        let SizeAlign { size, align } = self.types.size_align(ty, Bitness::_64);
+        self.instruction(Instruction::Alloca(AllocaInstruction {
+            target,
+            size,
+            align: align.value(),
+        }))
+    }
+
+    fn load(&mut self, target: Operand, place: Place, size: usize) -> CodeLocation {
+        self.instruction(Instruction::Load(LoadInstruction {
+            target,
+            address: place.base,
+            offset: place.offset,
+            size,
+        }))
+    }
+
+    fn store(&mut self, place: Place, value: Operand, size: usize) -> CodeLocation {
+        self.instruction(Instruction::Store(StoreInstruction {
+            address: place.base,
+            offset: place.offset,
+            value,
+            size,
+        }))
+    }
+
+    fn memory_copy(&mut self, dst: Operand, src: Operand, size: usize) -> CodeLocation {
+        self.instruction(Instruction::MemoryCopy(MemoryCopyInstruction {
+            dst,
+            src,
+            size,
+        }))
+    }
+
+    fn bounds_check(&mut self, index: Operand, length: Operand) -> CodeLocation {
+        self.instruction(Instruction::BoundsCheck(BoundsCheckInstruction {
+            index,
+            length,
+        }))
+    }
+
+    /// Makes `jumps` go to `block`.
+    fn patch(&mut self, jumps: Vec<CodeLocation>, block: NodeIndex) {
+        let label = self.cfg[block].label.clone();
+        for jump in jumps {
+            if let Instruction::ConditionalJump(ConditionalJumpInstruction { target, .. })
+            | Instruction::Jump(JumpInstruction { target }) = jump.instruction_mut(&mut self.cfg)
+            {
+                *target = Operand::Label(label.clone());
+            }
+            self.cfg.add_edge(jump.block, block, ());
+        }
     }
 
     fn parent_scope(&mut self) {
@@ -180,12 +231,9 @@ impl<'a> CodeGeneratorTac<'a> {
 }
 
 impl<'a> PipelineStage for CodeGeneratorTac<'a> {
-    type Input = (&'a Ast, &'a mut SymbolTableGraph, &'a TypeCollection);
+    type Input = (&'a Ast, &'a mut SymbolTableGraph, &'a mut TypeCollection);
     type Options = ();
-    type Output = (
-        ControlFlowGraph,
-        HashMap<FunctionRange, Vec<LivenessInterval>>,
-    );
+    type Output = ControlFlowGraph;
 
     fn new((ast, symbols, types): Self::Input) -> Self {
         Self {
@@ -195,12 +243,18 @@ impl<'a> PipelineStage for CodeGeneratorTac<'a> {
             labels: LabelGenerators {
                 temp: LabelGenerator::new("$t", None),
                 loop_start: LabelGenerator::new("$lstart_", None),
+                loop_body: LabelGenerator::new("$lbody_", None),
                 loop_end: LabelGenerator::new("$lend_", None),
                 cond_then: LabelGenerator::new("$cthen_", None),
                 cond_else: LabelGenerator::new("$celse_", None),
                 cond_end: LabelGenerator::new("$cend_", None),
+                short_circuit: LabelGenerator::new("$sc_", None),
             },
             scope: symbols.root,
+            shadowing: Default::default(),
+            addresses: Default::default(),
+            memory_globals: Default::default(),
+            span: None,
             symbols,
             types,
             loop_stack: Default::default(),
@@ -212,26 +266,22 @@ impl<'a> PipelineStage for CodeGeneratorTac<'a> {
         _diag: &mut dyn DiagnosticConsumer,
         _: Self::Options,
     ) -> Option<Self::Output> {
+        self.types
+            .compute_memory_layouts::<CMemoryLayoutBuilder>(Bitness::_64);
+        self.declare_globals();
         AstVisitor::new()
             .without_ifs()
+            .without_block_end_exprs()
             .visit(self.ast, |ty, node, _| self.traverse(ty, node));
-        let mut intervals: HashMap<FunctionRange, Vec<LivenessInterval>> = Default::default();
-        for interval in self.liveness_analysis() {
-            if let Some(func) = self
-                .cfg
-                .functions
-                .iter()
-                .find(|f| f.contains(interval.span))
-            {
-                intervals.entry(*func).or_default().push(interval);
-            }
-        }
-        Some((self.cfg, intervals))
+        Some(self.cfg)
     }
 }
 
 impl<'a> CodeGeneratorTac<'a> {
     fn traverse(&mut self, ty: TraversalType, node: AstNode<'_>) -> AstVisitorAction {
+        if let (TraversalType::Preorder, AstNode::Stmt(stmt)) = (ty, node) {
+            self.span = Some(stmt.span);
+        }
         match (ty, node) {
             (
                 TraversalType::Preorder,
@@ -261,6 +311,13 @@ impl<'a> CodeGeneratorTac<'a> {
                     ..
                 }),
             ) => self.expr_stmt(stmt),
+            (
+                TraversalType::Preorder,
+                AstNode::Stmt(Sourced {
+                    value: Stmt::ReturnStmt(stmt),
+                    ..
+                }),
+            ) => self.return_stmt(stmt),
             (
                 TraversalType::Preorder,
                 AstNode::Stmt(Sourced {
@@ -271,31 +328,197 @@ impl<'a> CodeGeneratorTac<'a> {
             (
                 TraversalType::Postorder,
                 AstNode::Stmt(Sourced {
-                    value: Stmt::WhileStmt(_),
+                    value: Stmt::WhileStmt(stmt),
                     ..
                 }),
-            ) => self.end_while_stmt(),
+            ) => self.end_while_stmt(stmt),
             _ => {}
         };
         AstVisitorAction::Continue
     }
 
     fn begin_func_decl_stmt(&mut self, decl: &FuncDeclStmt) {
-        let Some((scope, _, _)) = self.symbols.lookup(self.scope, decl.name.value.as_str()) else {
+        let Some(scope) = self
+            .symbols
+            .lookup(self.scope, decl.name.value.as_str())
+            .and_then(|(_, _, entry)| entry.table)
+        else {
             return;
         };
         self.scope = scope;
+        self.shadowing.clear();
+        self.addresses.clear();
         self.labels.temp = LabelGenerator::new("$t", None);
         self.block(decl.name.value.clone(), false, true);
-        if decl.body.is_some() {
-            self.function(decl.name.value.clone());
+        if let Some(body) = &decl.body {
+            let params = decl
+                .params
+                .iter()
+                .map(|param| Operand::Variable(param.value.name.value.clone()))
+                .collect();
+            self.function(decl.name.value.clone(), params);
+            self.allocate_locals(scope, body);
         } else if decl.is_extern {
             self.extern_(decl.name.value.clone());
         }
     }
 
+    /// Gives the variables of the function in `scope` that live in memory their stack memory, and
+    /// locals that shadow a variable of the same name their own temporary. Parameters of structs
+    /// already hold the address of the copy the caller made.
+    fn allocate_locals(&mut self, scope: NodeIndex, body: &SourcedExpr) {
+        let address_taken = self.taken_addresses(body);
+        let variables = self
+            .symbols
+            .nested_scopes(scope)
+            .into_iter()
+            .flat_map(|scope| {
+                self.symbols.graph[scope]
+                    .entries
+                    .iter()
+                    .map(move |entry| (scope, entry))
+            })
+            .filter(|(_, entry)| {
+                matches!(
+                    entry.ty,
+                    SymbolTableEntryType::Parameter | SymbolTableEntryType::LocalVar
+                )
+            })
+            .filter_map(|(scope, entry)| {
+                let is_param = entry.ty == SymbolTableEntryType::Parameter;
+                Some((
+                    (scope, entry.name.clone()),
+                    entry.var_type?,
+                    is_param,
+                    entry.span,
+                ))
+            })
+            .collect::<Vec<_>>();
+        let mut names = HashSet::new();
+        for (key, ty, is_param, span) in variables {
+            let variable = if names.insert(key.1.clone()) {
+                Operand::Variable(key.1.clone())
+            } else {
+                let temp = self.alloc_temp(ty, span);
+                self.shadowing.insert(key.clone(), temp.clone());
+                temp
+            };
+            if self.is_memory(ty) {
+                if is_param {
+                    self.types.reference(ty);
+                } else {
+                    self.alloca(variable.clone(), ty);
+                }
+                self.addresses.insert(key, variable);
+            } else if address_taken.contains(&key) {
+                let pointer = self.types.reference(ty);
+                let address = self.alloc_temp(pointer, span);
+                self.alloca(address.clone(), ty);
+                if is_param {
+                    let place = Place {
+                        base: address.clone(),
+                        offset: 0,
+                    };
+                    let size = self.size(ty);
+                    self.store(place, variable, size);
+                }
+                self.addresses.insert(key, address);
+            }
+        }
+    }
+
+    /// Variables whose address `body` takes, keyed by their scope and name.
+    fn taken_addresses(&self, body: &SourcedExpr) -> HashSet<(NodeIndex, String)> {
+        let mut address_taken = HashSet::new();
+        AstVisitor::new().visit_expr(body, NodeType::Root, &mut |_, node, _| {
+            if let AstNode::Expr(Sourced {
+                value:
+                    TypedExpr {
+                        expr: Expr::OperatorExpr(OperatorExpr { operator, args }),
+                        ..
+                    },
+                ..
+            }) = node
+            {
+                if let [Sourced {
+                    value:
+                        TypedExpr {
+                            expr: Expr::IdentExpr(ident),
+                            ..
+                        },
+                    span,
+                }] = args.as_slice()
+                {
+                    if *operator == operators::ADDRESS_OF {
+                        if let Some(scope) = self.declaring_scope(&ident.ident, *span) {
+                            address_taken.insert((scope, ident.ident.clone()));
+                        }
+                    }
+                }
+            }
+            AstVisitorAction::Continue
+        });
+        address_taken
+    }
+
+    /// Declares the variables of the root scope. Those whose type lives in memory or whose address
+    /// is taken anywhere get memory of their own instead of a quadword.
+    fn declare_globals(&mut self) {
+        let root = self.symbols.root;
+        let mut address_taken = HashSet::new();
+        for stmt in self.ast {
+            let Stmt::FuncDeclStmt(FuncDeclStmt {
+                name,
+                body: Some(body),
+                ..
+            }) = &stmt.value
+            else {
+                continue;
+            };
+            if let Some(scope) = self
+                .symbols
+                .lookup(root, name.value.as_str())
+                .and_then(|(_, _, entry)| entry.table)
+            {
+                self.scope = scope;
+                address_taken.extend(self.taken_addresses(body));
+            }
+        }
+        self.scope = root;
+
+        let globals = self.symbols.graph[root]
+            .entries
+            .iter()
+            .filter(|entry| entry.ty == SymbolTableEntryType::LocalVar)
+            .map(|entry| (entry.name.clone(), entry.var_type))
+            .collect::<Vec<_>>();
+        for (name, ty) in globals {
+            let memory = ty
+                .filter(|ty| self.is_memory(*ty) || address_taken.contains(&(root, name.clone())))
+                .map(|ty| self.types.size_align(ty, Bitness::_64));
+            if memory.is_some() {
+                self.memory_globals.insert(name.clone());
+            }
+            self.cfg.globals.push(GlobalVariable {
+                name,
+                init: None,
+                memory,
+            });
+        }
+    }
+
     fn end_func_decl_stmt(&mut self, decl: &FuncDeclStmt) {
-        if decl.body.is_some() {
+        if let Some(body) = &decl.body {
+            if let Some(value) = self.block_end_expr(body) {
+                if !self.types.eq(body.ty, self.types.unit()) {
+                    self._return(Some(value));
+                }
+            }
+            // The epilogue belongs to the closing brace.
+            let span = body.span;
+            self.span = Some(SourceSpan::from(
+                span.offset() + span.len().saturating_sub(1),
+            ));
             self.end_function(decl.name.value.clone());
             self.cfg.end_function();
         }
@@ -303,51 +526,71 @@ impl<'a> CodeGeneratorTac<'a> {
     }
 
     fn var_decl_stmt(&mut self, decl: &VarDeclStmt) {
-        if let Some(init) = &decl.decl.init {
+        if self.scope == self.symbols.root {
+            let init = decl.decl.init.as_ref().map(|init| match &init.value.expr {
+                Expr::LitExpr(expr) => self.lit_expr(expr),
+                _ => Operand::Placeholder,
+            });
+            let name = &decl.decl.name.value;
+            match self
+                .cfg
+                .globals
+                .iter_mut()
+                .find(|global| global.name == *name)
+            {
+                Some(global) => global.init = init,
+                None => self.cfg.globals.push(GlobalVariable {
+                    name: name.clone(),
+                    init,
+                    memory: None,
+                }),
+            }
+        } else if let Some(init) = &decl.decl.init {
             let temp = self.expr(init);
-            self.copy(Operand::Variable(decl.decl.name.value.clone()), temp);
+            let name = &decl.decl.name.value;
+            if let Some(base) = self.address_of(name, decl.decl.span) {
+                self.write(Place { base, offset: 0 }, temp, init.ty, init.span);
+            } else {
+                let target = self.variable(name, decl.decl.span);
+                self.copy(target, temp);
+            }
         }
     }
 
     fn begin_while_stmt(&mut self, stmt: &WhileStmt) {
         let start_label = self.labels.loop_start.next();
-        self.block(start_label.to_string(), true, false);
-        let condition = self.expr(&stmt.condition);
-        let jump_instruction = self.conditional_jump(
-            Operand::Placeholder,
-            operators::EQUALS,
-            condition,
-            Some(Operand::Literal(Lit::Boolean(true))),
-        );
+        let start_block = self.block(start_label.to_string(), true, false);
+        let exits = self.branch(&stmt.condition, false);
+        // A conditional jump ends its block, so the body starts a new one.
+        let body_label = self.labels.loop_body.next();
+        self.block(body_label.to_string(), true, false);
         self.loop_stack.push(PartialLoop {
-            jump_instruction,
+            exits,
             start_label,
+            start_block,
         });
     }
 
-    fn end_while_stmt(&mut self) {
+    fn end_while_stmt(&mut self, stmt: &WhileStmt) {
+        let _ = self.block_end_expr(&stmt.body);
         let loop_ = self.loop_stack.pop().expect("loop stack is empty");
         let end_label = self.labels.loop_end.next();
         self.jump(Operand::Label(loop_.start_label.to_string()));
-        if let Instruction::ConditionalJump(inst) =
-            &mut loop_.jump_instruction.instruction_mut(&mut self.cfg)
-        {
-            inst.target = Operand::Label(end_label.to_string());
-        }
-        self.cfg.add_edge(
-            self.current_block.unwrap(),
-            loop_.jump_instruction.block,
-            (),
-        );
-        let end_block = self.block(end_label.to_string(), false, false);
         self.cfg
-            .add_edge(loop_.jump_instruction.block, end_block, ());
+            .add_edge(self.current_block.unwrap(), loop_.start_block, ());
+        let end_block = self.block(end_label.to_string(), false, false);
+        self.patch(loop_.exits, end_block);
     }
 
     fn expr_stmt(&mut self, stmt: &ExprStmt) {
         let _ = self.expr(&stmt.expr);
     }
 
+    fn return_stmt(&mut self, stmt: &ReturnStmt) {
+        let value = stmt.expr.as_ref().map(|expr| self.expr(expr));
+        self._return(value);
+    }
+
     fn expr(&mut self, expr: &SourcedExpr) -> Operand {
         match expr {
             Sourced {
@@ -361,11 +604,48 @@ impl<'a> CodeGeneratorTac<'a> {
             Sourced {
                 value:
                     TypedExpr {
-                        expr: Expr::IdentExpr(expr),
+                        expr: Expr::IdentExpr(inner),
+                        ..
+                    },
+                span,
+            } => {
+                if self.address_of(&inner.ident, *span).is_some() {
+                    self.load_expr(expr)
+                } else {
+                    self.ident_expr(inner, *span)
+                }
+            }
+            Sourced {
+                value:
+                    TypedExpr {
+                        expr: Expr::MemberExpr(inner),
+                        ..
+                    },
+                ..
+            } => {
+                // The length of an array is known, it is not stored with the array.
+                let ty = self.types.dereference_all(inner.expr.ty);
+                match self.types.element(ty) {
+                    Some((_, Some(len))) => Operand::Literal(Lit::Integer(len as isize)),
+                    _ => self.load_expr(expr),
+                }
+            }
+            Sourced {
+                value:
+                    TypedExpr {
+                        expr: Expr::IndexExpr(_),
+                        ..
+                    },
+                ..
+            } => self.load_expr(expr),
+            Sourced {
+                value:
+                    TypedExpr {
+                        expr: Expr::ArrayExpr(_),
                         ..
                     },
                 ..
-            } => self.ident_expr(expr),
+            } => self.array_expr(expr),
             Sourced {
                 value:
                     TypedExpr {
@@ -398,16 +678,72 @@ impl<'a> CodeGeneratorTac<'a> {
                     },
                 ..
             } => self.block_expr(expr).unwrap_or(Operand::Placeholder),
-            _ => Operand::Placeholder,
         }
     }
 
+    fn block_end_expr(&mut self, block: &SourcedExpr) -> Option<Operand> {
+        let Sourced {
+            value:
+                TypedExpr {
+                    expr:
+                        Expr::BlockExpr(BlockExpr {
+                            expr: Some(expr), ..
+                        }),
+                    ..
+                },
+            ..
+        } = block
+        else {
+            return None;
+        };
+        self.span = Some(expr.span);
+        Some(self.expr(expr))
+    }
+
     fn lit_expr(&mut self, expr: &LitExpr) -> Operand {
         Operand::Literal(expr.lit.clone())
     }
 
-    fn ident_expr(&mut self, expr: &IdentExpr) -> Operand {
-        Operand::Variable(expr.ident.clone())
+    fn ident_expr(&mut self, expr: &IdentExpr, span: SourceSpan) -> Operand {
+        self.variable(&expr.ident, span)
+    }
+
+    /// Scope declaring the variable `name` that is visible at `span`.
+    fn declaring_scope(&self, name: &str, span: SourceSpan) -> Option<NodeIndex> {
+        let scope = self.symbols.scope_at(self.scope, span.offset());
+        self.symbols.lookup(scope, name).map(|(scope, _, _)| scope)
+    }
+
+    /// The operand naming the variable `name` visible at `span`, which is global unless the
+    /// current function declares it.
+    fn variable(&self, name: &str, span: SourceSpan) -> Operand {
+        let scope = self.symbols.scope_at(self.scope, span.offset());
+        match self.symbols.lookup(scope, name) {
+            Some((scope, _, entry))
+                if scope == self.symbols.root && entry.ty == SymbolTableEntryType::LocalVar =>
+            {
+                Operand::Global(name.to_string())
+            }
+            Some((scope, _, _)) => self
+                .shadowing
+                .get(&(scope, name.to_string()))
+                .cloned()
+                .unwrap_or_else(|| Operand::Variable(name.to_string())),
+            None => Operand::Variable(name.to_string()),
+        }
+    }
+
+    /// Operand holding the address of the variable `name` visible at `span`, if it lives in
+    /// memory.
+    fn address_of(&self, name: &str, span: SourceSpan) -> Option<Operand> {
+        let scope = self.declaring_scope(name, span)?;
+        if scope == self.symbols.root {
+            return self
+                .memory_globals
+                .contains(name)
+                .then(|| Operand::Global(name.to_string()));
+        }
+        self.addresses.get(&(scope, name.to_string())).cloned()
     }
 
     fn operator_expr(&mut self, expr: &SourcedExpr) -> Operand {
@@ -425,15 +761,41 @@ impl<'a> CodeGeneratorTac<'a> {
         };
         assert!(!inner.args.is_empty() && inner.args.len() <= 2);
 
+        if matches!(
+            inner.operator,
+            operators::LOGICAL_AND | operators::LOGICAL_OR
+        ) {
+            return self.logical_expr(expr, *ty, *span);
+        }
         if inner.args.len() == 1 {
+            match inner.operator {
+                operators::DEREFERENCE => return self.load_expr(expr),
+                operators::ADDRESS_OF => {
+                    if let Some(place) = self.place(&inner.args[0]) {
+                        let address = self.address(place, inner.args[0].ty, *span);
+                        return match self.types.element(inner.args[0].ty) {
+                            Some((_, Some(len))) => self.slice(address, len, *ty, *span),
+                            _ => address,
+                        };
+                    }
+                }
+                _ => {}
+            }
             let right = self.expr(&inner.args[0]);
             let temp = self.alloc_temp(*ty, *span);
             self.assign(temp.clone(), inner.operator, right, None);
             temp
         } else if inner.args.len() == 2 {
+            if inner.operator == operators::ASSIGN {
+                if let Some(place) = self.place(&inner.args[0]) {
+                    let right = self.expr(&inner.args[1]);
+                    self.write(place, right.clone(), inner.args[0].ty, *span);
+                    return right;
+                }
+            }
             let left = self.expr(&inner.args[0]);
             let right = self.expr(&inner.args[1]);
-            if inner.operator == "=" {
+            if inner.operator == operators::ASSIGN {
                 self.copy(left.clone(), right);
                 left
             } else {
@@ -446,6 +808,276 @@ impl<'a> CodeGeneratorTac<'a> {
         }
     }
 
+    /// Where `expr` is stored, if it lives in memory. Member access and indexing go through
+    /// references, as the type checker lets them, and indices are checked against the length.
+    fn place(&mut self, expr: &SourcedExpr) -> Option<Place> {
+        match &expr.expr {
+            Expr::IdentExpr(inner) => self
+                .address_of(&inner.ident, expr.span)
+                .map(|base| Place { base, offset: 0 }),
+            Expr::MemberExpr(inner) => {
+                let (mut place, ty) = self.dereferenced_place(&inner.expr)?;
+                let (_, offset) = self.types.member(ty, &inner.ident)?;
+                place.offset += offset;
+                Some(place)
+            }
+            Expr::IndexExpr(inner) => {
+                let (place, ty) = self.dereferenced_place(&inner.expr)?;
+                let (element, len) = self.types.element(ty)?;
+                let (base, length) = match len {
+                    Some(len) => (
+                        self.address(place, ty, expr.span),
+                        Operand::Literal(Lit::Integer(len as isize)),
+                    ),
+                    None => {
+                        // The reference to a slice holds the address of its first element.
+                        let pointer = self.types.reference(element);
+                        let data = self.alloc_temp(pointer, expr.span);
+                        let size = self.size(pointer);
+                        let data_place = Place {
+                            base: place.base.clone(),
+                            offset: place.offset,
+                        };
+                        self.load(data.clone(), data_place, size);
+                        let (len_ty, offset) = self.types.member(ty, "len")?;
+                        let length = self.alloc_temp(len_ty, expr.span);
+                        let size = self.size(len_ty);
+                        let length_place = Place {
+                            base: place.base,
+                            offset: place.offset + offset,
+                        };
+                        self.load(length.clone(), length_place, size);
+                        (data, length)
+                    }
+                };
+                let index = self.expr(&inner.index);
+                self.bounds_check(index.clone(), length);
+                let pointer = self.types.reference(element);
+                let target = self.alloc_temp(pointer, expr.span);
+                let scale = self.size(element);
+                self.instruction(Instruction::Address(AddressInstruction {
+                    target: target.clone(),
+                    base,
+                    index: Some(index),
+                    scale,
+                    offset: 0,
+                }));
+                Some(Place {
+                    base: target,
+                    offset: 0,
+                })
+            }
+            Expr::OperatorExpr(OperatorExpr { operator, args })
+                if *operator == operators::DEREFERENCE && args.len() == 1 =>
+            {
+                Some(Place {
+                    base: self.expr(&args[0]),
+                    offset: 0,
+                })
+            }
+            _ if self.is_memory(expr.ty) => Some(Place {
+                base: self.expr(expr),
+                offset: 0,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Place of the value `expr` refers to through any number of references, and its type.
+    fn dereferenced_place(&mut self, expr: &SourcedExpr) -> Option<(Place, Index)> {
+        let mut ty = self.types.canonicalize(expr.ty);
+        let Some(to) = self.types.dereference(ty) else {
+            return Some((self.place(expr)?, ty));
+        };
+        let mut base = self.expr(expr);
+        ty = to;
+        while let Some(to) = self.types.dereference(ty) {
+            // A reference to a slice lives in memory, which its address stands for.
+            if !self.is_memory(ty) {
+                let temp = self.alloc_temp(ty, expr.span);
+                let size = self.size(ty);
+                self.load(temp.clone(), Place { base, offset: 0 }, size);
+                base = temp;
+            }
+            ty = to;
+        }
+        Some((Place { base, offset: 0 }, ty))
+    }
+
+    /// Loads `expr` from memory. Structs are not loaded, their address stands for them.
+    fn load_expr(&mut self, expr: &SourcedExpr) -> Operand {
+        let Some(place) = self.place(expr) else {
+            return Operand::Placeholder;
+        };
+        if self.is_memory(expr.ty) {
+            return self.address(place, expr.ty, expr.span);
+        }
+        let temp = self.alloc_temp(expr.ty, expr.span);
+        let size = self.size(expr.ty);
+        self.load(temp.clone(), place, size);
+        temp
+    }
+
+    /// Address of `place`, which holds a value of type `ty`.
+    fn address(&mut self, place: Place, ty: Index, span: SourceSpan) -> Operand {
+        if place.offset == 0 {
+            return place.base;
+        }
+        let pointer = self.types.reference(ty);
+        let target = self.alloc_temp(pointer, span);
+        self.instruction(Instruction::Address(AddressInstruction {
+            target: target.clone(),
+            base: place.base,
+            index: None,
+            scale: 1,
+            offset: place.offset,
+        }));
+        target
+    }
+
+    /// Stores `value` of type `ty` to `place`, copying the memory of structs.
+    fn write(&mut self, place: Place, value: Operand, ty: Index, span: SourceSpan) {
+        let size = self.size(ty);
+        if self.is_memory(ty) {
+            let dst = self.address(place, ty, span);
+            self.memory_copy(dst, value, size);
+        } else {
+            self.store(place, value, size);
+        }
+    }
+
+    /// Makes a reference to a slice, of type `ty`, of the `len` elements starting at `data`.
+    fn slice(&mut self, data: Operand, len: usize, ty: Index, span: SourceSpan) -> Operand {
+        let pointer = self.types.reference(ty);
+        let target = self.alloc_temp(pointer, span);
+        self.alloca(target.clone(), ty);
+        let members = self.types.memory_layouts[&ty].members().to_vec();
+        let values = [data, Operand::Literal(Lit::Integer(len as isize))];
+        for (member, value) in members.iter().zip(values) {
+            let place = Place {
+                base: target.clone(),
+                offset: member.offset,
+            };
+            self.store(place, value, member.size_align.size);
+        }
+        target
+    }
+
+    /// Stores the elements of an array literal to memory of its own.
+    fn array_expr(&mut self, expr: &SourcedExpr) -> Operand {
+        let Expr::ArrayExpr(ArrayExpr { elements }) = &expr.expr else {
+            unreachable!()
+        };
+        let Some((element, _)) = self.types.element(expr.ty) else {
+            return Operand::Placeholder;
+        };
+        let pointer = self.types.reference(expr.ty);
+        let target = self.alloc_temp(pointer, expr.span);
+        self.alloca(target.clone(), expr.ty);
+        let stride = self.size(element);
+        for (index, element_expr) in elements.iter().enumerate() {
+            let value = self.expr(element_expr);
+            let place = Place {
+                base: target.clone(),
+                offset: index * stride,
+            };
+            self.write(place, value, element, element_expr.span);
+        }
+        target
+    }
+
+    /// Lowers `&&` or `||` outside of a condition to a temporary that is only set to true on
+    /// the path where the condition holds, so the second operand is only evaluated if it
+    /// decides the result.
+    fn logical_expr(&mut self, expr: &SourcedExpr, ty: Index, span: SourceSpan) -> Operand {
+        let temp = self.alloc_temp(ty, span);
+        self.copy(temp.clone(), Operand::Literal(Lit::Boolean(false)));
+        let exits = self.branch(expr, false);
+        let label = self.labels.cond_then.next().to_string();
+        self.block(label, true, false);
+        self.copy(temp.clone(), Operand::Literal(Lit::Boolean(true)));
+        let label = self.labels.cond_end.next().to_string();
+        let end_block = self.block(label, true, false);
+        self.patch(exits, end_block);
+        temp
+    }
+
+    /// Lowers `condition` to jumps that are taken when it evaluates to `jump_if`, and falls
+    /// through otherwise. `&&` and `||` only evaluate their second operand when the first does
+    /// not decide the result, and comparisons jump on the operator itself instead of computing a
+    /// bool first. The targets of the returned jumps are left for the caller to patch, and the
+    /// block they end has to be followed by a new one.
+    fn branch(&mut self, condition: &SourcedExpr, jump_if: bool) -> Vec<CodeLocation> {
+        if let Expr::OperatorExpr(inner) = &condition.expr {
+            match (inner.operator, inner.args.as_slice()) {
+                (operators::LOGICAL_AND | operators::LOGICAL_OR, [left, right]) => {
+                    let is_and = inner.operator == operators::LOGICAL_AND;
+                    if is_and != jump_if {
+                        // Either operand alone takes the jump, like a false one for `&&`.
+                        let mut jumps = self.branch(left, jump_if);
+                        self.short_circuit_block();
+                        jumps.extend(self.branch(right, jump_if));
+                        return jumps;
+                    }
+                    // The first operand can only skip the second one.
+                    let skips = self.branch(left, !jump_if);
+                    self.short_circuit_block();
+                    let jumps = self.branch(right, jump_if);
+                    let after = self.short_circuit_block();
+                    self.patch(skips, after);
+                    return jumps;
+                }
+                (operators::LOGICAL_NOT, [operand]) => return self.branch(operand, !jump_if),
+                (operator, [left, right]) if !self.is_float(left.ty) => {
+                    let operator = if jump_if {
+                        Some(operator)
+                    } else {
+                        negated(operator)
+                    };
+                    if let Some(operator) = operator.filter(|op| is_relational(op)) {
+                        let left = self.expr(left);
+                        let right = self.expr(right);
+                        return vec![self.conditional_jump(
+                            Operand::Placeholder,
+                            operator,
+                            right,
+                            Some(left),
+                        )];
+                    }
+                }
+                _ => {}
+            }
+        }
+        let value = self.expr(condition);
+        vec![self.conditional_jump(
+            Operand::Placeholder,
+            operators::EQUALS,
+            value,
+            Some(Operand::Literal(Lit::Boolean(jump_if))),
+        )]
+    }
+
+    /// Starts the block after a jump of a short-circuit operator.
+    fn short_circuit_block(&mut self) -> NodeIndex {
+        let label = self.labels.short_circuit.next().to_string();
+        self.block(label, true, false)
+    }
+
+    fn is_float(&self, ty: Index) -> bool {
+        matches!(self.types.kind(ty), TypeKind::Float | TypeKind::Double)
+    }
+
+    /// Whether values of `ty` live in memory, with operands holding their address instead. Arrays
+    /// do even without elements, so that indexing them is still checked against their length.
+    fn is_memory(&self, ty: Index) -> bool {
+        matches!(self.types.kind(ty), TypeKind::Memory)
+            && (self.size(ty) > 0 || self.types.element(ty).is_some())
+    }
+
+    fn size(&self, ty: Index) -> usize {
+        self.types.size_align(ty, Bitness::_64).size
+    }
+
     fn call_expr(&mut self, expr: &SourcedExpr) -> Option<Operand> {
         let Sourced {
             value:
@@ -475,9 +1107,21 @@ impl<'a> CodeGeneratorTac<'a> {
         } else {
             None
         };
+        // Structs are passed as the address of a copy, which the callee is free to change.
         let args = args
             .iter()
-            .map(|arg| self.expr(arg))
+            .map(|arg| {
+                let value = self.expr(arg);
+                if !self.is_memory(arg.ty) {
+                    return value;
+                }
+                let pointer = self.types.reference(arg.ty);
+                let copy = self.alloc_temp(pointer, arg.span);
+                self.alloca(copy.clone(), arg.ty);
+                let size = self.size(arg.ty);
+                self.memory_copy(copy.clone(), value, size);
+                copy
+            })
             .rev()
             .collect::<Vec<_>>();
         for arg in args {
@@ -505,14 +1149,7 @@ impl<'a> CodeGeneratorTac<'a> {
             None
         };
 
-        let condition = self.expr(&expr.condition);
-        let cond_jump = self.conditional_jump(
-            Operand::Placeholder,
-            operators::EQUALS,
-            condition,
-            Some(Operand::Literal(Lit::Boolean(false))),
-        );
-        let prev_block = self.current_block.unwrap();
+        let exits = self.branch(&expr.condition, false);
 
         let label = self.labels.cond_then.next().to_string();
         let then_block = self.block(label, true, false);
@@ -525,11 +1162,8 @@ impl<'a> CodeGeneratorTac<'a> {
             let jump = self.jump(Operand::Placeholder);
 
             let label = self.labels.cond_else.next().to_string();
-            if let Instruction::ConditionalJump(jump) = cond_jump.instruction_mut(&mut self.cfg) {
-                jump.target = Operand::Label(label.clone());
-            }
             let else_block = self.block(label, false, false);
-            self.cfg.add_edge(prev_block, else_block, ());
+            self.patch(exits, else_block);
             let else_ = self.expr(else_);
             if let Some(temp) = &temp {
                 self.copy(temp.clone(), else_);
@@ -544,11 +1178,8 @@ impl<'a> CodeGeneratorTac<'a> {
             self.cfg.add_edge(else_block, next_block, ());
         } else {
             let label = self.labels.cond_end.next().to_string();
-            if let Instruction::ConditionalJump(jump) = cond_jump.instruction_mut(&mut self.cfg) {
-                jump.target = Operand::Label(label.clone());
-            }
             let next_block = self.block(label, true, false);
-            self.cfg.add_edge(prev_block, next_block, ());
+            self.patch(exits, next_block);
         }
 
         temp
@@ -561,21 +1192,7 @@ impl<'a> CodeGeneratorTac<'a> {
             .visit_block_expr(expr, NodeType::Root, false, &mut |ty, node, _| {
                 self.traverse(ty, node)
             });
-        let Sourced {
-            value:
-                TypedExpr {
-                    expr:
-                        Expr::BlockExpr(BlockExpr {
-                            expr: Some(expr), ..
-                        }),
-                    ..
-                },
-            ..
-        } = expr
-        else {
-            return None;
-        };
-        Some(self.expr(expr))
+        self.block_end_expr(expr)
     }
 }
 
@@ -597,132 +1214,27 @@ impl<'a> CodeGeneratorTac<'a> {
     }
 }
 
-impl<'a> CodeGeneratorTac<'a> {
-    fn liveness_read_written(&mut self) {
-        for block in self.cfg.node_weights_mut() {
-            let mut written_so_far = HashSet::new();
-            let mut block_use = HashSet::new();
-            let mut block_def = HashSet::new();
-            for inst in block.instructions.iter_mut() {
-                inst.live.read = inst.instruction.variables_read();
-                inst.live.written = inst.instruction.variables_written();
-                for var in inst.live.read.iter() {
-                    if !written_so_far.contains(var) {
-                        block_use.insert(var.clone());
-                    }
-                }
-                for var in inst.live.written.iter() {
-                    written_so_far.insert(var.clone());
-                    block_def.insert(var.clone());
-                }
-            }
-            block.live.read = block_use;
-            block.live.written = block_def;
-        }
-    }
-
-    fn liveness_per_instruction(&mut self) {
-        for block in self.cfg.node_weights_mut() {
-            let mut output = block.live.output.clone();
-            for inst in block.instructions.iter_mut().rev() {
-                inst.live.output = output;
-                let diff = inst
-                    .live
-                    .output
-                    .difference(&inst.live.written)
-                    .cloned()
-                    .collect();
-                output = inst.live.read.union(&diff).cloned().collect();
-                inst.live.input = output.clone();
-            }
-        }
-    }
-
-    fn liveness_analysis(&mut self) -> Vec<LivenessInterval> {
-        self.liveness_read_written();
-
-        let exit_block = self.current_block.unwrap();
-        let reversed_blocks = Reversed(&self.cfg.graph);
-
-        let mut worklist = Dfs::new(&reversed_blocks, exit_block)
-            .iter(&reversed_blocks)
-            .collect::<VecDeque<_>>();
-        let mut in_worklist = worklist.iter().cloned().collect::<HashSet<_>>();
-
-        while let Some(block_idx) = worklist.pop_front() {
-            in_worklist.remove(&block_idx);
-
-            let old_input = self.cfg[block_idx].live.input.clone();
-            let old_output = self.cfg[block_idx].live.output.clone();
-
-            let output = self
-                .cfg
-                .neighbors(block_idx)
-                .flat_map(|block| self.cfg[block].live.input.iter().cloned())
-                .collect::<HashSet<_>>();
-
-            let diff = output
-                .difference(&self.cfg[block_idx].live.written)
-                .cloned()
-                .collect();
-            let input = self.cfg[block_idx]
-                .live
-                .read
-                .union(&diff)
-                .cloned()
-                .collect();
-
-            if old_input != input || old_output != output {
-                for pred in self.cfg.neighbors_directed(block_idx, Direction::Incoming) {
-                    if !in_worklist.contains(&pred) {
-                        worklist.push_back(pred);
-                        in_worklist.insert(pred);
-                    }
-                }
-            }
-
-            self.cfg[block_idx].live.input = input;
-            self.cfg[block_idx].live.output = output;
-        }
+fn is_relational(operator: &str) -> bool {
+    matches!(
+        operator,
+        operators::EQUALS
+            | operators::NOT_EQUALS
+            | operators::LESS
+            | operators::LESS_EQUALS
+            | operators::GREATER
+            | operators::GREATER_EQUALS
+    )
+}
 
-        self.liveness_per_instruction();
-        self.liveness_intervals()
-    }
-
-    pub fn liveness_intervals(&self) -> Vec<LivenessInterval> {
-        let mut result: Vec<LivenessInterval> = Default::default();
-        let mut active_intervals: HashMap<String, LivenessInterval> = HashMap::new();
-        for node in self.cfg.node_indices() {
-            let block = &self.cfg[node];
-            for (inst_idx, inst) in block.instructions.iter().enumerate() {
-                let loc = CodeLocation::new(node, inst_idx);
-                for live_var in &inst.live.input {
-                    active_intervals
-                        .entry(live_var.clone())
-                        .or_insert_with(|| LivenessInterval {
-                            variable: live_var.clone(),
-                            span: CodeSpan::from_location(loc),
-                            uses: Vec::new(),
-                        });
-                }
-                for read_var in &inst.live.read {
-                    if let Some(interval) = active_intervals.get_mut(read_var) {
-                        interval.uses.push(loc);
-                    }
-                }
-                let (mut still_live, now_dead) = active_intervals
-                    .drain()
-                    .partition::<HashMap<_, _>, _>(|(v, _)| inst.live.output.contains(v));
-                for (_, interval) in still_live.iter_mut() {
-                    interval.span.end = loc;
-                }
-                active_intervals = still_live;
-                for (_, mut interval) in now_dead {
-                    interval.span.end = CodeLocation::new(node, inst_idx + 1);
-                    result.push(interval);
-                }
-            }
-        }
-        result
-    }
+/// Comparison that holds exactly when `operator` does not.
+fn negated(operator: &str) -> Option<&'static str> {
+    Some(match operator {
+        operators::EQUALS => operators::NOT_EQUALS,
+        operators::NOT_EQUALS => operators::EQUALS,
+        operators::LESS => operators::GREATER_EQUALS,
+        operators::LESS_EQUALS => operators::GREATER,
+        operators::GREATER => operators::LESS_EQUALS,
+        operators::GREATER_EQUALS => operators::LESS,
+        _ => return None,
+    })
 }
@@ -0,0 +1,390 @@
+use crate::diagnostic::DiagnosticConsumer;
+use crate::generators::x86::{AssembledFunction, AssembledModule, DataSymbol};
+use crate::pipeline::PipelineStage;
+use iced_x86::{
+    Decoder, DecoderOptions, Formatter, GasFormatter, Instruction, NasmFormatter, NumberBase,
+    OpKind, SymbolResolver, SymbolResult,
+};
+use lexion_lib::miette::NamedSource;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::Arc;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum AsmSyntax {
+    /// AT&T syntax for the GNU assembler.
+    #[default]
+    Gas,
+    /// Intel syntax for NASM.
+    Nasm,
+}
+
+impl AsmSyntax {
+    /// Extension of the source files the assembler reads.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AsmSyntax::Gas => "s",
+            AsmSyntax::Nasm => "asm",
+        }
+    }
+
+    fn comment(&self) -> &'static str {
+        match self {
+            AsmSyntax::Gas => "#",
+            AsmSyntax::Nasm => ";",
+        }
+    }
+
+    /// Turns a TAC or data label into a name the assembler accepts. TAC labels start with `$`,
+    /// which AT&T syntax reads as an immediate, so they become local labels instead.
+    fn symbol(&self, name: &str) -> String {
+        if let Some(local) = name.strip_prefix('$') {
+            return format!(".L{local}");
+        }
+        match self {
+            // A leading dot scopes a NASM label to the preceding one, which would hide data
+            // symbols from every function but the last.
+            AsmSyntax::Nasm => name.trim_start_matches('.').to_string(),
+            AsmSyntax::Gas => name.to_string(),
+        }
+    }
+}
+
+/// Names branch targets after the labels at their address and relocated operands after the
+/// symbol the linker fills in.
+struct Symbols {
+    labels: HashMap<u64, String>,
+    relocations: Vec<(usize, String)>,
+}
+
+impl SymbolResolver for Symbols {
+    fn symbol(
+        &mut self,
+        instruction: &Instruction,
+        _operand: u32,
+        instruction_operand: Option<u32>,
+        address: u64,
+        _address_size: u32,
+    ) -> Option<SymbolResult<'_>> {
+        let is_address = match instruction.op_kind(instruction_operand?) {
+            OpKind::NearBranch64 => true,
+            OpKind::Memory => instruction.is_ip_rel_memory_operand(),
+            _ => false,
+        };
+        if !is_address {
+            return None;
+        }
+        let start = instruction.ip() as usize;
+        let relocation = self
+            .relocations
+            .iter()
+            .find(|(offset, _)| (start..start + instruction.len()).contains(offset));
+        let name = match relocation {
+            Some((_, symbol)) => symbol.clone(),
+            None => self.labels.get(&address)?.clone(),
+        };
+        Some(SymbolResult::with_string(address, name))
+    }
+}
+
+/// Renders an [`AssembledModule`] as assembler source, with the Lexion source lines the
+/// instructions came from as comments.
+pub struct AsmWriter<'a> {
+    src: NamedSource<Arc<String>>,
+    module: &'a AssembledModule,
+    output: String,
+}
+
+impl<'a> PipelineStage for AsmWriter<'a> {
+    type Input = (NamedSource<Arc<String>>, &'a AssembledModule);
+    type Options = AsmSyntax;
+    type Output = String;
+
+    fn new((src, module): Self::Input) -> Self {
+        Self {
+            src,
+            module,
+            output: String::new(),
+        }
+    }
+
+    fn exec(
+        mut self,
+        _diag: &mut dyn DiagnosticConsumer,
+        syntax: Self::Options,
+    ) -> Option<Self::Output> {
+        self.header(syntax);
+        self.text(syntax);
+        self.data(syntax, ".rodata", &self.module.rodata);
+        self.data(syntax, ".data", &self.module.data);
+        self.bss(syntax);
+        match syntax {
+            AsmSyntax::Gas => self.line(".section .note.GNU-stack,\"\",@progbits"),
+            AsmSyntax::Nasm => self.line("section .note.GNU-stack noalloc noexec nowrite progbits"),
+        }
+        Some(self.output)
+    }
+}
+
+impl<'a> AsmWriter<'a> {
+    fn line(&mut self, line: impl AsRef<str>) {
+        self.output.push('\t');
+        self.output.push_str(line.as_ref());
+        self.output.push('\n');
+    }
+
+    fn label(&mut self, name: &str) {
+        let _ = writeln!(self.output, "{name}:");
+    }
+
+    fn header(&mut self, syntax: AsmSyntax) {
+        let module = self.module;
+        let _ = writeln!(
+            self.output,
+            "{} generated from {}",
+            syntax.comment(),
+            self.src.name()
+        );
+        if syntax == AsmSyntax::Nasm {
+            self.line("default rel");
+            for name in &module.externs {
+                self.line(format!("extern {name}"));
+            }
+        }
+        for function in &module.functions {
+            match syntax {
+                AsmSyntax::Gas => self.line(format!(".globl {}", function.name)),
+                AsmSyntax::Nasm => self.line(format!("global {}", function.name)),
+            }
+        }
+    }
+
+    fn text(&mut self, syntax: AsmSyntax) {
+        let module = self.module;
+        let labels = self.labels(syntax);
+        let symbols = Symbols {
+            labels: labels
+                .iter()
+                .map(|(offset, name)| (*offset as u64, name.clone()))
+                .collect(),
+            relocations: module
+                .relocations
+                .iter()
+                .map(|r| match syntax {
+                    // Calls to the C library go through the PLT, as position independent
+                    // executables require. GAS does so by itself.
+                    AsmSyntax::Nasm if module.externs.contains(&r.symbol) => {
+                        (r.offset, format!("{} wrt ..plt", r.symbol))
+                    }
+                    _ => (r.offset, syntax.symbol(&r.symbol)),
+                })
+                .collect(),
+        };
+        let mut formatter: Box<dyn Formatter> = match syntax {
+            AsmSyntax::Gas => Box::new(GasFormatter::with_options(Some(Box::new(symbols)), None)),
+            AsmSyntax::Nasm => Box::new(NasmFormatter::with_options(Some(Box::new(symbols)), None)),
+        };
+        let options = formatter.options_mut();
+        options.set_number_base(NumberBase::Decimal);
+        options.set_gas_show_mnemonic_size_suffix(true);
+        options.set_show_branch_size(false);
+        // NASM addresses symbols RIP-relative by itself under `default rel`.
+        options.set_rip_relative_addresses(syntax == AsmSyntax::Gas);
+        options.set_space_after_operand_separator(true);
+
+        match syntax {
+            AsmSyntax::Gas => self.line(".text"),
+            AsmSyntax::Nasm => self.line("section .text"),
+        }
+        let source = self.src.inner().clone();
+        let lines = SourceLines::new(&source);
+        let mut labels = labels.iter().peekable();
+        let mut spans = module.source_map.iter().peekable();
+        let mut current_line = None;
+        for function in &module.functions {
+            let _ = writeln!(self.output);
+            if syntax == AsmSyntax::Gas {
+                self.line(format!(".type {}, @function", function.name));
+            }
+            for instruction in self.decode(function) {
+                let offset = instruction.ip() as usize;
+                while let Some((_, name)) = labels.next_if(|(label, _)| *label <= offset) {
+                    self.label(name);
+                }
+                while let Some(mapping) = spans.next_if(|mapping| mapping.offset <= offset) {
+                    let (number, text) = lines.line(mapping.span.offset());
+                    if current_line != Some(number) {
+                        current_line = Some(number);
+                        self.line(format!("{} {number}: {text}", syntax.comment()));
+                    }
+                }
+                let mut formatted = String::new();
+                formatter.format(&instruction, &mut formatted);
+                self.line(formatted);
+            }
+            if syntax == AsmSyntax::Gas {
+                self.line(format!(".size {0}, .-{0}", function.name));
+            }
+        }
+    }
+
+    fn decode(&self, function: &AssembledFunction) -> Decoder<'a> {
+        let module = self.module;
+        let bytes = &module.code[function.offset..function.offset + function.size];
+        Decoder::with_ip(64, bytes, function.offset as u64, DecoderOptions::NONE)
+    }
+
+    /// Labels of the module sorted by offset, with local labels added for the branch targets
+    /// that have none, so that no branch is written with a bare address.
+    fn labels(&self, syntax: AsmSyntax) -> Vec<(usize, String)> {
+        let module = self.module;
+        let mut labels: Vec<_> = module
+            .labels
+            .iter()
+            .map(|label| (label.offset, syntax.symbol(&label.name)))
+            .collect();
+        let targets: HashSet<_> = labels.iter().map(|(offset, _)| *offset).collect();
+        let mut missing = HashSet::new();
+        for function in &module.functions {
+            for instruction in self.decode(function) {
+                let start = instruction.ip() as usize;
+                // Relocated branches are named after their symbol instead.
+                let relocated = module
+                    .relocations
+                    .iter()
+                    .any(|r| (start..start + instruction.len()).contains(&r.offset));
+                if instruction.op0_kind() == OpKind::NearBranch64 && !relocated {
+                    missing.insert(instruction.near_branch_target() as usize);
+                }
+            }
+        }
+        labels.extend(
+            missing
+                .into_iter()
+                .filter(|offset| !targets.contains(offset))
+                .map(|offset| (offset, syntax.symbol(&format!("$target_{offset}")))),
+        );
+        labels.sort();
+        labels
+    }
+
+    fn data(&mut self, syntax: AsmSyntax, section: &str, symbols: &[DataSymbol]) {
+        if symbols.is_empty() {
+            return;
+        }
+        let _ = writeln!(self.output);
+        match syntax {
+            AsmSyntax::Gas => self.line(format!(".section {section}")),
+            AsmSyntax::Nasm => self.line(format!("section {section}")),
+        }
+        for symbol in symbols {
+            if section != ".rodata" {
+                match syntax {
+                    AsmSyntax::Gas => self.line(".p2align 3"),
+                    AsmSyntax::Nasm => self.line("align 8"),
+                }
+            }
+            self.label(&syntax.symbol(&symbol.name));
+            let line = match (syntax, <[u8; 8]>::try_from(symbol.bytes.as_slice())) {
+                (AsmSyntax::Gas, Ok(quad)) if section != ".rodata" => {
+                    format!(".quad {}", i64::from_le_bytes(quad))
+                }
+                (AsmSyntax::Nasm, Ok(quad)) if section != ".rodata" => {
+                    format!("dq {}", i64::from_le_bytes(quad))
+                }
+                (AsmSyntax::Gas, _) => gas_string(&symbol.bytes),
+                (AsmSyntax::Nasm, _) => format!("db {}", nasm_bytes(&symbol.bytes)),
+            };
+            self.line(line);
+        }
+    }
+
+    fn bss(&mut self, syntax: AsmSyntax) {
+        let module = self.module;
+        if module.bss.is_empty() {
+            return;
+        }
+        let _ = writeln!(self.output);
+        match syntax {
+            AsmSyntax::Gas => self.line(".bss"),
+            AsmSyntax::Nasm => self.line("section .bss"),
+        }
+        for symbol in &module.bss {
+            match syntax {
+                AsmSyntax::Gas => self.line(".p2align 3"),
+                AsmSyntax::Nasm => self.line("alignb 8"),
+            }
+            self.label(&syntax.symbol(&symbol.name));
+            match syntax {
+                AsmSyntax::Gas => self.line(format!(".zero {}", symbol.bytes.len())),
+                AsmSyntax::Nasm => self.line(format!("resb {}", symbol.bytes.len())),
+            }
+        }
+    }
+}
+
+fn gas_string(bytes: &[u8]) -> String {
+    let (directive, bytes) = match bytes.split_last() {
+        Some((0, rest)) => (".asciz", rest),
+        _ => (".ascii", bytes),
+    };
+    let mut string = String::new();
+    for byte in bytes {
+        match byte {
+            b'"' | b'\\' => {
+                string.push('\\');
+                string.push(*byte as char);
+            }
+            b' '..=b'~' => string.push(*byte as char),
+            byte => {
+                let _ = write!(string, "\\{byte:03o}");
+            }
+        }
+    }
+    format!("{directive} \"{string}\"")
+}
+
+/// Quotes runs of printable characters and lists every other byte as a number.
+fn nasm_bytes(bytes: &[u8]) -> String {
+    let mut items = Vec::new();
+    let mut run = String::new();
+    for byte in bytes {
+        if (b' '..=b'~').contains(byte) && *byte != b'"' && *byte != b'\\' {
+            run.push(*byte as char);
+            continue;
+        }
+        if !run.is_empty() {
+            items.push(format!("\"{}\"", std::mem::take(&mut run)));
+        }
+        items.push(byte.to_string());
+    }
+    if !run.is_empty() {
+        items.push(format!("\"{run}\""));
+    }
+    items.join(", ")
+}
+
+struct SourceLines<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> SourceLines<'a> {
+    fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self { source, starts }
+    }
+
+    /// One-based number and trimmed text of the line containing `offset`.
+    fn line(&self, offset: usize) -> (usize, &'a str) {
+        let index = self.starts.partition_point(|start| *start <= offset) - 1;
+        let end = self
+            .starts
+            .get(index + 1)
+            .copied()
+            .unwrap_or(self.source.len());
+        (index + 1, self.source[self.starts[index]..end].trim())
+    }
+}
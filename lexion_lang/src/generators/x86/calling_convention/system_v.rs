@@ -5,6 +5,27 @@ use iced_x86::Register;
 
 pub struct SystemV64;
 
+impl SystemV64 {
+    pub const INTEGER_REGISTERS: [Register; 6] = [
+        Register::RDI,
+        Register::RSI,
+        Register::RDX,
+        Register::RCX,
+        Register::R8,
+        Register::R9,
+    ];
+    pub const FLOAT_REGISTERS: [Register; 8] = [
+        Register::XMM0,
+        Register::XMM1,
+        Register::XMM2,
+        Register::XMM3,
+        Register::XMM4,
+        Register::XMM5,
+        Register::XMM6,
+        Register::XMM7,
+    ];
+}
+
 impl CallingConvention for SystemV64 {
     fn assign_args(
         &self,
@@ -12,10 +33,8 @@ impl CallingConvention for SystemV64 {
         stack_start: usize,
         signature: &FunctionType,
     ) -> Vec<Location> {
-        use Register::*;
-
-        const INTEGER_REGISTERS: [Register; 6] = [RDI, RSI, RDX, RCX, R8, R9];
-        const FLOAT_REGISTERS: [Register; 8] = [XMM0, XMM1, XMM2, XMM3, XMM4, XMM5, XMM6, XMM7];
+        const INTEGER_REGISTERS: [Register; 6] = SystemV64::INTEGER_REGISTERS;
+        const FLOAT_REGISTERS: [Register; 8] = SystemV64::FLOAT_REGISTERS;
 
         let mut float_arg_idx = 0;
         let mut int_arg_idx = 0;
@@ -63,6 +82,8 @@ impl CallingConvention for SystemV64 {
                             stack_offset += 2;
                         }
                     } else {
+                        result.push(Location::Stack(StackOffset(stack_offset)));
+                        stack_offset += size.div_ceil(8);
                     }
                 }
                 TypeKind::Unknown => {
@@ -115,6 +136,8 @@ impl CallingConvention for SystemV64 {
             Register::RAX,
             Register::RDX,
             Register::RCX,
+            Register::RSI,
+            Register::RDI,
             Register::R8,
             Register::R9,
             Register::R10,
@@ -127,6 +150,8 @@ impl CallingConvention for SystemV64 {
             Register::RAX,
             Register::RDX,
             Register::RCX,
+            Register::RSI,
+            Register::RDI,
             Register::R8,
             Register::R9,
             Register::R10,
@@ -110,6 +110,11 @@ impl MemoryLayout {
             members: vec![],
         }
     }
+
+    /// Members in declaration order.
+    pub fn members(&self) -> &[MemberLayout] {
+        &self.members
+    }
 }
 
 impl Layout for MemoryLayout {
@@ -1,9 +1,11 @@
+mod asm;
 mod calling_convention;
 mod memory_layout;
 mod register_allocator;
 #[allow(clippy::module_inception)]
 mod x86;
 
+pub use asm::*;
 pub use calling_convention::*;
 pub use memory_layout::*;
 use num_enum::IntoPrimitive;
@@ -13,10 +13,21 @@ pub struct AssignedLivenessInterval {
     location: Location,
 }
 
+impl AssignedLivenessInterval {
+    pub fn interval(&self) -> &LivenessInterval {
+        &self.interval
+    }
+
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+}
+
 pub struct LinearRegisterAllocator<'a> {
     registers: Vec<Register>,
     active: Vec<AssignedLivenessInterval>,
     available: VecDeque<Register>,
+    #[allow(dead_code)]
     cfg: &'a ControlFlowGraph,
     stack_offset: usize,
 }
@@ -122,6 +133,11 @@ impl<'a> LinearRegisterAllocator<'a> {
                 };
                 self.insert_active(new_assigned.clone());
                 assigned.push(new_assigned);
+                // The evicted interval lives on the stack for its whole span, not just from here.
+                assigned.retain(|a| {
+                    a.interval.variable != spilled.interval.variable
+                        || a.interval.span != spilled.interval.span
+                });
                 &spilled.interval
             } else {
                 &interval
@@ -1,32 +1,1400 @@
-use crate::ast::types::TypeCollection;
-use crate::diagnostic::DiagnosticConsumer;
-use crate::generators::tac::instructions::ControlFlowGraph;
+use crate::ast::types::{FunctionType, Type, TypeCollection, TypeKind};
+use crate::ast::Lit;
+use crate::diagnostic::{DiagnosticConsumer, LexionDiagnosticError};
+use crate::generators::tac::instructions::{
+    AddressInstruction, AssignmentInstruction, BoundsCheckInstruction, ConditionalJumpInstruction,
+    ControlFlowGraph, FunctionCallInstruction, FunctionRange, GlobalVariable, Instruction,
+    InstructionInstance, LoadInstruction, MemoryCopyInstruction, Operand, StoreInstruction,
+};
+use crate::generators::x86::system_v::SystemV64;
+use crate::generators::x86::{
+    Align, AssignedLivenessInterval, Bitness, CallingConvention, Location, OperandSize,
+};
+use crate::operators;
 use crate::pipeline::PipelineStage;
-use crate::symbol_table::SymbolTable;
+use crate::symbol_table::SymbolTableGraph;
+use generational_arena::Index;
 use iced_x86::code_asm::*;
+use iced_x86::{BlockEncoderOptions, Register};
+use lexion_lib::miette::{NamedSource, SourceSpan};
+use lexion_lib::petgraph::graph::NodeIndex;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Registers the generator computes in; they are never handed out by the register allocator.
+pub const SCRATCH_REGISTERS: [Register; 2] = [Register::R10, Register::R11];
+
+/// Machine code of every function in a module, laid out back to back.
+#[derive(Debug, Default)]
+pub struct AssembledModule {
+    pub code: Vec<u8>,
+    pub functions: Vec<AssembledFunction>,
+    pub externs: Vec<String>,
+    pub rodata: Vec<DataSymbol>,
+    /// Globals with an initializer.
+    pub data: Vec<DataSymbol>,
+    /// Zero-initialized globals; only the length of their bytes matters.
+    pub bss: Vec<DataSymbol>,
+    pub relocations: Vec<Relocation>,
+    /// Code offsets of the TAC block labels, function epilogues and the code after bounds
+    /// checks, sorted by offset.
+    pub labels: Vec<AssembledLabel>,
+    /// Code offsets at which the instructions lowered from a source span start.
+    pub source_map: Vec<SourceMapping>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AssembledLabel {
+    pub name: String,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SourceMapping {
+    pub offset: usize,
+    pub span: SourceSpan,
+}
+
+#[derive(Debug, Clone)]
+pub struct AssembledFunction {
+    pub name: String,
+    pub offset: usize,
+    pub size: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct DataSymbol {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+    /// `call rel32` to a function that is not defined in the module.
+    Call,
+    /// RIP-relative `rel32` reference to a data symbol.
+    Data,
+}
+
+#[derive(Debug, Clone)]
+pub struct Relocation {
+    pub offset: usize,
+    pub symbol: String,
+    pub kind: RelocationKind,
+    pub addend: i64,
+}
+
+#[derive(Debug, Error)]
+enum CodeGenError {
+    #[error("{0}")]
+    Assembler(#[from] IcedError),
+    #[error("{0} is not supported by the x86-64 backend")]
+    Unsupported(String),
+}
+
+type CodeGenResult = Result<(), CodeGenError>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Value {
+    Register(Register),
+    /// `[rbp + displacement]`
+    Frame(i32),
+    Immediate(i64),
+    /// Address of an entry in `AssembledModule::rodata`.
+    Data(usize),
+    /// `[rip + symbol]` of a global variable.
+    Global(usize),
+    /// Address of a global variable that lives in memory.
+    GlobalAddress(usize),
+    Undefined,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Condition {
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Condition {
+    fn from_operator(operator: &str) -> Option<Self> {
+        match operator {
+            operators::EQUALS => Some(Condition::Equal),
+            operators::NOT_EQUALS => Some(Condition::NotEqual),
+            operators::LESS => Some(Condition::Less),
+            operators::LESS_EQUALS => Some(Condition::LessEqual),
+            operators::GREATER => Some(Condition::Greater),
+            operators::GREATER_EQUALS => Some(Condition::GreaterEqual),
+            _ => None,
+        }
+    }
+}
+
+struct PendingRelocation {
+    label: CodeLabel,
+    offset: usize,
+    symbol: String,
+    kind: RelocationKind,
+}
+
+struct FunctionFrame {
+    locations: HashMap<String, Value>,
+    types: HashMap<String, Index>,
+    return_type: Option<Index>,
+    saved: Vec<Register>,
+    frame_size: i32,
+    epilogue: CodeLabel,
+    args: Vec<Value>,
+    span: Option<SourceSpan>,
+    /// `rbp` displacements of the memory `alloca` instructions reserve, by block and index.
+    allocas: HashMap<(NodeIndex, usize), i32>,
+}
 
 pub struct CodeGeneratorX86<'a> {
+    src: NamedSource<Arc<String>>,
     cfg: &'a ControlFlowGraph,
     types: &'a TypeCollection,
-    symbols: &'a SymbolTable,
+    symbols: &'a SymbolTableGraph,
     assembler: CodeAssembler,
+    convention: SystemV64,
+    labels: HashMap<String, CodeLabel>,
+    labelled: Option<usize>,
+    defined: HashSet<String>,
+    strings: HashMap<String, usize>,
+    globals: Vec<String>,
+    /// Globals that live in memory, whose operand stands for their address.
+    memory_globals: HashSet<String>,
+    /// Labels of code that has no TAC block, with the names they are listed under.
+    internal_labels: Vec<(CodeLabel, String)>,
+    /// Spans paired with the index of the first assembler instruction lowered from them.
+    spans: Vec<(usize, SourceSpan)>,
+    relocations: Vec<PendingRelocation>,
+    module: AssembledModule,
 }
 
 impl<'a> PipelineStage for CodeGeneratorX86<'a> {
-    type Input = (&'a ControlFlowGraph, &'a TypeCollection, &'a SymbolTable);
-    type Options = ();
-    type Output = ();
+    type Input = (
+        NamedSource<Arc<String>>,
+        &'a ControlFlowGraph,
+        &'a TypeCollection,
+        &'a SymbolTableGraph,
+    );
+    type Options = HashMap<FunctionRange, Vec<AssignedLivenessInterval>>;
+    type Output = AssembledModule;
 
-    fn new((cfg, types, symbols): Self::Input) -> Self {
+    fn new((src, cfg, types, symbols): Self::Input) -> Self {
         Self {
+            src,
             cfg,
             types,
             symbols,
             assembler: CodeAssembler::new(64).unwrap(),
+            convention: SystemV64,
+            labels: Default::default(),
+            labelled: None,
+            defined: Default::default(),
+            strings: Default::default(),
+            globals: Default::default(),
+            memory_globals: Default::default(),
+            internal_labels: Default::default(),
+            spans: Default::default(),
+            relocations: Default::default(),
+            module: Default::default(),
+        }
+    }
+
+    fn exec(
+        mut self,
+        diag: &mut dyn DiagnosticConsumer,
+        assigned: Self::Options,
+    ) -> Option<Self::Output> {
+        let cfg = self.cfg;
+        for block in cfg.node_weights() {
+            let label = self.assembler.create_label();
+            self.labels.insert(block.label.clone(), label);
+            if let Some(Instruction::Function(function)) =
+                block.instructions.first().map(|i| &i.instruction)
+            {
+                self.defined.insert(function.label.clone());
+            }
+        }
+
+        let mut success = true;
+        for global in &cfg.globals {
+            if let Err(err) = self.global(global) {
+                self.error(diag, self.symbol_span(&global.name), err);
+                success = false;
+            }
+        }
+        for func in &cfg.functions {
+            match cfg[func.start].instructions.first().map(|i| &i.instruction) {
+                Some(Instruction::Extern(external))
+                    if !self.module.externs.contains(&external.label) =>
+                {
+                    self.module.externs.push(external.label.clone());
+                }
+                Some(Instruction::Function(function)) => {
+                    let intervals = assigned.get(func).map(Vec::as_slice).unwrap_or_default();
+                    if let Err((err, span)) = self.function(func, &function.label, intervals) {
+                        let span = span.or_else(|| self.symbol_span(&function.label));
+                        self.error(diag, span, err);
+                        success = false;
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !success {
+            return None;
+        }
+
+        match self.assemble() {
+            Ok(()) => Some(self.module),
+            Err(err) => {
+                self.error(diag, None, err);
+                None
+            }
+        }
+    }
+}
+
+impl<'a> CodeGeneratorX86<'a> {
+    fn error(
+        &self,
+        diag: &mut dyn DiagnosticConsumer,
+        span: Option<SourceSpan>,
+        err: CodeGenError,
+    ) {
+        diag.error(LexionDiagnosticError {
+            src: self.src.clone(),
+            span: span.unwrap_or(SourceSpan::from(0)),
+            message: err.to_string(),
+        });
+    }
+
+    fn symbol_span(&self, name: &str) -> Option<SourceSpan> {
+        self.symbols
+            .lookup(self.symbols.root, name)
+            .map(|(_, _, entry)| entry.span)
+    }
+
+    fn assemble(&mut self) -> CodeGenResult {
+        let result = self
+            .assembler
+            .assemble_options(0, BlockEncoderOptions::RETURN_NEW_INSTRUCTION_OFFSETS)?;
+        self.module.code = result.inner.code_buffer.clone();
+
+        let mut starts = Vec::new();
+        for func in &self.cfg.functions {
+            let name = &self.cfg[func.start].label;
+            if self.defined.contains(name) {
+                starts.push((result.label_ip(&self.labels[name])? as usize, name.clone()));
+            }
+        }
+        starts.sort();
+        let ends = starts
+            .iter()
+            .skip(1)
+            .map(|(offset, _)| *offset)
+            .chain([self.module.code.len()])
+            .collect::<Vec<_>>();
+        self.module.functions = starts
+            .into_iter()
+            .zip(ends)
+            .map(|((offset, name), end)| AssembledFunction {
+                name,
+                offset,
+                size: end - offset,
+            })
+            .collect();
+
+        for func in &self.cfg.functions {
+            if !self.defined.contains(&self.cfg[func.start].label) {
+                continue;
+            }
+            for node in self.cfg.function_nodes(func) {
+                let name = &self.cfg[node].label;
+                self.module.labels.push(AssembledLabel {
+                    name: name.clone(),
+                    offset: result.label_ip(&self.labels[name])? as usize,
+                });
+            }
+        }
+        for (label, name) in self.internal_labels.drain(..) {
+            self.module.labels.push(AssembledLabel {
+                name,
+                offset: result.label_ip(&label)? as usize,
+            });
+        }
+        self.module.labels.sort_by_key(|label| label.offset);
+
+        let offsets = &result.inner.new_instruction_offsets;
+        for (index, span) in self.spans.drain(..) {
+            let offset = offsets
+                .get(index)
+                .map(|offset| *offset as usize)
+                .unwrap_or(self.module.code.len());
+            // A span whose instructions emitted no code is superseded by the next one.
+            if let Some(last) = self.module.source_map.last_mut() {
+                if last.offset == offset {
+                    last.span = span;
+                    continue;
+                }
+            }
+            self.module.source_map.push(SourceMapping { offset, span });
+        }
+
+        for relocation in self.relocations.drain(..) {
+            self.module.relocations.push(Relocation {
+                offset: result.label_ip(&relocation.label)? as usize + relocation.offset,
+                symbol: relocation.symbol,
+                kind: relocation.kind,
+                addend: -4,
+            });
+        }
+        Ok(())
+    }
+
+    fn set_label(&mut self, label: &mut CodeLabel) -> CodeGenResult {
+        // iced allows one label per instruction, so empty blocks get a `nop` to hang theirs on.
+        if self.labelled == Some(self.assembler.instructions().len()) {
+            self.assembler.nop()?;
+        }
+        self.assembler.set_label(label)?;
+        self.labelled = Some(self.assembler.instructions().len());
+        Ok(())
+    }
+
+    fn set_named_label(&mut self, name: &str) -> CodeGenResult {
+        let mut label = self.labels[name];
+        self.set_label(&mut label)?;
+        self.labels.insert(name.to_string(), label);
+        Ok(())
+    }
+
+    fn signature(&self, function: &str) -> Option<FunctionType> {
+        let (_, _, entry) = self.symbols.lookup(self.symbols.root, function)?;
+        match self.types.get(entry.var_type?)? {
+            Type::FunctionType(signature) => Some(signature.clone()),
+            _ => None,
+        }
+    }
+
+    fn global(&mut self, global: &GlobalVariable) -> CodeGenResult {
+        // Every global gets at least a full quadword so it can be accessed at either operand
+        // size. The sections are aligned to 8 bytes, which no type needs more than.
+        let size = global.memory.map_or(8, |memory| memory.size.max(8));
+        let symbol = |value: i64| {
+            let mut bytes = value.to_le_bytes().to_vec();
+            bytes.resize(size, 0);
+            DataSymbol {
+                name: global.name.clone(),
+                bytes,
+            }
+        };
+        match &global.init {
+            None => self.module.bss.push(symbol(0)),
+            Some(Operand::Literal(Lit::Integer(value))) => {
+                self.module.data.push(symbol(*value as i64))
+            }
+            Some(Operand::Literal(Lit::Boolean(value))) => {
+                self.module.data.push(symbol(*value as i64))
+            }
+            Some(_) => {
+                return Err(CodeGenError::Unsupported(format!(
+                    "initializing global '{}' with anything but an integer or boolean literal",
+                    global.name
+                )))
+            }
+        }
+        self.globals.push(global.name.clone());
+        if global.memory.is_some() {
+            self.memory_globals.insert(global.name.clone());
+        }
+        Ok(())
+    }
+
+    fn string(&mut self, literal: &str) -> usize {
+        if let Some(index) = self.strings.get(literal) {
+            return *index;
+        }
+        let index = self.module.rodata.len();
+        self.module.rodata.push(DataSymbol {
+            name: format!(".L.str.{index}"),
+            bytes: unescape_string_literal(literal),
+        });
+        self.strings.insert(literal.to_string(), index);
+        index
+    }
+}
+
+impl<'a> CodeGeneratorX86<'a> {
+    fn function(
+        &mut self,
+        func: &FunctionRange,
+        name: &str,
+        intervals: &[AssignedLivenessInterval],
+    ) -> Result<(), (CodeGenError, Option<SourceSpan>)> {
+        let types = self.symbols.variable_types(name);
+        let saved = self
+            .convention
+            .callee_saved()
+            .iter()
+            .filter(|reg| **reg != Register::RBP)
+            .filter(|reg| {
+                intervals
+                    .iter()
+                    .any(|i| i.location().register() == Some(**reg))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let slots = intervals
+            .iter()
+            .filter_map(|i| i.location().stack_offset())
+            .map(|offset| *offset + 1)
+            .max()
+            .unwrap_or(0);
+        let saved_bytes = 8 * saved.len();
+        // Memory reserved by `alloca` goes below the spill slots.
+        let mut bytes = saved_bytes + 8 * slots;
+        let mut allocas = HashMap::new();
+        for node in self.cfg.function_nodes(func) {
+            for (index, inst) in self.cfg[node].instructions.iter().enumerate() {
+                if let Instruction::Alloca(alloca) = &inst.instruction {
+                    bytes = Align::new(alloca.align.max(1)).align(bytes + alloca.size);
+                    allocas.insert((node, index), -(bytes as i32));
+                }
+            }
+        }
+        let frame_size = bytes.next_multiple_of(self.convention.stack_alignment()) - saved_bytes;
+
+        let mut locations = HashMap::new();
+        for assigned in intervals {
+            let variable = &assigned.interval().variable;
+            if let Some(ty) = types.get(variable) {
+                if matches!(
+                    self.types.kind(*ty),
+                    TypeKind::Float | TypeKind::Double | TypeKind::Vector
+                ) {
+                    return Err((
+                        CodeGenError::Unsupported(String::from("floating point arithmetic")),
+                        None,
+                    ));
+                }
+            }
+            let value = match assigned.location() {
+                Location::Register(reg) => Value::Register(*reg),
+                Location::Stack(offset) => {
+                    Value::Frame(-((saved_bytes + 8 * (**offset + 1)) as i32))
+                }
+                location => {
+                    return Err((
+                        CodeGenError::Unsupported(format!("variable location {location:?}")),
+                        None,
+                    ))
+                }
+            };
+            locations.insert(variable.clone(), value);
+        }
+
+        let mut frame = FunctionFrame {
+            locations,
+            types,
+            return_type: self.signature(name).map(|s| s.return_type),
+            saved,
+            frame_size: frame_size as i32,
+            epilogue: self.assembler.create_label(),
+            args: Vec::new(),
+            span: None,
+            allocas,
+        };
+
+        // Errors point at the instruction being generated when it has a span.
+        for node in self.cfg.function_nodes(func) {
+            self.block(&mut frame, node)
+                .map_err(|err| (err, frame.span))?;
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, frame: &mut FunctionFrame, node: NodeIndex) -> CodeGenResult {
+        let cfg = self.cfg;
+        let block = &cfg[node];
+        self.set_named_label(&block.label)?;
+        for (
+            index,
+            InstructionInstance {
+                instruction,
+                live,
+                span,
+            },
+        ) in block.instructions.iter().enumerate()
+        {
+            if let Some(span) = span.filter(|span| frame.span != Some(*span)) {
+                frame.span = Some(span);
+                self.spans.push((self.assembler.instructions().len(), span));
+            }
+            match instruction {
+                Instruction::Function(function) => {
+                    self.prologue(frame, &function.label, &function.params)?
+                }
+                Instruction::EndFunction(end) => self.epilogue(frame, &end.label)?,
+                Instruction::Assignment(assignment) => self.assignment(frame, assignment)?,
+                Instruction::Copy(copy) => {
+                    if let Some(target) = self.target(frame, &copy.dst) {
+                        let value = self.value(frame, &copy.src)?;
+                        let size = self
+                            .size(frame, &copy.dst)
+                            .or(self.size(frame, &copy.src))
+                            .unwrap_or(OperandSize::_64);
+                        self.move_value(target, value, size)?;
+                    }
+                }
+                Instruction::ConditionalJump(jump) => self.conditional_jump(frame, jump)?,
+                Instruction::Jump(jump) => {
+                    let label = self.jump_target(&jump.target)?;
+                    self.assembler.jmp(label)?;
+                }
+                Instruction::Parameter(param) => {
+                    let value = self.value(frame, &param.param)?;
+                    frame.args.push(value);
+                }
+                Instruction::FunctionCall(call) => self.call(frame, call, &live.output)?,
+                Instruction::Return(ret) => {
+                    if let Some(value) = &ret.value {
+                        let value = self.value(frame, value)?;
+                        let size = frame
+                            .return_type
+                            .map(|ty| self.type_size(ty))
+                            .unwrap_or(OperandSize::_64);
+                        self.load(Register::RAX, value, size)?;
+                    }
+                    self.assembler.jmp(frame.epilogue)?;
+                }
+                Instruction::Extern(_) => {}
+                Instruction::Alloca(alloca) => {
+                    if let Some(target) = self.target(frame, &alloca.target) {
+                        let displacement = frame.allocas[&(node, index)];
+                        self.assembler.lea(r10, ptr(rbp + displacement))?;
+                        self.store(target, Register::R10, OperandSize::_64)?;
+                    }
+                }
+                Instruction::Address(address) => self.address(frame, address)?,
+                Instruction::Load(load) => self.load_memory(frame, load)?,
+                Instruction::Store(store) => self.store_memory(frame, store)?,
+                Instruction::MemoryCopy(copy) => self.copy_memory(frame, copy)?,
+                Instruction::BoundsCheck(check) => self.bounds_check(frame, check)?,
+                Instruction::Phi(_) => {
+                    return Err(CodeGenError::Unsupported(String::from(
+                        "phi instructions outside of SSA form",
+                    )))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn prologue(&mut self, frame: &FunctionFrame, name: &str, params: &[Operand]) -> CodeGenResult {
+        self.assembler.push(rbp)?;
+        self.assembler.mov(rbp, rsp)?;
+        for reg in &frame.saved {
+            self.assembler.push(gpr64(*reg))?;
+        }
+        if frame.frame_size > 0 {
+            self.assembler.sub(rsp, frame.frame_size)?;
+        }
+
+        let Some(mut signature) = self.signature(name) else {
+            return Ok(());
+        };
+        // Structs are passed as the address of a copy the caller makes.
+        for param in signature.params.iter_mut() {
+            if matches!(self.types.kind(*param), TypeKind::Memory) {
+                *param = self.types.reference_of(*param).unwrap_or(*param);
+            }
+        }
+        let sources = self
+            .convention
+            .assign_args(self.types, 0, &signature)
+            .into_iter()
+            .map(|location| match location {
+                Location::Register(reg) if reg.is_gpr64() => Ok(Value::Register(reg)),
+                // Above the saved `rbp` and the return address.
+                Location::Stack(offset) => Ok(Value::Frame(16 + 8 * *offset as i32)),
+                location => Err(CodeGenError::Unsupported(format!(
+                    "parameter location {location:?}"
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Parameters may have been allocated to each other's argument registers, so every
+        // argument goes through the stack instead of being moved in place.
+        for source in &sources {
+            self.push(*source)?;
+        }
+        for param in params.iter().take(sources.len()).rev() {
+            match Self::variable(param).and_then(|param| frame.locations.get(&param)) {
+                Some(Value::Register(reg)) => self.assembler.pop(gpr64(*reg))?,
+                Some(Value::Frame(displacement)) => {
+                    self.assembler.pop(qword_ptr(rbp + *displacement))?
+                }
+                _ => self.assembler.pop(r11)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn epilogue(&mut self, frame: &mut FunctionFrame, name: &str) -> CodeGenResult {
+        // Falling off the end of a function returns zero, which is what `main` wants.
+        self.assembler.xor(eax, eax)?;
+        let mut epilogue = frame.epilogue;
+        self.set_label(&mut epilogue)?;
+        self.internal_labels
+            .push((epilogue, format!("$return_{name}")));
+        if !frame.saved.is_empty() {
+            let saved_bytes = 8 * frame.saved.len() as i32;
+            self.assembler.lea(rsp, ptr(rbp - saved_bytes))?;
+            for reg in frame.saved.iter().rev() {
+                self.assembler.pop(gpr64(*reg))?;
+            }
+        } else if frame.frame_size > 0 {
+            self.assembler.mov(rsp, rbp)?;
+        }
+        self.assembler.pop(rbp)?;
+        self.assembler.ret()?;
+        Ok(())
+    }
+
+    fn assignment(
+        &mut self,
+        frame: &FunctionFrame,
+        assignment: &AssignmentInstruction,
+    ) -> CodeGenResult {
+        let target = match self.target(frame, &assignment.target) {
+            Some(target) => target,
+            // A division whose result is never read still runs, since it can trap. Its result is
+            // left in the scratch register.
+            None if assignment.left.is_some()
+                && matches!(
+                    assignment.operator,
+                    operators::DIVIDE | operators::REMAINDER
+                ) =>
+            {
+                Value::Register(Register::R10)
+            }
+            None => return Ok(()),
+        };
+        let right = self.value(frame, &assignment.right)?;
+
+        let Some(left) = &assignment.left else {
+            let size = self
+                .size(frame, &assignment.target)
+                .or(self.size(frame, &assignment.right))
+                .unwrap_or(OperandSize::_64);
+            match assignment.operator {
+                operators::UNARY_PLUS => self.load(Register::R10, right, size)?,
+                operators::UNARY_MINUS => {
+                    self.load(Register::R10, right, size)?;
+                    unary(&mut self.assembler, Unary::Neg, size, Register::R10)?;
+                }
+                operators::BITWISE_NOT => {
+                    self.load(Register::R10, right, size)?;
+                    unary(&mut self.assembler, Unary::Not, size, Register::R10)?;
+                }
+                operators::LOGICAL_NOT => {
+                    self.load(Register::R10, right, OperandSize::_32)?;
+                    self.assembler.xor(r10d, 1)?;
+                }
+                operator => {
+                    return Err(CodeGenError::Unsupported(format!(
+                        "unary operator '{operator}'"
+                    )))
+                }
+            }
+            return self.store(target, Register::R10, size);
+        };
+
+        let left_value = self.value(frame, left)?;
+        let signed = self.is_signed(frame, left, &assignment.right);
+        if let Some(condition) = Condition::from_operator(assignment.operator) {
+            let size = self
+                .size(frame, left)
+                .or(self.size(frame, &assignment.right))
+                .unwrap_or(OperandSize::_64);
+            self.compare(left_value, right, size)?;
+            set_condition(&mut self.assembler, condition, signed)?;
+            self.assembler.movzx(r10d, r10b)?;
+            return self.store(target, Register::R10, OperandSize::_32);
+        }
+
+        let size = self
+            .size(frame, &assignment.target)
+            .or(self.size(frame, left))
+            .or(self.size(frame, &assignment.right))
+            .unwrap_or(OperandSize::_64);
+        self.load(Register::R10, left_value, size)?;
+        self.load(Register::R11, right, size)?;
+        let (dst, src) = (Register::R10, Register::R11);
+        match assignment.operator {
+            operators::PLUS => binary(&mut self.assembler, Binary::Add, size, dst, src)?,
+            operators::MINUS => binary(&mut self.assembler, Binary::Sub, size, dst, src)?,
+            operators::MULTIPLY => binary(&mut self.assembler, Binary::Imul, size, dst, src)?,
+            operators::BITWISE_AND | operators::LOGICAL_AND => {
+                binary(&mut self.assembler, Binary::And, size, dst, src)?
+            }
+            operators::BITWISE_OR | operators::LOGICAL_OR => {
+                binary(&mut self.assembler, Binary::Or, size, dst, src)?
+            }
+            operators::BITWISE_XOR => binary(&mut self.assembler, Binary::Xor, size, dst, src)?,
+            operators::SHIFT_LEFT => self.shift(Unary::Shl, size)?,
+            operators::SHIFT_RIGHT => {
+                self.shift(if signed { Unary::Sar } else { Unary::Shr }, size)?
+            }
+            operators::DIVIDE => self.divide(size, signed, Register::RAX)?,
+            operators::REMAINDER => self.divide(size, signed, Register::RDX)?,
+            operator => {
+                return Err(CodeGenError::Unsupported(format!(
+                    "binary operator '{operator}'"
+                )))
+            }
         }
+        self.store(target, Register::R10, size)
     }
 
-    fn exec(self, diag: &mut dyn DiagnosticConsumer, opts: Self::Options) -> Option<Self::Output> {
-        None
+    fn address(&mut self, frame: &FunctionFrame, address: &AddressInstruction) -> CodeGenResult {
+        let Some(target) = self.target(frame, &address.target) else {
+            return Ok(());
+        };
+        let base = self.value(frame, &address.base)?;
+        self.load(Register::R10, base, OperandSize::_64)?;
+        let offset = address.offset as i32;
+        let Some(index) = &address.index else {
+            self.assembler.lea(r10, ptr(r10 + offset))?;
+            return self.store(target, Register::R10, OperandSize::_64);
+        };
+        let size = self.size(frame, index).unwrap_or(OperandSize::_64);
+        let value = self.value(frame, index)?;
+        self.load(Register::R11, value, size)?;
+        if size == OperandSize::_32 && self.is_signed(frame, index, index) {
+            self.assembler.movsxd(r11, r11d)?;
+        }
+        match address.scale {
+            scale @ (1 | 2 | 4 | 8) => self
+                .assembler
+                .lea(r10, ptr(r10 + r11 * scale as u32 + offset))?,
+            scale => {
+                self.assembler.imul_3(r11, r11, scale as i32)?;
+                self.assembler.lea(r10, ptr(r10 + r11 + offset))?;
+            }
+        }
+        self.store(target, Register::R10, OperandSize::_64)
+    }
+
+    fn load_memory(&mut self, frame: &FunctionFrame, load: &LoadInstruction) -> CodeGenResult {
+        let Some(target) = self.target(frame, &load.target) else {
+            return Ok(());
+        };
+        let address = self.value(frame, &load.address)?;
+        self.load(Register::R10, address, OperandSize::_64)?;
+        let offset = load.offset as i32;
+        match load.size {
+            1 => self.assembler.movzx(r10d, byte_ptr(r10 + offset))?,
+            2 => self.assembler.movzx(r10d, word_ptr(r10 + offset))?,
+            4 => self.assembler.mov(r10d, dword_ptr(r10 + offset))?,
+            _ => self.assembler.mov(r10, qword_ptr(r10 + offset))?,
+        }
+        let size = self.size(frame, &load.target).unwrap_or(OperandSize::_64);
+        self.store(target, Register::R10, size)
+    }
+
+    fn store_memory(&mut self, frame: &FunctionFrame, store: &StoreInstruction) -> CodeGenResult {
+        let address = self.value(frame, &store.address)?;
+        let value = self.value(frame, &store.value)?;
+        self.load(Register::R10, address, OperandSize::_64)?;
+        let size = if store.size > 4 {
+            OperandSize::_64
+        } else {
+            OperandSize::_32
+        };
+        self.load(Register::R11, value, size)?;
+        let offset = store.offset as i32;
+        match store.size {
+            1 => self.assembler.mov(byte_ptr(r10 + offset), r11b)?,
+            2 => self.assembler.mov(word_ptr(r10 + offset), r11w)?,
+            4 => self.assembler.mov(dword_ptr(r10 + offset), r11d)?,
+            _ => self.assembler.mov(qword_ptr(r10 + offset), r11)?,
+        }
+        Ok(())
+    }
+
+    /// Copies through `rax`, widest chunks first, since both scratch registers hold addresses.
+    fn copy_memory(
+        &mut self,
+        frame: &FunctionFrame,
+        copy: &MemoryCopyInstruction,
+    ) -> CodeGenResult {
+        let dst = self.value(frame, &copy.dst)?;
+        let src = self.value(frame, &copy.src)?;
+        self.load(Register::R10, dst, OperandSize::_64)?;
+        self.load(Register::R11, src, OperandSize::_64)?;
+        self.assembler.push(rax)?;
+        let mut offset = 0;
+        while offset < copy.size {
+            let at = offset as i32;
+            match copy.size - offset {
+                8.. => {
+                    self.assembler.mov(rax, qword_ptr(r11 + at))?;
+                    self.assembler.mov(qword_ptr(r10 + at), rax)?;
+                    offset += 8;
+                }
+                4..=7 => {
+                    self.assembler.mov(eax, dword_ptr(r11 + at))?;
+                    self.assembler.mov(dword_ptr(r10 + at), eax)?;
+                    offset += 4;
+                }
+                _ => {
+                    self.assembler.mov(al, byte_ptr(r11 + at))?;
+                    self.assembler.mov(byte_ptr(r10 + at), al)?;
+                    offset += 1;
+                }
+            }
+        }
+        self.assembler.pop(rax)?;
+        Ok(())
+    }
+
+    /// Shifts `r10` by `r11`, which has to go through `cl`.
+    fn shift(&mut self, shift: Unary, size: OperandSize) -> CodeGenResult {
+        self.assembler.push(rcx)?;
+        self.assembler.mov(rcx, r11)?;
+        unary(&mut self.assembler, shift, size, Register::R10)?;
+        self.assembler.pop(rcx)?;
+        Ok(())
+    }
+
+    /// Divides `r10` by `r11` and leaves the quotient (`rax`) or remainder (`rdx`) in `r10`.
+    fn divide(&mut self, size: OperandSize, signed: bool, result: Register) -> CodeGenResult {
+        self.assembler.push(rax)?;
+        self.assembler.push(rdx)?;
+        self.assembler.mov(rax, r10)?;
+        match (signed, size) {
+            (true, OperandSize::_32) => self.assembler.cdq()?,
+            (true, OperandSize::_64) => self.assembler.cqo()?,
+            (false, _) => self.assembler.xor(edx, edx)?,
+        }
+        let divide = if signed { Unary::Idiv } else { Unary::Div };
+        unary(&mut self.assembler, divide, size, Register::R11)?;
+        self.assembler.mov(r10, gpr64(result))?;
+        self.assembler.pop(rdx)?;
+        self.assembler.pop(rax)?;
+        Ok(())
+    }
+
+    fn compare(&mut self, left: Value, right: Value, size: OperandSize) -> CodeGenResult {
+        self.load(Register::R10, left, size)?;
+        self.load(Register::R11, right, size)?;
+        binary(
+            &mut self.assembler,
+            Binary::Cmp,
+            size,
+            Register::R10,
+            Register::R11,
+        )?;
+        Ok(())
+    }
+
+    /// Traps on an index out of bounds. The comparison is unsigned, so that negative indices
+    /// fail it as well.
+    fn bounds_check(
+        &mut self,
+        frame: &FunctionFrame,
+        check: &BoundsCheckInstruction,
+    ) -> CodeGenResult {
+        let size = self
+            .size(frame, &check.index)
+            .or(self.size(frame, &check.length))
+            .unwrap_or(OperandSize::_64);
+        let index = self.value(frame, &check.index)?;
+        let length = self.value(frame, &check.length)?;
+        self.compare(index, length, size)?;
+        let mut in_bounds = self.assembler.create_label();
+        self.assembler.jb(in_bounds)?;
+        self.assembler.ud2()?;
+        self.set_label(&mut in_bounds)?;
+        let name = format!("$bounds_{}", self.internal_labels.len());
+        self.internal_labels.push((in_bounds, name));
+        Ok(())
+    }
+
+    fn conditional_jump(
+        &mut self,
+        frame: &FunctionFrame,
+        jump: &ConditionalJumpInstruction,
+    ) -> CodeGenResult {
+        let (Some(left), Some(condition)) = (&jump.left, Condition::from_operator(jump.operator))
+        else {
+            return Err(CodeGenError::Unsupported(format!(
+                "conditional jump on '{}'",
+                jump.operator
+            )));
+        };
+        let size = self
+            .size(frame, left)
+            .or(self.size(frame, &jump.right))
+            .unwrap_or(OperandSize::_64);
+        let signed = self.is_signed(frame, left, &jump.right);
+        let left = self.value(frame, left)?;
+        let right = self.value(frame, &jump.right)?;
+        let label = self.jump_target(&jump.target)?;
+        self.compare(left, right, size)?;
+        jump_condition(&mut self.assembler, condition, signed, label)?;
+        Ok(())
+    }
+
+    fn call(
+        &mut self,
+        frame: &mut FunctionFrame,
+        call: &FunctionCallInstruction,
+        live_out: &HashSet<String>,
+    ) -> CodeGenResult {
+        let args = std::mem::take(&mut frame.args);
+        let registers = args.len().min(SystemV64::INTEGER_REGISTERS.len());
+        let stack_args = args.len() - registers;
+        let signature = self.signature(&call.function);
+        let return_target = call.return_target.as_ref().map(|t| t.to_string());
+
+        let mut saved = live_out
+            .iter()
+            .filter(|variable| Some(*variable) != return_target.as_ref())
+            .filter_map(|variable| match frame.locations.get(variable) {
+                Some(Value::Register(reg)) if self.convention.caller_saved().contains(reg) => {
+                    Some(*reg)
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        saved.sort_by_key(|reg| reg.number());
+        saved.dedup();
+        for reg in &saved {
+            self.assembler.push(gpr64(*reg))?;
+        }
+        // Arguments left on the stack count towards its 16-byte alignment at the call.
+        let padded = (saved.len() + stack_args) % 2 == 1;
+        if padded {
+            self.assembler.sub(rsp, 8)?;
+        }
+
+        // `param` instructions come last argument first, so popping after pushing them all
+        // yields the first argument first, and the ones that don't fit in registers stay on
+        // the stack in the order the callee expects. Going through the stack also avoids
+        // clobbering an argument register that still holds a later argument.
+        for arg in &args {
+            self.push(*arg)?;
+        }
+        for reg in &SystemV64::INTEGER_REGISTERS[..registers] {
+            self.assembler.pop(gpr64(*reg))?;
+        }
+        if signature.as_ref().is_some_and(|s| s.is_vararg) {
+            // Number of vector registers used by a variadic call.
+            self.assembler.xor(eax, eax)?;
+        }
+
+        if self.defined.contains(&call.function) {
+            self.assembler.call(self.labels[&call.function])?;
+        } else {
+            if !self.module.externs.contains(&call.function) {
+                self.module.externs.push(call.function.clone());
+            }
+            self.relocated(&[0xE8, 0, 0, 0, 0], 1, &call.function, RelocationKind::Call)?;
+        }
+
+        if let Some(target) = call.return_target.as_ref() {
+            if let Some(location) = self.target(frame, target) {
+                let size = signature
+                    .map(|s| self.type_size(s.return_type))
+                    .unwrap_or(OperandSize::_64);
+                self.move_value(location, Value::Register(Register::RAX), size)?;
+            }
+        }
+
+        let pushed = 8 * (stack_args + padded as usize) as i32;
+        if pushed > 0 {
+            self.assembler.add(rsp, pushed)?;
+        }
+        for reg in saved.iter().rev() {
+            self.assembler.pop(gpr64(*reg))?;
+        }
+        Ok(())
+    }
+
+    /// Emits raw instruction bytes with a `rel32` field at `offset` to be filled in by the linker.
+    fn relocated(
+        &mut self,
+        bytes: &[u8],
+        offset: usize,
+        symbol: &str,
+        kind: RelocationKind,
+    ) -> CodeGenResult {
+        let mut label = self.assembler.create_label();
+        self.set_label(&mut label)?;
+        self.assembler.db(bytes)?;
+        self.relocations.push(PendingRelocation {
+            label,
+            offset,
+            symbol: symbol.to_string(),
+            kind,
+        });
+        Ok(())
+    }
+}
+
+impl<'a> CodeGeneratorX86<'a> {
+    fn jump_target(&self, target: &Operand) -> Result<CodeLabel, CodeGenError> {
+        match target {
+            Operand::Label(label) if self.labels.contains_key(label) => Ok(self.labels[label]),
+            target => Err(CodeGenError::Unsupported(format!("jump to '{target}'"))),
+        }
+    }
+
+    fn variable(operand: &Operand) -> Option<String> {
+        match operand {
+            Operand::Variable(name) | Operand::Global(name) => Some(name.clone()),
+            Operand::Temporary(label) => Some(label.to_string()),
+            Operand::Value(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Location written by an instruction, or `None` when the result is never read.
+    fn target(&self, frame: &FunctionFrame, operand: &Operand) -> Option<Value> {
+        match operand {
+            Operand::Global(name) => {
+                self.globals
+                    .iter()
+                    .position(|global| global == name)
+                    .map(|index| {
+                        if self.memory_globals.contains(name) {
+                            Value::GlobalAddress(index)
+                        } else {
+                            Value::Global(index)
+                        }
+                    })
+            }
+            operand => Self::variable(operand).and_then(|name| frame.locations.get(&name).copied()),
+        }
+    }
+
+    fn value(&mut self, frame: &FunctionFrame, operand: &Operand) -> Result<Value, CodeGenError> {
+        Ok(match operand {
+            Operand::Variable(_)
+            | Operand::Temporary(_)
+            | Operand::Value(_)
+            | Operand::Global(_) => self.target(frame, operand).unwrap_or(Value::Undefined),
+            Operand::Literal(Lit::Integer(value)) => Value::Immediate(*value as i64),
+            Operand::Literal(Lit::Boolean(value)) => Value::Immediate(*value as i64),
+            Operand::Literal(Lit::String(value)) => Value::Data(self.string(value)),
+            Operand::Literal(Lit::Float(_)) => {
+                return Err(CodeGenError::Unsupported(String::from(
+                    "floating point literal",
+                )))
+            }
+            Operand::Label(label) => {
+                return Err(CodeGenError::Unsupported(format!(
+                    "taking the address of '{label}'"
+                )))
+            }
+            Operand::Placeholder => {
+                return Err(CodeGenError::Unsupported(String::from(
+                    "an expression that is not lowered to TAC",
+                )))
+            }
+        })
+    }
+
+    fn type_size(&self, ty: Index) -> OperandSize {
+        // Variables of structs hold the address of their memory.
+        if matches!(self.types.kind(ty), TypeKind::Memory) {
+            return OperandSize::_64;
+        }
+        if self.types.size_align(ty, Bitness::_64).size <= 4 {
+            OperandSize::_32
+        } else {
+            OperandSize::_64
+        }
+    }
+
+    fn size(&self, frame: &FunctionFrame, operand: &Operand) -> Option<OperandSize> {
+        match operand {
+            Operand::Literal(Lit::String(_)) => Some(OperandSize::_64),
+            operand => Self::variable(operand)
+                .and_then(|name| frame.types.get(&name))
+                .map(|ty| self.type_size(*ty)),
+        }
+    }
+
+    fn is_signed(&self, frame: &FunctionFrame, left: &Operand, right: &Operand) -> bool {
+        Self::variable(left)
+            .and_then(|name| frame.types.get(&name))
+            .or_else(|| Self::variable(right).and_then(|name| frame.types.get(&name)))
+            .map(|ty| self.types.is_signed(*ty))
+            .unwrap_or(true)
+    }
+
+    fn load(&mut self, dst: Register, value: Value, size: OperandSize) -> CodeGenResult {
+        match (value, size) {
+            (Value::Register(src), _) if src.full_register() == dst.full_register() => {}
+            (Value::Register(src), OperandSize::_32) => {
+                self.assembler.mov(gpr32(dst), gpr32(src))?
+            }
+            (Value::Register(src), OperandSize::_64) => {
+                self.assembler.mov(gpr64(dst), gpr64(src))?
+            }
+            (Value::Frame(displacement), OperandSize::_32) => self
+                .assembler
+                .mov(gpr32(dst), dword_ptr(rbp + displacement))?,
+            (Value::Frame(displacement), OperandSize::_64) => self
+                .assembler
+                .mov(gpr64(dst), qword_ptr(rbp + displacement))?,
+            (Value::Immediate(value), OperandSize::_32) => {
+                self.assembler.mov(gpr32(dst), value as i32)?
+            }
+            (Value::Immediate(value), OperandSize::_64) => self.assembler.mov(gpr64(dst), value)?,
+            (Value::Data(index), _) => {
+                let symbol = self.module.rodata[index].name.clone();
+                self.rip_relative(LEA, dst, OperandSize::_64, &symbol)?;
+            }
+            (Value::Global(index), size) => {
+                let symbol = self.globals[index].clone();
+                self.rip_relative(MOV_LOAD, dst, size, &symbol)?;
+            }
+            (Value::GlobalAddress(index), _) => {
+                let symbol = self.globals[index].clone();
+                self.rip_relative(LEA, dst, OperandSize::_64, &symbol)?;
+            }
+            (Value::Undefined, _) => self.assembler.xor(gpr32(dst), gpr32(dst))?,
+        }
+        Ok(())
+    }
+
+    fn store(&mut self, target: Value, src: Register, size: OperandSize) -> CodeGenResult {
+        match (target, size) {
+            (Value::Register(dst), _) => self.load(dst, Value::Register(src), size)?,
+            (Value::Frame(displacement), OperandSize::_32) => self
+                .assembler
+                .mov(dword_ptr(rbp + displacement), gpr32(src))?,
+            (Value::Frame(displacement), OperandSize::_64) => self
+                .assembler
+                .mov(qword_ptr(rbp + displacement), gpr64(src))?,
+            (Value::Global(index), size) => {
+                let symbol = self.globals[index].clone();
+                self.rip_relative(MOV_STORE, src, size, &symbol)?;
+            }
+            (target, _) => unreachable!("cannot store to {target:?}"),
+        }
+        Ok(())
+    }
+
+    fn move_value(&mut self, target: Value, value: Value, size: OperandSize) -> CodeGenResult {
+        match (target, value) {
+            (Value::Register(dst), value) => self.load(dst, value, size),
+            (target, Value::Register(src)) => self.store(target, src, size),
+            (target, value) => {
+                self.load(Register::R10, value, size)?;
+                self.store(target, Register::R10, size)
+            }
+        }
+    }
+
+    /// Emits `opcode reg, [rip + symbol]` with the displacement left to the linker.
+    fn rip_relative(
+        &mut self,
+        opcode: u8,
+        reg: Register,
+        size: OperandSize,
+        symbol: &str,
+    ) -> CodeGenResult {
+        let number = reg.full_register().number() as u8;
+        let mut rex = 0x40;
+        if size == OperandSize::_64 {
+            rex |= 0x08;
+        }
+        if number >= 8 {
+            rex |= 0x04;
+        }
+        let modrm = ((number & 7) << 3) | 0b101;
+        self.relocated(
+            &[rex, opcode, modrm, 0, 0, 0, 0],
+            3,
+            symbol,
+            RelocationKind::Data,
+        )
+    }
+
+    fn push(&mut self, value: Value) -> CodeGenResult {
+        match value {
+            Value::Register(reg) => self.assembler.push(gpr64(reg))?,
+            Value::Frame(displacement) => self.assembler.push(qword_ptr(rbp + displacement))?,
+            Value::Immediate(value) if i32::try_from(value).is_ok() => {
+                self.assembler.push(value as i32)?
+            }
+            value => {
+                self.load(Register::R11, value, OperandSize::_64)?;
+                self.assembler.push(r11)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+const LEA: u8 = 0x8D;
+const MOV_LOAD: u8 = 0x8B;
+const MOV_STORE: u8 = 0x89;
+
+#[derive(Debug, Clone, Copy)]
+enum Unary {
+    Neg,
+    Not,
+    Shl,
+    Shr,
+    Sar,
+    Div,
+    Idiv,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Binary {
+    Add,
+    Sub,
+    Imul,
+    And,
+    Or,
+    Xor,
+    Cmp,
+}
+
+fn gpr64(reg: Register) -> AsmRegister64 {
+    get_gpr64(reg.full_register()).expect("not a general purpose register")
+}
+
+fn gpr32(reg: Register) -> AsmRegister32 {
+    get_gpr32(reg.full_register32()).expect("not a general purpose register")
+}
+
+/// Evaluates `$body` with the given registers narrowed to the requested operand size.
+macro_rules! sized {
+    ($size:expr, |$($reg:ident),+| $body:expr) => {
+        match $size {
+            OperandSize::_32 => {
+                $(let $reg = gpr32($reg);)+
+                $body
+            }
+            OperandSize::_64 => {
+                $(let $reg = gpr64($reg);)+
+                $body
+            }
+        }
+    };
+}
+
+fn unary(asm: &mut CodeAssembler, op: Unary, size: OperandSize, reg: Register) -> CodeGenResult {
+    sized!(size, |reg| match op {
+        Unary::Neg => asm.neg(reg),
+        Unary::Not => asm.not(reg),
+        Unary::Shl => asm.shl(reg, cl),
+        Unary::Shr => asm.shr(reg, cl),
+        Unary::Sar => asm.sar(reg, cl),
+        Unary::Div => asm.div(reg),
+        Unary::Idiv => asm.idiv(reg),
+    })?;
+    Ok(())
+}
+
+fn binary(
+    asm: &mut CodeAssembler,
+    op: Binary,
+    size: OperandSize,
+    dst: Register,
+    src: Register,
+) -> CodeGenResult {
+    sized!(size, |dst, src| match op {
+        Binary::Add => asm.add(dst, src),
+        Binary::Sub => asm.sub(dst, src),
+        Binary::Imul => asm.imul_2(dst, src),
+        Binary::And => asm.and(dst, src),
+        Binary::Or => asm.or(dst, src),
+        Binary::Xor => asm.xor(dst, src),
+        Binary::Cmp => asm.cmp(dst, src),
+    })?;
+    Ok(())
+}
+
+/// Sets `r10b` to the outcome of the preceding `cmp`.
+fn set_condition(asm: &mut CodeAssembler, condition: Condition, signed: bool) -> CodeGenResult {
+    match (condition, signed) {
+        (Condition::Equal, _) => asm.sete(r10b)?,
+        (Condition::NotEqual, _) => asm.setne(r10b)?,
+        (Condition::Less, true) => asm.setl(r10b)?,
+        (Condition::LessEqual, true) => asm.setle(r10b)?,
+        (Condition::Greater, true) => asm.setg(r10b)?,
+        (Condition::GreaterEqual, true) => asm.setge(r10b)?,
+        (Condition::Less, false) => asm.setb(r10b)?,
+        (Condition::LessEqual, false) => asm.setbe(r10b)?,
+        (Condition::Greater, false) => asm.seta(r10b)?,
+        (Condition::GreaterEqual, false) => asm.setae(r10b)?,
+    }
+    Ok(())
+}
+
+fn jump_condition(
+    asm: &mut CodeAssembler,
+    condition: Condition,
+    signed: bool,
+    label: CodeLabel,
+) -> CodeGenResult {
+    match (condition, signed) {
+        (Condition::Equal, _) => asm.je(label)?,
+        (Condition::NotEqual, _) => asm.jne(label)?,
+        (Condition::Less, true) => asm.jl(label)?,
+        (Condition::LessEqual, true) => asm.jle(label)?,
+        (Condition::Greater, true) => asm.jg(label)?,
+        (Condition::GreaterEqual, true) => asm.jge(label)?,
+        (Condition::Less, false) => asm.jb(label)?,
+        (Condition::LessEqual, false) => asm.jbe(label)?,
+        (Condition::Greater, false) => asm.ja(label)?,
+        (Condition::GreaterEqual, false) => asm.jae(label)?,
+    }
+    Ok(())
+}
+
+/// Turns the source text of a string literal (quotes and escapes included) into the
+/// NUL-terminated bytes it denotes.
+pub fn unescape_string_literal(literal: &str) -> Vec<u8> {
+    let inner = literal
+        .strip_prefix(['"', '\''])
+        .and_then(|s| s.strip_suffix(['"', '\'']))
+        .unwrap_or(literal);
+    let mut bytes = Vec::with_capacity(inner.len() + 1);
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        let c = if c == '\\' {
+            match chars.next() {
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some('0') => '\0',
+                Some(other) => other,
+                None => '\\',
+            }
+        } else {
+            c
+        };
+        let mut buffer = [0; 4];
+        bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
     }
+    bytes.push(0);
+    bytes
 }
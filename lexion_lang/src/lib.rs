@@ -12,6 +12,7 @@ pub mod generators;
 pub mod operators;
 pub mod parser;
 pub mod pipeline;
+pub mod repl;
 pub mod symbol_table;
 pub mod type_checker;
 
@@ -27,6 +28,13 @@ pub enum Dump {
     Types,
     IntermediateRepresentation,
     ControlFlowGraph,
+    StaticSingleAssignment,
+    ConstantPropagation,
+    CopyPropagation,
+    DeadCodeElimination,
+    CommonSubexpressionElimination,
+    UnreachableBlocks,
+    JumpThreading,
 }
 
 impl Dump {
@@ -38,6 +46,13 @@ impl Dump {
     const TYPES: &'static str = "types";
     const IR: &'static str = "ir";
     const CFG: &'static str = "cfg";
+    const SSA: &'static str = "ssa";
+    const CONSTANT_PROPAGATION: &'static str = "const";
+    const COPY_PROPAGATION: &'static str = "copy";
+    const DCE: &'static str = "dce";
+    const CSE: &'static str = "cse";
+    const UNREACHABLE_BLOCKS: &'static str = "unreachable";
+    const JUMP_THREADING: &'static str = "jumps";
     const ALL: &'static str = "all";
 
     pub fn dump_options() -> impl Iterator<Item = (&'static str, BitFlags<Dump>)> {
@@ -50,6 +65,13 @@ impl Dump {
             (Self::TYPES, Dump::Types.into()),
             (Self::IR, Dump::IntermediateRepresentation.into()),
             (Self::CFG, Dump::ControlFlowGraph.into()),
+            (Self::SSA, Dump::StaticSingleAssignment.into()),
+            (Self::CONSTANT_PROPAGATION, Dump::ConstantPropagation.into()),
+            (Self::COPY_PROPAGATION, Dump::CopyPropagation.into()),
+            (Self::DCE, Dump::DeadCodeElimination.into()),
+            (Self::CSE, Dump::CommonSubexpressionElimination.into()),
+            (Self::UNREACHABLE_BLOCKS, Dump::UnreachableBlocks.into()),
+            (Self::JUMP_THREADING, Dump::JumpThreading.into()),
             (Self::ALL, Dump::all()),
         ]
         .into_iter()
@@ -65,6 +87,13 @@ impl Dump {
             Dump::Types => Self::TYPES,
             Dump::IntermediateRepresentation => Self::IR,
             Dump::ControlFlowGraph => Self::CFG,
+            Dump::StaticSingleAssignment => Self::SSA,
+            Dump::ConstantPropagation => Self::CONSTANT_PROPAGATION,
+            Dump::CopyPropagation => Self::COPY_PROPAGATION,
+            Dump::DeadCodeElimination => Self::DCE,
+            Dump::CommonSubexpressionElimination => Self::CSE,
+            Dump::UnreachableBlocks => Self::UNREACHABLE_BLOCKS,
+            Dump::JumpThreading => Self::JUMP_THREADING,
         }
     }
 }
@@ -89,8 +118,8 @@ impl FromStr for DumpFlags {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut flags = Dump::empty();
 
-        for part in s.split(",") {
-            let part = part.trim().to_lowercase();
+        for part in s.split(",").map(str::trim).filter(|part| !part.is_empty()) {
+            let part = part.to_lowercase();
             let Some((_, f)) = Dump::dump_options().find(|&(flag, _)| flag == part) else {
                 return Err(format!("invalid dump flag: {part}"));
             };
@@ -118,4 +147,6 @@ pub enum CompilationError {
     IO(std::io::Error),
     #[error("failed to compile source code")]
     CompilationFailed,
+    #[error("failed to link {0}: {1}")]
+    LinkFailed(String, String),
 }
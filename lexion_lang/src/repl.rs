@@ -0,0 +1,365 @@
+use crate::ast::types::TypeCollection;
+use crate::ast::{
+    Ast, AstView, BlockExpr, Expr, ExprStmt, FuncDeclStmt, Sourced, SourcedStmt, Stmt, VarDecl,
+    VarDeclStmt,
+};
+use crate::compiler::RunError;
+use crate::diagnostic::{
+    DiagnosticConsumer, LexionDiagnosticError, LexionDiagnosticList, LexionRuntimeErrorList,
+};
+use crate::generators::tac::interpreter::{InterpreterState, TacInterpreter, Value};
+use crate::generators::tac::CodeGeneratorTac;
+use crate::parser::ParserLexion;
+use crate::pipeline::PipelineStage;
+use crate::symbol_table::{SymbolTableEntryType, SymbolTableGenerator, SymbolTableGraph};
+use crate::type_checker::TypeChecker;
+use generational_arena::Index;
+use lexion_lib::miette::{NamedSource, SourceSpan};
+use lexion_lib::petgraph::graph::NodeIndex;
+use lexion_lib::tokenizer::Tokenizer;
+use lexion_lib::Parser;
+use std::io::Write;
+use std::sync::Arc;
+
+/// Variable that receives the value of an expression input.
+const RESULT: &str = "$it";
+
+/// What to restore when an input is rejected.
+struct Checkpoint {
+    source: usize,
+    program: usize,
+    inputs: usize,
+    symbols: SymbolTableGraph,
+    types: TypeCollection,
+}
+
+/// Evaluates statements and expressions one input at a time. Symbols, types and the values of
+/// variables persist across inputs, and the inputs form one source that diagnostics point into.
+///
+/// Declarations of functions and structs are kept as they are. The other statements of an
+/// input run in a function of their own, whose top-level variables then become globals.
+#[derive(Default)]
+pub struct Repl {
+    source: String,
+    /// Every input accepted so far. The whole program is lowered again for each input, so that
+    /// it can call the functions of earlier ones.
+    program: Ast,
+    symbols: SymbolTableGraph,
+    types: TypeCollection,
+    state: InterpreterState,
+    inputs: usize,
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `input`, writing program output to `stdout`. An input that does not end with `;`
+    /// or `}` is an expression, and its value is returned together with its type.
+    pub fn eval(
+        &mut self,
+        input: &str,
+        stdout: &mut dyn Write,
+    ) -> Result<(Option<String>, LexionDiagnosticList), RunError> {
+        let mut diagnostics = LexionDiagnosticList::default();
+        let checkpoint = self.checkpoint();
+        let name = format!("$repl_{}", self.inputs);
+        let Some(mut ast) = self.check(&mut diagnostics, input, &name, false) else {
+            self.restore(checkpoint);
+            return Err(RunError::Compilation(diagnostics));
+        };
+        let Some(function) = self.function_scope(&name) else {
+            self.program.extend(ast);
+            return Ok((None, diagnostics));
+        };
+
+        let result_type = self.variable_type(function, RESULT);
+        if result_type.is_some_and(|ty| self.types.eq(ty, self.types.unit())) {
+            self.symbols.remove_entry(function, RESULT);
+            Self::discard_result(&mut ast);
+        }
+        self.promote(function);
+        self.program.extend(ast);
+        self.inputs += 1;
+
+        let source = self.named_source();
+        let Some(cfg) = CodeGeneratorTac::new((&self.program, &mut self.symbols, &mut self.types))
+            .exec(&mut diagnostics, ())
+        else {
+            self.restore(checkpoint);
+            return Err(RunError::Compilation(diagnostics));
+        };
+        let mut errors = LexionDiagnosticList::default();
+        let result = TacInterpreter::new((
+            source,
+            &cfg,
+            &self.types,
+            &self.symbols,
+            &mut self.state,
+            stdout,
+        ))
+        .exec(&mut errors, &name);
+
+        // The result is only read once, so it must not shadow the result of the next input.
+        let root = self.symbols.root;
+        let result_type = self
+            .symbols
+            .remove_entry(root, RESULT)
+            .and_then(|entry| entry.var_type);
+        if result.is_none() {
+            return Err(RunError::Runtime(
+                diagnostics,
+                LexionRuntimeErrorList { list: errors.list },
+            ));
+        }
+        diagnostics.list.extend(errors.list);
+        let Some(result_type) = result_type else {
+            return Ok((None, diagnostics));
+        };
+        let value = self.state.global(RESULT).unwrap_or(Value::Integer(0));
+        let value = self.state.format_value(value, result_type, &self.types);
+        Ok((
+            Some(format!(
+                "{value}: {}",
+                self.types.to_string_index(result_type)
+            )),
+            diagnostics,
+        ))
+    }
+
+    /// Type of the expression `input`, which is checked but not run.
+    pub fn type_of(
+        &mut self,
+        input: &str,
+    ) -> Result<(String, LexionDiagnosticList), LexionDiagnosticList> {
+        let mut diagnostics = LexionDiagnosticList::default();
+        let checkpoint = self.checkpoint();
+        let name = format!("$repl_{}", self.inputs);
+        let result_type = self
+            .check(&mut diagnostics, input, &name, true)
+            .and_then(|_| self.function_scope(&name))
+            .and_then(|function| self.variable_type(function, RESULT))
+            .map(|ty| self.types.to_string_index(ty).into_owned());
+        self.restore(checkpoint);
+        match result_type {
+            Some(ty) if !diagnostics.has_errors() => Ok((ty, diagnostics)),
+            _ => Err(diagnostics),
+        }
+    }
+
+    /// Syntax tree of `input`, which is parsed but neither checked nor run.
+    pub fn ast(&mut self, input: &str) -> Result<String, LexionDiagnosticList> {
+        let mut diagnostics = LexionDiagnosticList::default();
+        let checkpoint = self.checkpoint();
+        let ast = self.parse(&mut diagnostics, input);
+        self.restore(checkpoint);
+        ast.map(|(ast, _)| AstView::new(&ast).to_string())
+            .ok_or(diagnostics)
+    }
+}
+
+impl Repl {
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            source: self.source.len(),
+            program: self.program.len(),
+            inputs: self.inputs,
+            symbols: self.symbols.clone(),
+            types: self.types.clone(),
+        }
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        self.source.truncate(checkpoint.source);
+        self.program.truncate(checkpoint.program);
+        self.inputs = checkpoint.inputs;
+        self.symbols = checkpoint.symbols;
+        self.types = checkpoint.types;
+    }
+
+    fn named_source(&self) -> NamedSource<Arc<String>> {
+        NamedSource::new("repl", Arc::new(self.source.clone()))
+    }
+
+    /// Appends `input` to the source and parses it. Returns whether it is an expression.
+    fn parse(&mut self, diag: &mut dyn DiagnosticConsumer, input: &str) -> Option<(Ast, bool)> {
+        let input = input.trim_end();
+        let is_expression = !input.is_empty() && !input.ends_with([';', '}']);
+        let start = self.source.len();
+        self.source.push_str(input);
+        if is_expression {
+            self.source.push(';');
+        }
+        self.source.push('\n');
+
+        // Parsing starts after the earlier inputs, so that spans point into the whole source.
+        let mut parser = ParserLexion {
+            types: std::mem::take(&mut self.types),
+        };
+        let mut tokenizer =
+            Tokenizer::from_string(Arc::new(self.source.clone()), ParserLexion::token_types());
+        tokenizer.seek(start);
+        let result = parser.parse(tokenizer);
+        self.types = parser.types;
+        match result {
+            Ok(ast) => {
+                let is_expression = is_expression
+                    && matches!(
+                        ast.last(),
+                        Some(Sourced {
+                            value: Stmt::ExprStmt(_),
+                            ..
+                        })
+                    );
+                Some((ast, is_expression))
+            }
+            Err(err) => {
+                diag.error((self.named_source(), err).into());
+                None
+            }
+        }
+    }
+
+    /// Parses `input`, wraps it in the function `name` and checks it against the symbols of
+    /// earlier inputs.
+    fn check(
+        &mut self,
+        diag: &mut LexionDiagnosticList,
+        input: &str,
+        name: &str,
+        expect_expression: bool,
+    ) -> Option<Ast> {
+        let start = self.source.len();
+        let (ast, is_expression) = self.parse(diag, input)?;
+        let span = SourceSpan::from((start, self.source.len() - start));
+        if expect_expression && !is_expression {
+            diag.error(LexionDiagnosticError {
+                src: self.named_source(),
+                span,
+                message: String::from("expected an expression"),
+            });
+            return None;
+        }
+        let mut ast = Self::wrap(ast, name, span, is_expression);
+
+        let source = self.named_source();
+        let symbols = std::mem::take(&mut self.symbols);
+        self.symbols = SymbolTableGenerator::new((source.clone(), &ast, &mut self.types))
+            .with_table(symbols)
+            .exec(diag, ())?;
+        TypeChecker::new((source, &mut self.symbols, &mut self.types)).exec(diag, &mut ast)?;
+        (!diag.has_errors()).then_some(ast)
+    }
+
+    /// Moves the statements of `ast` that are not declarations into a function called `name`.
+    /// An expression becomes the initializer of the result variable.
+    fn wrap(ast: Ast, name: &str, span: SourceSpan, is_expression: bool) -> Ast {
+        let (mut result, mut stmts): (Ast, Vec<SourcedStmt>) = ast.into_iter().partition(|stmt| {
+            matches!(stmt.value, Stmt::FuncDeclStmt(_) | Stmt::StructDeclStmt(_))
+        });
+        if is_expression {
+            if let Some(Sourced {
+                value: Stmt::ExprStmt(ExprStmt { expr }),
+                span,
+            }) = stmts.pop()
+            {
+                stmts.push(
+                    (
+                        span,
+                        Stmt::VarDeclStmt(VarDeclStmt {
+                            decl: (
+                                span,
+                                VarDecl {
+                                    name: (span, String::from(RESULT)).into(),
+                                    ty: None,
+                                    init: Some(expr),
+                                },
+                            )
+                                .into(),
+                        }),
+                    )
+                        .into(),
+                );
+            }
+        }
+        if stmts.is_empty() {
+            return result;
+        }
+        result.push(
+            (
+                span,
+                Stmt::FuncDeclStmt(FuncDeclStmt {
+                    name: (span, name.to_string()).into(),
+                    params: vec![],
+                    ty: None,
+                    body: Some(
+                        (
+                            span,
+                            Expr::BlockExpr(BlockExpr { stmts, expr: None }).into(),
+                        )
+                            .into(),
+                    ),
+                    is_vararg: false,
+                    is_extern: false,
+                }),
+            )
+                .into(),
+        );
+        result
+    }
+
+    /// Turns the result variable back into an expression statement, for expressions without
+    /// a value.
+    fn discard_result(ast: &mut Ast) {
+        let Some(Stmt::FuncDeclStmt(FuncDeclStmt {
+            body: Some(body), ..
+        })) = ast.last_mut().map(|stmt| &mut stmt.value)
+        else {
+            return;
+        };
+        let Expr::BlockExpr(block) = &mut body.value.expr else {
+            return;
+        };
+        let Some(stmt) = block.stmts.last_mut() else {
+            return;
+        };
+        if let Stmt::VarDeclStmt(VarDeclStmt { decl }) = &mut stmt.value {
+            if let Some(expr) = decl.value.init.take() {
+                stmt.value = Stmt::ExprStmt(ExprStmt { expr });
+            }
+        }
+    }
+
+    /// Scope of the function `name`, where the variables of an input are declared.
+    fn function_scope(&self, name: &str) -> Option<NodeIndex> {
+        let (_, _, function) = self.symbols.lookup(self.symbols.root, name)?;
+        function.table
+    }
+
+    fn variable_type(&self, scope: NodeIndex, name: &str) -> Option<Index> {
+        self.symbols
+            .graph
+            .node_weight(scope)?
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .and_then(|entry| entry.var_type)
+    }
+
+    /// Moves the variables declared in `scope` to the root scope, where they become globals
+    /// that later inputs can use. A variable declared again replaces the earlier one.
+    fn promote(&mut self, scope: NodeIndex) {
+        let root = self.symbols.root;
+        let entries = std::mem::take(&mut self.symbols.graph[scope].entries);
+        let (variables, rest): (Vec<_>, Vec<_>) = entries
+            .into_iter()
+            .partition(|entry| entry.ty == SymbolTableEntryType::LocalVar);
+        self.symbols.graph[scope].entries = rest;
+        for variable in variables {
+            if let Err(index) = self.symbols.insert_entry(root, variable.clone()) {
+                self.symbols.graph[root].entries[index] = variable;
+            }
+        }
+    }
+}
@@ -10,10 +10,11 @@ use lexion_lib::miette::{NamedSource, SourceSpan};
 use lexion_lib::petgraph::graph::NodeIndex;
 use lexion_lib::petgraph::prelude::Bfs;
 use lexion_lib::petgraph::visit::Walker;
-use lexion_lib::petgraph::Graph;
+use lexion_lib::petgraph::{Direction, Graph};
 use lexion_lib::tabled::builder::Builder;
 use lexion_lib::tabled::settings::Style;
 use lexion_lib::tabled::Table;
+use std::collections::HashMap;
 use std::fmt::{Debug, Display, Formatter};
 use std::sync::Arc;
 
@@ -69,7 +70,7 @@ pub struct SymbolTableEntry {
     pub layout: Option<MemoryLayout>,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct SymbolTable {
     pub name: String,
     pub entries: Vec<SymbolTableEntry>,
@@ -94,7 +95,7 @@ impl SymbolTable {
     }
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct SymbolTableGraph {
     pub root: NodeIndex,
     pub graph: Graph<SymbolTable, ()>,
@@ -105,6 +106,59 @@ impl SymbolTableGraph {
         self.graph.neighbors(scope).next()
     }
 
+    /// `scope` followed by every scope nested in it, breadth first.
+    pub fn nested_scopes(&self, scope: NodeIndex) -> Vec<NodeIndex> {
+        let mut result = vec![scope];
+        let mut index = 0;
+        while let Some(scope) = result.get(index).copied() {
+            result.extend(self.graph.neighbors_directed(scope, Direction::Incoming));
+            index += 1;
+        }
+        result
+    }
+
+    /// Innermost scope nested in `scope` whose block contains the source `offset`.
+    pub fn scope_at(&self, scope: NodeIndex, offset: usize) -> NodeIndex {
+        let mut scope = scope;
+        while let Some(inner) = self.graph[scope].entries.iter().find_map(|entry| {
+            let start = entry.span.offset();
+            let contains = (start..start + entry.span.len()).contains(&offset);
+            match entry.table {
+                Some(table) if entry.ty == SymbolTableEntryType::Scope && contains => Some(table),
+                _ => None,
+            }
+        }) {
+            scope = inner;
+        }
+        scope
+    }
+
+    /// Types of the parameters, locals and temporaries of `function`, and of the globals it can
+    /// see. Globals come last so that locals shadowing them keep their own type.
+    pub fn variable_types(&self, function: &str) -> HashMap<String, Index> {
+        let mut result = HashMap::new();
+        let Some(scope) = self
+            .lookup(self.root, function)
+            .and_then(|(_, _, entry)| entry.table)
+        else {
+            return result;
+        };
+        for scope in self.nested_scopes(scope).into_iter().chain([self.root]) {
+            for entry in &self.graph[scope].entries {
+                if let (
+                    SymbolTableEntryType::Parameter
+                    | SymbolTableEntryType::LocalVar
+                    | SymbolTableEntryType::Temporary,
+                    Some(ty),
+                ) = (entry.ty, entry.var_type)
+                {
+                    result.entry(entry.name.clone()).or_insert(ty);
+                }
+            }
+        }
+        result
+    }
+
     pub fn parent_entry(&self, scope: NodeIndex) -> Option<(NodeIndex, usize, &SymbolTableEntry)> {
         let scope_name = self.graph.node_weight(scope)?.name.clone();
         let parent_scope = self.parent_scope(scope)?;
@@ -129,6 +183,16 @@ impl SymbolTableGraph {
         Ok(())
     }
 
+    pub fn remove_entry(&mut self, node: NodeIndex, identifier: &str) -> Option<SymbolTableEntry> {
+        let table = self.graph.node_weight_mut(node)?;
+        let index = table
+            .entries
+            .as_slice()
+            .binary_search_by(|probe| probe.name.as_str().cmp(identifier))
+            .ok()?;
+        Some(table.entries.remove(index))
+    }
+
     pub fn lookup(
         &self,
         node: NodeIndex,
@@ -215,6 +279,14 @@ pub struct SymbolTableGenerator<'a> {
 }
 
 impl<'a> SymbolTableGenerator<'a> {
+    /// Declares the symbols of the AST in `table` instead of a new graph, so that they can
+    /// refer to symbols declared before, e.g. by earlier REPL inputs.
+    pub fn with_table(mut self, table: SymbolTableGraph) -> Self {
+        self.current_scope = table.root;
+        self.table = table;
+        self
+    }
+
     fn parent_scope(&mut self) {
         if let Some(parent) = self.table.parent_scope(self.current_scope) {
             self.current_scope = parent;
@@ -321,18 +393,20 @@ impl<'a> PipelineStage for SymbolTableGenerator<'a> {
     }
 
     fn exec(mut self, diag: &mut dyn DiagnosticConsumer, _: Self::Options) -> Option<Self::Output> {
-        self.create_scope(
-            diag,
-            SymbolTableEntry {
-                ty: SymbolTableEntryType::Global,
-                name: String::from("root"),
-                table: None,
-                span: 0.into(),
-                var_type: None,
-                layout: None,
-            },
-        );
-        self.table.root = self.current_scope;
+        if self.table.graph.node_count() == 0 {
+            self.create_scope(
+                diag,
+                SymbolTableEntry {
+                    ty: SymbolTableEntryType::Global,
+                    name: String::from("root"),
+                    table: None,
+                    span: 0.into(),
+                    var_type: None,
+                    layout: None,
+                },
+            );
+            self.table.root = self.current_scope;
+        }
         AstVisitor::new().visit(self.ast, |ty, node, _| {
             match (ty, node) {
                 (
@@ -6,18 +6,18 @@ use lexion_lib::miette::{NamedSource, SourceSpan};
 use lexion_lib::petgraph::graph::NodeIndex;
 
 use crate::ast::types::{FunctionType, Type, TypeCollection};
-use crate::ast::visitor::{
-    AstNode, AstNodeMut, AstVisitor, AstVisitorAction, NodeType, TraversalType,
-};
+use crate::ast::visitor::{AstNodeMut, AstVisitor, AstVisitorAction, TraversalType};
+use crate::ast::Type as AstType;
 use crate::ast::{
-    Ast, BlockExpr, CallExpr, Expr, ExprStmt, FuncDeclStmt, IdentExpr, IfExpr, IndexExpr, Lit,
-    LitExpr, MemberExpr, OperatorExpr, ReturnStmt, Sourced, SourcedExpr, Stmt, StructDeclStmt,
-    TypedExpr, VarDecl, VarDeclStmt, WhileStmt,
+    ArrayExpr, ArrayType, Ast, BlockExpr, CallExpr, Expr, ExprStmt, FuncDeclStmt, IdentExpr,
+    IfExpr, IndexExpr, Lit, LitExpr, MemberExpr, OperatorExpr, ReferenceType, ReturnStmt,
+    SliceType, Sourced, SourcedExpr, Stmt, StructDeclStmt, TupleType, TypedExpr, VarDecl,
+    VarDeclStmt, WhileStmt,
 };
 use crate::diagnostic::{DiagnosticConsumer, LexionDiagnosticError};
 use crate::operators;
 use crate::pipeline::PipelineStage;
-use crate::symbol_table::SymbolTableGraph;
+use crate::symbol_table::{SymbolTableEntryType, SymbolTableGraph};
 use crate::type_checker::operator_table::OperatorTable;
 
 pub struct TypeChecker<'a> {
@@ -131,6 +131,14 @@ impl<'a> TypeChecker<'a> {
                     },
                 ..
             } => self.lit(expr),
+            Sourced {
+                value:
+                    TypedExpr {
+                        expr: Expr::ArrayExpr(expr),
+                        ..
+                    },
+                ..
+            } => self.array(diag, expr),
         };
         if let Some(ty) = ty {
             expr.value.ty = ty;
@@ -244,6 +252,9 @@ impl<'a> TypeChecker<'a> {
                     }
                 }
             }
+            Type::ArrayType(_) | Type::SliceType(_) if expr.ident == "len" => {
+                return Some(self.types.i32());
+            }
             _ => {}
         }
 
@@ -260,15 +271,41 @@ impl<'a> TypeChecker<'a> {
         None
     }
 
-    #[allow(unused)]
     fn index(
         &mut self,
         diag: &mut dyn DiagnosticConsumer,
         expr: &mut IndexExpr,
         span: SourceSpan,
     ) -> Option<Index> {
-        // TODO
-        None
+        let ty_idx = self.expr(diag, &mut expr.expr)?;
+        let index_ty = self.expr(diag, &mut expr.index)?;
+
+        let ty_idx = self.types.canonicalize(ty_idx);
+        let ty_idx = self.types.dereference_all(ty_idx);
+        let Some((element, _)) = self.types.element(ty_idx) else {
+            diag.error(LexionDiagnosticError {
+                src: self.src.clone(),
+                span,
+                message: format!(
+                    "type '{}' cannot be indexed",
+                    self.types.to_string_index(ty_idx)
+                ),
+            });
+            return None;
+        };
+        if !self.types.eq(index_ty, self.types.u32()) && !self.types.eq(index_ty, self.types.i32())
+        {
+            diag.error(LexionDiagnosticError {
+                src: self.src.clone(),
+                span: expr.index.span,
+                message: format!(
+                    "expected an index of type 'u32' or 'i32', instead got '{}'",
+                    self.types.to_string_index(index_ty)
+                ),
+            });
+            return None;
+        }
+        Some(element)
     }
 
     fn call(
@@ -363,6 +400,15 @@ impl<'a> TypeChecker<'a> {
         })
     }
 
+    fn array(&mut self, diag: &mut dyn DiagnosticConsumer, expr: &mut ArrayExpr) -> Option<Index> {
+        let (first, rest) = expr.elements.split_first_mut()?;
+        let element = self.tc(diag, first, None)?;
+        for other in rest {
+            self.tc(diag, other, Some(element))?;
+        }
+        Some(self.types.array(element, expr.elements.len()))
+    }
+
     fn assign(&mut self, diag: &mut dyn DiagnosticConsumer, expr: &OperatorExpr) -> bool {
         let left = &expr.args[0];
         if !Self::is_assignable(left) {
@@ -377,46 +423,23 @@ impl<'a> TypeChecker<'a> {
         }
     }
 
+    /// Whether `expr` names memory, where only the base of member access and indexing counts,
+    /// not the index.
     fn is_assignable(expr: &SourcedExpr) -> bool {
-        let mut result = true;
-        AstVisitor::new().visit_expr(expr, NodeType::Root, &mut |ty, node, _| match (ty, node) {
-            (
-                TraversalType::Postorder,
-                AstNode::Expr(Sourced {
-                    value:
-                        TypedExpr {
-                            expr: Expr::IdentExpr(_) | Expr::MemberExpr(_) | Expr::IndexExpr(_),
-                            ..
-                        },
-                    ..
-                }),
-            ) => AstVisitorAction::Continue,
-            (
-                TraversalType::Postorder,
-                AstNode::Expr(Sourced {
-                    value:
-                        TypedExpr {
-                            expr: Expr::OperatorExpr(OperatorExpr { operator, args }),
-                            ..
-                        },
-                    ..
-                }),
-            ) if ((*operator).eq(operators::DEREFERENCE)
-                || (*operator).eq(operators::ADDRESS_OF))
-                && args.len() == 1 =>
-            {
-                AstVisitorAction::Continue
+        match &expr.expr {
+            Expr::IdentExpr(_) => true,
+            Expr::MemberExpr(MemberExpr { expr, .. }) | Expr::IndexExpr(IndexExpr { expr, .. }) => {
+                Self::is_assignable(expr)
             }
-            (TraversalType::Preorder, _) => AstVisitorAction::Continue,
-            _ => {
-                if let AstNode::Expr(expr) = node {
-                    println!("{expr:?}");
-                }
-                result = false;
-                AstVisitorAction::Terminate
+            Expr::OperatorExpr(OperatorExpr { operator, args })
+                if ((*operator).eq(operators::DEREFERENCE)
+                    || (*operator).eq(operators::ADDRESS_OF))
+                    && args.len() == 1 =>
+            {
+                Self::is_assignable(&args[0])
             }
-        });
-        result
+            _ => false,
+        }
     }
 
     fn init_operators(&mut self) {
@@ -602,9 +625,14 @@ impl<'a> TypeChecker<'a> {
             String::from("&"),
             Box::new(|types, list| {
                 let expr = list[0];
+                // Taking the address of an array makes a slice of it.
+                let return_type = match types.element(expr) {
+                    Some((element, Some(_))) => types.slice_ref(element),
+                    _ => types.reference(expr),
+                };
                 Ok(vec![FunctionType {
                     params: vec![expr],
-                    return_type: types.reference(expr),
+                    return_type,
                     is_vararg: false,
                 }])
             }),
@@ -641,7 +669,35 @@ impl<'a> TypeChecker<'a> {
 }
 
 impl<'a> TypeChecker<'a> {
-    fn begin_func_decl(&mut self, decl: &mut FuncDeclStmt) {
+    /// Reports slice types in `ty` that are not directly behind a reference.
+    fn check_type(&self, diag: &mut dyn DiagnosticConsumer, ty: &Sourced<AstType>) {
+        match &ty.value {
+            AstType::Path(_) => {}
+            AstType::Reference(ReferenceType { to }) => match &to.value {
+                AstType::Slice(SliceType { ty }) => self.check_type(diag, ty),
+                _ => self.check_type(diag, to),
+            },
+            AstType::Tuple(TupleType { types }) => {
+                for ty in types {
+                    self.check_type(diag, ty);
+                }
+            }
+            AstType::Array(ArrayType { ty, .. }) => self.check_type(diag, ty),
+            AstType::Slice(_) => diag.error(LexionDiagnosticError {
+                src: self.src.clone(),
+                span: ty.span,
+                message: String::from("slice types can only be used behind a reference"),
+            }),
+        }
+    }
+
+    fn begin_func_decl(&mut self, diag: &mut dyn DiagnosticConsumer, decl: &mut FuncDeclStmt) {
+        for param in &decl.params {
+            self.check_type(diag, &param.value.ty);
+        }
+        if let Some(ty) = &decl.ty {
+            self.check_type(diag, ty);
+        }
         if let Some((_, _, entry)) = self.table.lookup(self.current_scope, decl.name.as_str()) {
             self.current_scope = entry.table.unwrap();
         }
@@ -676,6 +732,9 @@ impl<'a> TypeChecker<'a> {
     }
 
     fn begin_var_decl(&mut self, diag: &mut dyn DiagnosticConsumer, decl: &mut VarDecl) {
+        if let Some(ty) = &decl.ty {
+            self.check_type(diag, ty);
+        }
         let mut ty = decl
             .ty
             .as_ref()
@@ -705,11 +764,16 @@ impl<'a> TypeChecker<'a> {
         stmt: &mut ReturnStmt,
         span: SourceSpan,
     ) {
-        let Some(parent_ty) = self
-            .table
-            .parent_entry(self.current_scope)
-            .and_then(|(_, _, entry)| entry.var_type)
-        else {
+        let mut scope = self.current_scope;
+        let parent_ty = loop {
+            match self.table.parent_entry(scope) {
+                Some((parent, _, entry)) if entry.ty == SymbolTableEntryType::Scope => {
+                    scope = parent;
+                }
+                entry => break entry.and_then(|(_, _, entry)| entry.var_type),
+            }
+        };
+        let Some(parent_ty) = parent_ty else {
             diag.error(LexionDiagnosticError {
                 src: self.src.clone(),
                 span,
@@ -743,7 +807,11 @@ impl<'a> TypeChecker<'a> {
         }
     }
 
-    fn begin_struct_stmt(&mut self, stmt: &StructDeclStmt) {}
+    fn begin_struct_stmt(&mut self, diag: &mut dyn DiagnosticConsumer, stmt: &StructDeclStmt) {
+        for field in &stmt.fields {
+            self.check_type(diag, &field.value.ty);
+        }
+    }
 
     fn begin_while_stmt(&mut self, diag: &mut dyn DiagnosticConsumer, stmt: &mut WhileStmt) {
         self.tc(diag, &mut stmt.condition, Some(self.types.bool()));
@@ -793,7 +861,7 @@ impl<'a> PipelineStage for TypeChecker<'a> {
                         value: Stmt::FuncDeclStmt(decl),
                         ..
                     }),
-                ) => self.begin_func_decl(decl),
+                ) => self.begin_func_decl(diag, decl),
                 (
                     TraversalType::Postorder,
                     AstNodeMut::Stmt(Sourced {
@@ -847,7 +915,7 @@ impl<'a> PipelineStage for TypeChecker<'a> {
                         value: Stmt::StructDeclStmt(stmt),
                         ..
                     }),
-                ) => self.begin_struct_stmt(stmt),
+                ) => self.begin_struct_stmt(diag, stmt),
                 (
                     TraversalType::Preorder,
                     AstNodeMut::Stmt(Sourced {
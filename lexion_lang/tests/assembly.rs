@@ -0,0 +1,66 @@
+mod common;
+
+use lexion_lang::generators::x86::AsmSyntax;
+
+fn assembly(fixture: &str, syntax: AsmSyntax) -> String {
+    let path = common::assembly(fixture, syntax).unwrap();
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn test_gas_syntax() {
+    let assembly = assembly("fibonacci.lex", AsmSyntax::Gas);
+    assert!(assembly.contains("\t.globl main\n"), "{assembly}");
+    assert!(assembly.contains("\n.Llstart_1:\n"), "{assembly}");
+    // `while a < 100` leaves the loop on the negated comparison.
+    assert!(assembly.contains("\tjge .Llend_1\n"), "{assembly}");
+    assert!(
+        assembly.contains("\tleaq .L.str.0(%rip), %r11\n"),
+        "{assembly}"
+    );
+    assert!(assembly.contains("\tcallq printf\n"), "{assembly}");
+    assert!(assembly.contains("\t.asciz \"%d\\012\"\n"), "{assembly}");
+}
+
+#[test]
+fn test_nasm_syntax() {
+    let assembly = assembly("globals.lex", AsmSyntax::Nasm);
+    assert!(assembly.contains("\textern printf\n"), "{assembly}");
+    assert!(assembly.contains("\tcall factorial\n"), "{assembly}");
+    assert!(assembly.contains("\tcall printf wrt ..plt\n"), "{assembly}");
+    assert!(assembly.contains("[rel calls]"), "{assembly}");
+    assert!(assembly.contains("\tdb \"%d\", 10, 0\n"), "{assembly}");
+    assert!(assembly.contains("base:\n\tdq 10\n"), "{assembly}");
+    assert!(assembly.contains("calls:\n\tresb 8\n"), "{assembly}");
+}
+
+#[test]
+fn test_source_line_comments() {
+    let assembly = assembly("fibonacci.lex", AsmSyntax::Gas);
+    let lines = assembly.lines().map(str::trim).collect::<Vec<_>>();
+    let comment = lines
+        .iter()
+        .position(|line| *line == "# 9: printf(\"%d\\n\", a);")
+        .unwrap_or_else(|| panic!("{assembly}"));
+    assert!(lines[comment + 1..]
+        .iter()
+        .take_while(|line| !line.starts_with('#'))
+        .any(|line| *line == "callq printf"));
+    let labels = lines
+        .iter()
+        .filter(|line| line.starts_with(".L") && line.ends_with(':'));
+    assert!(labels.count() >= 3, "{assembly}");
+}
+
+#[test]
+fn test_branch_targets_are_labels() {
+    let assembly = assembly("arrays.lex", AsmSyntax::Gas);
+    assert!(assembly.contains("\tjb .Lbounds_"), "{assembly}");
+    for line in assembly.lines().map(str::trim) {
+        if let Some((mnemonic, target)) = line.split_once(' ') {
+            if mnemonic.starts_with('j') || mnemonic == "callq" {
+                assert!(target.parse::<u64>().is_err(), "{line}");
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+fn lexion(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_main"))
+        .args(args)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run the compiler: {err}"))
+}
+
+#[test]
+fn test_output_executable() {
+    std::fs::create_dir_all("target/test-bins/cli").unwrap();
+    let output = lexion(&[
+        "tests/fixtures/globals.lex",
+        "-o",
+        "target/test-bins/cli/globals",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    // Building the parse table reports nothing, conflicts included.
+    assert!(output.stderr.is_empty(), "{output:?}");
+    let output = Command::new("target/test-bins/cli/globals")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "120\n3 2\n-3 -1\n13 5\n"
+    );
+}
+
+#[test]
+fn test_ill_typed_program_is_not_linked() {
+    std::fs::create_dir_all("target/test-bins/cli").unwrap();
+    let _ = std::fs::remove_file("target/test-bins/cli/arguments");
+    let output = lexion(&[
+        "tests/fixtures/errors/arguments.lex",
+        "-o",
+        "target/test-bins/cli/arguments",
+    ]);
+    assert!(!output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("called with 3 argument(s)"), "{stdout}");
+    assert!(!std::path::Path::new("target/test-bins/cli/arguments").exists());
+}
+
+#[test]
+fn test_run_failure() {
+    let output = lexion(&["tests/fixtures/runtime/division_by_zero.lex", "--run"]);
+    assert_eq!(output.status.code(), Some(101), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("5\n"), "{stdout}");
+    assert!(stdout.contains("Runtime errors:"), "{stdout}");
+    assert!(stdout.contains("division by zero"), "{stdout}");
+    assert!(!stdout.contains("Compilation errors:"), "{stdout}");
+}
+
+#[test]
+fn test_run_ill_typed_program() {
+    let output = lexion(&["tests/fixtures/errors/ill_typed.lex", "--run"]);
+    assert_eq!(output.status.code(), Some(1), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Compilation errors:"), "{stdout}");
+    assert!(!stdout.starts_with("ran\n"), "{stdout}");
+}
+
+#[test]
+fn test_emit_nasm() {
+    std::fs::create_dir_all("target/test-bins/cli").unwrap();
+    std::fs::copy(
+        "tests/fixtures/globals.lex",
+        "target/test-bins/cli/nasm.lex",
+    )
+    .unwrap();
+    let output = lexion(&[
+        "target/test-bins/cli/nasm.lex",
+        "--emit",
+        "asm",
+        "--asm-syntax",
+        "nasm",
+    ]);
+    assert!(output.status.success(), "{output:?}");
+    let assembly = std::fs::read_to_string("target/test-bins/cli/nasm.asm").unwrap();
+    assert!(assembly.contains("default rel"), "{assembly}");
+}
+
+/// Fixtures the x86-64 backend does not build: one uses floating point arithmetic and the
+/// other has no `main`.
+const NOT_LINKED: [&str; 2] = ["formatting.lex", "structs.lex"];
+
+fn fixtures() -> Vec<String> {
+    let mut fixtures = Vec::new();
+    for dir in ["", "runtime/"] {
+        for entry in std::fs::read_dir(format!("tests/fixtures/{dir}")).unwrap() {
+            let name = entry.unwrap().file_name().into_string().unwrap();
+            if name.ends_with(".lex") && !NOT_LINKED.contains(&name.as_str()) {
+                fixtures.push(format!("{dir}{name}"));
+            }
+        }
+    }
+    fixtures.sort();
+    fixtures
+}
+
+fn build(command: &mut Command) {
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "{command:?}: {output:?}");
+}
+
+#[test]
+fn test_emit_gas_matches_executable() {
+    // Assembling and linking the source of every fixture gives a program that behaves like
+    // the executable written by `-o`.
+    std::fs::create_dir_all("target/test-bins/cli/gas/runtime").unwrap();
+    for fixture in fixtures() {
+        let source = format!("tests/fixtures/{fixture}");
+        let base = Path::new("target/test-bins/cli/gas").join(fixture.trim_end_matches(".lex"));
+        let (assembly, object) = (base.with_extension("s"), base.with_extension("o"));
+        let executable = base.with_extension("gas");
+        build(
+            Command::new(env!("CARGO_BIN_EXE_main"))
+                .args([&source, "--emit", "asm", "-o"])
+                .arg(&assembly),
+        );
+        build(Command::new("as").arg(&assembly).arg("-o").arg(&object));
+        build(Command::new("cc").arg(&object).arg("-o").arg(&executable));
+        build(
+            Command::new(env!("CARGO_BIN_EXE_main"))
+                .args([&source, "-o"])
+                .arg(&base),
+        );
+
+        let output = Command::new(&executable).output().unwrap();
+        let expected = Command::new(&base).output().unwrap();
+        assert_eq!(output.status, expected.status, "{fixture}");
+        assert_eq!(output.stdout, expected.stdout, "{fixture}");
+    }
+}
+
+#[test]
+fn test_run() {
+    let output = lexion(&["tests/fixtures/globals.lex", "--run"]);
+    assert_eq!(output.status.code(), Some(5), "{output:?}");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "120\n3 2\n-3 -1\n13 5\n"
+    );
+}
+
+#[test]
+fn test_repl() {
+    let mut repl = Command::new(env!("CARGO_BIN_EXE_lexion-repl"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap_or_else(|err| panic!("failed to run the REPL: {err}"));
+    repl.stdin
+        .take()
+        .unwrap()
+        .write_all(b"let x = 20;\nx + 22\nx / 0\n:quit\n")
+        .unwrap();
+    let output = repl.wait_with_output().unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("42: i32"), "{stdout}");
+    assert!(stdout.contains("Runtime errors:"), "{stdout}");
+    assert!(!stdout.contains("Compilation errors:"), "{stdout}");
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use iced_x86::{
+    Decoder, DecoderOptions, FlowControl, Formatter, Instruction, Mnemonic, NasmFormatter,
+};
+use lexion_lang::ast::types::{FunctionType, StructMember, StructType, Type, TypeCollection};
+use lexion_lang::generators::x86::system_v::SystemV64;
+use lexion_lang::generators::x86::{
+    Align, AssembledModule, CMemoryLayoutBuilder, CallingConvention, MemoryLayoutBuilder,
+    RelocationKind, StackOffset,
+};
+
+const FIXTURES: [&str; 7] = [
+    "variables.lex",
+    "functions.lex",
+    "control_flow.lex",
+    "structs.lex",
+    "contextual_keywords.lex",
+    "fibonacci.lex",
+    "globals.lex",
+];
+
+fn disassemble(instructions: &[Instruction]) -> String {
+    let mut formatter = NasmFormatter::new();
+    let mut output = String::new();
+    for instruction in instructions {
+        output.push_str(&format!("{:04x}  ", instruction.ip()));
+        formatter.format(instruction, &mut output);
+        output.push('\n');
+    }
+    output
+}
+
+fn decode(module: &AssembledModule, function: &str) -> Vec<Instruction> {
+    let function = module
+        .functions
+        .iter()
+        .find(|f| f.name == function)
+        .unwrap_or_else(|| panic!("function '{function}' was not assembled"));
+    let bytes = &module.code[function.offset..function.offset + function.size];
+    let instructions = Decoder::with_ip(64, bytes, function.offset as u64, DecoderOptions::NONE)
+        .into_iter()
+        .collect::<Vec<_>>();
+    assert!(
+        instructions.iter().all(|i| !i.is_invalid()),
+        "invalid instruction in '{}':\n{}",
+        function.name,
+        disassemble(&instructions)
+    );
+    instructions
+}
+
+#[test]
+fn test_fixtures_decode() {
+    for fixture in FIXTURES {
+        let module = common::assemble(fixture).unwrap();
+        assert!(!module.functions.is_empty(), "{fixture}");
+        let covered: usize = module.functions.iter().map(|f| f.size).sum();
+        assert_eq!(covered, module.code.len(), "{fixture}");
+        for function in &module.functions {
+            let instructions = decode(&module, &function.name);
+            let listing = disassemble(&instructions);
+            assert_eq!(instructions[0].mnemonic(), Mnemonic::Push, "{listing}");
+            assert_eq!(instructions[1].mnemonic(), Mnemonic::Mov, "{listing}");
+            assert_eq!(
+                instructions.last().unwrap().mnemonic(),
+                Mnemonic::Ret,
+                "{listing}"
+            );
+            let end = (function.offset + function.size) as u64;
+            for branch in instructions
+                .iter()
+                .filter(|i| i.flow_control() == FlowControl::ConditionalBranch)
+                .chain(
+                    instructions
+                        .iter()
+                        .filter(|i| i.flow_control() == FlowControl::UnconditionalBranch),
+                )
+            {
+                let target = branch.near_branch_target();
+                assert!(
+                    (function.offset as u64..end).contains(&target),
+                    "{fixture}: branch out of '{}':\n{listing}",
+                    function.name
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_call_targets_function() {
+    let module = common::assemble("functions.lex").unwrap();
+    let add = module.functions.iter().find(|f| f.name == "add").unwrap();
+    let main = decode(&module, "main");
+    let call = main
+        .iter()
+        .find(|i| i.mnemonic() == Mnemonic::Call)
+        .expect("main calls add");
+    assert_eq!(call.near_branch_target(), add.offset as u64);
+    assert!(module.relocations.is_empty());
+}
+
+#[test]
+fn test_conditional_branch() {
+    let module = common::assemble("control_flow.lex").unwrap();
+    let main = decode(&module, "main");
+    assert!(main
+        .iter()
+        .any(|i| i.flow_control() == FlowControl::ConditionalBranch));
+}
+
+#[test]
+fn test_extern_call_relocation() {
+    let module = common::assemble("fibonacci.lex").unwrap();
+    assert_eq!(module.externs, ["printf"]);
+    assert_eq!(module.rodata.len(), 1);
+    assert_eq!(module.rodata[0].bytes, b"%d\n\0");
+
+    let main = decode(&module, "main");
+    let call = main
+        .iter()
+        .find(|i| i.mnemonic() == Mnemonic::Call)
+        .expect("main calls printf");
+    let relocation = module
+        .relocations
+        .iter()
+        .find(|r| r.kind == RelocationKind::Call)
+        .unwrap();
+    assert_eq!(relocation.symbol, "printf");
+    assert_eq!(relocation.offset as u64, call.ip() + 1);
+
+    let lea = main
+        .iter()
+        .find(|i| i.mnemonic() == Mnemonic::Lea && i.is_ip_rel_memory_operand())
+        .expect("format string is loaded RIP-relative");
+    let relocation = module
+        .relocations
+        .iter()
+        .find(|r| r.kind == RelocationKind::Data)
+        .unwrap();
+    assert_eq!(relocation.symbol, module.rodata[0].name);
+    assert_eq!(relocation.offset as u64, lea.ip() + 3);
+}
+
+#[test]
+fn test_system_v_large_aggregates_on_stack() {
+    let mut types = TypeCollection::default();
+    let members = (0..5)
+        .map(|i| StructMember {
+            name: format!("m{i}"),
+            ty: types.i32(),
+        })
+        .collect();
+    let large = types.insert(&Type::StructType(StructType {
+        ident: String::from("Large"),
+        members,
+    }));
+    let mut layout = CMemoryLayoutBuilder::new();
+    for _ in 0..5 {
+        layout.member(4, Align::new(4));
+    }
+    types.memory_layouts.insert(large, layout.build());
+    let signature = FunctionType {
+        params: vec![large, types.i32(), large],
+        return_type: types.unit(),
+        is_vararg: false,
+    };
+
+    let locations = SystemV64.assign_args(&types, 2, &signature);
+    assert_eq!(locations.len(), 3);
+    assert!(matches!(locations[0].stack_offset(), Some(StackOffset(2))));
+    assert_eq!(
+        locations[1].register(),
+        Some(SystemV64::INTEGER_REGISTERS[0])
+    );
+    assert!(matches!(locations[2].stack_offset(), Some(StackOffset(5))));
+}
+
+#[test]
+fn test_global_sections() {
+    let module = common::assemble("globals.lex").unwrap();
+    assert_eq!(module.data.len(), 1);
+    assert_eq!(module.data[0].name, "base");
+    assert_eq!(module.data[0].bytes, 10i64.to_le_bytes());
+    assert_eq!(module.bss.len(), 1);
+    assert_eq!(module.bss[0].name, "calls");
+
+    let factorial = decode(&module, "factorial");
+    let load = factorial
+        .iter()
+        .find(|i| i.mnemonic() == Mnemonic::Mov && i.is_ip_rel_memory_operand())
+        .expect("globals are accessed RIP-relative");
+    assert!(module
+        .relocations
+        .iter()
+        .any(|r| r.symbol == "calls" && r.offset as u64 == load.ip() + 3));
+}
@@ -1,19 +1,140 @@
-use enumflags2::BitFlag;
+#![allow(dead_code)]
+
+use enumflags2::{BitFlag, BitFlags};
 use lexion_lang::compiler::{LexionCompiler, LexionCompilerOptions};
+use lexion_lang::diagnostic::LexionDiagnosticList;
+use lexion_lang::generators::tac::opt::OptLevel;
+use lexion_lang::generators::tac::ssa::SsaGraph;
+use lexion_lang::generators::x86::{AsmSyntax, AssembledModule};
 use lexion_lang::{Dump, DumpFlags};
 use lexion_lib::miette::NamedSource;
+use std::path::PathBuf;
 use std::sync::Arc;
 
-pub fn compile(fixture: &str) -> Result<(), Vec<String>> {
+/// Fixtures that run to completion, with the exit code and output that the interpreter and the
+/// linked executables must both give at every optimization level.
+pub const PROGRAMS: [(&str, i32, &str); 16] = [
+    ("variables.lex", 0, ""),
+    ("functions.lex", 0, ""),
+    ("control_flow.lex", 1, ""),
+    ("contextual_keywords.lex", 0, ""),
+    ("fibonacci.lex", 0, "1\n1\n2\n3\n5\n8\n13\n21\n34\n55\n89\n"),
+    ("globals.lex", 5, "120\n3 2\n-3 -1\n13 5\n"),
+    ("loops.lex", 2, "2 1 1 2\n111 21 12\n"),
+    ("optimizations.lex", 5, "43 26 -2147483648 20\n"),
+    ("short_circuit.lex", 5, "0 1 0 4\n5 3 0\n"),
+    ("memory.lex", 3, "1 10 7 6\n25 3\n9 0 9 10\n"),
+    // Structs returned from a call are copied by the caller after the frame released its memory.
+    ("frames.lex", 0, "3 7 50005000\n1 2 3 4 5 204\n"),
+    // Locals shadowing a global or another local of a different type keep their own storage.
+    ("shadowing.lex", 4, "4\n30 50\n42\n2 1\n"),
+    ("arrays.lex", 7, "5 27 27\n1 11 62\n13 46\n"),
+    // `nested` needs an edge split, and `fib` after it has a parameter of the same name.
+    ("split_edges.lex", 0, "18 6765\n"),
+    ("shifts.lex", 0, "2 -4 -2147483648\n"),
+    (
+        "unsigned.lex",
+        0,
+        "\n4294967295 ffffffff 2147483647\nmax > ten\n4294967295 ffffffff -1\n",
+    ),
+];
+
+fn source(fixture: &str) -> NamedSource<Arc<String>> {
     let path = format!("tests/fixtures/{fixture}");
     let source_code = Arc::new(std::fs::read_to_string(&path).expect("fixture not found"));
-    let source = NamedSource::new(&path, source_code);
-    let options = LexionCompilerOptions {
-        dump_flags: DumpFlags::from(Dump::all()),
+    NamedSource::new(&path, source_code)
+}
+
+fn options(opt_level: OptLevel, dump: BitFlags<Dump>) -> LexionCompilerOptions {
+    LexionCompilerOptions {
+        dump_flags: DumpFlags::from(dump),
         dump_dir: "target/test-dumps".into(),
-    };
-    LexionCompiler::new(options)
-        .exec(source)
+        opt_level,
+    }
+}
+
+fn compiler(opt_level: OptLevel) -> LexionCompiler {
+    LexionCompiler::new(options(opt_level, Dump::empty()))
+}
+
+fn messages(diagnostics: LexionDiagnosticList) -> Vec<String> {
+    diagnostics.list.iter().map(|d| d.to_string()).collect()
+}
+
+pub fn compile(fixture: &str) -> Result<(), Vec<String>> {
+    LexionCompiler::new(options(OptLevel::O0, Dump::all()))
+        .exec(source(fixture))
         .map(|_| ())
-        .map_err(|diag| diag.list.iter().map(|d| d.to_string()).collect())
+        .map_err(messages)
+}
+
+pub fn assemble(fixture: &str) -> Result<AssembledModule, Vec<String>> {
+    compiler(OptLevel::O0)
+        .compile(source(fixture))
+        .map(|(module, _)| module)
+        .map_err(messages)
+}
+
+pub fn link(fixture: &str) -> Result<PathBuf, Vec<String>> {
+    link_optimized(fixture, OptLevel::O0)
+}
+
+pub fn link_optimized(fixture: &str, opt_level: OptLevel) -> Result<PathBuf, Vec<String>> {
+    let mut compiler = compiler(opt_level);
+    let (module, _) = compiler.compile(source(fixture)).map_err(messages)?;
+    let name = match opt_level {
+        OptLevel::O0 => fixture.trim_end_matches(".lex").to_string(),
+        level => format!("{}-O{level}", fixture.trim_end_matches(".lex")),
+    };
+    let output = PathBuf::from("target/test-bins").join(name);
+    std::fs::create_dir_all(output.parent().unwrap()).unwrap();
+    compiler
+        .link(&module, &output)
+        .map_err(|err| vec![err.to_string()])?;
+    Ok(output)
+}
+
+pub fn assembly(fixture: &str, syntax: AsmSyntax) -> Result<PathBuf, Vec<String>> {
+    let source = source(fixture);
+    let mut compiler = compiler(OptLevel::O0);
+    let (module, _) = compiler.compile(source.clone()).map_err(messages)?;
+    std::fs::create_dir_all("target/test-bins").unwrap();
+    let output = PathBuf::from("target/test-bins")
+        .join(fixture.trim_end_matches(".lex"))
+        .with_extension(syntax.extension());
+    compiler
+        .write_assembly(source, &module, syntax, &output)
+        .map_err(|err| vec![err.to_string()])?;
+    Ok(output)
+}
+
+pub fn interpret(fixture: &str) -> Result<(i64, String), Vec<String>> {
+    interpret_optimized(fixture, OptLevel::O0)
+}
+
+pub fn interpret_optimized(
+    fixture: &str,
+    opt_level: OptLevel,
+) -> Result<(i64, String), Vec<String>> {
+    let mut stdout = Vec::new();
+    let (exit_code, _) = compiler(opt_level)
+        .run(source(fixture), &mut stdout)
+        .map_err(|err| {
+            err.errors()
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+        })?;
+    Ok((exit_code, String::from_utf8(stdout).unwrap()))
+}
+
+pub fn ssa(fixture: &str) -> Result<SsaGraph, Vec<String>> {
+    ssa_optimized(fixture, OptLevel::O0)
+}
+
+pub fn ssa_optimized(fixture: &str, opt_level: OptLevel) -> Result<SsaGraph, Vec<String>> {
+    compiler(opt_level)
+        .ssa(source(fixture))
+        .map(|(ssa, _)| ssa)
+        .map_err(messages)
 }
@@ -13,3 +13,32 @@ fn test_undefined_var() {
     let errors = common::compile("errors/undefined_var.lex").unwrap_err();
     insta::assert_snapshot!(errors.join("\n"));
 }
+
+#[test]
+fn test_index_errors() {
+    let errors = common::compile("errors/index.lex").unwrap_err();
+    assert!(
+        errors
+            .iter()
+            .any(|e| e.contains("type 'i32' cannot be indexed")),
+        "{errors:?}"
+    );
+    assert!(
+        errors
+            .iter()
+            .any(|e| e.contains("expected an index of type 'u32' or 'i32', instead got 'bool'")),
+        "{errors:?}"
+    );
+    assert!(
+        errors
+            .iter()
+            .any(|e| e.contains("expected type 'i32', instead got 'bool'")),
+        "{errors:?}"
+    );
+    assert!(
+        errors
+            .iter()
+            .any(|e| e.contains("slice types can only be used behind a reference")),
+        "{errors:?}"
+    );
+}
@@ -20,6 +20,16 @@ fn test_structs() {
     assert!(common::compile("structs.lex").is_ok());
 }
 
+#[test]
+fn test_arrays() {
+    assert!(common::compile("arrays.lex").is_ok());
+}
+
+#[test]
+fn test_contextual_keywords() {
+    assert!(common::compile("contextual_keywords.lex").is_ok());
+}
+
 #[test]
 fn print_grammar_conflicts() {
     use lexion_lang::parser::ParserLexion;
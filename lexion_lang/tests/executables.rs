@@ -0,0 +1,124 @@
+mod common;
+
+use lexion_lang::generators::tac::opt::OptLevel;
+use lexion_lang::generators::x86::AsmSyntax;
+use std::process::{Command, Output};
+
+fn run(fixture: &str) -> Output {
+    run_optimized(fixture, OptLevel::O0)
+}
+
+fn run_optimized(fixture: &str, opt_level: OptLevel) -> Output {
+    let executable = common::link_optimized(fixture, opt_level).unwrap();
+    Command::new(&executable)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run {executable:?}: {err}"))
+}
+
+#[test]
+fn test_programs() {
+    for (fixture, code, stdout) in common::PROGRAMS {
+        for level in [OptLevel::O0, OptLevel::O2] {
+            let output = run_optimized(fixture, level);
+            assert_eq!(output.status.code(), Some(code), "{fixture} at -O{level}");
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                stdout,
+                "{fixture} at -O{level}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_index_out_of_bounds() {
+    // The failed check traps, so the process is killed by a signal instead of exiting.
+    let output = run("runtime/index_out_of_bounds.lex");
+    assert_eq!(output.status.code(), None);
+}
+
+#[test]
+fn test_empty_array() {
+    let output = run("runtime/empty_array.lex");
+    assert_eq!(output.status.code(), None);
+}
+
+#[test]
+fn test_optimization_levels_agree() {
+    for fixture in [
+        "runtime/division_overflow.lex",
+        "runtime/unused_division_overflow.lex",
+    ] {
+        let unoptimized = run(fixture);
+        let optimized = run_optimized(fixture, OptLevel::O2);
+        assert_eq!(
+            unoptimized.status.code(),
+            optimized.status.code(),
+            "{fixture}"
+        );
+        assert_eq!(unoptimized.stdout, optimized.stdout, "{fixture}");
+    }
+    // Dividing the smallest i32 by -1 traps, at any level.
+    assert_eq!(run("runtime/division_overflow.lex").status.code(), None);
+    assert_eq!(
+        run("runtime/unused_division_overflow.lex").status.code(),
+        None
+    );
+}
+
+#[test]
+fn test_missing_main_fails_to_link() {
+    let err = common::link("structs.lex").unwrap_err();
+    assert!(err[0].contains("main"), "{err:?}");
+}
+
+#[test]
+fn test_nasm_assembly() {
+    if Command::new("nasm").arg("-v").output().is_err() {
+        eprintln!("nasm is not installed, skipping");
+        return;
+    }
+    let assembly = common::assembly("globals.lex", AsmSyntax::Nasm).unwrap();
+    let object = assembly.with_extension("nasm.o");
+    let executable = assembly.with_extension("nasm");
+    let status = Command::new("nasm")
+        .args(["-f", "elf64"])
+        .arg(&assembly)
+        .arg("-o")
+        .arg(&object)
+        .status()
+        .unwrap();
+    assert!(status.success(), "{assembly:?} does not assemble");
+    let status = Command::new("cc")
+        .arg(&object)
+        .arg("-o")
+        .arg(&executable)
+        .status()
+        .unwrap();
+    assert!(status.success(), "{object:?} does not link");
+    let output = Command::new(&executable).output().unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "120\n3 2\n-3 -1\n13 5\n"
+    );
+}
+
+#[test]
+fn test_gas_assembly() {
+    let assembly = common::assembly("globals.lex", AsmSyntax::Gas).unwrap();
+    let executable = assembly.with_extension("gas");
+    let status = Command::new("cc")
+        .arg(&assembly)
+        .arg("-o")
+        .arg(&executable)
+        .status()
+        .unwrap();
+    assert!(status.success(), "{assembly:?} does not assemble");
+    let output = Command::new(&executable).output().unwrap();
+    assert_eq!(output.status.code(), Some(5));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "120\n3 2\n-3 -1\n13 5\n"
+    );
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use lexion_lang::generators::tac::opt::OptLevel;
+
+#[test]
+fn test_programs() {
+    for (fixture, code, stdout) in common::PROGRAMS {
+        for level in [OptLevel::O0, OptLevel::O2] {
+            let result = common::interpret_optimized(fixture, level).unwrap();
+            assert_eq!(
+                result,
+                (i64::from(code), stdout.to_string()),
+                "{fixture} at -O{level}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_printf_formatting() {
+    let (_, stdout) = common::interpret("formatting.lex").unwrap();
+    assert_eq!(
+        stdout,
+        "formatting:\n\
+         [   42] [42   ] [00042] [+42]\n\
+         [ff] [FF] [10] [A] [%]\n\
+         [lexion] [lex] [     tac]\n\
+         [2.500000] [7.50] [2.500000e+00] [2.5]\n"
+    );
+}
+
+#[test]
+fn test_division_by_zero() {
+    let err = common::interpret("runtime/division_by_zero.lex").unwrap_err();
+    assert!(err[0].contains("division by zero"), "{err:?}");
+}
+
+#[test]
+fn test_optimized_division_by_zero() {
+    let err =
+        common::interpret_optimized("runtime/division_by_zero.lex", OptLevel::O2).unwrap_err();
+    assert!(err[0].contains("division by zero"), "{err:?}");
+}
+
+#[test]
+fn test_division_overflow() {
+    for fixture in [
+        "runtime/division_overflow.lex",
+        "runtime/unused_division_overflow.lex",
+    ] {
+        for level in [OptLevel::O0, OptLevel::O2] {
+            let err = common::interpret_optimized(fixture, level).unwrap_err();
+            assert!(err[0].contains("division overflow"), "{fixture}: {err:?}");
+        }
+    }
+}
+
+#[test]
+fn test_index_out_of_bounds() {
+    for level in [OptLevel::O0, OptLevel::O2] {
+        let err =
+            common::interpret_optimized("runtime/index_out_of_bounds.lex", level).unwrap_err();
+        assert!(
+            err[0].contains("index 3 out of bounds for length 3"),
+            "{err:?}"
+        );
+    }
+}
+
+#[test]
+fn test_empty_array() {
+    for level in [OptLevel::O0, OptLevel::O2] {
+        let err = common::interpret_optimized("runtime/empty_array.lex", level).unwrap_err();
+        assert!(
+            err[0].contains("index 0 out of bounds for length 0"),
+            "{err:?}"
+        );
+    }
+}
+
+#[test]
+fn test_ill_typed_program_is_not_run() {
+    let err = common::interpret("errors/ill_typed.lex").unwrap_err();
+    assert!(
+        err.iter()
+            .any(|e| e.contains("expected type 'bool', instead got 'i32'")),
+        "{err:?}"
+    );
+}
+
+#[test]
+fn test_missing_main() {
+    let err = common::interpret("structs.lex").unwrap_err();
+    assert!(err[0].contains("undefined function 'main'"), "{err:?}");
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use lexion_lang::generators::tac::instructions::{BaseInstruction, Instruction};
+use lexion_lang::generators::tac::opt::OptLevel;
+use lexion_lang::generators::tac::ssa::SsaGraph;
+use lexion_lang::operators;
+use std::collections::HashSet;
+
+const FIXTURES: [&str; 10] = [
+    "variables.lex",
+    "functions.lex",
+    "control_flow.lex",
+    "fibonacci.lex",
+    "globals.lex",
+    "loops.lex",
+    "optimizations.lex",
+    "short_circuit.lex",
+    "memory.lex",
+    "arrays.lex",
+];
+
+fn instructions(ssa: &SsaGraph) -> Vec<String> {
+    ssa.cfg
+        .node_weights()
+        .flat_map(|block| block.instructions.iter())
+        .map(|inst| inst.instruction.to_string())
+        .collect()
+}
+
+#[test]
+fn test_opt_level_from_str() {
+    assert_eq!("0".parse::<OptLevel>(), Ok(OptLevel::O0));
+    assert_eq!("1".parse::<OptLevel>(), Ok(OptLevel::O1));
+    assert_eq!("2".parse::<OptLevel>(), Ok(OptLevel::O2));
+    assert!("3".parse::<OptLevel>().is_err());
+}
+
+#[test]
+fn test_constants_are_folded() {
+    let ssa = common::ssa_optimized("optimizations.lex", OptLevel::O1).unwrap();
+    let instructions = instructions(&ssa);
+    assert!(instructions.contains(&String::from("return 43")));
+    // Folding wraps to the type of the value, as running the program does.
+    assert!(instructions.contains(&String::from("return -2147483648")));
+}
+
+#[test]
+fn test_constant_bounds_checks_are_removed() {
+    let checks = |ssa: &SsaGraph| {
+        instructions(ssa)
+            .into_iter()
+            .filter(|inst| inst.starts_with("check"))
+            .collect::<Vec<_>>()
+    };
+    assert!(checks(&common::ssa("arrays.lex").unwrap()).contains(&String::from("check 4 < 5")));
+    // Only the checks against the length of a slice, which is loaded, are left.
+    let ssa = common::ssa_optimized("arrays.lex", OptLevel::O1).unwrap();
+    let checks = checks(&ssa);
+    assert_eq!(checks.len(), 3, "{checks:?}");
+    assert!(
+        checks.iter().all(|check| check.contains(" < %")),
+        "{checks:?}"
+    );
+}
+
+#[test]
+fn test_unreachable_blocks_are_removed() {
+    let unoptimized = common::ssa("optimizations.lex").unwrap();
+    let ssa = common::ssa_optimized("optimizations.lex", OptLevel::O1).unwrap();
+    let has_block = |ssa: &SsaGraph| ssa.cfg.node_weights().any(|b| b.label == "$cthen_1");
+    assert!(has_block(&unoptimized));
+    assert!(!has_block(&ssa));
+}
+
+#[test]
+fn test_common_subexpressions_are_computed_once() {
+    // `a * b` and `b * a` in `common` are the only products left after folding `width * 7`.
+    let count = |level| {
+        let ssa = common::ssa_optimized("optimizations.lex", level).unwrap();
+        ssa.cfg
+            .node_weights()
+            .flat_map(|block| block.instructions.iter())
+            .filter(|inst| {
+                matches!(
+                    &inst.instruction,
+                    Instruction::Assignment(assignment)
+                        if assignment.operator == operators::MULTIPLY
+                )
+            })
+            .count()
+    };
+    assert_eq!(count(OptLevel::O1), 2);
+    assert_eq!(count(OptLevel::O2), 1);
+}
+
+#[test]
+fn test_dead_values_are_removed() {
+    for fixture in FIXTURES {
+        let ssa = common::ssa_optimized(fixture, OptLevel::O2).unwrap();
+        let read = ssa
+            .cfg
+            .node_weights()
+            .flat_map(|block| block.instructions.iter())
+            .flat_map(|inst| inst.instruction.variables_read())
+            .collect::<HashSet<_>>();
+        for block in ssa.cfg.node_weights() {
+            for inst in &block.instructions {
+                let target = match &inst.instruction {
+                    Instruction::Copy(copy) => &copy.dst,
+                    Instruction::Phi(phi) => &phi.target,
+                    Instruction::Assignment(assignment) => &assignment.target,
+                    _ => continue,
+                };
+                if let Some(value) = target.value() {
+                    assert!(
+                        read.contains(&value.to_string()),
+                        "{fixture}: {} is dead",
+                        inst.instruction
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_optimized_values_are_assigned_once() {
+    for fixture in FIXTURES {
+        let mut ssa = common::ssa_optimized(fixture, OptLevel::O2).unwrap();
+        let mut assigned = HashSet::new();
+        for block in ssa.cfg.node_weights_mut() {
+            for inst in block.instructions.iter_mut() {
+                for operand in inst.instruction.operands_written_mut() {
+                    if let Some(value) = operand.value() {
+                        assert!(
+                            assigned.insert(value),
+                            "{fixture}: {value} is assigned twice"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
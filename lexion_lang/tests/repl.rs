@@ -0,0 +1,118 @@
+use lexion_lang::compiler::RunError;
+use lexion_lang::repl::Repl;
+
+fn eval(repl: &mut Repl, input: &str) -> (Option<String>, String) {
+    let mut stdout = Vec::new();
+    let (value, _) = repl
+        .eval(input, &mut stdout)
+        .unwrap_or_else(|err| panic!("{input}: {:?}", err.errors()));
+    (value, String::from_utf8(stdout).unwrap())
+}
+
+fn errors(repl: &mut Repl, input: &str) -> Vec<String> {
+    repl.eval(input, &mut Vec::new())
+        .map(|_| panic!("{input} was accepted"))
+        .unwrap_err()
+        .errors()
+        .iter()
+        .map(|d| d.to_string())
+        .collect()
+}
+
+#[test]
+fn test_expressions() {
+    let mut repl = Repl::new();
+    for (input, value) in [
+        ("1 + 2 * 3", "7: i32"),
+        ("17 % 5 == 2", "true: bool"),
+        ("1.5 * 3.0", "4.5: f32"),
+        ("\"lexion\"", "\"lexion\": &str"),
+        ("-(3 - 5)", "2: i32"),
+    ] {
+        assert_eq!(eval(&mut repl, input).0.as_deref(), Some(value), "{input}");
+    }
+}
+
+#[test]
+fn test_statements_persist() {
+    let mut repl = Repl::new();
+    assert_eq!(eval(&mut repl, "let x = 4;").0, None);
+    eval(&mut repl, "fn square(n: i32) -> i32 { return n * n; }");
+    assert_eq!(
+        eval(&mut repl, "square(x) + 1").0.as_deref(),
+        Some("17: i32")
+    );
+    eval(&mut repl, "x = x + 1;");
+    assert_eq!(eval(&mut repl, "square(x)").0.as_deref(), Some("25: i32"));
+    eval(&mut repl, "let x = true;");
+    assert_eq!(eval(&mut repl, "x").0.as_deref(), Some("true: bool"));
+}
+
+#[test]
+fn test_output() {
+    let mut repl = Repl::new();
+    eval(&mut repl, "extern fn printf(fmt: &str, ...) -> i32;");
+    eval(&mut repl, "let greeting = \"hello\";");
+    let (value, stdout) = eval(
+        &mut repl,
+        "let i = 0;\nwhile i < 3 {\n    printf(\"%s %d\\n\", greeting, i);\n    i = i + 1;\n}",
+    );
+    assert_eq!(value, None);
+    assert_eq!(stdout, "hello 0\nhello 1\nhello 2\n");
+    assert_eq!(eval(&mut repl, "i").0.as_deref(), Some("3: i32"));
+}
+
+#[test]
+fn test_rejected_input_is_forgotten() {
+    let mut repl = Repl::new();
+    let err = errors(&mut repl, "fn broken() -> i32 { return true; }");
+    assert!(err[0].contains("expected type 'i32'"), "{err:?}");
+    eval(&mut repl, "fn broken() -> i32 { return 1; }");
+    assert_eq!(eval(&mut repl, "broken()").0.as_deref(), Some("1: i32"));
+
+    let err = errors(&mut repl, "undeclared + 1");
+    assert!(err[0].contains("undeclared identifier"), "{err:?}");
+}
+
+#[test]
+fn test_memory() {
+    let mut repl = Repl::new();
+    eval(&mut repl, "struct Point { x: i32, y: i32 }");
+    eval(&mut repl, "let p: Point;");
+    eval(&mut repl, "p.x = 3;");
+    eval(&mut repl, "p.y = p.x + 1;");
+    assert_eq!(eval(&mut repl, "p.x * p.y").0.as_deref(), Some("12: i32"));
+
+    eval(&mut repl, "let arr = [1, 2, 3];");
+    eval(&mut repl, "arr[1] = arr[2] * 5;");
+    assert_eq!(eval(&mut repl, "arr[1]").0.as_deref(), Some("15: i32"));
+
+    eval(&mut repl, "let q = 4;");
+    eval(&mut repl, "let r = &q;");
+    eval(&mut repl, "q = 9;");
+    assert_eq!(eval(&mut repl, "*r").0.as_deref(), Some("9: i32"));
+}
+
+#[test]
+fn test_runtime_error() {
+    let mut repl = Repl::new();
+    let err = repl.eval("1 / 0", &mut Vec::new()).unwrap_err();
+    assert!(matches!(err, RunError::Runtime(..)));
+    assert!(err.errors()[0].to_string().contains("division by zero"));
+    assert_eq!(eval(&mut repl, "1 / 1").0.as_deref(), Some("1: i32"));
+}
+
+#[test]
+fn test_meta_commands() {
+    let mut repl = Repl::new();
+    eval(&mut repl, "extern fn printf(fmt: &str, ...) -> i32;");
+    assert_eq!(repl.type_of("printf(\"%d\", 1) > 0").unwrap().0, "bool");
+    assert!(repl.type_of("let x = 1;").is_err());
+    assert_eq!(
+        repl.ast("let y = 1 + 2;").unwrap(),
+        "[Program]\n└─[VarDeclStmt]\n  └─[OperatorExpr]\n    ├─[LitExpr]\n    └─[LitExpr]\n"
+    );
+    // Neither command declares or runs anything.
+    let err = errors(&mut repl, "y");
+    assert!(err[0].contains("undeclared identifier 'y'"), "{err:?}");
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use lexion_lang::generators::tac::instructions::{BaseInstruction, Instruction, Operand};
+use lexion_lang::generators::tac::ssa::SsaGraph;
+use lexion_lang::operators;
+use std::collections::HashSet;
+
+const FIXTURES: [&str; 10] = [
+    "variables.lex",
+    "functions.lex",
+    "control_flow.lex",
+    "fibonacci.lex",
+    "globals.lex",
+    "loops.lex",
+    "short_circuit.lex",
+    "structs.lex",
+    "memory.lex",
+    "arrays.lex",
+];
+
+fn is_variable(operand: &Operand) -> bool {
+    matches!(operand, Operand::Variable(_) | Operand::Temporary(_))
+}
+
+fn phis(ssa: &SsaGraph, label: &str) -> Vec<String> {
+    let block = ssa
+        .cfg
+        .node_weights()
+        .find(|block| block.label == label)
+        .unwrap_or_else(|| panic!("no block '{label}'"));
+    block
+        .instructions
+        .iter()
+        .filter_map(|inst| match &inst.instruction {
+            Instruction::Phi(phi) => Some(ssa.variable(phi.target.value().unwrap()).to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_values_are_assigned_once() {
+    for fixture in FIXTURES {
+        let mut ssa = common::ssa(fixture).unwrap();
+        let mut assigned = HashSet::new();
+        for block in ssa.cfg.node_weights_mut() {
+            for inst in block.instructions.iter_mut() {
+                for operand in inst.instruction.operands_read_mut() {
+                    assert!(!is_variable(operand), "{fixture}: {operand} is not renamed");
+                }
+                for operand in inst.instruction.operands_written_mut() {
+                    assert!(!is_variable(operand), "{fixture}: {operand} is not renamed");
+                    if let Some(value) = operand.value() {
+                        assert!(
+                            assigned.insert(value),
+                            "{fixture}: {value} is assigned twice"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_loop_header_phis() {
+    let ssa = common::ssa("fibonacci.lex").unwrap();
+    // `t` is assigned before it is read in each iteration, so it needs no phi.
+    assert_eq!(phis(&ssa, "$lstart_1"), ["a", "p"]);
+    assert!(phis(&ssa, "$lbody_1").is_empty());
+}
+
+#[test]
+fn test_phi_per_predecessor() {
+    let ssa = common::ssa("loops.lex").unwrap();
+    assert_eq!(phis(&ssa, "$lstart_1"), ["n", "steps"]);
+    for node in ssa.cfg.node_indices() {
+        let predecessors = ssa.cfg.predecessors(node);
+        for inst in &ssa.cfg[node].instructions {
+            if let Instruction::Phi(phi) = &inst.instruction {
+                let mut sources = phi
+                    .sources
+                    .iter()
+                    .map(|(block, _)| *block)
+                    .collect::<Vec<_>>();
+                sources.sort();
+                assert_eq!(sources, predecessors, "{}", inst.instruction);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_if_expression_phi() {
+    let ssa = common::ssa("loops.lex").unwrap();
+    let phis = phis(&ssa, "$cend_4");
+    assert_eq!(phis.len(), 1);
+    assert!(phis[0].starts_with("$t"), "{phis:?}");
+}
+
+#[test]
+fn test_conditions_branch_on_comparisons() {
+    let ssa = common::ssa("loops.lex").unwrap();
+    let block = ssa
+        .cfg
+        .node_weights()
+        .find(|block| block.label == "$lstart_1")
+        .unwrap();
+    // `while n != 1` leaves the loop when `n == 1`, without a bool temporary in between.
+    let instructions = block
+        .instructions
+        .iter()
+        .filter(|inst| !matches!(inst.instruction, Instruction::Phi(_)))
+        .collect::<Vec<_>>();
+    assert_eq!(instructions.len(), 1);
+    let Instruction::ConditionalJump(jump) = &instructions[0].instruction else {
+        panic!("{:?}", instructions[0]);
+    };
+    assert_eq!(jump.operator, operators::EQUALS);
+    assert_eq!(jump.right.to_string(), "1");
+}
+
+#[test]
+fn test_short_circuit_blocks() {
+    let ssa = common::ssa("short_circuit.lex").unwrap();
+    // The second operand of each `&&` and `||` is evaluated in a block of its own.
+    for block in ssa.cfg.node_weights() {
+        let calls = block
+            .instructions
+            .iter()
+            .filter(|inst| matches!(&inst.instruction, Instruction::FunctionCall(call) if call.function == "check"))
+            .count();
+        assert!(calls <= 1, "{block:?}");
+    }
+    let main = ssa
+        .cfg
+        .node_weights()
+        .find(|block| block.label == "main")
+        .unwrap();
+    assert!(matches!(
+        main.instructions.last().map(|inst| &inst.instruction),
+        Some(Instruction::ConditionalJump(_))
+    ));
+}
+
+#[test]
+fn test_member_access_is_loaded() {
+    let ssa = common::ssa("structs.lex").unwrap();
+    let block = ssa
+        .cfg
+        .node_weights()
+        .find(|block| block.label == "get_x")
+        .unwrap();
+    // `p` holds the address of the struct, and `x` is its first member.
+    let loads = block
+        .instructions
+        .iter()
+        .filter_map(|inst| match &inst.instruction {
+            Instruction::Load(load) => Some((load.offset, load.size)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(loads, [(0, 4)]);
+}
+
+#[test]
+fn test_memory_instructions() {
+    let ssa = common::ssa("memory.lex").unwrap();
+    let function = |label: &str| {
+        ssa.cfg
+            .node_weights()
+            .find(|block| block.label == label)
+            .unwrap()
+            .instructions
+            .iter()
+            .map(|inst| &inst.instruction)
+            .collect::<Vec<_>>()
+    };
+    // `*target = value` writes through the reference.
+    assert!(function("set")
+        .iter()
+        .any(|inst| matches!(inst, Instruction::Store(store) if store.size == 4)));
+    // `line.to.y` of `&Line` is 12 bytes into the struct.
+    assert!(function("length")
+        .iter()
+        .any(|inst| matches!(inst, Instruction::Load(load) if load.offset == 12)));
+    let main = function("main");
+    // Two points, a line and `n`, whose address is taken.
+    let allocas = main
+        .iter()
+        .filter_map(|inst| match inst {
+            Instruction::Alloca(alloca) => Some(alloca.size),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(allocas[..4], [8, 8, 20, 4]);
+    // `let b = a;` and `line.from = a;` copy the struct instead of its address.
+    assert!(main
+        .iter()
+        .any(|inst| matches!(inst, Instruction::MemoryCopy(copy) if copy.size == 8)));
+}
+
+#[test]
+fn test_bounds_checks() {
+    let ssa = common::ssa("arrays.lex").unwrap();
+    let function = |label: &str| {
+        ssa.cfg
+            .node_weights()
+            .skip_while(|block| block.label != label)
+            .take_while(|block| block.label == label || block.label.starts_with('$'))
+            .flat_map(|block| block.instructions.iter())
+            .map(|inst| &inst.instruction)
+            .collect::<Vec<_>>()
+    };
+    // `values[i]` of a slice checks against the length loaded 8 bytes into the reference.
+    let sum = function("sum");
+    assert!(sum
+        .iter()
+        .any(|inst| matches!(inst, Instruction::Load(load) if load.offset == 8 && load.size == 4)));
+    assert!(sum.iter().any(|inst| matches!(
+        inst,
+        Instruction::BoundsCheck(check)
+            if !matches!(check.index, Operand::Literal(_))
+                && !matches!(check.length, Operand::Literal(_))
+    )));
+    // `primes[4]` of an array checks against its length, and `grid[1][2]` checks twice.
+    let main = function("main");
+    let checks = main
+        .iter()
+        .filter_map(|inst| match inst {
+            Instruction::BoundsCheck(check) => Some(check.to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert!(checks.contains(&String::from("check 4 < 5")));
+    assert_eq!(checks[3..5], ["check 1 < 2", "check 2 < 3"]);
+    // `&primes` stores the address of the first element and the length.
+    assert!(main.iter().any(|inst| matches!(
+        inst,
+        Instruction::Store(store)
+            if store.offset == 8 && store.size == 8 && store.value.to_string() == "5"
+    )));
+}
@@ -0,0 +1,281 @@
+use crate::fq::*;
+use crate::serialize::RuleData;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+use std::collections::HashSet;
+
+/// Symbol of an alternative that has a node in the derivation, stored in a field of the
+/// alternative's struct.
+struct Child {
+    symbol: String,
+    field: Ident,
+}
+
+/// Rule of the grammar, turned into a struct and a variant of its non-terminal's enum.
+struct Alternative {
+    rule_index: usize,
+    name: Ident,
+    variant: Ident,
+    children: Vec<Child>,
+}
+
+/// Name of `symbol` without quotes, if it can be part of a Rust identifier.
+fn symbol_name(symbol: &str) -> Option<&str> {
+    let name = symbol.trim_matches('\'');
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {}
+        _ => return None,
+    }
+    chars
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        .then_some(name)
+}
+
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().unwrap().to_ascii_uppercase().to_string() + chars.as_str()
+        })
+        .collect()
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && !snake.is_empty() && !snake.ends_with('_') {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+/// Identifier for `name`, as a raw identifier if it is a keyword.
+fn ident(name: &str) -> Ident {
+    syn::parse_str(name).unwrap_or_else(|_| Ident::new_raw(name, Span::call_site()))
+}
+
+/// Alternatives of `non_terminal`, named so they don't clash with the types in `names`.
+fn alternatives(
+    rules: &[RuleData],
+    non_terminal: &str,
+    names: &mut HashSet<String>,
+) -> Vec<Alternative> {
+    let mut variants = HashSet::new();
+    let alternatives = rules
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.left == non_terminal)
+        .collect::<Vec<_>>();
+    alternatives
+        .iter()
+        .enumerate()
+        .map(|(k, (i, rule))| {
+            let right = rule
+                .right
+                .iter()
+                .filter(|s| s.as_str() != "ε")
+                .collect::<Vec<_>>();
+            let mut variant = if right.is_empty() {
+                String::from("Empty")
+            } else {
+                right
+                    .iter()
+                    .filter_map(|s| symbol_name(s))
+                    .map(camel_case)
+                    .collect::<String>()
+            };
+            if variant.is_empty() || variants.contains(&variant) {
+                variant = format!("Alt{}", k + 1);
+            }
+            variants.insert(variant.clone());
+
+            let mut name = if alternatives.len() == 1 {
+                String::from(non_terminal)
+            } else {
+                format!("{non_terminal}{variant}")
+            };
+            if alternatives.len() > 1 && !names.insert(name.clone()) {
+                name = format!("{non_terminal}Alt{}", k + 1);
+                names.insert(name.clone());
+            }
+
+            let children = right
+                .iter()
+                .enumerate()
+                .map(|(j, symbol)| {
+                    let terminal = symbol.starts_with('\'');
+                    let mut field = match symbol_name(symbol) {
+                        Some(name) if terminal => format!("{name}_token"),
+                        Some(name) => snake_case(name),
+                        None => format!("token_{}", j + 1),
+                    };
+                    if right.iter().filter(|s| s == &symbol).count() > 1 {
+                        field = format!("{field}_{}", j + 1);
+                    }
+                    Child {
+                        symbol: symbol.to_string(),
+                        field: ident(&field),
+                    }
+                })
+                .collect();
+            Alternative {
+                rule_index: i + 1,
+                name: ident(&name),
+                variant: ident(&variant),
+                children,
+            }
+        })
+        .collect()
+}
+
+fn alternative_impl(non_terminal: &str, alternative: &Alternative) -> TokenStream {
+    let (name, index) = (&alternative.name, alternative.rule_index);
+    let mut fields = quote! {};
+    let mut inits = quote! {};
+    let mut accessors = quote! {};
+    for (i, child) in alternative.children.iter().enumerate() {
+        let field = &child.field;
+        if child.symbol.starts_with('\'') {
+            fields.extend(quote! { #field: #FQTokenInstance, });
+            inits.extend(quote! { #field: derivation[children[#i]].token.clone(), });
+            accessors.extend(quote! {
+                pub fn #field(&self) -> &#FQTokenInstance {
+                    &self.#field
+                }
+            });
+        } else {
+            let ty = ident(&child.symbol);
+            fields.extend(quote! { #field: #FQBox<#ty>, });
+            inits.extend(quote! {
+                #field: #FQBox::new(#ty::from_derivation(derivation, children[#i])?),
+            });
+            accessors.extend(quote! {
+                pub fn #field(&self) -> &#ty {
+                    &self.#field
+                }
+            });
+        }
+    }
+    quote! {
+        #[derive(Debug, Clone)]
+        pub struct #name {
+            span: #FQSourceSpan,
+            #fields
+        }
+
+        impl #name {
+            pub fn from_derivation(
+                derivation: &#FQDerivation,
+                node: #FQNodeIndex,
+            ) -> #FQResult<Self, #FQProductionError> {
+                if derivation[node].rule_index != #index {
+                    return Err(#FQProductionError::new(derivation, node, #non_terminal));
+                }
+                let children = derivation.children(node);
+                Ok(Self {
+                    span: derivation.span(node),
+                    #inits
+                })
+            }
+
+            pub fn span(&self) -> #FQSourceSpan {
+                self.span
+            }
+
+            #accessors
+        }
+    }
+}
+
+fn enum_impl(non_terminal: &str, alternatives: &[Alternative]) -> TokenStream {
+    let symbol = non_terminal;
+    let non_terminal = ident(non_terminal);
+    let mut variants = quote! {};
+    let mut cases = quote! {};
+    let mut spans = quote! {};
+    for alternative in alternatives {
+        let (name, variant, index) = (
+            &alternative.name,
+            &alternative.variant,
+            alternative.rule_index,
+        );
+        variants.extend(quote! { #variant(#name), });
+        cases.extend(quote! {
+            #index => Self::#variant(#name::from_derivation(derivation, node)?),
+        });
+        spans.extend(quote! { Self::#variant(node) => node.span(), });
+    }
+    quote! {
+        #[derive(Debug, Clone)]
+        pub enum #non_terminal {
+            #variants
+        }
+
+        impl #non_terminal {
+            pub fn from_derivation(
+                derivation: &#FQDerivation,
+                node: #FQNodeIndex,
+            ) -> #FQResult<Self, #FQProductionError> {
+                Ok(match derivation[node].rule_index {
+                    #cases
+                    _ => {
+                        return Err(#FQProductionError::new(derivation, node, #symbol));
+                    }
+                })
+            }
+
+            pub fn span(&self) -> #FQSourceSpan {
+                match self {
+                    #spans
+                }
+            }
+        }
+    }
+}
+
+/// Module of typed syntax nodes for a grammar without reductions: a struct per rule, with an
+/// accessor per symbol, and an enum per non-terminal with several rules.
+pub(crate) fn ast_impl(rules: &[RuleData]) -> TokenStream {
+    if let Some(rule) = rules.iter().find(|r| r.reduction.is_some()) {
+        panic!(
+            "Unexpected '{}' rule reduction, grammars generating an AST have no reductions",
+            rule.left
+        );
+    }
+    let mut non_terminals: Vec<&str> = vec![];
+    for rule in rules.iter().filter(|r| !r.left.starts_with('\'')) {
+        if !non_terminals.contains(&rule.left.as_str()) {
+            non_terminals.push(&rule.left);
+        }
+    }
+    let mut names: HashSet<String> = non_terminals.iter().map(|nt| nt.to_string()).collect();
+    let mut nodes = quote! {};
+    for non_terminal in non_terminals.iter() {
+        let alternatives = alternatives(rules, non_terminal, &mut names);
+        for alternative in alternatives.iter() {
+            nodes.extend(alternative_impl(non_terminal, alternative));
+        }
+        if alternatives.len() > 1 {
+            nodes.extend(enum_impl(non_terminal, &alternatives));
+        }
+    }
+    let start = ident(&rules[0].left);
+    quote! {
+        #[allow(dead_code)]
+        pub mod ast {
+            #nodes
+
+            impl TryFrom<&#FQDerivation> for #start {
+                type Error = #FQProductionError;
+
+                fn try_from(derivation: &#FQDerivation) -> #FQResult<Self, Self::Error> {
+                    Self::from_derivation(derivation, derivation.root)
+                }
+            }
+        }
+    }
+}
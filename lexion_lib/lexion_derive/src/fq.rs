@@ -4,10 +4,9 @@ use quote::{quote, ToTokens};
 
 pub(crate) struct FQOption;
 pub(crate) struct FQResult;
+pub(crate) struct FQBox;
 
 pub(crate) struct FQNodeIndex;
-pub(crate) struct FQEdgeRef;
-pub(crate) struct FQGraph;
 
 pub(crate) struct FQTableBuilder;
 pub(crate) struct FQParseTableOverride;
@@ -17,15 +16,17 @@ pub(crate) struct FQParseTableActionInstance {
 }
 
 pub(crate) struct FQDerivation;
-pub(crate) struct FQDerivationNode;
 pub(crate) struct FQGrammar;
 pub(crate) struct FQGrammarRule;
 pub(crate) struct FQGrammarParserLR;
 pub(crate) struct FQGrammarParserSLR1;
 pub(crate) struct FQParseError;
+pub(crate) struct FQProductionError;
 pub(crate) struct FQParser;
 pub(crate) struct FQTokenizer;
 pub(crate) struct FQTokenType;
+pub(crate) struct FQTokenInstance;
+pub(crate) struct FQSourceSpan;
 
 pub(crate) struct FQLazyStatic;
 
@@ -41,21 +42,15 @@ impl ToTokens for FQResult {
     }
 }
 
-impl ToTokens for FQNodeIndex {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        quote!(lexion_lib::petgraph::graph::NodeIndex).to_tokens(tokens)
-    }
-}
-
-impl ToTokens for FQEdgeRef {
+impl ToTokens for FQBox {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        quote!(lexion_lib::petgraph::visit::EdgeRef).to_tokens(tokens)
+        quote!(std::boxed::Box).to_tokens(tokens)
     }
 }
 
-impl ToTokens for FQGraph {
+impl ToTokens for FQNodeIndex {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        quote!(lexion_lib::petgraph::graph::Graph).to_tokens(tokens)
+        quote!(lexion_lib::petgraph::graph::NodeIndex).to_tokens(tokens)
     }
 }
 
@@ -116,12 +111,6 @@ impl ToTokens for FQDerivation {
     }
 }
 
-impl ToTokens for FQDerivationNode {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        quote!(lexion_lib::grammar::DerivationNode).to_tokens(tokens)
-    }
-}
-
 impl ToTokens for FQGrammar {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         quote!(lexion_lib::grammar::Grammar).to_tokens(tokens)
@@ -152,6 +141,12 @@ impl ToTokens for FQParseError {
     }
 }
 
+impl ToTokens for FQProductionError {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        quote!(lexion_lib::error::ProductionError).to_tokens(tokens)
+    }
+}
+
 impl ToTokens for FQParser {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         quote!(lexion_lib::Parser).to_tokens(tokens)
@@ -170,6 +165,18 @@ impl ToTokens for FQTokenType {
     }
 }
 
+impl ToTokens for FQTokenInstance {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        quote!(lexion_lib::tokenizer::TokenInstance).to_tokens(tokens)
+    }
+}
+
+impl ToTokens for FQSourceSpan {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        quote!(lexion_lib::miette::SourceSpan).to_tokens(tokens)
+    }
+}
+
 impl ToTokens for FQLazyStatic {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         quote!(lazy_static::lazy_static!).to_tokens(tokens)
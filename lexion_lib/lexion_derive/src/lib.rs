@@ -11,6 +11,7 @@ use std::collections::HashSet;
 use std::fs::File;
 use syn::{DeriveInput, LitInt, LitStr, Type};
 
+mod ast;
 mod fq;
 mod serialize;
 
@@ -19,6 +20,9 @@ mod serialize;
 struct ParserOptions {
     ident: Ident,
     path: String,
+    /// Generate typed syntax nodes in an `ast` module instead of running reductions.
+    #[darling(default)]
+    ast: bool,
 }
 
 #[proc_macro_derive(Parser, attributes(grammar))]
@@ -31,13 +35,39 @@ pub fn derive_parser(input: TokenStream) -> TokenStream {
     let file = File::open(opts.path).expect("Failed to open grammar file");
     let json: GrammarData = serde_json::from_reader(file).unwrap();
 
-    let (start_symbol, parse_result) = symbol_result_impl(&json.rules);
-    let grammar = grammar_impl(&json);
-    let methods = methods_impl(&json.rules);
+    let mut grammar = grammar_impl(&json);
+    let (parse_result, methods) = if opts.ast {
+        grammar.extend(ast::ast_impl(&json.rules));
+        let start_symbol = Ident::new(json.rules[0].left.as_str(), Span::call_site());
+        let parse_result = quote! { ast::#start_symbol };
+        let methods = quote! {
+            pub fn transform(
+                &mut self,
+                derivation: &#FQDerivation,
+            ) -> #FQResult<#parse_result, #FQProductionError> {
+                #parse_result::try_from(derivation)
+            }
+        };
+        (parse_result, methods)
+    } else {
+        let (start_symbol, parse_result) = symbol_result_impl(&json.rules);
+        let methods = methods_impl(&json.rules);
+        let methods = quote! {
+            #methods
+
+            pub fn transform(&mut self, derivation: &#FQDerivation) -> #parse_result {
+                self.#start_symbol(derivation, derivation.root)
+            }
+        };
+        (parse_result, methods)
+    };
+    let result = match opts.ast {
+        true => quote! { self.transform(&derivation)? },
+        false => quote! { self.transform(&derivation) },
+    };
 
     quote! {
         use #FQGrammarParserLR;
-        use #FQEdgeRef;
 
         #grammar
 
@@ -46,10 +76,6 @@ pub fn derive_parser(input: TokenStream) -> TokenStream {
             pub const PARSER: &'static PARSER = &PARSER;
 
             #methods
-
-            pub fn transform(&mut self, derivation: &#FQDerivation) -> #parse_result {
-                self.#start_symbol(&derivation.graph, derivation.root)
-            }
         }
 
         impl #FQParser for #struct_name {
@@ -66,7 +92,7 @@ pub fn derive_parser(input: TokenStream) -> TokenStream {
             ) -> #FQResult<Self::Result, #FQParseError> {
                 let derivation =
                     PARSER.parse_trace(&GRAMMAR, tokenizer, trace)?;
-                Ok(self.transform(&derivation))
+                Ok(#result)
             }
 
         }
@@ -213,7 +239,7 @@ fn methods_impl(rules: &[RuleData]) -> proc_macro2::TokenStream {
                     let ident2 = Ident::new(n.as_str(), Span::call_site());
                     quote! {
                         #acc
-                        let mut #ident1 = self.#ident(graph, *#ident2);
+                        let mut #ident1 = self.#ident(derivation, *#ident2);
                     }
                 });
             let t_args = args2
@@ -224,7 +250,7 @@ fn methods_impl(rules: &[RuleData]) -> proc_macro2::TokenStream {
                     let ident2 = Ident::new(n.as_str(), Span::call_site());
                     quote! {
                         #acc
-                        let mut #ident1 = graph.node_weight(*#ident2).unwrap().token.clone();
+                        let mut #ident1 = derivation[*#ident2].token.clone();
                     }
                 });
             nr_rule_cases.extend(quote! {
@@ -247,11 +273,9 @@ fn methods_impl(rules: &[RuleData]) -> proc_macro2::TokenStream {
         };
         let nt_ident = Ident::new(nt.as_str(), Span::call_site());
         methods.extend(quote! {
-            fn #nt_ident(&mut self, graph: &#FQGraph<#FQDerivationNode, usize>, node_id: #FQNodeIndex) #return_type {
-                let node = graph.node_weight(node_id).unwrap();
-                let mut children = graph.edges(node_id).collect::<Vec<_>>();
-                children.sort_by_key(|e| *e.weight());
-                let children: Vec<#FQNodeIndex> = children.into_iter().map(|e| e.target()).collect();
+            fn #nt_ident(&mut self, derivation: &#FQDerivation, node_id: #FQNodeIndex) #return_type {
+                let node = &derivation[node_id];
+                let children = derivation.children(node_id);
                 #nr_rule_cases
             }
         });
@@ -3,8 +3,12 @@ use std::io::Error;
 use std::sync::Arc;
 
 use miette::{Diagnostic, NamedSource, SourceSpan};
+use petgraph::graph::NodeIndex;
 use thiserror::Error;
 
+use crate::grammar::Derivation;
+use crate::tokenizer::tokens::EPSILON;
+
 #[derive(Debug, Clone, Error, Diagnostic)]
 #[error("{message}")]
 #[diagnostic()]
@@ -16,10 +20,47 @@ pub struct SyntaxError {
     pub message: String,
 }
 
+/// Derivation node of a production that has no typed node where it was found.
+#[derive(Debug, Clone, Error)]
+#[error("unexpected production {rule_index} `{production}` for {non_terminal}")]
+pub struct ProductionError {
+    pub non_terminal: String,
+    pub rule_index: usize,
+    pub production: String,
+    pub span: SourceSpan,
+}
+
+impl ProductionError {
+    /// Error for `node` of `derivation`, found where a `non_terminal` was expected.
+    pub fn new(derivation: &Derivation, node: NodeIndex, non_terminal: &str) -> Self {
+        let children = derivation.children(node);
+        let right = match children.is_empty() {
+            true => String::from(EPSILON),
+            false => children
+                .iter()
+                .map(|child| derivation[*child].token.token.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        Self {
+            non_terminal: String::from(non_terminal),
+            rule_index: derivation[node].rule_index,
+            production: format!("{} -> {right}", derivation[node].token.token),
+            span: derivation.span(node),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum ParseError {
     Syntax(SyntaxError),
     Io(Error),
+    Production(ProductionError),
+    /// Semantic actions are bound to unit rules that the parse table no longer reduces, see
+    /// [`eliminate_unit_rules`](crate::parsers::ParseTableLR::eliminate_unit_rules).
+    EliminatedUnitRules,
+    /// The LL(1) parse table has more than one rule for a non-terminal and lookahead.
+    NotLL1,
 }
 
 impl From<SyntaxError> for ParseError {
@@ -28,6 +69,12 @@ impl From<SyntaxError> for ParseError {
     }
 }
 
+impl From<ProductionError> for ParseError {
+    fn from(value: ProductionError) -> Self {
+        Self::Production(value)
+    }
+}
+
 impl From<Error> for ParseError {
     fn from(value: Error) -> Self {
         Self::Io(value)
@@ -39,6 +86,28 @@ impl Display for ParseError {
         match self {
             ParseError::Syntax(e) => e.fmt(f),
             ParseError::Io(e) => e.fmt(f),
+            ParseError::Production(e) => e.fmt(f),
+            ParseError::EliminatedUnitRules => write!(
+                f,
+                "the parse table has no nodes for the unit rules with semantic actions"
+            ),
+            ParseError::NotLL1 => {
+                write!(f, "the grammar is not LL(1), its parse table has conflicts")
+            }
         }
     }
 }
+
+#[derive(Debug, Error)]
+pub enum ArtifactError {
+    #[error(transparent)]
+    Io(#[from] Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("unrecognized artifact format '{0}'")]
+    Format(String),
+    #[error("unsupported artifact version {found}, expected {expected}")]
+    Version { found: u64, expected: u32 },
+    #[error("invalid parse table override '{0}'")]
+    Override(String),
+}
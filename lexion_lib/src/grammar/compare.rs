@@ -0,0 +1,252 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use crate::grammar::{GeneratorOptions, Grammar, SentenceGenerator};
+use crate::parsers::{GrammarParserEarley, ParseTableAction, ParserAlgorithm};
+use crate::tokenizer::Tokenizer;
+
+/// Limits of the sentences checked by a [`GrammarComparison`].
+#[derive(Debug, Clone)]
+pub struct CompareOptions {
+    /// Levels above the shortest derivation tree of a grammar up to which its sentences are
+    /// enumerated exhaustively, and twice as many for random sentences.
+    pub extra_depth: usize,
+    /// Maximum number of sentences enumerated exhaustively from each grammar.
+    pub max_sentences: usize,
+    /// Number of random sentences generated from each grammar, on top of the enumerated ones.
+    pub random_sentences: usize,
+    pub seed: u64,
+    /// Algorithm of the parse tables whose conflicts are compared.
+    pub algorithm: ParserAlgorithm,
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            extra_depth: 4,
+            max_sentences: 500,
+            random_sentences: 200,
+            seed: 0,
+            algorithm: ParserAlgorithm::LALR1,
+        }
+    }
+}
+
+/// Elements of two sets found in only one of them, sorted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Difference {
+    pub only_left: Vec<String>,
+    pub only_right: Vec<String>,
+}
+
+impl Difference {
+    pub fn between<'a>(
+        left: impl IntoIterator<Item = &'a String>,
+        right: impl IntoIterator<Item = &'a String>,
+    ) -> Self {
+        let left = left.into_iter().collect::<BTreeSet<_>>();
+        let right = right.into_iter().collect::<BTreeSet<_>>();
+        Self {
+            only_left: left.difference(&right).map(|s| s.to_string()).collect(),
+            only_right: right.difference(&left).map(|s| s.to_string()).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.only_left.is_empty() && self.only_right.is_empty()
+    }
+}
+
+/// Differences between two versions of a grammar: the sentences accepted by only one of them,
+/// and the changes to their sets and parse table conflicts.
+#[derive(Debug, Clone, Default)]
+pub struct GrammarDiff {
+    /// Number of distinct sentences parsed with both grammars.
+    pub checked: usize,
+    pub sentences: Difference,
+    pub non_terminals: Difference,
+    /// FIRST sets that changed, for the non-terminals of both grammars.
+    pub first_sets: BTreeMap<String, Difference>,
+    /// FOLLOW sets that changed, for the non-terminals of both grammars.
+    pub follow_sets: BTreeMap<String, Difference>,
+    pub conflicts: Difference,
+}
+
+impl GrammarDiff {
+    /// No sentence was found in the language of only one of the grammars.
+    pub fn is_equivalent(&self) -> bool {
+        self.sentences.is_empty()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sentences.is_empty()
+            && self.non_terminals.is_empty()
+            && self.first_sets.is_empty()
+            && self.follow_sets.is_empty()
+            && self.conflicts.is_empty()
+    }
+}
+
+impl Display for GrammarDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let section = |f: &mut Formatter<'_>, title: &str, diff: &Difference| {
+            if diff.is_empty() {
+                return Ok(());
+            }
+            writeln!(f, "{title}:")?;
+            for s in diff.only_left.iter() {
+                writeln!(f, "  - {s}")?;
+            }
+            for s in diff.only_right.iter() {
+                writeln!(f, "  + {s}")?;
+            }
+            Ok(())
+        };
+        section(f, "sentences", &self.sentences)?;
+        section(f, "non-terminals", &self.non_terminals)?;
+        for (symbol, diff) in self.first_sets.iter() {
+            section(f, &format!("FIRST({symbol})"), diff)?;
+        }
+        for (symbol, diff) in self.follow_sets.iter() {
+            section(f, &format!("FOLLOW({symbol})"), diff)?;
+        }
+        section(f, "conflicts", &self.conflicts)?;
+        writeln!(
+            f,
+            "{} sentence(s) checked, {}",
+            self.checked,
+            if self.is_equivalent() {
+                "no difference in the languages"
+            } else {
+                "the languages differ"
+            }
+        )
+    }
+}
+
+/// Compares two versions of a grammar, parsing the sentences generated from each one with the
+/// other, so a refactoring can be checked to keep the language the same.
+pub struct GrammarComparison<'a> {
+    left: &'a Grammar,
+    right: &'a Grammar,
+    options: CompareOptions,
+}
+
+impl<'a> GrammarComparison<'a> {
+    pub fn new(left: &'a Grammar, right: &'a Grammar, options: CompareOptions) -> Self {
+        Self {
+            left,
+            right,
+            options,
+        }
+    }
+
+    pub fn compare(&self) -> GrammarDiff {
+        let left_sentences = self.sentences(self.left);
+        let right_sentences = self.sentences(self.right);
+        let checked = left_sentences.union(&right_sentences).count();
+        let left_parser = GrammarParserEarley::from_grammar(self.left);
+        let right_parser = GrammarParserEarley::from_grammar(self.right);
+        let sentences = Difference::between(
+            left_sentences
+                .iter()
+                .filter(|s| !Self::accepts(&right_parser, self.right, s)),
+            right_sentences
+                .iter()
+                .filter(|s| !Self::accepts(&left_parser, self.left, s)),
+        );
+
+        let left_non_terminals = self.left.get_non_terminals();
+        let right_non_terminals = self.right.get_non_terminals();
+        let common = left_non_terminals
+            .intersection(right_non_terminals)
+            .collect::<Vec<_>>();
+        let set_diffs = |set_of: fn(&'a Grammar, &str) -> Option<&'a HashSet<String>>| {
+            common
+                .iter()
+                .map(|symbol| {
+                    let diff = Difference::between(
+                        set_of(self.left, symbol).into_iter().flatten(),
+                        set_of(self.right, symbol).into_iter().flatten(),
+                    );
+                    (symbol.to_string(), diff)
+                })
+                .filter(|(_, diff)| !diff.is_empty())
+                .collect::<BTreeMap<_, _>>()
+        };
+
+        GrammarDiff {
+            checked,
+            sentences,
+            non_terminals: Difference::between(left_non_terminals, right_non_terminals),
+            first_sets: set_diffs(Grammar::first_of),
+            follow_sets: set_diffs(Grammar::follow_of),
+            conflicts: Difference::between(
+                self.conflicts(self.left).iter(),
+                self.conflicts(self.right).iter(),
+            ),
+        }
+    }
+
+    /// Texts of the sentences generated at random and enumerated exhaustively.
+    fn sentences(&self, grammar: &Grammar) -> BTreeSet<String> {
+        let extra_depth = self.options.extra_depth;
+        let Some(min_depth) =
+            SentenceGenerator::new(grammar, GeneratorOptions::default()).min_depth()
+        else {
+            return BTreeSet::new();
+        };
+        let mut generator = SentenceGenerator::new(
+            grammar,
+            GeneratorOptions {
+                max_depth: min_depth + 2 * extra_depth,
+                seed: self.options.seed,
+                ..Default::default()
+            },
+        );
+        // Random sentences come first, as they aim for the rules the least used so far and
+        // the enumerated ones would steer them towards the deepest rules
+        let mut sentences = (0..self.options.random_sentences)
+            .filter_map(|_| generator.generate())
+            .map(|s| s.text)
+            .collect::<BTreeSet<_>>();
+        sentences.extend(
+            generator
+                .exhaustive(min_depth + extra_depth, self.options.max_sentences)
+                .into_iter()
+                .map(|s| s.text),
+        );
+        sentences
+    }
+
+    fn accepts(parser: &GrammarParserEarley, grammar: &Grammar, sentence: &str) -> bool {
+        let tokenizer =
+            Tokenizer::from_string(Arc::new(sentence.to_string()), grammar.get_token_types());
+        parser.parse(grammar, tokenizer).is_ok()
+    }
+
+    /// Conflicts of the parse table, described without the states so they can be matched
+    /// between grammars with different automata.
+    fn conflicts(&self, grammar: &Grammar) -> BTreeSet<String> {
+        let describe = |action: &ParseTableAction| match action {
+            ParseTableAction::Shift(_) => String::from("shift"),
+            ParseTableAction::Reduce(rule) => format!("reduce {}", grammar.get_rule(*rule)),
+            action => action.to_string(),
+        };
+        self.options
+            .algorithm
+            .build_table(grammar)
+            .entries()
+            .filter_map(|(_, symbol, action)| match action {
+                ParseTableAction::Conflict(actions) => Some(format!(
+                    "on {symbol}: {}",
+                    actions.iter().map(describe).sorted().join(" / ")
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+}
@@ -1,15 +1,22 @@
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter, Result};
+use std::ops::Index;
 
+use miette::SourceSpan;
 use petgraph::graph::NodeIndex;
-use petgraph::Graph;
+use petgraph::visit::EdgeRef;
+use petgraph::{Direction, Graph};
 
 use crate::grammar::{Grammar, GrammarRule};
-use crate::tokenizer::TokenInstance;
+use crate::tokenizer::{SpanBuilder, TokenInstance};
 
 #[derive(Debug)]
 pub struct DerivationNode {
     pub token: TokenInstance,
     pub rule_index: usize,
+    /// LR state on top of the stack when this node was pushed, used to decide
+    /// whether the subtree can be reused by an incremental reparse.
+    pub state: usize,
 }
 
 pub struct Derivation {
@@ -50,8 +57,9 @@ impl Derivation {
             indent += "  ";
         }
         writeln!(f, "{}", graph.node_weight(node_id).ok_or(std::fmt::Error)?)?;
-        let num_children = graph.neighbors(node_id).count();
-        for (i, child) in graph.neighbors(node_id).enumerate() {
+        let children = Self::children_of(graph, node_id);
+        let num_children = children.len();
+        for (i, child) in children.into_iter().enumerate() {
             Derivation::write(
                 graph,
                 child,
@@ -64,12 +72,281 @@ impl Derivation {
     }
 }
 
+impl Derivation {
+    /// Children of `node` in the graph of a derivation, in the order of the rule's right-hand
+    /// side.
+    pub fn children_of(graph: &Graph<DerivationNode, usize>, node: NodeIndex) -> Vec<NodeIndex> {
+        let mut edges = graph.edges(node).collect::<Vec<_>>();
+        edges.sort_by_key(|e| *e.weight());
+        edges.into_iter().map(|e| e.target()).collect()
+    }
+
+    pub fn children(&self, node: NodeIndex) -> Vec<NodeIndex> {
+        Self::children_of(&self.graph, node)
+    }
+
+    pub fn parent(&self, node: NodeIndex) -> Option<NodeIndex> {
+        self.graph
+            .neighbors_directed(node, Direction::Incoming)
+            .next()
+    }
+
+    pub fn is_token(&self, node: NodeIndex) -> bool {
+        Grammar::is_terminal(&self[node].token.token)
+    }
+
+    /// Rule reduced to produce `node`, or `None` if it is a token.
+    pub fn rule<'a>(&self, grammar: &'a Grammar, node: NodeIndex) -> Option<&'a GrammarRule> {
+        if self.is_token(node) {
+            None
+        } else {
+            Some(grammar.get_rule(self[node].rule_index))
+        }
+    }
+
+    /// Tokens under `node`, in source order.
+    pub fn tokens(&self, node: NodeIndex) -> impl Iterator<Item = &TokenInstance> + '_ {
+        self.pre_order(node)
+            .filter(|n| self.is_token(*n))
+            .map(|n| &self[n].token)
+    }
+
+    /// Span from the first to the last token under `node`, which is empty at the position of
+    /// `node` if it derives `ε`.
+    pub fn span(&self, node: NodeIndex) -> SourceSpan {
+        let mut tokens = self.tokens(node);
+        match tokens.next() {
+            Some(first) => SpanBuilder::merge(first.span, tokens.last().unwrap_or(first).span),
+            None => SpanBuilder::start(self[node].token.span),
+        }
+    }
+
+    /// Text of `source` covered by `node`.
+    pub fn text<'a>(&self, source: &'a str, node: NodeIndex) -> &'a str {
+        let span = self.span(node);
+        &source[span.offset()..span.offset() + span.len()]
+    }
+
+    pub fn pre_order(&self, node: NodeIndex) -> PreOrder<'_> {
+        PreOrder {
+            derivation: self,
+            stack: vec![node],
+        }
+    }
+
+    pub fn post_order(&self, node: NodeIndex) -> PostOrder<'_> {
+        PostOrder {
+            derivation: self,
+            stack: vec![(node, false)],
+        }
+    }
+
+    /// Walks the derivation depth-first from the root, calling `visitor` on every node.
+    pub fn walk<V: DerivationVisitor + ?Sized>(&self, grammar: &Grammar, visitor: &mut V) {
+        let mut stack = vec![(self.root, false)];
+        while let Some((node, visited)) = stack.pop() {
+            let Some(rule) = self.rule(grammar, node) else {
+                visitor.token(self, node, &self[node].token);
+                continue;
+            };
+            if visited {
+                visitor.exit(self, node, rule);
+            } else if visitor.enter(self, node, rule) {
+                stack.push((node, true));
+                stack.extend(self.children(node).into_iter().rev().map(|c| (c, false)));
+            }
+        }
+    }
+}
+
+impl Index<NodeIndex> for Derivation {
+    type Output = DerivationNode;
+
+    fn index(&self, index: NodeIndex) -> &Self::Output {
+        &self.graph[index]
+    }
+}
+
+/// Pre-order iterator over a subtree of a [`Derivation`], with children in order.
+pub struct PreOrder<'a> {
+    derivation: &'a Derivation,
+    stack: Vec<NodeIndex>,
+}
+
+impl Iterator for PreOrder<'_> {
+    type Item = NodeIndex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.stack.pop()?;
+        self.stack
+            .extend(self.derivation.children(node).into_iter().rev());
+        Some(node)
+    }
+}
+
+/// Post-order iterator over a subtree of a [`Derivation`], with children in order.
+pub struct PostOrder<'a> {
+    derivation: &'a Derivation,
+    stack: Vec<(NodeIndex, bool)>,
+}
+
+impl Iterator for PostOrder<'_> {
+    type Item = NodeIndex;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, expanded) = self.stack.pop()?;
+            if expanded {
+                return Some(node);
+            }
+            self.stack.push((node, true));
+            self.stack.extend(
+                self.derivation
+                    .children(node)
+                    .into_iter()
+                    .rev()
+                    .map(|c| (c, false)),
+            );
+        }
+    }
+}
+
+/// Callbacks of [`Derivation::walk`]. Non-terminal nodes are passed with the rule that produced
+/// them, so implementations dispatch on `rule.left` to handle each non-terminal, or register
+/// callbacks by non-terminal with a [`DerivationDispatch`].
+pub trait DerivationVisitor {
+    /// Called before the children of a non-terminal node, which are skipped if it returns
+    /// `false`, along with the matching call to [`exit`](DerivationVisitor::exit).
+    fn enter(&mut self, _derivation: &Derivation, _node: NodeIndex, _rule: &GrammarRule) -> bool {
+        true
+    }
+
+    /// Called after the children of a non-terminal node.
+    fn exit(&mut self, _derivation: &Derivation, _node: NodeIndex, _rule: &GrammarRule) {}
+
+    fn token(&mut self, _derivation: &Derivation, _node: NodeIndex, _token: &TokenInstance) {}
+}
+
+type EnterCallback<S> = Box<dyn Fn(&mut S, &Derivation, NodeIndex) -> bool>;
+type ExitCallback<S> = Box<dyn Fn(&mut S, &Derivation, NodeIndex)>;
+type TokenCallback<S> = Box<dyn Fn(&mut S, &Derivation, &TokenInstance)>;
+
+/// Callbacks of a walk registered by non-terminal for its nodes, and by terminal for its
+/// tokens, which share a state of type `S`. Nodes without a callback are walked through.
+pub struct DerivationDispatch<S> {
+    enter: HashMap<String, EnterCallback<S>>,
+    exit: HashMap<String, ExitCallback<S>>,
+    token: HashMap<String, TokenCallback<S>>,
+}
+
+impl<S> Default for DerivationDispatch<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> DerivationDispatch<S> {
+    pub fn new() -> Self {
+        Self {
+            enter: HashMap::new(),
+            exit: HashMap::new(),
+            token: HashMap::new(),
+        }
+    }
+
+    /// Calls `callback` before the children of the nodes of `non_terminal`, which are skipped
+    /// if it returns `false`.
+    pub fn on_enter(
+        mut self,
+        non_terminal: &str,
+        callback: impl Fn(&mut S, &Derivation, NodeIndex) -> bool + 'static,
+    ) -> Self {
+        self.enter
+            .insert(non_terminal.to_string(), Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` after the children of the nodes of `non_terminal`.
+    pub fn on_exit(
+        mut self,
+        non_terminal: &str,
+        callback: impl Fn(&mut S, &Derivation, NodeIndex) + 'static,
+    ) -> Self {
+        self.exit
+            .insert(non_terminal.to_string(), Box::new(callback));
+        self
+    }
+
+    /// Calls `callback` on the tokens of `terminal`.
+    pub fn on_token(
+        mut self,
+        terminal: &str,
+        callback: impl Fn(&mut S, &Derivation, &TokenInstance) + 'static,
+    ) -> Self {
+        self.token.insert(terminal.to_string(), Box::new(callback));
+        self
+    }
+
+    /// Walks `derivation` like [`Derivation::walk`], calling the callbacks with `state`.
+    pub fn walk(&self, grammar: &Grammar, derivation: &Derivation, state: &mut S) {
+        derivation.walk(
+            grammar,
+            &mut Dispatcher {
+                dispatch: self,
+                state,
+            },
+        );
+    }
+}
+
+struct Dispatcher<'a, S> {
+    dispatch: &'a DerivationDispatch<S>,
+    state: &'a mut S,
+}
+
+impl<S> DerivationVisitor for Dispatcher<'_, S> {
+    fn enter(&mut self, derivation: &Derivation, node: NodeIndex, rule: &GrammarRule) -> bool {
+        match self.dispatch.enter.get(&rule.left) {
+            Some(callback) => callback(self.state, derivation, node),
+            None => true,
+        }
+    }
+
+    fn exit(&mut self, derivation: &Derivation, node: NodeIndex, rule: &GrammarRule) {
+        if let Some(callback) = self.dispatch.exit.get(&rule.left) {
+            callback(self.state, derivation, node);
+        }
+    }
+
+    fn token(&mut self, derivation: &Derivation, _node: NodeIndex, token: &TokenInstance) {
+        if let Some(callback) = self.dispatch.token.get(&token.token) {
+            callback(self.state, derivation, token);
+        }
+    }
+}
+
 impl Display for Derivation {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         Self::write(&self.graph, self.root, f, String::from(""), 2)
     }
 }
 
+/// Partial derivation, made of the subtrees rooted at `roots`, such as the symbols on the
+/// stack of a parser in the middle of a parse.
+pub struct DerivationForest<'a> {
+    pub graph: &'a Graph<DerivationNode, usize>,
+    pub roots: &'a [NodeIndex],
+}
+
+impl Display for DerivationForest<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for root in self.roots {
+            Derivation::write(self.graph, *root, f, String::from(""), 2)?;
+        }
+        Ok(())
+    }
+}
+
 impl Default for DerivationNode {
     fn default() -> Self {
         Self::new()
@@ -81,6 +358,7 @@ impl DerivationNode {
         Self {
             token: Default::default(),
             rule_index: 0,
+            state: 0,
         }
     }
 
@@ -88,11 +366,21 @@ impl DerivationNode {
         Self {
             token,
             rule_index: 0,
+            state: 0,
         }
     }
 
     pub fn from(token: TokenInstance, rule_index: usize) -> Self {
-        Self { token, rule_index }
+        Self {
+            token,
+            rule_index,
+            state: 0,
+        }
+    }
+
+    pub fn with_state(mut self, state: usize) -> Self {
+        self.state = state;
+        self
     }
 
     pub fn get_rule<'a>(&'a self, grammar: &'a Grammar) -> &'a GrammarRule {
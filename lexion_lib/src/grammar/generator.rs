@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use regex_syntax::hir::{Class, Hir, HirKind};
+
+use crate::grammar::Grammar;
+use crate::tokenizer::tokens::{EOF, EPSILON};
+use crate::tokenizer::{TokenInstance, Tokenizer};
+
+/// Limits of the sentences produced by a [`SentenceGenerator`].
+#[derive(Debug, Clone)]
+pub struct GeneratorOptions {
+    /// Maximum height of the derivation tree of a sentence.
+    pub max_depth: usize,
+    /// Maximum number of tokens in a sentence.
+    pub max_tokens: usize,
+    /// Number of extra repetitions allowed for unbounded regex repetitions such as `*` and `+`.
+    pub max_repeat: u32,
+    /// Prefer the rules used the least so far, to cover the whole grammar in fewer sentences.
+    pub target_coverage: bool,
+    pub seed: u64,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 16,
+            max_tokens: 64,
+            max_repeat: 4,
+            target_coverage: true,
+            seed: 0,
+        }
+    }
+}
+
+/// Sentence of a grammar, with the rules of its leftmost derivation.
+#[derive(Debug, Clone)]
+pub struct Sentence {
+    pub text: String,
+    pub tokens: Vec<TokenInstance>,
+    pub rules: Vec<usize>,
+}
+
+impl Display for Sentence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Produces random or exhaustive sentences of a grammar, sampling the text of each token from
+/// the regex of its token type.
+pub struct SentenceGenerator<'a> {
+    grammar: &'a Grammar,
+    options: GeneratorOptions,
+    rng: StdRng,
+    /// Height of the shortest derivation tree of each non-terminal, missing if it derives no
+    /// sentence.
+    min_depth: HashMap<&'a str, usize>,
+    /// Length in tokens of the shortest sentence derived from each non-terminal.
+    min_tokens: HashMap<&'a str, usize>,
+    token_regexes: HashMap<&'a str, Hir>,
+    coverage: Vec<usize>,
+}
+
+impl<'a> SentenceGenerator<'a> {
+    pub fn new(grammar: &'a Grammar, options: GeneratorOptions) -> Self {
+        let mut token_regexes = grammar
+            .get_token_types()
+            .iter()
+            .filter(|t| !t.name.is_empty())
+            .filter_map(|t| {
+                let hir = regex_syntax::parse(t.regex.as_str()).ok()?;
+                Some((t.name.as_str(), hir))
+            })
+            .collect::<HashMap<_, _>>();
+        // Keywords have no token type of their own and are sampled from their words
+        let keywords = grammar
+            .get_token_types()
+            .iter()
+            .flat_map(|t| t.keywords.iter())
+            .map(|(word, terminal)| (terminal.as_str(), regex::escape(word)))
+            .into_group_map();
+        for (terminal, words) in keywords {
+            if let Ok(hir) = regex_syntax::parse(&words.join("|")) {
+                token_regexes.insert(terminal, hir);
+            }
+        }
+        let mut generator = Self {
+            grammar,
+            rng: StdRng::seed_from_u64(options.seed),
+            options,
+            min_depth: HashMap::new(),
+            min_tokens: HashMap::new(),
+            token_regexes,
+            coverage: vec![0; grammar.get_rules().len()],
+        };
+        generator.build_minimums();
+        generator
+    }
+
+    fn build_minimums(&mut self) {
+        let rules = self.grammar.get_rules();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for rule in rules.iter().skip(1) {
+                let (Some(depth), Some(tokens)) =
+                    (self.rule_depth(&rule.right), self.rule_tokens(&rule.right))
+                else {
+                    continue;
+                };
+                let left = rule.left.as_str();
+                if self.min_depth.get(left).is_none_or(|d| depth < *d) {
+                    self.min_depth.insert(left, depth);
+                    changed = true;
+                }
+                if self.min_tokens.get(left).is_none_or(|t| tokens < *t) {
+                    self.min_tokens.insert(left, tokens);
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    /// Height of the shortest derivation tree of a rule with right-hand side `right`.
+    fn rule_depth(&self, right: &[String]) -> Option<usize> {
+        right
+            .iter()
+            .filter(|s| Grammar::is_non_terminal(s))
+            .map(|s| self.min_depth.get(s.as_str()).copied())
+            .try_fold(0, |depth, d| Some(depth.max(d?)))
+            .map(|depth| depth + 1)
+    }
+
+    fn rule_tokens(&self, right: &[String]) -> Option<usize> {
+        right
+            .iter()
+            .filter(|s| *s != EPSILON)
+            .map(|s| self.symbol_tokens(s))
+            .sum()
+    }
+
+    fn symbol_tokens(&self, symbol: &str) -> Option<usize> {
+        if Grammar::is_terminal(symbol) {
+            Some(1)
+        } else {
+            self.min_tokens.get(symbol).copied()
+        }
+    }
+
+    /// Height of the shortest derivation tree of a sentence, or `None` if the grammar has no
+    /// sentences.
+    pub fn min_depth(&self) -> Option<usize> {
+        self.min_depth
+            .get(self.grammar.get_start_symbol().as_str())
+            .copied()
+    }
+
+    /// Number of times each rule was used by the sentences generated so far.
+    pub fn coverage(&self) -> &[usize] {
+        &self.coverage
+    }
+
+    /// Rules not used by any sentence generated so far, without the augmented start rule.
+    pub fn uncovered_rules(&self) -> Vec<usize> {
+        (1..self.coverage.len())
+            .filter(|i| self.coverage[*i] == 0)
+            .collect()
+    }
+
+    /// Random sentence within the limits, or `None` if the grammar has none or a token could
+    /// not be sampled.
+    pub fn generate(&mut self) -> Option<Sentence> {
+        let start = self.grammar.get_start_symbol();
+        let mut reserved = self.symbol_tokens(&start)?;
+        let mut stack = vec![(start, self.options.max_depth)];
+        let mut terminals = vec![];
+        let mut rules = vec![];
+        // Uses are counted as rules are chosen, so a sentence doesn't keep picking the same
+        // uncovered rule, and only kept if the sentence is complete
+        let mut uses = self.coverage.clone();
+        while let Some((symbol, depth)) = stack.pop() {
+            if Grammar::is_terminal(&symbol) {
+                reserved -= 1;
+                terminals.push(symbol);
+                continue;
+            }
+            reserved -= self.symbol_tokens(&symbol)?;
+            let budget = self
+                .options
+                .max_tokens
+                .checked_sub(terminals.len() + reserved)?;
+            let rule_index = self.choose_rule(&symbol, depth, budget, &uses)?;
+            uses[rule_index] += 1;
+            let rule = self.grammar.get_rule(rule_index);
+            let right = rule.right.iter().filter(|s| *s != EPSILON);
+            reserved += self.rule_tokens(&rule.right)?;
+            stack.extend(right.rev().map(|s| (s.clone(), depth - 1)));
+            rules.push(rule_index);
+        }
+        let sentence = self.sentence(&terminals, rules)?;
+        self.coverage = uses;
+        Some(sentence)
+    }
+
+    /// Rule to expand `non_terminal` with, among the ones that fit in `depth` levels and
+    /// `budget` tokens.
+    fn choose_rule(
+        &mut self,
+        non_terminal: &str,
+        depth: usize,
+        budget: usize,
+        uses: &[usize],
+    ) -> Option<usize> {
+        let candidates = self
+            .grammar
+            .get_rules()
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|(_, r)| r.left == non_terminal)
+            .filter(|(_, r)| {
+                self.rule_depth(&r.right).is_some_and(|d| d <= depth)
+                    && self.rule_tokens(&r.right).is_some_and(|t| t <= budget)
+            })
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        let least_used = candidates.iter().map(|i| uses[*i]).min()?;
+        candidates
+            .into_iter()
+            .filter(|i| !self.options.target_coverage || uses[*i] == least_used)
+            .collect::<Vec<_>>()
+            .choose(&mut self.rng)
+            .copied()
+    }
+
+    /// Random sentences until every rule is used, or after `max_sentences` attempts.
+    pub fn cover(&mut self, max_sentences: usize) -> Vec<Sentence> {
+        let mut sentences = vec![];
+        for _ in 0..max_sentences {
+            if self.uncovered_rules().is_empty() {
+                break;
+            }
+            sentences.extend(self.generate());
+        }
+        sentences
+    }
+
+    /// Every sentence with a derivation tree of at most `depth` levels, up to `max_sentences`
+    /// of them, with one sampled text per token.
+    pub fn exhaustive(&mut self, depth: usize, max_sentences: usize) -> Vec<Sentence> {
+        let start = self.grammar.get_start_symbol();
+        let mut memo = HashMap::new();
+        let derivations = self.derivations(&start, depth, max_sentences, &mut memo);
+        let sentences = derivations
+            .iter()
+            .filter_map(|(terminals, rules)| self.sentence(terminals, rules.clone()))
+            .collect::<Vec<_>>();
+        for rule in sentences.iter().flat_map(|s| s.rules.iter()) {
+            self.coverage[*rule] += 1;
+        }
+        sentences
+    }
+
+    /// Terminals and rules of the leftmost derivations of `symbol` with at most `depth` levels.
+    #[allow(clippy::type_complexity)]
+    fn derivations(
+        &self,
+        symbol: &str,
+        depth: usize,
+        max_sentences: usize,
+        memo: &mut HashMap<(String, usize), Rc<Vec<(Vec<String>, Vec<usize>)>>>,
+    ) -> Rc<Vec<(Vec<String>, Vec<usize>)>> {
+        if Grammar::is_terminal(symbol) {
+            return Rc::new(vec![(vec![symbol.to_string()], vec![])]);
+        }
+        if depth == 0 {
+            return Rc::new(vec![]);
+        }
+        if let Some(derivations) = memo.get(&(symbol.to_string(), depth)) {
+            return derivations.clone();
+        }
+        let mut derivations = vec![];
+        for (i, rule) in self.grammar.get_rules().iter().enumerate().skip(1) {
+            if rule.left != symbol {
+                continue;
+            }
+            let mut partial = vec![(vec![], vec![i])];
+            for child in rule.right.iter().filter(|s| *s != EPSILON) {
+                let children = self.derivations(child, depth - 1, max_sentences, memo);
+                partial = partial
+                    .iter()
+                    .flat_map(|(terminals, rules): &(Vec<String>, Vec<usize>)| {
+                        children
+                            .iter()
+                            .filter(|(t, _)| terminals.len() + t.len() <= self.options.max_tokens)
+                            .map(move |(t, r)| {
+                                let mut terminals = terminals.clone();
+                                terminals.extend(t.iter().cloned());
+                                let mut rules = rules.clone();
+                                rules.extend(r.iter().copied());
+                                (terminals, rules)
+                            })
+                    })
+                    .take(max_sentences)
+                    .collect();
+            }
+            derivations.extend(partial);
+            if derivations.len() >= max_sentences {
+                derivations.truncate(max_sentences);
+                break;
+            }
+        }
+        let derivations = Rc::new(derivations);
+        memo.insert((symbol.to_string(), depth), derivations.clone());
+        derivations
+    }
+
+    /// Sentence made of sampled texts for `terminals`, separated by spaces.
+    fn sentence(&mut self, terminals: &[String], rules: Vec<usize>) -> Option<Sentence> {
+        let mut text = String::new();
+        let mut tokens = vec![];
+        for terminal in terminals.iter().filter(|t| *t != EOF) {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            let value = self.sample_token(terminal)?;
+            tokens.push(TokenInstance::from(
+                terminal,
+                &value,
+                (text.len(), value.len()).into(),
+            ));
+            text.push_str(&value);
+        }
+        Some(Sentence {
+            text,
+            tokens,
+            rules,
+        })
+    }
+
+    /// Text matched by the regex of `terminal` that the tokenizer reads back as `terminal`,
+    /// and not as a longer or earlier token type such as a keyword.
+    fn sample_token(&mut self, terminal: &str) -> Option<String> {
+        const ATTEMPTS: usize = 32;
+        let hir = self.token_regexes.get(terminal)?;
+        for _ in 0..ATTEMPTS {
+            let mut value = String::new();
+            Self::sample(hir, &mut self.rng, self.options.max_repeat, &mut value);
+            let types = self.grammar.get_token_types();
+            let tokenizer = Tokenizer::from_string(Arc::new(value.clone()), types);
+            match tokenizer.match_next() {
+                Ok((s, i))
+                    if !value.is_empty() && s == value && types[i].name_of(&s) == terminal =>
+                {
+                    return Some(value);
+                }
+                _ => continue,
+            }
+        }
+        None
+    }
+
+    pub(crate) fn sample(hir: &Hir, rng: &mut StdRng, max_repeat: u32, value: &mut String) {
+        match hir.kind() {
+            HirKind::Empty | HirKind::Look(_) => {}
+            HirKind::Literal(literal) => value.push_str(&String::from_utf8_lossy(&literal.0)),
+            HirKind::Class(Class::Unicode(class)) => {
+                // Stay in printable ASCII when the class allows it, to keep sentences readable
+                let ascii = class
+                    .ranges()
+                    .iter()
+                    .filter_map(|r| {
+                        let (start, end) = (r.start().max(' '), r.end().min('~'));
+                        (start <= end).then_some((start, end))
+                    })
+                    .collect::<Vec<_>>();
+                let ranges = if ascii.is_empty() {
+                    class
+                        .ranges()
+                        .iter()
+                        .map(|r| (r.start(), r.end()))
+                        .collect()
+                } else {
+                    ascii
+                };
+                if let Some(c) = Self::sample_ranges(&ranges, rng, |c| c as u32) {
+                    value.push(char::from_u32(c).unwrap_or(' '));
+                }
+            }
+            HirKind::Class(Class::Bytes(class)) => {
+                let ranges = class
+                    .ranges()
+                    .iter()
+                    .filter(|r| r.start().is_ascii())
+                    .map(|r| (r.start(), r.end().min(0x7f)))
+                    .collect::<Vec<_>>();
+                if let Some(b) = Self::sample_ranges(&ranges, rng, |b| b as u32) {
+                    value.push(char::from_u32(b).unwrap_or(' '));
+                }
+            }
+            HirKind::Repetition(repetition) => {
+                let max = repetition
+                    .max
+                    .unwrap_or(repetition.min + max_repeat)
+                    .min(repetition.min + max_repeat);
+                for _ in 0..rng.gen_range(repetition.min..=max) {
+                    Self::sample(&repetition.sub, rng, max_repeat, value);
+                }
+            }
+            HirKind::Capture(capture) => Self::sample(&capture.sub, rng, max_repeat, value),
+            HirKind::Concat(hirs) => {
+                for hir in hirs {
+                    Self::sample(hir, rng, max_repeat, value);
+                }
+            }
+            HirKind::Alternation(hirs) => {
+                if let Some(hir) = hirs.choose(rng) {
+                    Self::sample(hir, rng, max_repeat, value);
+                }
+            }
+        }
+    }
+
+    /// Uniformly random value in the union of the inclusive `ranges`, as a code point.
+    fn sample_ranges<T: Copy>(
+        ranges: &[(T, T)],
+        rng: &mut StdRng,
+        code: impl Fn(T) -> u32,
+    ) -> Option<u32> {
+        let total: u32 = ranges.iter().map(|(s, e)| code(*e) - code(*s) + 1).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut index = rng.gen_range(0..total);
+        for (start, end) in ranges {
+            let size = code(*end) - code(*start) + 1;
+            if index < size {
+                return Some(code(*start) + index);
+            }
+            index -= size;
+        }
+        None
+    }
+}
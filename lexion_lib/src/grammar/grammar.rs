@@ -1,10 +1,10 @@
-use crate::grammar::serialize::GrammarData;
+use crate::grammar::serialize::{GrammarData, TokenTypeData};
 use crate::tokenizer::tokens::*;
 use crate::tokenizer::*;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::collections::{HashMap, HashSet};
 use std::fmt::{Display, Formatter, Result};
 use std::fs::File;
@@ -102,28 +102,93 @@ impl GrammarRule {
     }
 }
 
+#[derive(Serialize)]
+struct GrammarRef<'a> {
+    rules: Vec<&'a GrammarRule>,
+    token_types: Vec<TokenTypeData>,
+}
+
+#[derive(Deserialize)]
+struct GrammarOwned {
+    rules: Vec<GrammarRule>,
+    token_types: Vec<TokenTypeData>,
+}
+
+/// Serialized as the source rules, without the augmented start rule, along with the token types
+/// in matching order, so that a deserialized grammar tokenizes exactly like the original one.
+impl Serialize for Grammar {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        GrammarRef {
+            rules: self.rules[1..]
+                .iter()
+                .chain(self.terminal_rules.iter())
+                .collect(),
+            token_types: self
+                .token_types
+                .iter()
+                .map(|t| TokenTypeData {
+                    name: t.name.clone(),
+                    regex: t.regex.to_string(),
+                    keywords: t.keywords.clone(),
+                })
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Grammar {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let data = GrammarOwned::deserialize(deserializer)?;
+        let mut grammar = Grammar::from_rules(data.rules);
+        grammar.token_types = data
+            .token_types
+            .into_iter()
+            .map(|t| {
+                Ok(TokenType {
+                    regex: Regex::new(&t.regex).map_err(serde::de::Error::custom)?,
+                    name: t.name,
+                    keywords: t.keywords,
+                })
+            })
+            .collect::<std::result::Result<_, D::Error>>()?;
+        Ok(grammar)
+    }
+}
+
 impl Grammar {
     pub fn from_json_file(file: &str) -> serde_json::Result<Self> {
         let data: GrammarData = serde_json::from_reader(File::open(file).unwrap())?;
-        Ok(Grammar::from_rules(
+        Ok(Grammar::from_data(&data))
+    }
+
+    /// Builds a grammar from parsed `.grm` data, where an empty right-hand side stands for `ε`.
+    pub fn from_data(data: &GrammarData) -> Self {
+        Grammar::from_rules(
             data.rules
-                .into_iter()
+                .iter()
                 .map(|r| GrammarRule {
-                    left: r.left,
-                    right: r.right,
+                    left: r.left.clone(),
+                    right: r.right_or_epsilon(),
                 })
                 .collect(),
-        ))
+        )
     }
 
     pub fn from_rules(rules: Vec<GrammarRule>) -> Self {
+        Grammar::with_start_symbol(rules, "")
+    }
+
+    /// Builds a grammar starting from `start_symbol` rather than from the left side of the
+    /// first rule, with the same rule indices.
+    pub fn with_start_symbol(rules: Vec<GrammarRule>, start_symbol: &str) -> Self {
         let (terminal_rules, rules) = rules
             .into_iter()
             .partition(|r| Grammar::is_terminal(&r.left));
         let mut grammar = Grammar {
             rules,
             terminal_rules,
-            start_symbol: String::from(""),
+            start_symbol: String::from(start_symbol),
             symbols: HashSet::new(),
             terminals: HashSet::new(),
             non_terminals: HashSet::new(),
@@ -387,42 +452,69 @@ impl Grammar {
     }
 
     fn build_token_types(&mut self) {
-        self.token_types = vec![
-            TokenType {
-                name: String::from(""),
-                regex: WHITESPACE.clone(),
-            },
-            TokenType {
-                name: String::from(""),
-                regex: SINGLE_LINE_COMMENT.clone(),
-            },
-            TokenType {
-                name: String::from(""),
-                regex: MULTI_LINE_COMMENT.clone(),
-            },
-        ];
-        self.token_types.extend(
-            self.terminals
+        self.token_types = [&*WHITESPACE, &*SINGLE_LINE_COMMENT, &*MULTI_LINE_COMMENT]
+            .into_iter()
+            .map(|regex| TokenType::new("", regex.clone()))
+            .collect();
+
+        // Keywords are read through the token type of their host rather than their own, so
+        // that a longer match of the host such as `whilex` is never split
+        let mut keywords: HashMap<&str, Vec<(String, String)>> = HashMap::new();
+        for terminal in self.terminals.iter() {
+            if let Some((host, words)) = self.keyword_of(terminal) {
+                let entries = keywords.entry(host).or_default();
+                entries.extend(words.into_iter().map(|w| (w, terminal.clone())));
+            }
+        }
+        let token_types = self
+            .terminals
+            .iter()
+            .map(String::as_str)
+            .filter(|t| *t != EOF && !keywords.contains_key(t) && self.keyword_of(t).is_none())
+            .chain(keywords.keys().copied())
+            // Literals first, then terminal rules in declaration order, so that ties between
+            // equally long matches are resolved the same way on every build
+            .sorted_by_key(|t| {
+                let position = self.terminal_rules.iter().position(|r| r.left == *t);
+                (position.map_or(0, |p| p + 1), *t)
+            })
+            .map(|t| {
+                let rule = self.terminal_rules.iter().find(|r| r.left == t);
+                let regex = match rule {
+                    Some(r) => format!("^{}", r.right[0]),
+                    None => format!("^{}", regex::escape(&Grammar::stringify(t))),
+                };
+                let mut token_type = TokenType::new(t, Regex::new(&regex).unwrap());
+                token_type.keywords = keywords.get(t).into_iter().flatten().cloned().collect();
+                token_type
+            })
+            .collect::<Vec<_>>();
+        self.token_types.extend(token_types);
+    }
+
+    /// Host terminal and words of `terminal` if it is declared a keyword of another terminal
+    /// rule, as in `'while' -> 'ident' ;` or `'bool_literal' -> 'ident' 'true' 'false' ;`.
+    /// Without words, the keyword is the text of the terminal itself.
+    pub fn keyword_of(&self, terminal: &str) -> Option<(&str, Vec<String>)> {
+        let rule = self.terminal_rules.iter().find(|r| r.left == terminal)?;
+        let host = rule.right.first()?;
+        let host_rule = self.terminal_rules.iter().find(|r| r.left == *host)?;
+        if self
+            .terminal_rules
+            .iter()
+            .any(|r| r.left == host_rule.right[0])
+        {
+            return None;
+        }
+        let words = if rule.right.len() > 1 {
+            rule.right[1..]
                 .iter()
-                .filter(|t| **t != EOF)
-                .sorted_by_key(|t| {
-                    self.terminal_rules
-                        .iter()
-                        .position(|r| r.left == **t)
-                        .unwrap_or_default()
-                })
-                .map(|t| {
-                    let rule = self.terminal_rules.iter().find(|r| r.left == *t);
-                    let regex = match rule {
-                        Some(r) => format!("^{}", r.right[0]),
-                        None => format!("^{}", regex::escape(&Grammar::stringify(t))),
-                    };
-                    TokenType {
-                        name: String::from(t),
-                        regex: Regex::new(&regex).unwrap(),
-                    }
-                }),
-        );
+                .map(|w| Grammar::stringify(w))
+                .collect()
+        } else {
+            vec![Grammar::stringify(terminal)]
+        };
+        Some((host_rule.left.as_str(), words))
     }
 
     pub fn first_of(&self, symbol: &str) -> Option<&StringSet> {
@@ -441,6 +533,24 @@ impl Grammar {
         sequence.iter().all(|s| self.is_nullable(s))
     }
 
+    /// FIRST set of a sequence of symbols, without `ε`.
+    pub fn first_of_sequence(&self, sequence: &[String]) -> StringSet {
+        let mut first = HashSet::new();
+        for symbol in sequence.iter().filter(|s| *s != EPSILON) {
+            if let Some(set) = self.first_of(symbol) {
+                first.extend(set.iter().filter(|s| *s != EPSILON).cloned());
+            }
+            if !self.is_nullable(symbol) {
+                break;
+            }
+        }
+        first
+    }
+
+    pub fn get_non_terminals(&self) -> &StringSet {
+        &self.non_terminals
+    }
+
     pub fn get_rule(&self, rule_index: usize) -> &GrammarRule {
         &self.rules[rule_index]
     }
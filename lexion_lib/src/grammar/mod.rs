@@ -1,10 +1,16 @@
+mod compare;
 mod derivation;
+mod generator;
 #[allow(clippy::module_inception)]
 mod grammar;
 pub mod serialize;
+mod tokens;
 
+pub use compare::*;
 pub use derivation::*;
+pub use generator::*;
 pub use grammar::*;
+pub use tokens::*;
 
 #[cfg(test)]
 mod tests;
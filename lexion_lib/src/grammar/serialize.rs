@@ -1,5 +1,9 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
+use crate::tokenizer::tokens::EPSILON;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReductionData {
     pub ty: String,
@@ -13,6 +17,17 @@ pub struct RuleData {
     pub reduction: Option<ReductionData>,
 }
 
+impl RuleData {
+    /// Right-hand side of the rule, `ε` if it is empty as in `A -> ;`.
+    pub fn right_or_epsilon(&self) -> Vec<String> {
+        if self.right.is_empty() {
+            vec![String::from(EPSILON)]
+        } else {
+            self.right.clone()
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ParseTableOverrideData {
     pub symbol: String,
@@ -25,3 +40,11 @@ pub struct GrammarData {
     pub rules: Vec<RuleData>,
     pub overrides: Option<Vec<ParseTableOverrideData>>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenTypeData {
+    pub name: String,
+    pub regex: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub keywords: BTreeMap<String, String>,
+}
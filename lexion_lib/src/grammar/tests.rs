@@ -1,6 +1,12 @@
-use crate::grammar::{Grammar, GrammarRule};
+use crate::grammar::{
+    CompareOptions, GeneratorOptions, Grammar, GrammarComparison, GrammarRule, Sentence,
+    SentenceGenerator,
+};
+use crate::parsers::{GrammarParserLALR1, GrammarParserLR};
 use crate::tokenizer::tokens::*;
+use crate::tokenizer::Tokenizer;
 use std::collections::HashSet;
+use std::sync::Arc;
 
 /// E -> E '+' T
 /// E-> T
@@ -47,6 +53,106 @@ fn epsilon_grammar() -> Grammar {
     ])
 }
 
+/// Stmts -> Stmt Stmts | ε
+/// Stmt -> 'let' 'ident' '=' Expr ';' | Expr ';'
+/// Expr -> Expr 'add_op' Atom | Atom
+/// Atom -> 'ident' | 'num' | '(' Expr ')'
+fn statement_grammar() -> Grammar {
+    let rule = |left: &str, right: &[&str]| GrammarRule {
+        left: left.into(),
+        right: right.iter().map(|s| s.to_string()).collect(),
+    };
+    Grammar::from_rules(vec![
+        rule("Stmts", &["Stmt", "Stmts"]),
+        rule("Stmts", &[EPSILON]),
+        rule("Stmt", &["'let'", "'ident'", "'='", "Expr", "';'"]),
+        rule("Stmt", &["Expr", "';'"]),
+        rule("Expr", &["Expr", "'add_op'", "Atom"]),
+        rule("Expr", &["Atom"]),
+        rule("Atom", &["'ident'"]),
+        rule("Atom", &["'num'"]),
+        rule("Atom", &["'('", "Expr", "')'"]),
+        rule("'add_op'", &[r"[+\-]"]),
+        rule("'num'", &[r"[0-9]+"]),
+        rule("'ident'", &[r"[a-z_][a-z0-9_]*"]),
+    ])
+}
+
+/// [`statement_grammar`] with `Expr` rewritten without left recursion and, if `with_let` is
+/// false, without `let` statements.
+fn refactored_statement_grammar(with_let: bool) -> Grammar {
+    let rule = |left: &str, right: &[&str]| GrammarRule {
+        left: left.into(),
+        right: right.iter().map(|s| s.to_string()).collect(),
+    };
+    let mut rules = vec![
+        rule("Stmts", &["Stmt", "Stmts"]),
+        rule("Stmts", &[EPSILON]),
+        rule("Stmt", &["Expr", "';'"]),
+        rule("Expr", &["Atom", "ExprTail"]),
+        rule("ExprTail", &["'add_op'", "Atom", "ExprTail"]),
+        rule("ExprTail", &[EPSILON]),
+        rule("Atom", &["'ident'"]),
+        rule("Atom", &["'num'"]),
+        rule("Atom", &["'('", "Expr", "')'"]),
+        rule("'add_op'", &[r"[+\-]"]),
+        rule("'num'", &[r"[0-9]+"]),
+        rule("'ident'", &[r"[a-z_][a-z0-9_]*"]),
+    ];
+    if with_let {
+        rules.push(rule("Stmt", &["'let'", "'ident'", "'='", "Expr", "';'"]));
+    }
+    Grammar::from_rules(rules)
+}
+
+/// [`statement_grammar`] with boolean literals where, if `declared`, `let`, `true` and `false`
+/// are declared keywords of `'ident'` rather than terminals of their own.
+fn keyword_grammar(declared: bool) -> Grammar {
+    let rule = |left: &str, right: &[&str]| GrammarRule {
+        left: left.into(),
+        right: right.iter().map(|s| s.to_string()).collect(),
+    };
+    let mut rules = vec![
+        rule("Stmts", &["Stmt", "Stmts"]),
+        rule("Stmts", &[EPSILON]),
+        rule("Stmt", &["'let'", "'ident'", "'='", "Expr", "';'"]),
+        rule("Stmt", &["Expr", "';'"]),
+        rule("Expr", &["Expr", "'add_op'", "Atom"]),
+        rule("Expr", &["Atom"]),
+        rule("Atom", &["'ident'"]),
+        rule("Atom", &["'bool_literal'"]),
+        rule("'add_op'", &[r"[+\-]"]),
+        rule("'ident'", &[r"[a-z_][a-z0-9_]*"]),
+    ];
+    if declared {
+        rules.push(rule("'let'", &["'ident'"]));
+        rules.push(rule("'bool_literal'", &["'ident'", "'true'", "'false'"]));
+    } else {
+        rules.push(rule("'bool_literal'", &["true|false"]));
+    }
+    Grammar::from_rules(rules)
+}
+
+/// Parses every sentence with LALR(1), checking that the tokens read back match the sentence.
+fn assert_round_trip(grammar: &Grammar, sentences: &[Sentence]) {
+    let parser = GrammarParserLALR1::from_grammar(grammar);
+    for sentence in sentences {
+        let derivation = parser
+            .parse_from_string(grammar, Arc::new(sentence.text.clone()))
+            .unwrap_or_else(|err| panic!("`{sentence}` does not parse: {err}"));
+        let tokens = derivation
+            .tokens(derivation.root)
+            .map(|t| (&t.token, &t.value))
+            .collect::<Vec<_>>();
+        let expected = sentence
+            .tokens
+            .iter()
+            .map(|t| (&t.token, &t.value))
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, expected, "`{sentence}` was read back differently");
+    }
+}
+
 #[test]
 fn test_is_terminal() {
     assert!(Grammar::is_terminal("'+'"));
@@ -75,6 +181,28 @@ fn test_grammar_from_rules() {
     );
 }
 
+/// Among equally long matches, the first token type wins: literals come first, sorted by name,
+/// then terminal rules in declaration order.
+#[test]
+fn test_token_type_order() {
+    let rule = |left: &str, right: &str| GrammarRule {
+        left: left.into(),
+        right: right.split(' ').map(String::from).collect(),
+    };
+    let grammar = Grammar::from_rules(vec![
+        rule("E", "'(' 'ident' '+' 'num' ')'"),
+        rule("'num'", r"\d+"),
+        rule("'ident'", "[a-z]+"),
+    ]);
+    let names = grammar
+        .get_token_types()
+        .iter()
+        .map(|t| t.name.as_str())
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["'('", "')'", "'+'", "'num'", "'ident'"]);
+}
+
 #[test]
 fn test_first_set_simple() {
     let grammar = simple_grammar();
@@ -126,3 +254,190 @@ fn test_start_symbol() {
     let start = grammar.get_start_symbol();
     assert_eq!(start, "E");
 }
+
+#[test]
+fn test_generator_respects_limits() {
+    let grammar = statement_grammar();
+    let options = GeneratorOptions {
+        max_depth: 8,
+        max_tokens: 12,
+        ..Default::default()
+    };
+    let mut generator = SentenceGenerator::new(&grammar, options);
+    for _ in 0..100 {
+        let sentence = generator.generate().unwrap();
+        assert!(sentence.tokens.len() <= 12, "`{sentence}` is too long");
+        for token in sentence.tokens.iter() {
+            let span = token.span.offset()..token.span.offset() + token.span.len();
+            assert_eq!(&sentence.text[span], token.value);
+        }
+    }
+}
+
+#[test]
+fn test_generator_covers_rules() {
+    let grammar = statement_grammar();
+    let mut generator = SentenceGenerator::new(&grammar, GeneratorOptions::default());
+    let sentences = generator.cover(50);
+    assert!(generator.uncovered_rules().is_empty());
+    assert!(sentences.len() < 50);
+    assert_round_trip(&grammar, &sentences);
+}
+
+#[test]
+fn test_generator_exhaustive() {
+    let grammar = epsilon_grammar();
+    let mut generator = SentenceGenerator::new(&grammar, GeneratorOptions::default());
+    let sentences = generator
+        .exhaustive(3, 100)
+        .iter()
+        .map(|s| s.text.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(sentences, vec!["a b", "b"]);
+
+    let grammar = statement_grammar();
+    let mut generator = SentenceGenerator::new(&grammar, GeneratorOptions::default());
+    assert_eq!(generator.exhaustive(6, 20).len(), 20);
+}
+
+#[test]
+fn test_generated_sentences_round_trip() {
+    for grammar in [simple_grammar(), epsilon_grammar(), statement_grammar()] {
+        let mut generator = SentenceGenerator::new(
+            &grammar,
+            GeneratorOptions {
+                seed: 42,
+                target_coverage: false,
+                ..Default::default()
+            },
+        );
+        let mut sentences = (0..200)
+            .filter_map(|_| generator.generate())
+            .collect::<Vec<_>>();
+        sentences.extend(generator.exhaustive(5, 200));
+        assert!(!sentences.is_empty());
+        assert_round_trip(&grammar, &sentences);
+    }
+}
+
+#[test]
+fn test_compare_equivalent_grammars() {
+    let left = statement_grammar();
+    let right = refactored_statement_grammar(true);
+    let diff = GrammarComparison::new(&left, &right, CompareOptions::default()).compare();
+    assert!(diff.is_equivalent(), "{diff}");
+    assert!(diff.checked > 100);
+    assert_eq!(diff.non_terminals.only_right, vec!["ExprTail"]);
+    assert!(diff.non_terminals.only_left.is_empty());
+    assert_eq!(diff.follow_sets["Expr"].only_left, vec!["'add_op'"]);
+    assert!(diff.first_sets.is_empty());
+    assert!(diff.conflicts.is_empty());
+}
+
+#[test]
+fn test_compare_different_languages() {
+    let left = statement_grammar();
+    let right = refactored_statement_grammar(false);
+    let diff = GrammarComparison::new(&left, &right, CompareOptions::default()).compare();
+    assert!(!diff.is_equivalent());
+    assert!(diff.sentences.only_right.is_empty());
+    assert!(!diff.sentences.only_left.is_empty());
+    assert!(diff.sentences.only_left.iter().all(|s| s.contains("let")));
+    assert_eq!(diff.first_sets["Stmt"].only_left, vec!["'let'"]);
+}
+
+#[test]
+fn test_compare_conflicts() {
+    let left = simple_grammar();
+    let right = Grammar::from_rules(vec![
+        GrammarRule {
+            left: "E".into(),
+            right: vec!["E".into(), "'+'".into(), "E".into()],
+        },
+        GrammarRule {
+            left: "E".into(),
+            right: vec!["T".into()],
+        },
+        GrammarRule {
+            left: "T".into(),
+            right: vec!["'num'".into()],
+        },
+    ]);
+    let diff = GrammarComparison::new(&left, &right, CompareOptions::default()).compare();
+    assert!(diff.is_equivalent(), "{diff}");
+    assert!(diff.conflicts.only_left.is_empty());
+    assert_eq!(
+        diff.conflicts.only_right,
+        vec!["on '+': reduce E -> E '+' E / shift"]
+    );
+}
+
+#[test]
+fn test_keywords_tokenize() {
+    let grammar = keyword_grammar(true);
+    let types = grammar.get_token_types();
+    assert!(types
+        .iter()
+        .all(|t| t.name != "'let'" && t.name != "'bool_literal'"));
+    let ident = types.iter().find(|t| t.name == "'ident'").unwrap();
+    assert_eq!(
+        ident.keywords.iter().collect::<Vec<_>>(),
+        vec![
+            (&"false".into(), &"'bool_literal'".into()),
+            (&"let".into(), &"'let'".into()),
+            (&"true".into(), &"'bool_literal'".into()),
+        ]
+    );
+
+    let source = Arc::new(String::from("let letter = true + truest;"));
+    let serialized = serde_json::to_string(&grammar).unwrap();
+    let deserialized: Grammar = serde_json::from_str(&serialized).unwrap();
+    for grammar in [&grammar, &deserialized] {
+        let mut tokenizer = Tokenizer::from_string(source.clone(), grammar.get_token_types());
+        let mut tokens = vec![];
+        while tokenizer.has_next() {
+            let token = tokenizer.next_token().unwrap();
+            tokens.push((token.token, token.value));
+        }
+        let expected = [
+            ("'let'", "let"),
+            ("'ident'", "letter"),
+            ("'='", "="),
+            ("'bool_literal'", "true"),
+            ("'add_op'", "+"),
+            ("'ident'", "truest"),
+            ("';'", ";"),
+        ];
+        assert_eq!(
+            tokens,
+            expected.map(|(t, v)| (t.to_string(), v.to_string()))
+        );
+    }
+}
+
+#[test]
+fn test_keywords_generated_sentences() {
+    let grammar = keyword_grammar(true);
+    let mut generator = SentenceGenerator::new(&grammar, GeneratorOptions::default());
+    let sentences = generator.cover(50);
+    assert!(generator.uncovered_rules().is_empty());
+    assert_round_trip(&grammar, &sentences);
+}
+
+#[test]
+fn test_token_overlaps() {
+    let overlaps = keyword_grammar(false).token_overlaps();
+    let overlaps = overlaps
+        .iter()
+        .map(|o| (o.first.as_str(), o.second.as_str(), o.read_as.as_str()))
+        .collect::<Vec<_>>();
+    // `true` is read as an identifier, as `'ident'` is declared first
+    assert_eq!(
+        overlaps,
+        vec![
+            ("'let'", "'ident'", "'let'"),
+            ("'ident'", "'bool_literal'", "'ident'"),
+        ]
+    );
+    assert!(keyword_grammar(true).token_overlaps().is_empty());
+}
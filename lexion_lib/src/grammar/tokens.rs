@@ -0,0 +1,102 @@
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use regex::Regex;
+use regex_syntax::hir::literal::{ExtractKind, Extractor};
+
+use crate::grammar::{Grammar, SentenceGenerator};
+use crate::tokenizer::Tokenizer;
+
+/// Two token types whose regexes both match the whole of `example`, which the tokenizer reads
+/// as `read_as` only because of the order of the token types.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenOverlap {
+    pub first: String,
+    pub second: String,
+    pub example: String,
+    pub read_as: String,
+}
+
+impl Display for TokenOverlap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} and {} both match '{}', read as {}",
+            self.first, self.second, self.example, self.read_as
+        )
+    }
+}
+
+impl Grammar {
+    /// Overlaps between the regexes of the token types, other than the keywords declared for
+    /// a token type. Finite regexes are checked on each of their matches, the others on a
+    /// sample of them, so an overlap is reported at most once per pair with the first
+    /// example found.
+    pub fn token_overlaps(&self) -> Vec<TokenOverlap> {
+        const SAMPLES: usize = 64;
+        let types = self.get_token_types();
+        let mut rng = StdRng::seed_from_u64(0);
+        let examples = types
+            .iter()
+            .map(|t| {
+                if t.name.is_empty() {
+                    return vec![];
+                }
+                let Ok(hir) = regex_syntax::parse(t.regex.as_str()) else {
+                    return vec![];
+                };
+                let literals = Extractor::new().kind(ExtractKind::Prefix).extract(&hir);
+                match literals.literals() {
+                    Some(literals) if literals.iter().all(|l| l.is_exact()) => literals
+                        .iter()
+                        .map(|l| String::from_utf8_lossy(l.as_bytes()).to_string())
+                        .collect(),
+                    _ => (0..SAMPLES)
+                        .map(|_| {
+                            let mut value = String::new();
+                            SentenceGenerator::sample(&hir, &mut rng, 3, &mut value);
+                            value
+                        })
+                        .collect(),
+                }
+            })
+            .collect::<Vec<Vec<String>>>();
+
+        let matches_whole =
+            |regex: &Regex, value: &str| regex.find(value).is_some_and(|m| m.end() == value.len());
+        let mut overlaps = vec![];
+        for (i, first) in types.iter().enumerate() {
+            for (j, second) in types.iter().enumerate().skip(i + 1) {
+                if first.name.is_empty() || second.name.is_empty() {
+                    continue;
+                }
+                let example = examples[i]
+                    .iter()
+                    .filter(|e| matches_whole(&second.regex, e))
+                    .chain(
+                        examples[j]
+                            .iter()
+                            .filter(|e| matches_whole(&first.regex, e)),
+                    )
+                    .find(|e| !e.is_empty());
+                let Some(example) = example else {
+                    continue;
+                };
+                let tokenizer = Tokenizer::from_string(Arc::new(example.clone()), types);
+                let read_as = match tokenizer.match_next() {
+                    Ok((s, k)) => types[k].name_of(&s).to_string(),
+                    Err(_) => continue,
+                };
+                overlaps.push(TokenOverlap {
+                    first: first.name.clone(),
+                    second: second.name.clone(),
+                    example: example.clone(),
+                    read_as,
+                });
+            }
+        }
+        overlaps
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+
+use crate::grammar::{Derivation, Grammar};
+use crate::parsers::{ParseEvent, ParseEventKind};
+use crate::tokenizer::tokens::EPSILON;
+use crate::tokenizer::TokenInstance;
+
+pub type SemanticAction<V> = Box<dyn Fn(&[V]) -> V>;
+
+/// Callbacks turning a derivation into a value of type `V`, for grammars loaded at runtime that
+/// have no generated reductions. An LR parser runs them as it parses, through
+/// [`parse_with`](crate::parsers::GrammarParserLR::parse_with).
+///
+/// Tokens become values through the token callback, and each non-terminal node through the
+/// action registered for its rule or, failing that, for its non-terminal, called with the
+/// values of its children in order. Nodes without an action take the value of their first
+/// child, or `V::default()` if they have none.
+pub struct SemanticActions<V> {
+    token: Box<dyn Fn(&TokenInstance) -> V>,
+    rules: HashMap<usize, SemanticAction<V>>,
+    non_terminals: HashMap<String, SemanticAction<V>>,
+}
+
+impl<V: Default> SemanticActions<V> {
+    pub fn new(token: impl Fn(&TokenInstance) -> V + 'static) -> Self {
+        Self {
+            token: Box::new(token),
+            rules: HashMap::new(),
+            non_terminals: HashMap::new(),
+        }
+    }
+
+    /// Runs `action` on the reductions of the rule at `rule_index`.
+    pub fn on_rule(mut self, rule_index: usize, action: impl Fn(&[V]) -> V + 'static) -> Self {
+        self.rules.insert(rule_index, Box::new(action));
+        self
+    }
+
+    /// Runs `action` on the reductions of the rules of `non_terminal` without an action of
+    /// their own.
+    pub fn on_non_terminal(
+        mut self,
+        non_terminal: &str,
+        action: impl Fn(&[V]) -> V + 'static,
+    ) -> Self {
+        self.non_terminals
+            .insert(non_terminal.to_string(), Box::new(action));
+        self
+    }
+
+    /// Whether an action is registered on a unit rule `A -> B`, whose nodes are missing from
+    /// the derivations of a table with
+    /// [`eliminated unit rules`](crate::parsers::ParseTableLR::eliminate_unit_rules).
+    pub fn acts_on_unit_rules(&self, grammar: &Grammar) -> bool {
+        grammar.get_rules().iter().enumerate().any(|(index, rule)| {
+            rule.right.len() == 1
+                && Grammar::is_non_terminal(&rule.right[0])
+                && (self.rules.contains_key(&index) || self.non_terminals.contains_key(&rule.left))
+        })
+    }
+
+    /// Value of the root of `derivation`, computed bottom-up.
+    pub fn evaluate(&self, grammar: &Grammar, derivation: &Derivation) -> V {
+        let mut values: Vec<V> = vec![];
+        for node in derivation.post_order(derivation.root) {
+            if derivation.is_token(node) {
+                values.push((self.token)(&derivation[node].token));
+                continue;
+            }
+            let children = values.split_off(values.len() - derivation.children(node).len());
+            values.push(self.reduce(grammar, derivation[node].rule_index, children));
+        }
+        values.pop().unwrap_or_default()
+    }
+
+    /// Applies an event of an LR parse to the values of the nodes on its stack: a shifted token
+    /// pushes its value, and a reduction replaces the values of its children with its own.
+    pub fn apply(&self, grammar: &Grammar, event: &ParseEvent, values: &mut Vec<V>) {
+        match event.kind {
+            ParseEventKind::Shift(_) => values.push((self.token)(event.lookahead)),
+            ParseEventKind::Reduce(rule_index) => {
+                let rule = grammar.get_rule(*rule_index);
+                let num_children = if rule.right == [EPSILON] {
+                    0
+                } else {
+                    rule.right.len()
+                };
+                let children = values.split_off(values.len() - num_children);
+                values.push(self.reduce(grammar, *rule_index, children));
+            }
+            _ => {}
+        }
+    }
+
+    fn reduce(&self, grammar: &Grammar, rule_index: usize, children: Vec<V>) -> V {
+        let action = self
+            .rules
+            .get(&rule_index)
+            .or_else(|| self.non_terminals.get(&grammar.get_rule(rule_index).left));
+        match action {
+            Some(action) => action(&children),
+            None => children.into_iter().next().unwrap_or_default(),
+        }
+    }
+}
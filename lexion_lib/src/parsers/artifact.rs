@@ -0,0 +1,145 @@
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::ArtifactError;
+use crate::grammar::serialize::ParseTableOverrideData;
+use crate::grammar::Grammar;
+use crate::parsers::{
+    GrammarParserLALR1, GrammarParserLR, GrammarParserLR0, GrammarParserSLR1, ParseTableAction,
+    ParseTableLR,
+};
+
+pub const PARSE_TABLE_FORMAT: &str = "lexion-parse-table";
+pub const PARSE_TABLE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParserAlgorithm {
+    LR0,
+    SLR1,
+    LALR1,
+}
+
+impl ParserAlgorithm {
+    pub fn build_table(&self, grammar: &Grammar) -> ParseTableLR {
+        match self {
+            ParserAlgorithm::LR0 => GrammarParserLR0::from_grammar(grammar)
+                .get_parse_table()
+                .clone(),
+            ParserAlgorithm::SLR1 => GrammarParserSLR1::from_grammar(grammar).table,
+            ParserAlgorithm::LALR1 => GrammarParserLALR1::from_grammar(grammar)
+                .get_parse_table()
+                .clone(),
+        }
+    }
+}
+
+impl Display for ParserAlgorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ParserAlgorithm::LR0 => "lr0",
+                ParserAlgorithm::SLR1 => "slr1",
+                ParserAlgorithm::LALR1 => "lalr1",
+            }
+        )
+    }
+}
+
+impl FromStr for ParserAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "lr0" => Ok(ParserAlgorithm::LR0),
+            "slr1" => Ok(ParserAlgorithm::SLR1),
+            "lalr1" => Ok(ParserAlgorithm::LALR1),
+            _ => Err(format!("unknown parser algorithm '{s}'")),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ArtifactHeader {
+    format: String,
+    version: u64,
+}
+
+/// Self-describing parse table, holding the grammar rules and token types it was built from.
+///
+/// A loaded artifact is a ready [`GrammarParserLR`] for its `grammar`, no canonical collection
+/// is rebuilt.
+#[derive(Serialize, Deserialize)]
+pub struct ParseTableArtifact {
+    pub format: String,
+    pub version: u32,
+    pub algorithm: ParserAlgorithm,
+    pub grammar: Grammar,
+    pub table: ParseTableLR,
+}
+
+impl GrammarParserLR for ParseTableArtifact {
+    fn get_parse_table(&self) -> &ParseTableLR {
+        &self.table
+    }
+}
+
+impl ParseTableArtifact {
+    pub fn from_grammar(grammar: Grammar, algorithm: ParserAlgorithm) -> Self {
+        let table = algorithm.build_table(&grammar);
+        Self {
+            format: String::from(PARSE_TABLE_FORMAT),
+            version: PARSE_TABLE_VERSION,
+            algorithm,
+            grammar,
+            table,
+        }
+    }
+
+    pub fn apply_overrides(
+        &mut self,
+        overrides: &[ParseTableOverrideData],
+    ) -> Result<(), ArtifactError> {
+        for o in overrides {
+            let action = ParseTableAction::from_str(&o.action)
+                .map_err(|_| ArtifactError::Override(o.action.clone()))?;
+            self.table.insert_entry(o.state, &o.symbol, action);
+        }
+        self.table.compute_default_reductions();
+        Ok(())
+    }
+
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, ArtifactError> {
+        let value: serde_json::Value = serde_json::from_reader(reader)?;
+        let header = ArtifactHeader::deserialize(&value)?;
+        if header.format != PARSE_TABLE_FORMAT {
+            return Err(ArtifactError::Format(header.format));
+        }
+        if header.version != PARSE_TABLE_VERSION as u64 {
+            return Err(ArtifactError::Version {
+                found: header.version,
+                expected: PARSE_TABLE_VERSION,
+            });
+        }
+        Ok(Self::deserialize(value)?)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, ArtifactError> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    pub fn to_writer<W: Write>(&self, writer: W) -> Result<(), ArtifactError> {
+        Ok(serde_json::to_writer_pretty(writer, self)?)
+    }
+
+    pub fn to_file(&self, path: &str) -> Result<(), ArtifactError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.to_writer(&mut writer)?;
+        Ok(writer.flush()?)
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+
+use miette::SourceSpan;
+use petgraph::graph::NodeIndex;
+use petgraph::Graph;
+
+use crate::error::{ParseError, SyntaxError};
+use crate::grammar::{Derivation, DerivationNode, Grammar};
+use crate::tokenizer::tokens::{EOF, EPSILON};
+use crate::tokenizer::{SpanBuilder, TokenInstance, Tokenizer};
+
+/// Rule with a dot in its right-hand side, started at token `origin`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct EarleyItem {
+    rule: usize,
+    dot: usize,
+    origin: usize,
+}
+
+impl EarleyItem {
+    fn advance(self) -> Self {
+        Self {
+            dot: self.dot + 1,
+            ..self
+        }
+    }
+}
+
+#[derive(Default)]
+struct EarleySet {
+    items: Vec<EarleyItem>,
+    contains: HashSet<EarleyItem>,
+}
+
+impl EarleySet {
+    fn add(&mut self, item: EarleyItem) {
+        if self.contains.insert(item) {
+            self.items.push(item);
+        }
+    }
+}
+
+/// Non-terminal that derives the tokens of `span` in more than one way, with the rule at the
+/// root of each derivation.
+#[derive(Debug, Clone)]
+pub struct Ambiguity {
+    pub non_terminal: String,
+    pub span: SourceSpan,
+    pub rules: Vec<usize>,
+}
+
+/// Result of an Earley parse: one of the derivations of the input, and the places where other
+/// derivations exist.
+pub struct EarleyParse {
+    pub derivation: Derivation,
+    pub ambiguities: Vec<Ambiguity>,
+}
+
+impl EarleyParse {
+    pub fn is_ambiguous(&self) -> bool {
+        !self.ambiguities.is_empty()
+    }
+}
+
+/// Earley parser for any context-free grammar, including ambiguous, left-recursive and
+/// non-LR grammars, with the nullable completion of Aycock and Horspool for `ε` rules.
+pub struct GrammarParserEarley {
+    rules_by_left: HashMap<String, Vec<usize>>,
+}
+
+impl GrammarParserEarley {
+    pub fn from_grammar(grammar: &Grammar) -> Self {
+        let mut rules_by_left: HashMap<String, Vec<usize>> = HashMap::new();
+        for (i, rule) in grammar.get_rules().iter().enumerate() {
+            rules_by_left.entry(rule.left.clone()).or_default().push(i);
+        }
+        Self { rules_by_left }
+    }
+
+    pub fn parse(
+        &self,
+        grammar: &Grammar,
+        mut tokenizer: Tokenizer<'_>,
+    ) -> Result<EarleyParse, ParseError> {
+        let mut tokens = vec![tokenizer.next_token()?];
+        while tokens.last().unwrap().token != EOF {
+            tokens.push(tokenizer.next_token()?);
+        }
+        let input_len = tokens.len() - 1;
+
+        let mut sets: Vec<EarleySet> = (0..=input_len).map(|_| EarleySet::default()).collect();
+        sets[0].add(EarleyItem {
+            rule: 0,
+            dot: 0,
+            origin: 0,
+        });
+        for i in 0..=input_len {
+            let mut j = 0;
+            while j < sets[i].items.len() {
+                let item = sets[i].items[j];
+                j += 1;
+                match Self::next_symbol(grammar, item) {
+                    // Complete
+                    None => {
+                        let left = &grammar.get_rule(item.rule).left;
+                        let parents = sets[item.origin]
+                            .items
+                            .iter()
+                            .filter(|p| Self::next_symbol(grammar, **p) == Some(left))
+                            .map(|p| p.advance())
+                            .collect::<Vec<_>>();
+                        parents.into_iter().for_each(|p| sets[i].add(p));
+                    }
+                    // Predict, and skip over the non-terminal right away if it's nullable
+                    Some(symbol) if Grammar::is_non_terminal(symbol) => {
+                        for rule in self.rules_by_left.get(symbol).into_iter().flatten() {
+                            sets[i].add(EarleyItem {
+                                rule: *rule,
+                                dot: 0,
+                                origin: i,
+                            });
+                        }
+                        if grammar.is_nullable(symbol) {
+                            sets[i].add(item.advance());
+                        }
+                    }
+                    // Scan
+                    Some(symbol) => {
+                        if i < input_len && tokens[i].token == *symbol {
+                            sets[i + 1].add(item.advance());
+                        }
+                    }
+                }
+            }
+            if i < input_len && sets[i + 1].items.is_empty() {
+                return Err(Self::unexpected(&tokenizer, &tokens[i]).into());
+            }
+        }
+
+        let accepted = EarleyItem {
+            rule: 0,
+            dot: 1,
+            origin: 0,
+        };
+        if !sets[input_len].contains.contains(&accepted) {
+            return Err(Self::unexpected(&tokenizer, &tokens[input_len]).into());
+        }
+        let start = grammar.get_start_symbol();
+        let mut builder = TreeBuilder {
+            parser: self,
+            grammar,
+            sets: &sets,
+            tokens: &tokens,
+            graph: Graph::new(),
+            ambiguities: vec![],
+            path: HashSet::new(),
+        };
+        let root = builder
+            .build(&start, 0, input_len)
+            .expect("accepted input has a derivation");
+        Ok(EarleyParse {
+            derivation: Derivation {
+                graph: builder.graph,
+                root,
+            },
+            ambiguities: builder.ambiguities,
+        })
+    }
+
+    fn right(grammar: &Grammar, rule: usize) -> &[String] {
+        let right = &grammar.get_rule(rule).right;
+        if right == &[EPSILON] {
+            &right[..0]
+        } else {
+            right
+        }
+    }
+
+    fn next_symbol(grammar: &Grammar, item: EarleyItem) -> Option<&String> {
+        Self::right(grammar, item.rule).get(item.dot)
+    }
+
+    fn unexpected(tokenizer: &Tokenizer<'_>, token: &TokenInstance) -> SyntaxError {
+        SyntaxError {
+            src: tokenizer.source(),
+            span: token.span,
+            message: if token.token == EOF {
+                String::from("unexpected end of input")
+            } else {
+                format!("unexpected token '{}'", token.value)
+            },
+        }
+    }
+}
+
+/// Builds a derivation out of the completed items of the Earley sets, top-down.
+struct TreeBuilder<'a> {
+    parser: &'a GrammarParserEarley,
+    grammar: &'a Grammar,
+    sets: &'a [EarleySet],
+    tokens: &'a [TokenInstance],
+    graph: Graph<DerivationNode, usize>,
+    ambiguities: Vec<Ambiguity>,
+    /// Non-terminals and token ranges being built, to cut cycles such as `A -> A`.
+    path: HashSet<(&'a str, usize, usize)>,
+}
+
+impl<'a> TreeBuilder<'a> {
+    /// Node deriving `symbol` from the tokens in `start..end`.
+    fn build(&mut self, symbol: &'a str, start: usize, end: usize) -> Option<NodeIndex> {
+        if Grammar::is_terminal(symbol) {
+            return Some(
+                self.graph
+                    .add_node(DerivationNode::from_token(self.tokens[start].clone())),
+            );
+        }
+        if !self.path.insert((symbol, start, end)) {
+            return None;
+        }
+        let alternatives = self
+            .parser
+            .rules_by_left
+            .get(symbol)
+            .into_iter()
+            .flatten()
+            .filter(|rule| {
+                let len = GrammarParserEarley::right(self.grammar, **rule).len();
+                self.sets[end].contains.contains(&EarleyItem {
+                    rule: **rule,
+                    dot: len,
+                    origin: start,
+                })
+            })
+            .flat_map(|rule| {
+                self.splits(*rule, start, end, 2)
+                    .into_iter()
+                    .map(move |split| (*rule, split))
+            })
+            .collect::<Vec<_>>();
+        if alternatives.len() > 1 {
+            self.ambiguities.push(Ambiguity {
+                non_terminal: symbol.to_string(),
+                span: self.span(start, end),
+                rules: alternatives.iter().map(|(rule, _)| *rule).collect(),
+            });
+        }
+        let node = alternatives
+            .into_iter()
+            .find_map(|(rule, split)| self.build_rule(symbol, rule, &split));
+        self.path.remove(&(symbol, start, end));
+        node
+    }
+
+    fn build_rule(&mut self, symbol: &'a str, rule: usize, split: &[usize]) -> Option<NodeIndex> {
+        let right = GrammarParserEarley::right(self.grammar, rule);
+        let children = right
+            .iter()
+            .enumerate()
+            .map(|(i, s)| self.build(s, split[i], split[i + 1]))
+            .collect::<Option<Vec<_>>>()?;
+        let span = self.span(split[0], split[split.len() - 1]);
+        let node = self.graph.add_node(DerivationNode::from(
+            TokenInstance::from(symbol, symbol, span),
+            rule,
+        ));
+        for (i, child) in children.into_iter().enumerate() {
+            self.graph.add_edge(node, child, i);
+        }
+        Some(node)
+    }
+
+    /// Up to `limit` ways to split `start..end` between the symbols of `rule`, as the token
+    /// positions at the boundaries of the symbols.
+    fn splits(&self, rule: usize, start: usize, end: usize, limit: usize) -> Vec<Vec<usize>> {
+        let mut splits = vec![];
+        let mut positions = vec![end];
+        self.split_from(rule, start, &mut positions, limit, &mut splits);
+        splits
+    }
+
+    fn split_from(
+        &self,
+        rule: usize,
+        start: usize,
+        positions: &mut Vec<usize>,
+        limit: usize,
+        splits: &mut Vec<Vec<usize>>,
+    ) {
+        let right = GrammarParserEarley::right(self.grammar, rule);
+        let k = right.len() - (positions.len() - 1);
+        let end = *positions.last().unwrap();
+        if k == 0 {
+            if end == start {
+                splits.push(positions.iter().rev().copied().collect());
+            }
+            return;
+        }
+        let symbol = &right[k - 1];
+        let candidates = if Grammar::is_terminal(symbol) {
+            (end > start && self.tokens[end - 1].token == *symbol)
+                .then_some(end - 1)
+                .into_iter()
+                .collect::<Vec<_>>()
+        } else {
+            let mut origins = self.sets[end]
+                .items
+                .iter()
+                .filter(|item| {
+                    item.origin >= start
+                        && self.grammar.get_rule(item.rule).left == *symbol
+                        && GrammarParserEarley::next_symbol(self.grammar, **item).is_none()
+                })
+                .map(|item| item.origin)
+                .collect::<Vec<_>>();
+            origins.sort_unstable();
+            origins.dedup();
+            origins
+        };
+        for position in candidates {
+            let prefix = EarleyItem {
+                rule,
+                dot: k - 1,
+                origin: start,
+            };
+            if !self.sets[position].contains.contains(&prefix) {
+                continue;
+            }
+            positions.push(position);
+            self.split_from(rule, start, positions, limit, splits);
+            positions.pop();
+            if splits.len() >= limit {
+                return;
+            }
+        }
+    }
+
+    /// Span of the tokens in `start..end`, empty at the position of `start` if there are none.
+    fn span(&self, start: usize, end: usize) -> SourceSpan {
+        if start == end {
+            SpanBuilder::start(self.tokens[start].span)
+        } else {
+            SpanBuilder::merge(self.tokens[start].span, self.tokens[end - 1].span)
+        }
+    }
+}
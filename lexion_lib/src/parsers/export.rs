@@ -0,0 +1,412 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::hash::Hash;
+
+use petgraph::graph::NodeIndex;
+use petgraph::prelude::EdgeRef;
+use serde_json::json;
+
+use crate::grammar::Grammar;
+use crate::parsers::items::{CanonicalCollectionGraph, ClosurableItem, GraphState, LRItem};
+use crate::parsers::{ParseTableAction, ParseTableLR};
+use crate::tokenizer::tokens::{EOF, EPSILON};
+
+/// Textual exports of an LR automaton and its parse table, for teaching and debugging.
+pub struct ParseTableExport<'a, T: Eq + Hash + LRItem + Clone> {
+    grammar: &'a Grammar,
+    collection: &'a CanonicalCollectionGraph<T>,
+    table: &'a ParseTableLR,
+}
+
+impl<'a, T: Eq + Ord + Hash + LRItem + Clone + ClosurableItem<T>> ParseTableExport<'a, T> {
+    pub fn new(
+        grammar: &'a Grammar,
+        collection: &'a CanonicalCollectionGraph<T>,
+        table: &'a ParseTableLR,
+    ) -> Self {
+        Self {
+            grammar,
+            collection,
+            table,
+        }
+    }
+
+    /// Report in the layout of Bison's `.output` file: the grammar, then for each state its
+    /// items, the lookaheads of its reductions, its actions and its conflicts.
+    pub fn to_bison_output(&self) -> String {
+        let mut out = String::new();
+        let conflicts = self.conflicts();
+        for (state, kinds) in conflicts.iter() {
+            writeln!(out, "State {state} conflicts: {}", kinds.join(", ")).unwrap();
+        }
+        if !conflicts.is_empty() {
+            writeln!(out, "\n").unwrap();
+        }
+
+        writeln!(out, "Grammar\n").unwrap();
+        let mut previous_left = "";
+        for (index, rule) in self.grammar.get_rules().iter().enumerate() {
+            if rule.left != previous_left {
+                if index > 0 {
+                    writeln!(out).unwrap();
+                }
+                writeln!(
+                    out,
+                    "{index:>5} {}: {}",
+                    rule.left,
+                    Self::right(&rule.right)
+                )
+                .unwrap();
+            } else {
+                let pad = " ".repeat(rule.left.len());
+                writeln!(out, "{index:>5} {pad}| {}", Self::right(&rule.right)).unwrap();
+            }
+            previous_left = &rule.left;
+        }
+
+        writeln!(out, "\n\nTerminals, with rules where they appear\n").unwrap();
+        for (symbol, rules) in self.symbol_occurrences(true) {
+            writeln!(out, "    {symbol} ({})", rules.join(" ")).unwrap();
+        }
+        writeln!(out, "\n\nNonterminals, with rules where they appear\n").unwrap();
+        for (symbol, rules) in self.symbol_occurrences(false) {
+            writeln!(out, "    {symbol} ({})", rules.join(" ")).unwrap();
+        }
+
+        for index in self.collection.node_indices() {
+            let state = &self.collection[index];
+            writeln!(out, "\n\nState {}\n", index.index()).unwrap();
+            let (kernel, closure): (Vec<&T>, Vec<&T>) =
+                state.get_items().iter().partition(|i| Self::is_kernel(i));
+            for item in kernel.iter().chain(closure.iter()) {
+                write!(out, "{:>5} {}", item.get_rule_index(), self.item(item)).unwrap();
+                if item.is_final(self.grammar) && !item.is_accept(self.grammar) {
+                    let lookaheads = self.lookaheads(index.index(), item.get_rule_index());
+                    write!(out, "  [{}]", lookaheads.join(", ")).unwrap();
+                }
+                writeln!(out).unwrap();
+            }
+
+            let actions = self.actions(index.index());
+            let (terminals, non_terminals): (Vec<_>, Vec<_>) =
+                actions.iter().partition(|(s, _)| Grammar::is_terminal(s));
+            if !terminals.is_empty() {
+                writeln!(out).unwrap();
+            }
+            for (symbol, action) in terminals.iter() {
+                match action {
+                    ParseTableAction::Conflict(actions) => {
+                        // The last action of a conflict is the one the parser takes
+                        let (taken, discarded) = actions.split_last().unwrap();
+                        writeln!(out, "    {symbol}  {}", self.describe(taken)).unwrap();
+                        for action in discarded {
+                            writeln!(out, "    {symbol}  [{}]", self.describe(action)).unwrap();
+                        }
+                    }
+                    action => writeln!(out, "    {symbol}  {}", self.describe(action)).unwrap(),
+                }
+            }
+            if !non_terminals.is_empty() {
+                writeln!(out).unwrap();
+            }
+            for (symbol, action) in non_terminals.iter() {
+                writeln!(out, "    {symbol}  {}", self.describe(action)).unwrap();
+            }
+        }
+        out
+    }
+
+    /// Graphviz digraph of the canonical collection, with the item sets as node labels.
+    pub fn to_dot(&self) -> String {
+        let conflicts = self.conflicts();
+        let mut out = String::from("digraph automaton {\n");
+        writeln!(out, "    rankdir=LR;").unwrap();
+        writeln!(out, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+        for index in self.collection.node_indices() {
+            let state = &self.collection[index];
+            let mut label = format!("State {}\\l", index.index());
+            for item in state.get_items().iter() {
+                label += &Self::escape_dot(&self.item(item));
+                label += "\\l";
+            }
+            let mut attributes = vec![format!("label=\"{label}\"")];
+            if state.is_accept(self.grammar) {
+                attributes.push(String::from("peripheries=2"));
+            }
+            if conflicts.contains_key(&index.index()) {
+                attributes.push(String::from("color=red"));
+            }
+            writeln!(out, "    {} [{}];", index.index(), attributes.join(", ")).unwrap();
+        }
+        for edge in self.collection.edge_references() {
+            writeln!(
+                out,
+                "    {} -> {} [label=\"{}\"];",
+                edge.source().index(),
+                edge.target().index(),
+                Self::escape_dot(&edge.weight().symbol)
+            )
+            .unwrap();
+        }
+        out += "}\n";
+        out
+    }
+
+    /// Self-contained HTML page listing the states, where clicking a state shows its items,
+    /// actions and transitions.
+    pub fn to_html(&self) -> String {
+        let conflicts = self.conflicts();
+        let states = self
+            .collection
+            .node_indices()
+            .map(|index| self.state_json(index, &conflicts))
+            .collect::<Vec<_>>();
+        let rules = self
+            .grammar
+            .get_rules()
+            .iter()
+            .map(|r| format!("{}: {}", r.left, Self::right(&r.right)))
+            .collect::<Vec<_>>();
+        let data = json!({ "rules": rules, "states": states })
+            .to_string()
+            .replace("</", "<\\/");
+        HTML_TEMPLATE.replace("/*DATA*/", &data)
+    }
+
+    fn state_json(
+        &self,
+        index: NodeIndex,
+        conflicts: &BTreeMap<usize, Vec<String>>,
+    ) -> serde_json::Value {
+        let state: &GraphState<T> = &self.collection[index];
+        let items = state
+            .get_items()
+            .iter()
+            .map(|item| {
+                let lookaheads = if item.is_final(self.grammar) && !item.is_accept(self.grammar) {
+                    self.lookaheads(index.index(), item.get_rule_index())
+                } else {
+                    vec![]
+                };
+                json!({
+                    "rule": item.get_rule_index(),
+                    "text": self.item(item),
+                    "kernel": Self::is_kernel(item),
+                    "lookaheads": lookaheads,
+                })
+            })
+            .collect::<Vec<_>>();
+        let actions = self
+            .actions(index.index())
+            .into_iter()
+            .map(|(symbol, action)| {
+                let (action, conflict) = match action {
+                    ParseTableAction::Conflict(actions) => (
+                        actions
+                            .iter()
+                            .map(|a| self.describe(a))
+                            .collect::<Vec<_>>()
+                            .join(" / "),
+                        true,
+                    ),
+                    action => (self.describe(action), false),
+                };
+                json!({ "symbol": symbol, "action": action, "conflict": conflict })
+            })
+            .collect::<Vec<_>>();
+        let mut transitions = self
+            .collection
+            .edges(index)
+            .map(|e| (e.weight().symbol.clone(), e.target().index()))
+            .collect::<Vec<_>>();
+        transitions.sort();
+        json!({
+            "index": index.index(),
+            "accept": state.is_accept(self.grammar),
+            "conflict": conflicts.contains_key(&index.index()),
+            "items": items,
+            "actions": actions,
+            "transitions": transitions,
+        })
+    }
+
+    fn is_kernel(item: &T) -> bool {
+        item.get_dot_index() > 0 || item.get_rule_index() == 0
+    }
+
+    fn right(right: &[String]) -> String {
+        if right == [EPSILON] {
+            String::from("%empty")
+        } else {
+            right.join(" ")
+        }
+    }
+
+    fn item(&self, item: &T) -> String {
+        let rule = item.get_rule(self.grammar);
+        if rule.right == [EPSILON] {
+            return format!("{}: %empty •", rule.left);
+        }
+        let mut symbols = rule.right.iter().map(String::as_str).collect::<Vec<_>>();
+        symbols.insert(item.get_dot_index(), "•");
+        format!("{}: {}", rule.left, symbols.join(" "))
+    }
+
+    fn describe(&self, action: &ParseTableAction) -> String {
+        match action {
+            ParseTableAction::Shift(s) => format!("shift, and go to state {s}"),
+            ParseTableAction::Goto(s) => format!("go to state {s}"),
+            ParseTableAction::Reduce(r) => {
+                format!("reduce using rule {r} ({})", self.grammar.get_rule(*r).left)
+            }
+            ParseTableAction::Accept => String::from("accept"),
+            ParseTableAction::Reject => String::from("error"),
+            ParseTableAction::Conflict(actions) => actions
+                .iter()
+                .map(|a| self.describe(a))
+                .collect::<Vec<_>>()
+                .join(" / "),
+        }
+    }
+
+    /// Actions of a state, terminals first, each group sorted by symbol.
+    fn actions(&self, state: usize) -> Vec<(&'a str, &'a ParseTableAction)> {
+        let mut actions = self
+            .table
+            .entries()
+            .filter(|(s, _, _)| *s == state)
+            .map(|(_, symbol, action)| (symbol, action))
+            .collect::<Vec<_>>();
+        actions.sort_by_key(|(symbol, _)| (Grammar::is_non_terminal(symbol), *symbol));
+        actions
+    }
+
+    /// Terminals on which the given rule is reduced in a state.
+    fn lookaheads(&self, state: usize, rule_index: usize) -> Vec<&'a str> {
+        let reduces = |action: &ParseTableAction| match action {
+            ParseTableAction::Reduce(r) => *r == rule_index,
+            ParseTableAction::Conflict(actions) => actions
+                .iter()
+                .any(|a| matches!(a, ParseTableAction::Reduce(r) if *r == rule_index)),
+            _ => false,
+        };
+        self.actions(state)
+            .into_iter()
+            .filter(|(symbol, action)| Grammar::is_terminal(symbol) && reduces(action))
+            .map(|(symbol, _)| symbol)
+            .collect()
+    }
+
+    /// Kinds of conflicts found in each state, e.g. `1 shift/reduce`.
+    fn conflicts(&self) -> BTreeMap<usize, Vec<String>> {
+        let mut counts: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+        for (state, _, action) in self.table.entries() {
+            if let ParseTableAction::Conflict(actions) = action {
+                let count = counts.entry(state).or_default();
+                if actions.iter().any(|a| a.is_shift()) {
+                    count.0 += 1;
+                } else {
+                    count.1 += 1;
+                }
+            }
+        }
+        counts
+            .into_iter()
+            .map(|(state, (shift_reduce, reduce_reduce))| {
+                let mut kinds = vec![];
+                if shift_reduce > 0 {
+                    kinds.push(format!("{shift_reduce} shift/reduce"));
+                }
+                if reduce_reduce > 0 {
+                    kinds.push(format!("{reduce_reduce} reduce/reduce"));
+                }
+                (state, kinds)
+            })
+            .collect()
+    }
+
+    fn symbol_occurrences(&self, terminals: bool) -> BTreeMap<String, Vec<String>> {
+        let mut occurrences: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        if terminals {
+            occurrences.insert(String::from(EOF), vec![String::from("0")]);
+        }
+        for (index, rule) in self.grammar.get_rules().iter().enumerate() {
+            if !terminals {
+                occurrences.entry(rule.left.clone()).or_default();
+            }
+            for symbol in rule.right.iter() {
+                if symbol != EPSILON && Grammar::is_terminal(symbol) == terminals {
+                    let rules = occurrences.entry(symbol.clone()).or_default();
+                    if rules.last() != Some(&index.to_string()) {
+                        rules.push(index.to_string());
+                    }
+                }
+            }
+        }
+        occurrences
+    }
+
+    fn escape_dot(s: &str) -> String {
+        s.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+}
+
+const HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>LR automaton</title>
+<style>
+  body { font-family: sans-serif; margin: 0; display: flex; height: 100vh; }
+  nav { width: 12em; overflow-y: auto; border-right: 1px solid #ccc; padding: 0.5em; }
+  nav button { display: block; width: 100%; margin: 2px 0; text-align: left; }
+  nav button.conflict { color: #c00; }
+  nav button.accept { font-weight: bold; }
+  main { flex: 1; overflow-y: auto; padding: 1em; }
+  pre, td { font-family: monospace; }
+  table { border-collapse: collapse; }
+  td, th { border: 1px solid #ccc; padding: 2px 8px; text-align: left; }
+  tr.conflict td { color: #c00; }
+  .closure { color: #888; }
+  a { cursor: pointer; color: #06c; }
+</style>
+</head>
+<body>
+<nav id="states"></nav>
+<main id="state"></main>
+<script>
+const data = /*DATA*/;
+const nav = document.getElementById("states");
+const main = document.getElementById("state");
+const escape = (s) => s.replace(/[&<>"]/g, (c) => ({ "&": "&amp;", "<": "&lt;", ">": "&gt;", '"': "&quot;" })[c]);
+function show(index) {
+  const state = data.states[index];
+  let html = `<h2>State ${state.index}</h2><h3>Items</h3><pre>`;
+  for (const item of state.items) {
+    const lookaheads = item.lookaheads.length ? `  [${item.lookaheads.join(", ")}]` : "";
+    html += `<span class="${item.kernel ? "kernel" : "closure"}">${item.rule}  ${escape(item.text)}${escape(lookaheads)}</span>\n`;
+  }
+  html += `</pre><h3>Actions</h3><table><tr><th>Symbol</th><th>Action</th></tr>`;
+  for (const action of state.actions) {
+    html += `<tr class="${action.conflict ? "conflict" : ""}"><td>${escape(action.symbol)}</td><td>${escape(action.action)}</td></tr>`;
+  }
+  html += `</table><h3>Transitions</h3><ul>`;
+  for (const [symbol, target] of state.transitions) {
+    html += `<li>${escape(symbol)} &rarr; <a onclick="show(${target})">State ${target}</a></li>`;
+  }
+  html += `</ul>`;
+  main.innerHTML = html;
+}
+for (const state of data.states) {
+  const button = document.createElement("button");
+  button.textContent = `State ${state.index}`;
+  if (state.conflict) button.classList.add("conflict");
+  if (state.accept) button.classList.add("accept");
+  button.onclick = () => show(state.index);
+  nav.appendChild(button);
+}
+if (data.states.length) show(0);
+</script>
+</body>
+</html>
+"#;
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use miette::{NamedSource, SourceSpan};
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use petgraph::Graph;
+
+use crate::error::{ParseError, SyntaxError};
+use crate::grammar::{Derivation, DerivationNode, Grammar};
+use crate::parsers::{DerivationResult, ParseTableAction, ParseTableLR};
+use crate::tokenizer::tokens::{EOF, EPSILON};
+use crate::tokenizer::{TokenInstance, Tokenizer};
+
+/// Replacement of the bytes in `range` of a source text with `replacement`.
+#[derive(Debug, Clone)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub replacement: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, replacement: &str) -> Self {
+        Self {
+            range,
+            replacement: String::from(replacement),
+        }
+    }
+
+    pub fn apply(&self, source: &str) -> String {
+        let mut result = String::with_capacity(source.len() + self.replacement.len());
+        result.push_str(&source[..self.range.start]);
+        result.push_str(&self.replacement);
+        result.push_str(&source[self.range.end..]);
+        result
+    }
+
+    /// Number of bytes by which the text following the edit moves.
+    pub fn delta(&self) -> isize {
+        self.replacement.len() as isize - self.range.len() as isize
+    }
+}
+
+/// Wagner–Graham style reparse of an edited source against a previous derivation.
+///
+/// The token stream of the previous derivation is kept as is on both sides of the edit and only
+/// the damaged region is re-lexed. While parsing, a subtree of the previous derivation is shifted
+/// as a whole when the parser is about to shift its first token in the state recorded on the
+/// subtree, and neither its tokens nor the token following it were damaged by the edit. Under
+/// those conditions the LR driver would rebuild exactly the same subtree.
+pub struct IncrementalParse<'a> {
+    grammar: &'a Grammar,
+    table: &'a ParseTableLR,
+    previous: &'a Derivation,
+    src: NamedSource<Arc<String>>,
+    previous_tokens: Vec<TokenInstance>,
+    tokens: Vec<TokenInstance>,
+    /// Index in `previous_tokens` each token was carried over from, if it wasn't re-lexed.
+    origins: Vec<Option<usize>>,
+    /// Previous tokens `..first_damaged` and `resumed..` were carried over without re-lexing.
+    first_damaged: usize,
+    resumed: usize,
+    /// Non-empty subtrees of the previous derivation by index of their first token, outermost
+    /// first, along with the index of the token following them.
+    subtrees: HashMap<usize, Vec<(NodeIndex, usize)>>,
+}
+
+impl<'a> IncrementalParse<'a> {
+    pub fn new(
+        grammar: &'a Grammar,
+        table: &'a ParseTableLR,
+        previous: &'a Derivation,
+        source: Arc<String>,
+        edit: &TextEdit,
+    ) -> Result<Self, SyntaxError> {
+        let (leaves, subtrees) = Self::index(previous);
+        let delta = edit.delta();
+        let previous_len = (source.len() as isize - delta) as usize;
+        let mut previous_tokens: Vec<TokenInstance> = leaves
+            .into_iter()
+            .map(|id| previous.graph[id].token.clone())
+            .collect();
+        previous_tokens.push(TokenInstance::from(EOF, EOF, previous_len.into()));
+
+        // Tokens touching the edit are damaged, along with one token of lookback since its
+        // longest match may extend into the edited text.
+        let first_damaged = previous_tokens
+            .iter()
+            .position(|t| t.span.offset() + t.span.len() >= edit.range.start)
+            .unwrap_or(previous_tokens.len() - 1)
+            .saturating_sub(1);
+        let relex_from = match first_damaged {
+            0 => 0,
+            i => {
+                let span = previous_tokens[i - 1].span;
+                span.offset() + span.len()
+            }
+        };
+
+        let mut tokens = previous_tokens[..first_damaged].to_vec();
+        let mut origins: Vec<Option<usize>> = (0..first_damaged).map(Some).collect();
+        let mut resumed = previous_tokens.len();
+        let mut tokenizer = Tokenizer::from_string(source, grammar.get_token_types());
+        let edit_end = edit.range.start + edit.replacement.len();
+        tokenizer.seek(relex_from);
+        loop {
+            let token = tokenizer.next_token()?;
+            let offset = token.span.offset();
+            // Past the edit, lexing from the start of a previous token yields the same stream.
+            if offset >= edit_end {
+                let previous_offset = (offset as isize - delta) as usize;
+                if let Ok(start) =
+                    previous_tokens.binary_search_by_key(&previous_offset, |t| t.span.offset())
+                {
+                    resumed = start;
+                    for (i, token) in previous_tokens.iter().enumerate().skip(start) {
+                        tokens.push(TokenInstance {
+                            span: shift(token.span, delta),
+                            ..token.clone()
+                        });
+                        origins.push(Some(i));
+                    }
+                    break;
+                }
+            }
+            let eof = token.token == EOF;
+            tokens.push(token);
+            origins.push(None);
+            if eof {
+                break;
+            }
+        }
+
+        Ok(Self {
+            grammar,
+            table,
+            previous,
+            src: tokenizer.source(),
+            previous_tokens,
+            tokens,
+            origins,
+            first_damaged,
+            resumed,
+            subtrees,
+        })
+    }
+
+    pub fn parse(&self) -> DerivationResult {
+        self.parse_reused().map(|(derivation, _)| derivation)
+    }
+
+    /// Parses like [`parse`](IncrementalParse::parse), along with the nodes of the derivation
+    /// that were copied as whole subtrees of the previous one.
+    pub fn parse_reused(&self) -> Result<(Derivation, Vec<NodeIndex>), ParseError> {
+        let mut reused = vec![];
+        let mut graph: Graph<DerivationNode, usize> = Graph::new();
+        let mut states: Vec<usize> = vec![0];
+        let mut nodes: Vec<NodeIndex> = vec![];
+        let mut position = 0;
+        let mut lookahead = self.in_context(0, &self.tokens[0]);
+
+        loop {
+            let state = *states.last().unwrap();
+            match self.table.get_action(state, &lookahead.token).as_ref() {
+                ParseTableAction::Shift(next) => {
+                    if let Some((subtree, length)) = self.reusable(state, position) {
+                        let delta = lookahead.span.offset() as isize
+                            - self.previous_tokens[self.origins[position].unwrap()]
+                                .span
+                                .offset() as isize;
+                        let id = self.copy_subtree(&mut graph, subtree, delta);
+                        reused.push(id);
+                        let next = self.goto(state, &graph[id].token.token, &lookahead)?;
+                        nodes.push(id);
+                        states.push(next);
+                        position += length;
+                        // The token after the subtree was read in the same state as before
+                        lookahead = self.tokens[position].clone();
+                        continue;
+                    }
+                    let id = graph
+                        .add_node(DerivationNode::from_token(lookahead.clone()).with_state(state));
+                    nodes.push(id);
+                    states.push(*next);
+                    position += 1;
+                    lookahead = self.in_context(*next, &self.tokens[position]);
+                }
+                ParseTableAction::Reduce(rule_index) => {
+                    let rule = self.grammar.get_rule(*rule_index);
+                    let num_children = if rule.right == vec![String::from(EPSILON)] {
+                        0
+                    } else {
+                        rule.right.len()
+                    };
+                    states.truncate(states.len() - num_children);
+                    let from_state = *states.last().unwrap();
+                    let node_id = graph.add_node(
+                        DerivationNode::from(
+                            TokenInstance::from(&rule.left, &rule.left, lookahead.span),
+                            *rule_index,
+                        )
+                        .with_state(from_state),
+                    );
+                    for (i, child_id) in nodes.drain((nodes.len() - num_children)..).enumerate() {
+                        graph.add_edge(node_id, child_id, i);
+                    }
+                    states.push(self.goto(from_state, &rule.left, &lookahead)?);
+                    nodes.push(node_id);
+                }
+                ParseTableAction::Accept => {
+                    if let Some(root) = nodes.pop() {
+                        return Ok((Derivation { graph, root }, reused));
+                    }
+                    return Err(self.unexpected(&lookahead).into());
+                }
+                ParseTableAction::Goto(_)
+                | ParseTableAction::Reject
+                | ParseTableAction::Conflict(_) => {
+                    return Err(self.unexpected(&lookahead).into());
+                }
+            }
+        }
+    }
+
+    /// Leaves of `derivation` in order, and its non-empty subtrees keyed by first leaf index.
+    #[allow(clippy::type_complexity)]
+    fn index(derivation: &Derivation) -> (Vec<NodeIndex>, HashMap<usize, Vec<(NodeIndex, usize)>>) {
+        let mut leaves = vec![];
+        let mut subtrees: HashMap<usize, Vec<(NodeIndex, usize)>> = HashMap::new();
+        let mut stack: Vec<(NodeIndex, Option<usize>)> = vec![(derivation.root, None)];
+        while let Some((id, first)) = stack.pop() {
+            match first {
+                None => {
+                    if Grammar::is_terminal(&derivation.graph[id].token.token) {
+                        leaves.push(id);
+                        continue;
+                    }
+                    stack.push((id, Some(leaves.len())));
+                    stack.extend(derivation.children(id).into_iter().rev().map(|c| (c, None)));
+                }
+                Some(first) if leaves.len() > first => {
+                    subtrees.entry(first).or_default().push((id, leaves.len()));
+                }
+                Some(_) => {}
+            }
+        }
+        subtrees.values_mut().for_each(|v| v.reverse());
+        (leaves, subtrees)
+    }
+
+    /// Previous subtree that can be shifted in `state` at `position`, with its length in tokens.
+    fn reusable(&self, state: usize, position: usize) -> Option<(NodeIndex, usize)> {
+        let first = self.origins[position]?;
+        self.subtrees.get(&first)?.iter().find_map(|&(id, follow)| {
+            ((follow < self.first_damaged || first >= self.resumed)
+                && self.previous.graph[id].state == state)
+                .then_some((id, follow - first))
+        })
+    }
+
+    fn copy_subtree(
+        &self,
+        graph: &mut Graph<DerivationNode, usize>,
+        root: NodeIndex,
+        delta: isize,
+    ) -> NodeIndex {
+        let previous = &self.previous.graph;
+        let copy = |id: NodeIndex| {
+            let node = &previous[id];
+            DerivationNode {
+                token: TokenInstance {
+                    span: shift(node.token.span, delta),
+                    ..node.token.clone()
+                },
+                rule_index: node.rule_index,
+                state: node.state,
+            }
+        };
+        let new_root = graph.add_node(copy(root));
+        let mut stack = vec![(root, new_root)];
+        while let Some((id, new_id)) = stack.pop() {
+            for (i, child) in Derivation::children_of(previous, id)
+                .into_iter()
+                .enumerate()
+            {
+                let new_child = graph.add_node(copy(child));
+                graph.add_edge(new_id, new_child, i);
+                stack.push((child, new_child));
+            }
+        }
+        new_root
+    }
+
+    /// `token` read as the LR driver would in `state`, with its keyword resolved against the
+    /// terminals the state expects rather than those of the state it was lexed in.
+    fn in_context(&self, state: usize, token: &TokenInstance) -> TokenInstance {
+        let host = self.grammar.get_token_types().iter().find(|t| {
+            t.keywords
+                .get(&token.value)
+                .is_some_and(|keyword| *keyword == token.token || t.name == token.token)
+        });
+        match host {
+            Some(host) => TokenInstance {
+                token: String::from(
+                    host.name_in_context(&token.value, &|t| self.table.expects(state, t)),
+                ),
+                ..token.clone()
+            },
+            None => token.clone(),
+        }
+    }
+
+    fn goto(
+        &self,
+        state: usize,
+        symbol: &str,
+        lookahead: &TokenInstance,
+    ) -> Result<usize, SyntaxError> {
+        match self.table.get_action(state, symbol).as_ref() {
+            ParseTableAction::Goto(next) => Ok(*next),
+            _ => Err(self.unexpected(lookahead)),
+        }
+    }
+
+    fn unexpected(&self, lookahead: &TokenInstance) -> SyntaxError {
+        SyntaxError {
+            src: self.src.clone(),
+            span: lookahead.span,
+            message: if lookahead.value == EOF {
+                String::from("unexpected end of input")
+            } else {
+                format!("unexpected token '{}'", lookahead.value)
+            },
+        }
+    }
+}
+
+fn shift(span: SourceSpan, delta: isize) -> SourceSpan {
+    ((span.offset() as isize + delta) as usize, span.len()).into()
+}
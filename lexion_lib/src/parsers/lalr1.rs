@@ -1,14 +1,13 @@
 use crate::grammar::Grammar;
 use crate::parsers::items::{CanonicalCollectionGraph, LR0Item, LRItem};
 use crate::parsers::{GrammarParserLR, ParseTableLR};
-use crate::tokenizer::tokens::EOF;
+use crate::tokenizer::tokens::{EOF, EPSILON};
 use petgraph::prelude::{EdgeIndex, EdgeRef, NodeIndex};
 use petgraph::visit::IntoEdgesDirected;
 use petgraph::Direction;
 use std::collections::{HashMap, HashSet};
 
 pub struct GrammarParserLALR1 {
-    #[allow(dead_code)]
     collection: CanonicalCollectionGraph<LR0Item>,
     #[allow(dead_code)]
     lookahead_sets: HashMap<(usize, LR0Item), HashSet<String>>,
@@ -215,8 +214,14 @@ impl<'a> SetConstructorLALR1<'a> {
             let rule = item.get_rule(self.grammar);
             // Trace back through the rule's RHS to find predecessor states,
             // then look up the goto transition for rule.left from each predecessor.
+            // An ε rule has no transitions to trace, it's reduced in the predecessor itself.
+            let right = if rule.right == [EPSILON] {
+                &rule.right[..0]
+            } else {
+                &rule.right[..]
+            };
             let lookaheads: HashSet<String> = self
-                .trace_backwards(index, &rule.right)
+                .trace_backwards(index, right)
                 .into_iter()
                 .flat_map(|pred| {
                     self.collection
@@ -253,6 +258,10 @@ impl GrammarParserLALR1 {
             lookahead_sets,
         }
     }
+
+    pub fn get_collection(&self) -> &CanonicalCollectionGraph<LR0Item> {
+        &self.collection
+    }
 }
 
 impl GrammarParserLR for GrammarParserLALR1 {
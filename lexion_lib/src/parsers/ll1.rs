@@ -1,18 +1,25 @@
-use crate::grammar::Grammar;
+use crate::error::{ParseError, SyntaxError};
+use crate::grammar::{Derivation, DerivationNode, Grammar};
+use crate::parsers::DerivationResult;
 use crate::tokenizer::tokens::{EOF, EPSILON};
-use std::collections::{HashMap, HashSet};
+use crate::tokenizer::{TokenInstance, Tokenizer};
+use itertools::Itertools;
+use petgraph::graph::NodeIndex;
+use petgraph::Graph;
+use std::collections::{BTreeMap, HashMap};
+use tabled::builder::Builder;
+use tabled::settings::{Alignment, Style};
+use tabled::Table;
 
-type ParseTableLL1 = HashMap<String, HashMap<String, usize>>;
+type ParseTableLL1 = HashMap<String, HashMap<String, Vec<usize>>>;
 
 pub struct GrammarParserLL1 {
-    is_ll1: bool,
     parse_table: ParseTableLL1,
 }
 
 impl GrammarParserLL1 {
     pub fn from_grammar(grammar: &Grammar) -> GrammarParserLL1 {
         let mut parser = GrammarParserLL1 {
-            is_ll1: true,
             parse_table: HashMap::new(),
         };
         parser.build_parse_table(grammar);
@@ -22,36 +29,144 @@ impl GrammarParserLL1 {
 
 impl GrammarParserLL1 {
     fn build_parse_table(&mut self, grammar: &Grammar) {
-        for (i, rule) in grammar.get_rules().iter().enumerate() {
-            let mut symbol_set: HashSet<String> = grammar
-                .first_of(&rule.right[0])
-                .unwrap()
-                .iter()
-                .cloned()
-                .collect();
-            symbol_set.remove(EPSILON);
-            if rule.right == vec![String::from(EOF)] {
-                symbol_set.extend(grammar.follow_of(&rule.left).unwrap().iter().cloned());
+        // Rule 0 is the augmented start rule, parsing starts from the start symbol instead
+        for (i, rule) in grammar.get_rules().iter().enumerate().skip(1) {
+            let mut terminals = grammar.first_of_sequence(&rule.right);
+            if grammar.is_nullable_sequence(&rule.right) {
+                terminals.extend(grammar.follow_of(&rule.left).into_iter().flatten().cloned());
             }
-            for terminal in grammar.first_of(&rule.right[0]).unwrap().iter() {
-                match self.parse_table.get_mut(&*rule.left) {
-                    Some(m) => {
-                        m.insert(terminal.clone(), i);
-                    }
-                    None => {
-                        let mut map = HashMap::new();
-                        let prev = map.insert(terminal.clone(), i);
-                        if prev.is_some() {
-                            self.is_ll1 = false;
-                        }
-                        self.parse_table.insert(rule.left.clone(), map);
+            let entries = self.parse_table.entry(rule.left.clone()).or_default();
+            for terminal in terminals {
+                entries.entry(terminal).or_default().push(i);
+            }
+        }
+    }
+
+    pub fn is_ll1(&self) -> bool {
+        self.conflicts().is_empty()
+    }
+
+    pub fn get_rule_index(&self, non_terminal: &str, terminal: &str) -> Option<usize> {
+        self.parse_table
+            .get(non_terminal)?
+            .get(terminal)?
+            .first()
+            .copied()
+    }
+
+    /// Entries of the table with more than one rule, sorted by non-terminal and terminal.
+    pub fn conflicts(&self) -> Vec<(&str, &str, &[usize])> {
+        self.parse_table
+            .iter()
+            .flat_map(|(non_terminal, entries)| {
+                entries
+                    .iter()
+                    .filter(|(_, rules)| rules.len() > 1)
+                    .map(|(terminal, rules)| {
+                        (non_terminal.as_str(), terminal.as_str(), rules.as_slice())
+                    })
+            })
+            .sorted()
+            .collect()
+    }
+
+    pub fn to_table(&self) -> Table {
+        let rows = self
+            .parse_table
+            .iter()
+            .map(|(n, entries)| (n, entries.iter().collect::<BTreeMap<_, _>>()))
+            .collect::<BTreeMap<_, _>>();
+        let terminals = rows
+            .values()
+            .flat_map(|entries| entries.keys().copied())
+            .sorted()
+            .dedup()
+            .collect::<Vec<&String>>();
+        let mut builder = Builder::default();
+        builder.push_record(
+            std::iter::once(String::from("\\")).chain(terminals.iter().map(|t| t.to_string())),
+        );
+        for (non_terminal, entries) in rows {
+            builder.push_record(
+                std::iter::once(non_terminal.clone()).chain(terminals.iter().map(|t| {
+                    match entries.get(*t) {
+                        Some(rules) => rules.iter().map(|r| r.to_string()).join(" / "),
+                        None => String::new(),
                     }
+                })),
+            );
+        }
+        let mut table = builder.build();
+        table.with(Style::modern());
+        table.with(Alignment::center());
+        table
+    }
+
+    /// Predictive parse of the input. Grammars whose parse table has conflicts are refused, as
+    /// picking one of the rules could expand a left recursion forever.
+    pub fn parse(&self, grammar: &Grammar, mut tokenizer: Tokenizer<'_>) -> DerivationResult {
+        if !self.is_ll1() {
+            return Err(ParseError::NotLL1);
+        }
+        let mut graph: Graph<DerivationNode, usize> = Graph::new();
+        let mut stack: Vec<(String, Option<NodeIndex>, usize)> = vec![
+            (String::from(EOF), None, 0),
+            (grammar.get_start_symbol(), None, 0),
+        ];
+        let mut root = None;
+        let mut lookahead = tokenizer.next_token()?;
+        while let Some((symbol, parent, index)) = stack.pop() {
+            let node_id = if Grammar::is_terminal(&symbol) {
+                if symbol != lookahead.token {
+                    return Err(Self::unexpected(&tokenizer, &lookahead).into());
+                }
+                if symbol == EOF {
+                    break;
                 }
+                let token = std::mem::replace(&mut lookahead, tokenizer.next_token()?);
+                graph.add_node(DerivationNode::from_token(token))
+            } else {
+                let Some(rule_index) = self.get_rule_index(&symbol, &lookahead.token) else {
+                    return Err(Self::unexpected(&tokenizer, &lookahead).into());
+                };
+                let node_id = graph.add_node(DerivationNode::from(
+                    TokenInstance::from(&symbol, &symbol, lookahead.span),
+                    rule_index,
+                ));
+                let rule = grammar.get_rule(rule_index);
+                if rule.right != vec![String::from(EPSILON)] {
+                    stack.extend(
+                        rule.right
+                            .iter()
+                            .enumerate()
+                            .rev()
+                            .map(|(i, s)| (s.clone(), Some(node_id), i)),
+                    );
+                }
+                node_id
+            };
+            match parent {
+                Some(parent) => {
+                    graph.add_edge(parent, node_id, index);
+                }
+                None => root = Some(node_id),
             }
         }
+        match root {
+            Some(root) => Ok(Derivation { graph, root }),
+            None => Err(Self::unexpected(&tokenizer, &lookahead).into()),
+        }
     }
 
-    pub fn is_ll1(&self) -> bool {
-        self.is_ll1
+    fn unexpected(tokenizer: &Tokenizer<'_>, lookahead: &TokenInstance) -> SyntaxError {
+        SyntaxError {
+            src: tokenizer.source(),
+            span: lookahead.span,
+            message: if lookahead.value == EOF {
+                String::from("unexpected end of input")
+            } else {
+                format!("unexpected token '{}'", lookahead.value)
+            },
+        }
     }
 }
@@ -1,14 +1,16 @@
 use std::borrow::Cow;
 use std::sync::Arc;
 
-use itertools::Itertools;
 use petgraph::graph::NodeIndex;
 use petgraph::Graph;
 use tabled::builder::Builder;
 
 use crate::error::{ParseError, SyntaxError};
 use crate::grammar::{Derivation, DerivationNode, Grammar};
-use crate::parsers::{ParseTableAction, ParseTableLR};
+use crate::parsers::{
+    IncrementalParse, ParseEvent, ParseEventKind, ParseTableAction, ParseTableLR, ParseTrace,
+    SemanticActions, TextEdit,
+};
 use crate::tokenizer::tokens::{EOF, EPSILON};
 use crate::tokenizer::{TokenInstance, Tokenizer};
 
@@ -47,141 +49,209 @@ pub trait GrammarParserLR {
         self.parse_trace(grammar, tokenizer, trace)
     }
 
-    fn parse(&self, grammar: &Grammar, tokenizer: Tokenizer) -> DerivationResult {
-        self.parse_trace(grammar, tokenizer, None)
+    /// Reparses `source` after `edit` was applied to the text that produced `previous`.
+    ///
+    /// Only the tokens around the edited range are re-lexed, and subtrees of `previous`
+    /// that lie outside of it are reused whenever the parser reaches them in the same state.
+    fn reparse(
+        &self,
+        grammar: &Grammar,
+        previous: &Derivation,
+        source: Arc<String>,
+        edit: &TextEdit,
+    ) -> DerivationResult {
+        IncrementalParse::new(grammar, self.get_parse_table(), previous, source, edit)?.parse()
+    }
+
+    fn parse(&self, grammar: &Grammar, tokenizer: Tokenizer<'_>) -> DerivationResult {
+        self.parse_observe(grammar, tokenizer, &mut |_| {})
     }
 
     fn parse_trace(
         &self,
         grammar: &Grammar,
-        mut tokenizer: Tokenizer,
+        tokenizer: Tokenizer<'_>,
         mut trace: Option<&mut Builder>,
     ) -> DerivationResult {
-        enum StackItem {
-            State(usize),
-            Node(NodeIndex),
-        }
-
-        let mut graph: Graph<DerivationNode, usize> = Graph::new();
-        let table = self.get_parse_table();
-        let mut stack: Vec<StackItem> = vec![StackItem::State(0)];
-        let mut lookahead = tokenizer.next_token()?;
-        let mut step = 0;
-
         if let Some(trace) = trace.as_mut() {
             trace.push_record(["Step", "Stack", "Lookahead", "Action"]);
         }
-
-        while !stack.is_empty() {
-            step += 1;
-            let element = &stack[stack.len() - 1];
-            let action = match element {
-                StackItem::State(i) => table.get_action(*i, &lookahead.token),
-                StackItem::Node(id) => {
-                    let node = graph.node_weight(*id).unwrap();
-                    if let StackItem::State(i) = stack
-                        .iter()
-                        .rev()
-                        .find(|i| matches!(i, StackItem::State(_)))
-                        .unwrap()
-                    {
-                        table.get_action(*i, &node.get_rule(grammar).left)
-                    } else {
-                        Cow::Owned(ParseTableAction::Reject)
-                    }
-                }
-            };
+        self.parse_observe(grammar, tokenizer, &mut |event| {
             if let Some(trace) = trace.as_mut() {
                 trace.push_record([
-                    step.to_string(),
-                    format!(
-                        "[{}]",
-                        stack
-                            .iter()
-                            .map(|v| match v {
-                                StackItem::State(s) => s.to_string(),
-                                StackItem::Node(id) => {
-                                    let node = graph.node_weight(*id).unwrap();
-                                    node.token.value.clone()
-                                }
-                            })
-                            .intersperse(String::from(", "))
-                            .collect::<String>()
-                    ),
-                    lookahead.token.clone(),
-                    action.to_string(),
+                    event.step.to_string(),
+                    event.stack_string(),
+                    event.lookahead.token.clone(),
+                    event.kind.to_string(),
                 ]);
             }
-            match action.as_ref() {
-                ParseTableAction::Conflict(_) => {
-                    return Err(SyntaxError {
-                        src: tokenizer.source(),
-                        span: lookahead.span,
-                        message: format!("conflicting action '{}' in parse table", action.as_ref()),
+        })
+    }
+
+    /// Parses the input and evaluates it with `actions` on each reduction, for grammars loaded
+    /// at runtime. Fails without parsing if `actions` are bound to unit rules that the table
+    /// has eliminated.
+    fn parse_with<V: Default>(
+        &self,
+        grammar: &Grammar,
+        tokenizer: Tokenizer<'_>,
+        actions: &SemanticActions<V>,
+    ) -> Result<V, ParseError>
+    where
+        Self: Sized,
+    {
+        if self.get_parse_table().unit_rules_eliminated() && actions.acts_on_unit_rules(grammar) {
+            return Err(ParseError::EliminatedUnitRules);
+        }
+        let mut values = vec![];
+        self.parse_observe(grammar, tokenizer, &mut |event| {
+            actions.apply(grammar, event, &mut values)
+        })?;
+        Ok(values.pop().unwrap_or_default())
+    }
+
+    /// Parses the input and records every event so that the parse can be replayed.
+    fn record(&self, grammar: &Grammar, tokenizer: Tokenizer<'_>) -> ParseTrace {
+        let mut trace = ParseTrace::default();
+        let (graph, root) = drive(self.get_parse_table(), grammar, tokenizer, &mut |event| {
+            trace.record(event)
+        });
+        trace.finish(graph, root.ok());
+        trace
+    }
+
+    /// Parses the input, calling `observer` with each event before it is applied.
+    fn parse_observe(
+        &self,
+        grammar: &Grammar,
+        tokenizer: Tokenizer<'_>,
+        observer: &mut dyn FnMut(&ParseEvent),
+    ) -> DerivationResult {
+        let (graph, root) = drive(self.get_parse_table(), grammar, tokenizer, observer);
+        Ok(Derivation { graph, root: root? })
+    }
+}
+
+fn drive(
+    table: &ParseTableLR,
+    grammar: &Grammar,
+    mut tokenizer: Tokenizer<'_>,
+    observer: &mut dyn FnMut(&ParseEvent),
+) -> (Graph<DerivationNode, usize>, Result<NodeIndex, ParseError>) {
+    let mut graph: Graph<DerivationNode, usize> = Graph::new();
+    let mut states: Vec<usize> = vec![0];
+    let mut nodes: Vec<NodeIndex> = vec![];
+    let mut step = 0;
+    // Tokens are read in the context of the state they are looked ahead from, so keywords
+    // the state does not expect are read as their host terminal
+    let mut lookahead = match tokenizer.next_token_expecting(&|t| table.expects(0, t)) {
+        Ok(token) => token,
+        Err(err) => {
+            let kind = ParseEventKind::Error(err.clone());
+            observer(&ParseEvent {
+                step: 1,
+                kind: &kind,
+                states: &states,
+                nodes: &nodes,
+                lookahead: &TokenInstance::from("", "", err.span),
+                graph: &graph,
+            });
+            return (graph, Err(err.into()));
+        }
+    };
+
+    let mut lookahead_id = table.symbol_id(&lookahead.token);
+    loop {
+        step += 1;
+        let state = *states.last().unwrap();
+        // After a reduction, the goto state of the new node is pushed in a step of its own
+        let action = if states.len() == nodes.len() {
+            let node = &graph[*nodes.last().unwrap()];
+            table.get_action(state, &node.get_rule(grammar).left)
+        } else {
+            Cow::Borrowed(table.action(state, lookahead_id))
+        };
+        let kind = match action.as_ref() {
+            ParseTableAction::Shift(next) => ParseEventKind::Shift(*next),
+            ParseTableAction::Reduce(rule_index) => ParseEventKind::Reduce(*rule_index),
+            ParseTableAction::Goto(next) => ParseEventKind::Goto(*next),
+            ParseTableAction::Accept if !nodes.is_empty() => ParseEventKind::Accept,
+            action => ParseEventKind::Error(SyntaxError {
+                src: tokenizer.source(),
+                span: lookahead.span,
+                message: if let ParseTableAction::Conflict(_) = action {
+                    format!("conflicting action '{action}' in parse table")
+                } else if lookahead.value == EOF {
+                    String::from("unexpected end of input")
+                } else {
+                    format!("unexpected token '{}'", lookahead.value)
+                },
+            }),
+        };
+        observer(&ParseEvent {
+            step,
+            kind: &kind,
+            states: &states,
+            nodes: &nodes,
+            lookahead: &lookahead,
+            graph: &graph,
+        });
+
+        match kind {
+            ParseEventKind::Shift(next) => {
+                let id =
+                    graph.add_node(DerivationNode::from_token(lookahead.clone()).with_state(state));
+                nodes.push(id);
+                states.push(next);
+                lookahead = match tokenizer.next_token_expecting(&|t| table.expects(next, t)) {
+                    Ok(token) => {
+                        lookahead_id = table.symbol_id(&token.token);
+                        token
                     }
-                    .into());
-                }
-                ParseTableAction::Accept => {
-                    if let StackItem::Node(root, ..) = stack[stack.len() - 2] {
-                        return Ok(Derivation { graph, root });
+                    Err(err) => {
+                        let kind = ParseEventKind::Error(err.clone());
+                        observer(&ParseEvent {
+                            step: step + 1,
+                            kind: &kind,
+                            states: &states,
+                            nodes: &nodes,
+                            lookahead: &TokenInstance::from("", "", err.span),
+                            graph: &graph,
+                        });
+                        return (graph, Err(err.into()));
                     }
-                }
-                ParseTableAction::Goto(state) => {
-                    stack.push(StackItem::State(*state));
-                }
-                ParseTableAction::Shift(state) => {
-                    let id = graph.add_node(DerivationNode::from_token(lookahead.clone()));
-                    stack.push(StackItem::Node(id));
-                    stack.push(StackItem::State(*state));
-                    lookahead = tokenizer.next_token()?;
-                }
-                ParseTableAction::Reduce(rule_index) => {
-                    let rule = grammar.get_rule(*rule_index);
-                    let num_children = if rule.right == vec![String::from(EPSILON)] {
-                        0
-                    } else {
-                        rule.right.len()
-                    };
-                    let num_right = num_children * 2;
-                    let node_id = graph.add_node(DerivationNode::from(
+                };
+            }
+            ParseEventKind::Reduce(rule_index) => {
+                let rule = grammar.get_rule(rule_index);
+                let num_children = if rule.right == vec![String::from(EPSILON)] {
+                    0
+                } else {
+                    rule.right.len()
+                };
+                states.truncate(states.len() - num_children);
+                let node_id = graph.add_node(
+                    DerivationNode::from(
                         TokenInstance::from(&rule.left, &rule.left, lookahead.span),
-                        *rule_index,
-                    ));
-                    for child_id in stack
-                        .drain((stack.len() - num_right)..)
-                        .enumerate()
-                        .filter(|(i, _)| i % 2 == 0)
-                        .map(|(_, v)| {
-                            if let StackItem::Node(id) = v {
-                                return id;
-                            }
-                            unreachable!()
-                        })
-                    {
-                        graph.add_edge(node_id, child_id, graph.edges(node_id).count());
-                    }
-                    stack.push(StackItem::Node(node_id));
+                        rule_index,
+                    )
+                    .with_state(*states.last().unwrap()),
+                );
+                for (i, child_id) in nodes.drain((nodes.len() - num_children)..).enumerate() {
+                    graph.add_edge(node_id, child_id, i);
                 }
-                ParseTableAction::Reject => {
-                    return Err(SyntaxError {
-                        src: tokenizer.source(),
-                        span: lookahead.span,
-                        message: if &*lookahead.value == EOF {
-                            String::from("unexpected end of input")
-                        } else {
-                            format!("unexpected token '{}'", lookahead.value)
-                        },
-                    }
-                    .into());
-                }
-            };
-        }
-
-        Err(SyntaxError {
-            src: tokenizer.source(),
-            span: tokenizer.cursor_offset().into(),
-            message: String::from("unexpected end of input"),
+                nodes.push(node_id);
+            }
+            ParseEventKind::Goto(next) => {
+                states.push(next);
+            }
+            ParseEventKind::Accept => {
+                let root = *nodes.last().unwrap();
+                return (graph, Ok(root));
+            }
+            ParseEventKind::Error(err) => {
+                return (graph, Err(err.into()));
+            }
         }
-        .into())
     }
 }
@@ -3,7 +3,6 @@ use crate::parsers::items::{CanonicalCollectionGraph, LR0Item};
 use crate::parsers::{GrammarParserLR, ParseTableLR};
 
 pub struct GrammarParserLR0 {
-    #[allow(dead_code)]
     collection: CanonicalCollectionGraph<LR0Item>,
     table: ParseTableLR,
 }
@@ -21,4 +20,8 @@ impl GrammarParserLR0 {
             ParseTableLR::from_collection(grammar, &collection, |_, _, _| grammar.get_terminals());
         Self { collection, table }
     }
+
+    pub fn get_collection(&self) -> &CanonicalCollectionGraph<LR0Item> {
+        &self.collection
+    }
 }
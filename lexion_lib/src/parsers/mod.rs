@@ -1,19 +1,33 @@
 #![allow(unused_imports)]
 
+mod actions;
+mod artifact;
+mod earley;
+mod export;
+mod incremental;
 pub mod items;
 mod lalr1;
 mod ll1;
 mod lr;
 mod lr0;
+mod pratt;
 mod slr1;
 mod table;
+mod trace;
 
+pub use actions::*;
+pub use artifact::*;
+pub use earley::*;
+pub use export::*;
+pub use incremental::*;
 pub use lalr1::*;
 pub use ll1::*;
 pub use lr::*;
 pub use lr0::*;
+pub use pratt::*;
 pub use slr1::*;
 pub use table::*;
+pub use trace::*;
 
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,431 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use petgraph::graph::NodeIndex;
+use petgraph::Graph;
+
+use crate::error::{ParseError, SyntaxError};
+use crate::grammar::{Derivation, DerivationNode, Grammar, GrammarRule};
+use crate::parsers::{DerivationResult, ParseTableAction, ParseTableLR, ParserAlgorithm};
+use crate::tokenizer::tokens::{EOF, EPSILON};
+use crate::tokenizer::{SpanBuilder, TokenInstance, Tokenizer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Associativity {
+    Left,
+    Right,
+    /// Chaining the operator with another one of the same precedence is a syntax error.
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fixity {
+    Prefix,
+    Infix,
+    Postfix,
+}
+
+#[derive(Debug, Clone)]
+pub struct Operator {
+    pub token: String,
+    /// Operators with a higher precedence bind tighter.
+    pub precedence: u32,
+    pub associativity: Associativity,
+    pub fixity: Fixity,
+}
+
+impl Operator {
+    fn rule(&self, non_terminal: &str) -> GrammarRule {
+        let (nt, op) = (String::from(non_terminal), self.token.clone());
+        GrammarRule {
+            left: nt.clone(),
+            right: match self.fixity {
+                Fixity::Prefix => vec![op, nt],
+                Fixity::Infix => vec![nt.clone(), op, nt],
+                Fixity::Postfix => vec![nt, op],
+            },
+        }
+    }
+
+    /// Binding power of the operator on its left and on its right.
+    fn binding_power(&self) -> (u32, u32) {
+        let power = 2 * self.precedence + 1;
+        match (self.fixity, self.associativity) {
+            (Fixity::Infix, Associativity::Right) => (power + 1, power),
+            _ => (power, power + 1),
+        }
+    }
+}
+
+/// Operators of a non-terminal whose expressions are parsed by precedence climbing instead of
+/// a rule per precedence level, between operands derived from `operand`.
+#[derive(Debug, Clone)]
+pub struct OperatorTable {
+    pub non_terminal: String,
+    pub operand: String,
+    pub operators: Vec<Operator>,
+}
+
+impl OperatorTable {
+    pub fn new(non_terminal: &str, operand: &str) -> Self {
+        Self {
+            non_terminal: String::from(non_terminal),
+            operand: String::from(operand),
+            operators: vec![],
+        }
+    }
+
+    pub fn prefix(self, token: &str, precedence: u32) -> Self {
+        self.operator(token, precedence, Associativity::Right, Fixity::Prefix)
+    }
+
+    pub fn infix(self, token: &str, precedence: u32, associativity: Associativity) -> Self {
+        self.operator(token, precedence, associativity, Fixity::Infix)
+    }
+
+    pub fn postfix(self, token: &str, precedence: u32) -> Self {
+        self.operator(token, precedence, Associativity::Left, Fixity::Postfix)
+    }
+
+    fn operator(
+        mut self,
+        token: &str,
+        precedence: u32,
+        associativity: Associativity,
+        fixity: Fixity,
+    ) -> Self {
+        self.operators.push(Operator {
+            token: String::from(token),
+            precedence,
+            associativity,
+            fixity,
+        });
+        self
+    }
+
+    /// Rules of the derivation nodes of the non-terminal, to add to the grammar: `N -> operand`
+    /// and one rule per operator, such as `N -> N '+' N`, in order.
+    pub fn rules(&self) -> Vec<GrammarRule> {
+        let operand = GrammarRule {
+            left: self.non_terminal.clone(),
+            right: vec![self.operand.clone()],
+        };
+        std::iter::once(operand)
+            .chain(self.operators.iter().map(|o| o.rule(&self.non_terminal)))
+            .collect()
+    }
+
+    /// Terminal standing for a whole expression in the parse tables of the other symbols.
+    fn placeholder(&self) -> String {
+        format!("'<{}>'", self.non_terminal)
+    }
+}
+
+/// LR parser that hands the non-terminals of its operator tables to an embedded Pratt parser,
+/// which calls back into the LR parser for their operands.
+///
+/// Each expression reduces once per operator and once per operand, instead of once per
+/// precedence level. An expression starts wherever the LR parser could shift it and the
+/// lookahead is in its FIRST set, and extends as long as operators follow.
+pub struct GrammarParserPratt {
+    /// LR tables by entry symbol, the start symbol and the operands, with the non-terminals of
+    /// the operator tables as terminals.
+    tables: HashMap<String, ParseTableLR>,
+    operators: Vec<OperatorTable>,
+    /// Rule index of the operand and of each operator, by operator table.
+    rules: Vec<(usize, Vec<usize>)>,
+}
+
+impl GrammarParserPratt {
+    /// Builds the grammar of `rules` with the [`OperatorTable::rules`] of `operators` it lacks,
+    /// added after `rules` so that their indices are kept, and its parser.
+    pub fn from_rules(
+        mut rules: Vec<GrammarRule>,
+        algorithm: ParserAlgorithm,
+        operators: Vec<OperatorTable>,
+    ) -> (Grammar, Self) {
+        for rule in operators.iter().flat_map(|t| t.rules()) {
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
+        }
+        let grammar = Grammar::from_rules(rules);
+        let parser = Self::from_grammar(&grammar, algorithm, operators);
+        (grammar, parser)
+    }
+
+    /// Builds the parser for `grammar`, which must contain the [`OperatorTable::rules`] of
+    /// `operators`, as the grammars of [`from_rules`](GrammarParserPratt::from_rules) do.
+    pub fn from_grammar(
+        grammar: &Grammar,
+        algorithm: ParserAlgorithm,
+        operators: Vec<OperatorTable>,
+    ) -> Self {
+        let rule_index = |expected: &GrammarRule| {
+            grammar
+                .get_rules()
+                .iter()
+                .position(|r| r.left == expected.left && r.right == expected.right)
+                .unwrap_or_else(|| panic!("Missing operator table rule '{expected}'"))
+        };
+        let rules = operators
+            .iter()
+            .map(|table| {
+                let indices = table.rules().iter().map(rule_index).collect::<Vec<_>>();
+                (indices[0], indices[1..].to_vec())
+            })
+            .collect();
+
+        let placeholders = operators
+            .iter()
+            .map(|t| (t.non_terminal.as_str(), t.placeholder()))
+            .collect::<HashMap<_, _>>();
+        let table_rules = || {
+            grammar.get_rules()[1..]
+                .iter()
+                .map(|r| GrammarRule {
+                    left: r.left.clone(),
+                    right: if placeholders.contains_key(r.left.as_str()) {
+                        r.right.clone()
+                    } else {
+                        r.right
+                            .iter()
+                            .map(|s| placeholders.get(s.as_str()).unwrap_or(s).clone())
+                            .collect()
+                    },
+                })
+                .collect::<Vec<_>>()
+        };
+        let start = grammar.get_start_symbol();
+        let entries = operators
+            .iter()
+            .map(|t| t.operand.clone())
+            .chain((!placeholders.contains_key(start.as_str())).then_some(start));
+        let tables = entries
+            .map(|entry| {
+                let table_grammar = Grammar::with_start_symbol(table_rules(), &entry);
+                (entry, algorithm.build_table(&table_grammar))
+            })
+            .collect();
+        Self {
+            tables,
+            operators,
+            rules,
+        }
+    }
+
+    pub fn parse_from_string(&self, grammar: &Grammar, string: Arc<String>) -> DerivationResult {
+        let tokenizer = Tokenizer::from_string(string, grammar.get_token_types());
+        self.parse(grammar, tokenizer)
+    }
+
+    pub fn parse(&self, grammar: &Grammar, mut tokenizer: Tokenizer<'_>) -> DerivationResult {
+        let lookahead = tokenizer.next_token()?;
+        let mut driver = Driver {
+            parser: self,
+            grammar,
+            tokenizer,
+            lookahead,
+            graph: Graph::new(),
+        };
+        let start = grammar.get_start_symbol();
+        let root = match self.operators.iter().position(|t| t.non_terminal == start) {
+            Some(index) => {
+                let root = driver.parse_operators(index, 0)?;
+                if driver.lookahead.token != EOF {
+                    return Err(driver.unexpected().into());
+                }
+                root
+            }
+            None => driver.parse_lr(&start, false)?,
+        };
+        Ok(Derivation {
+            graph: driver.graph,
+            root,
+        })
+    }
+}
+
+struct Driver<'a> {
+    parser: &'a GrammarParserPratt,
+    grammar: &'a Grammar,
+    tokenizer: Tokenizer<'a>,
+    lookahead: TokenInstance,
+    graph: Graph<DerivationNode, usize>,
+}
+
+impl<'a> Driver<'a> {
+    /// Node deriving `entry` from the tokens up to the lookahead. A `nested` parse, of an
+    /// operand, ends at the first token that can't continue it, as if it were the end of input.
+    fn parse_lr(&mut self, entry: &str, nested: bool) -> Result<NodeIndex, ParseError> {
+        let table = &self.parser.tables[entry];
+        let mut states: Vec<usize> = vec![0];
+        let mut nodes: Vec<NodeIndex> = vec![];
+        loop {
+            let state = *states.last().unwrap();
+            if let Some((index, next)) = self.expression_at(table, state) {
+                nodes.push(self.parse_operators(index, 0)?);
+                states.push(next);
+                continue;
+            }
+            match self.action(table, state, nested).as_ref() {
+                ParseTableAction::Shift(next) => {
+                    nodes.push(self.shift()?);
+                    states.push(*next);
+                }
+                ParseTableAction::Reduce(rule_index) => {
+                    let rule = self.grammar.get_rule(*rule_index);
+                    let num_children = if rule.right == [EPSILON] {
+                        0
+                    } else {
+                        rule.right.len()
+                    };
+                    states.truncate(states.len() - num_children);
+                    let children = nodes.split_off(nodes.len() - num_children);
+                    nodes.push(self.add_node(*rule_index, &children));
+                    match table
+                        .get_action(*states.last().unwrap(), &rule.left)
+                        .as_ref()
+                    {
+                        ParseTableAction::Goto(next) => states.push(*next),
+                        _ => return Err(self.unexpected().into()),
+                    }
+                }
+                ParseTableAction::Accept if !nodes.is_empty() => {
+                    return Ok(*nodes.last().unwrap());
+                }
+                _ => return Err(self.unexpected().into()),
+            }
+        }
+    }
+
+    /// Operator tables of the expressions the lookahead can start.
+    fn expressions(&self) -> impl Iterator<Item = (usize, &'a OperatorTable)> + '_ {
+        self.parser.operators.iter().enumerate().filter(|(_, t)| {
+            self.grammar
+                .first_of(&t.non_terminal)
+                .is_some_and(|first| first.contains(&self.lookahead.token))
+        })
+    }
+
+    /// Action on the lookahead or, if there is none, on the expressions it starts, as their
+    /// tokens aren't part of the tables, and on the end of input for a nested parse.
+    fn action<'t>(
+        &self,
+        table: &'t ParseTableLR,
+        state: usize,
+        nested: bool,
+    ) -> Cow<'t, ParseTableAction> {
+        std::iter::once(self.lookahead.token.clone())
+            .chain(self.expressions().map(|(_, t)| t.placeholder()))
+            .chain(nested.then(|| String::from(EOF)))
+            .map(|symbol| table.get_action(state, &symbol))
+            .find(|action| !matches!(action.as_ref(), ParseTableAction::Reject))
+            .unwrap_or(Cow::Owned(ParseTableAction::Reject))
+    }
+
+    /// Operator table of the expression starting at the lookahead in `state`, with the state
+    /// to go to once it is parsed.
+    fn expression_at(&self, table: &ParseTableLR, state: usize) -> Option<(usize, usize)> {
+        self.expressions().find_map(|(i, t)| {
+            match table.get_action(state, &t.placeholder()).as_ref() {
+                ParseTableAction::Shift(next) => Some((i, *next)),
+                _ => None,
+            }
+        })
+    }
+
+    /// Expression of the operator table at `index` whose operators all bind at least as
+    /// tightly as `min_power`.
+    fn parse_operators(&mut self, index: usize, min_power: u32) -> Result<NodeIndex, ParseError> {
+        let table = &self.parser.operators[index];
+        let (operand_rule, operator_rules) = &self.parser.rules[index];
+        let find = |token: &str, prefix: bool| {
+            table
+                .operators
+                .iter()
+                .position(|o| o.token == token && (o.fixity == Fixity::Prefix) == prefix)
+        };
+
+        let (mut left, mut last) = match find(&self.lookahead.token, true) {
+            Some(i) => {
+                let operator = self.shift()?;
+                let right = self.parse_operators(index, table.operators[i].binding_power().1)?;
+                let node = self.add_node(operator_rules[i], &[operator, right]);
+                (node, Some(&table.operators[i]))
+            }
+            None => {
+                let operand = self.parse_lr(&table.operand, true)?;
+                (self.add_node(*operand_rule, &[operand]), None)
+            }
+        };
+        while let Some(i) = find(&self.lookahead.token, false) {
+            let operator = &table.operators[i];
+            let (left_power, right_power) = operator.binding_power();
+            if left_power < min_power {
+                break;
+            }
+            if operator.associativity == Associativity::None
+                && last.is_some_and(|l| {
+                    l.associativity == Associativity::None
+                        && l.fixity == Fixity::Infix
+                        && l.precedence == operator.precedence
+                })
+            {
+                return Err(SyntaxError {
+                    src: self.tokenizer.source(),
+                    span: self.lookahead.span,
+                    message: format!("operator '{}' is not associative", self.lookahead.value),
+                }
+                .into());
+            }
+            let token = self.shift()?;
+            left = match operator.fixity {
+                Fixity::Postfix => self.add_node(operator_rules[i], &[left, token]),
+                _ => {
+                    let right = self.parse_operators(index, right_power)?;
+                    self.add_node(operator_rules[i], &[left, token, right])
+                }
+            };
+            last = Some(operator);
+        }
+        Ok(left)
+    }
+
+    /// Node of the lookahead, moving on to the next token.
+    fn shift(&mut self) -> Result<NodeIndex, ParseError> {
+        let token = std::mem::replace(&mut self.lookahead, self.tokenizer.next_token()?);
+        Ok(self.graph.add_node(DerivationNode::from_token(token)))
+    }
+
+    fn add_node(&mut self, rule_index: usize, children: &[NodeIndex]) -> NodeIndex {
+        let left = &self.grammar.get_rule(rule_index).left;
+        let span = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => {
+                SpanBuilder::merge(self.graph[*first].token.span, self.graph[*last].token.span)
+            }
+            _ => SpanBuilder::start(self.lookahead.span),
+        };
+        let node = self.graph.add_node(DerivationNode::from(
+            TokenInstance::from(left, left, span),
+            rule_index,
+        ));
+        for (i, child) in children.iter().enumerate() {
+            self.graph.add_edge(node, *child, i);
+        }
+        node
+    }
+
+    fn unexpected(&self) -> SyntaxError {
+        SyntaxError {
+            src: self.tokenizer.source(),
+            span: self.lookahead.span,
+            message: if self.lookahead.token == EOF {
+                String::from("unexpected end of input")
+            } else {
+                format!("unexpected token '{}'", self.lookahead.value)
+            },
+        }
+    }
+}
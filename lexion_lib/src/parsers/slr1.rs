@@ -24,4 +24,8 @@ impl GrammarParserSLR1 {
         });
         Self { collection, table }
     }
+
+    pub fn get_collection(&self) -> &CanonicalCollectionGraph<LR0Item> {
+        &self.collection
+    }
 }
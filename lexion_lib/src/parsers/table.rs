@@ -5,9 +5,9 @@ use itertools::Itertools;
 use petgraph::prelude::EdgeRef;
 use petgraph::visit::{IntoEdges, Walker};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 use std::str::FromStr;
@@ -66,9 +66,59 @@ impl FromStr for ParseTableAction {
     }
 }
 
+/// Action table of an LR parser, with a row per state and a column per symbol id.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "ParseTableData", into = "ParseTableData")]
 pub struct ParseTableLR {
-    table: HashMap<String, HashMap<usize, ParseTableAction>>,
+    symbols: Vec<String>,
+    symbol_ids: HashMap<String, usize>,
+    /// Action of each state on each symbol id, `Reject` where there is none.
+    rows: Vec<Vec<ParseTableAction>>,
+    /// Reduction of each state on the lookaheads it has no action for, `Reject` if none.
+    default_reductions: Vec<ParseTableAction>,
+    num_states: usize,
+    /// Whether [`eliminate_unit_rules`](ParseTableLR::eliminate_unit_rules) was applied.
+    unit_rules_eliminated: bool,
+}
+
+/// Serialized form of a [`ParseTableLR`], the actions of each symbol by state.
+#[derive(Serialize, Deserialize)]
+struct ParseTableData {
+    table: BTreeMap<String, BTreeMap<usize, ParseTableAction>>,
     num_states: usize,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    unit_rules_eliminated: bool,
+}
+
+impl From<ParseTableData> for ParseTableLR {
+    fn from(data: ParseTableData) -> Self {
+        let mut table = ParseTableLR::new(data.num_states);
+        table.unit_rules_eliminated = data.unit_rules_eliminated;
+        for (symbol, states) in data.table {
+            for (state, action) in states {
+                table.insert_entry(state, &symbol, action);
+            }
+        }
+        table.compute_default_reductions();
+        table
+    }
+}
+
+impl From<ParseTableLR> for ParseTableData {
+    fn from(table: ParseTableLR) -> Self {
+        let mut data = ParseTableData {
+            table: BTreeMap::new(),
+            num_states: table.num_states,
+            unit_rules_eliminated: table.unit_rules_eliminated,
+        };
+        for (state, symbol, action) in table.entries() {
+            data.table
+                .entry(symbol.to_string())
+                .or_default()
+                .insert(state, action.clone());
+        }
+        data
+    }
 }
 
 impl Display for ParseTableAction {
@@ -89,6 +139,17 @@ impl Display for ParseTableAction {
 }
 
 impl ParseTableLR {
+    fn new(num_states: usize) -> ParseTableLR {
+        ParseTableLR {
+            symbols: vec![],
+            symbol_ids: HashMap::new(),
+            rows: vec![vec![]; num_states],
+            default_reductions: vec![ParseTableAction::Reject; num_states],
+            num_states,
+            unit_rules_eliminated: false,
+        }
+    }
+
     pub fn from_collection<
         'a,
         T: Eq + Hash + LRItem + Clone + ClosurableItem<T>,
@@ -98,10 +159,7 @@ impl ParseTableLR {
         collection: &CanonicalCollectionGraph<T>,
         reduce_terminal_set_fn: F,
     ) -> ParseTableLR {
-        let mut table = ParseTableLR {
-            table: HashMap::new(),
-            num_states: collection.node_count(),
-        };
+        let mut table = ParseTableLR::new(collection.node_count());
         for index in collection.node_indices() {
             let state = &collection[index];
             if state.is_accept(grammar) {
@@ -135,6 +193,7 @@ impl ParseTableLR {
                 );
             }
         }
+        table.compute_default_reductions();
         table
     }
 }
@@ -148,52 +207,187 @@ impl ParseTableLR {
         for o in overrides {
             self.insert_entry(o.state, o.symbol, o.action.clone());
         }
+        self.compute_default_reductions();
     }
 
+    /// Sets the action of a state on `symbol`, or adds it to a conflict. Call
+    /// [`compute_default_reductions`](ParseTableLR::compute_default_reductions) once done
+    /// inserting entries.
     pub fn insert_entry(&mut self, state_index: usize, symbol: &str, action: ParseTableAction) {
-        match self.table.get_mut(symbol) {
-            Some(v) => {
-                if let Some(old_action) = v.remove(&state_index) {
-                    if let ParseTableAction::Conflict(mut actions) = old_action {
-                        actions.push(action);
-                        v.insert(state_index, ParseTableAction::Conflict(actions));
-                    } else {
-                        let new_action = ParseTableAction::Conflict(vec![old_action, action]);
-                        println!("{state_index} {symbol} -> {new_action}");
-                        v.insert(state_index, new_action);
-                    }
-                } else {
-                    v.insert(state_index, action);
-                }
-            }
+        let symbol_id = match self.symbol_ids.get(symbol) {
+            Some(id) => *id,
             None => {
-                self.table
-                    .insert(String::from(symbol), HashMap::from([(state_index, action)]));
+                self.symbols.push(String::from(symbol));
+                self.symbol_ids
+                    .insert(String::from(symbol), self.symbols.len() - 1);
+                self.symbols.len() - 1
             }
         };
+        if state_index >= self.rows.len() {
+            self.rows.resize(state_index + 1, vec![]);
+        }
+        let row = &mut self.rows[state_index];
+        if row.len() <= symbol_id {
+            row.resize(symbol_id + 1, ParseTableAction::Reject);
+        }
+        row[symbol_id] = match std::mem::replace(&mut row[symbol_id], ParseTableAction::Reject) {
+            ParseTableAction::Reject => action,
+            ParseTableAction::Conflict(mut actions) => {
+                actions.push(action);
+                ParseTableAction::Conflict(actions)
+            }
+            old_action => ParseTableAction::Conflict(vec![old_action, action]),
+        };
     }
 
-    pub fn entries(&self) -> impl Iterator<Item = (usize, &str, &ParseTableAction)> {
-        self.table
+    /// Makes the most frequent reduction of each state without conflicts its default, so
+    /// [`action`](ParseTableLR::action) reduces instead of rejecting a lookahead it has no
+    /// action for. The error is then detected after the reductions, on the same token.
+    pub fn compute_default_reductions(&mut self) {
+        self.default_reductions = self
+            .rows
             .iter()
-            .flat_map(|(symbol, states)| states.iter().map(move |(s, a)| (*s, symbol.as_str(), a)))
+            .map(|row| {
+                let mut counts: BTreeMap<usize, usize> = BTreeMap::new();
+                for action in row.iter() {
+                    match action {
+                        ParseTableAction::Reduce(rule) => *counts.entry(*rule).or_default() += 1,
+                        ParseTableAction::Conflict(_) => return ParseTableAction::Reject,
+                        _ => {}
+                    }
+                }
+                counts
+                    .into_iter()
+                    .max_by_key(|(rule, count)| (*count, std::cmp::Reverse(*rule)))
+                    .map_or(ParseTableAction::Reject, |(rule, _)| {
+                        ParseTableAction::Reduce(rule)
+                    })
+            })
+            .collect();
     }
 
-    pub fn get_action(&self, state_index: usize, symbol: &str) -> Cow<ParseTableAction> {
-        match self.table.get(symbol) {
-            Some(v) => match v.get(&state_index) {
-                Some(ParseTableAction::Conflict(actions)) => {
-                    Cow::Borrowed(actions.last().expect("conflict action with empty list"))
+    /// Redirects the gotos into states whose only action is reducing a unit rule `A -> B`,
+    /// such as `AddExpr -> MulExpr`, to the goto on `A`, transitively. Parsing then skips these
+    /// reductions and their nodes: in the derivation, the `B` node stands where an `A` node
+    /// would be. Returns the number of redirected gotos.
+    ///
+    /// Code matching derivation nodes on their `rule_index`, such as the reductions and AST
+    /// generated by `lexion_derive` or rule actions of
+    /// [`SemanticActions`](crate::parsers::SemanticActions), finds a `B` node where it expects
+    /// an `A` node, so it cannot be used with such a table: see
+    /// [`unit_rules_eliminated`](ParseTableLR::unit_rules_eliminated).
+    pub fn eliminate_unit_rules(&mut self, grammar: &Grammar) -> usize {
+        self.unit_rules_eliminated = true;
+        let unit_states = (0..self.num_states)
+            .filter_map(|state| {
+                let mut actions = self.rows[state]
+                    .iter()
+                    .filter(|a| !matches!(a, ParseTableAction::Reject));
+                let ParseTableAction::Reduce(rule_index) = actions.next()? else {
+                    return None;
+                };
+                let rule = grammar.get_rule(*rule_index);
+                let unit = rule.right.len() == 1 && Grammar::is_non_terminal(&rule.right[0]);
+                (unit
+                    && actions.all(|a| matches!(a, ParseTableAction::Reduce(r) if r == rule_index)))
+                .then_some((state, rule.left.as_str()))
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut redirected = 0;
+        for state in 0..self.num_states {
+            for symbol_id in 0..self.rows[state].len() {
+                let ParseTableAction::Goto(mut target) = self.rows[state][symbol_id] else {
+                    continue;
+                };
+                let mut visited = HashSet::from([target]);
+                while let Some(left) = unit_states.get(&target) {
+                    match self.get_action(state, left).as_ref() {
+                        ParseTableAction::Goto(next) if visited.insert(*next) => target = *next,
+                        _ => break,
+                    }
+                }
+                if !matches!(self.rows[state][symbol_id], ParseTableAction::Goto(t) if t == target)
+                {
+                    self.rows[state][symbol_id] = ParseTableAction::Goto(target);
+                    redirected += 1;
                 }
-                Some(a) => Cow::Borrowed(a),
-                None => Cow::Owned(ParseTableAction::Reject),
-            },
+            }
+        }
+        redirected
+    }
+
+    /// Whether derivations parsed with this table lack the nodes of unit rules.
+    pub fn unit_rules_eliminated(&self) -> bool {
+        self.unit_rules_eliminated
+    }
+
+    pub fn num_states(&self) -> usize {
+        self.num_states
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = (usize, &str, &ParseTableAction)> {
+        self.rows.iter().enumerate().flat_map(move |(state, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, a)| !matches!(a, ParseTableAction::Reject))
+                .map(move |(id, a)| (state, self.symbols[id].as_str(), a))
+        })
+    }
+
+    /// Id of the column of `symbol`, or `None` if no state has an action on it.
+    pub fn symbol_id(&self, symbol: &str) -> Option<usize> {
+        self.symbol_ids.get(symbol).copied()
+    }
+
+    /// Whether `symbol` has an action of its own in a state, without its default reduction.
+    pub fn expects(&self, state_index: usize, symbol: &str) -> bool {
+        !matches!(
+            self.get_action(state_index, symbol).as_ref(),
+            ParseTableAction::Reject
+        )
+    }
+
+    pub fn default_reduction(&self, state_index: usize) -> Option<usize> {
+        match self.default_reductions.get(state_index) {
+            Some(ParseTableAction::Reduce(rule)) => Some(*rule),
+            _ => None,
+        }
+    }
+
+    /// Action of the parser in a state on the symbol with id `symbol_id`, with conflicts
+    /// resolved like [`get_action`](ParseTableLR::get_action), falling back on the default
+    /// reduction of the state.
+    pub fn action(&self, state_index: usize, symbol_id: Option<usize>) -> &ParseTableAction {
+        const REJECT: &ParseTableAction = &ParseTableAction::Reject;
+        let action = symbol_id
+            .and_then(|id| self.rows.get(state_index)?.get(id))
+            .unwrap_or(REJECT);
+        match action {
+            ParseTableAction::Conflict(actions) => {
+                actions.last().expect("conflict action with empty list")
+            }
+            ParseTableAction::Reject => self.default_reductions.get(state_index).unwrap_or(REJECT),
+            action => action,
+        }
+    }
+
+    /// Action of the parser in a state on `symbol`, as listed in the table.
+    pub fn get_action(&self, state_index: usize, symbol: &str) -> Cow<'_, ParseTableAction> {
+        let action = self
+            .symbol_id(symbol)
+            .and_then(|id| self.rows.get(state_index)?.get(id));
+        match action {
+            Some(ParseTableAction::Conflict(actions)) => {
+                Cow::Borrowed(actions.last().expect("conflict action with empty list"))
+            }
+            Some(a) => Cow::Borrowed(a),
             None => Cow::Owned(ParseTableAction::Reject),
         }
     }
 
     pub fn to_table(&self) -> Table {
-        let mut symbols: Vec<String> = self.table.keys().cloned().collect();
+        let mut symbols: Vec<String> = self.symbols.clone();
         symbols.sort_by(|a, b| {
             let rank = |s: &str| {
                 if s == EOF {
@@ -1,20 +1,87 @@
-use crate::grammar::{Derivation, Grammar, GrammarRule};
-use crate::parsers::{GrammarParserLALR1, GrammarParserLR, GrammarParserSLR1, ParseTableAction};
+use crate::error::{ArtifactError, ParseError};
+use crate::grammar::{Derivation, DerivationDispatch, DerivationVisitor, Grammar, GrammarRule};
+use crate::parsers::{
+    Associativity, EarleyParse, GrammarParserEarley, GrammarParserLALR1, GrammarParserLL1,
+    GrammarParserLR, GrammarParserPratt, GrammarParserSLR1, IncrementalParse, OperatorTable,
+    ParseEventKind, ParseTableAction, ParseTableArtifact, ParseTableExport, ParseTableOverride,
+    ParserAlgorithm, SemanticActions, TextEdit,
+};
 use crate::tokenizer::tokens::*;
-use petgraph::visit::Dfs;
+use crate::tokenizer::{TokenInstance, Tokenizer};
+use petgraph::graph::NodeIndex;
 use std::sync::Arc;
 use tabled::builder::Builder;
 use tabled::settings::{Alignment, Style};
 
 fn leaf_values(derivation: &Derivation) -> Vec<String> {
-    let mut dfs = Dfs::new(&derivation.graph, derivation.root);
-    let mut leaves = vec![];
-    while let Some(n) = dfs.next(&derivation.graph) {
-        if derivation.graph.neighbors(n).count() == 0 {
-            leaves.push(derivation.graph.node_weight(n).unwrap().token.value.clone());
-        }
+    derivation
+        .pre_order(derivation.root)
+        .filter(|n| derivation.children(*n).is_empty())
+        .map(|n| derivation[n].token.value.clone())
+        .collect()
+}
+
+/// Pre-order dump of a derivation with children in order, independent of node indices.
+fn derivation_canonical_string(derivation: &Derivation) -> String {
+    let mut lines = vec![];
+    let mut stack = vec![(derivation.root, 0)];
+    while let Some((id, depth)) = stack.pop() {
+        let node = &derivation[id];
+        lines.push(format!(
+            "{}{} `{}` {}+{} r{} q{}",
+            "  ".repeat(depth),
+            node.token.token,
+            node.token.value,
+            node.token.span.offset(),
+            node.token.span.len(),
+            node.rule_index,
+            node.state
+        ));
+        stack.extend(
+            derivation
+                .children(id)
+                .into_iter()
+                .rev()
+                .map(|c| (c, depth + 1)),
+        );
     }
-    leaves
+    lines.join("\n")
+}
+
+/// Applies `edit` to `source`, reparses incrementally and checks the result against a full
+/// parse, and that the subtrees copied from the previous derivation are those with the text of
+/// `reused`, in order.
+fn assert_reparse_matches(
+    grammar: &Grammar,
+    parser: &impl GrammarParserLR,
+    source: &str,
+    edit: TextEdit,
+    reused: &[&str],
+) {
+    let previous = parser
+        .parse_from_string(grammar, Arc::new(source.into()))
+        .unwrap();
+    let edited = Arc::new(edit.apply(source));
+    let full = parser.parse_from_string(grammar, edited.clone()).unwrap();
+    let (incremental, reused_nodes) = IncrementalParse::new(
+        grammar,
+        parser.get_parse_table(),
+        &previous,
+        edited.clone(),
+        &edit,
+    )
+    .unwrap()
+    .parse_reused()
+    .unwrap();
+    assert_eq!(
+        derivation_canonical_string(&incremental),
+        derivation_canonical_string(&full)
+    );
+    let reused_text = reused_nodes
+        .iter()
+        .map(|node| incremental.text(&edited, *node))
+        .collect::<Vec<_>>();
+    assert_eq!(reused_text, reused);
 }
 
 fn root_symbol<'a>(derivation: &'a Derivation, grammar: &'a Grammar) -> &'a str {
@@ -58,6 +125,177 @@ fn trivial_grammar() -> Grammar {
     }])
 }
 
+/// E -> T E'
+/// E' -> '+' T E' | ε
+/// T -> 'num'
+fn right_recursive_grammar() -> Grammar {
+    Grammar::from_rules(vec![
+        GrammarRule {
+            left: "E".into(),
+            right: vec!["T".into(), "E'".into()],
+        },
+        GrammarRule {
+            left: "E'".into(),
+            right: vec!["'+'".into(), "T".into(), "E'".into()],
+        },
+        GrammarRule {
+            left: "E'".into(),
+            right: vec![EPSILON.into()],
+        },
+        GrammarRule {
+            left: "T".into(),
+            right: vec!["'num'".into()],
+        },
+        GrammarRule {
+            left: "'num'".into(),
+            right: vec![r"\d+".into()],
+        },
+    ])
+}
+
+/// E -> E '+' T | T
+/// T -> T '*' F | F
+/// F -> 'num' | '(' E ')'
+fn precedence_grammar() -> Grammar {
+    let rule = |left: &str, right: &[&str]| GrammarRule {
+        left: left.into(),
+        right: right.iter().map(|s| s.to_string()).collect(),
+    };
+    Grammar::from_rules(vec![
+        rule("E", &["E", "'+'", "T"]),
+        rule("E", &["T"]),
+        rule("T", &["T", "'*'", "F"]),
+        rule("T", &["F"]),
+        rule("F", &["'num'"]),
+        rule("F", &["'('", "E", "')'"]),
+        rule("'num'", &[r"\d+"]),
+    ])
+}
+
+/// Stmts -> Stmt Stmts | ε
+/// Stmt -> 'let' 'ident' '=' Expr ';' | 'struct' 'ident' '{' '}' | Expr ';'
+/// Expr -> 'ident' | 'num'
+///
+/// where `let` and `struct` are keywords of `'ident'`.
+fn keyword_grammar() -> Grammar {
+    let rule = |left: &str, right: &[&str]| GrammarRule {
+        left: left.into(),
+        right: right.iter().map(|s| s.to_string()).collect(),
+    };
+    Grammar::from_rules(vec![
+        rule("Stmts", &["Stmt", "Stmts"]),
+        rule("Stmts", &[EPSILON]),
+        rule("Stmt", &["'let'", "'ident'", "'='", "Expr", "';'"]),
+        rule("Stmt", &["'struct'", "'ident'", "'{'", "'}'"]),
+        rule("Stmt", &["Expr", "';'"]),
+        rule("Expr", &["'ident'"]),
+        rule("Expr", &["'num'"]),
+        rule("'num'", &[r"\d+"]),
+        rule("'ident'", &[r"[a-z_][a-z0-9_]*"]),
+        rule("'let'", &["'ident'"]),
+        rule("'struct'", &["'ident'"]),
+    ])
+}
+
+/// Stmts -> Stmt Stmts | ε
+/// Stmt -> Expr ';'
+/// Atom -> 'num' | '(' Expr ')'
+/// with `Expr` over `Atom` from [`operator_table`]
+fn operator_grammar() -> Grammar {
+    let mut rules = operator_rules();
+    rules.splice(5..5, operator_table().rules());
+    Grammar::from_rules(rules)
+}
+
+/// Rules of [`operator_grammar`] without those of [`operator_table`].
+fn operator_rules() -> Vec<GrammarRule> {
+    let rule = |left: &str, right: &[&str]| GrammarRule {
+        left: left.into(),
+        right: right.iter().map(|s| s.to_string()).collect(),
+    };
+    vec![
+        rule("Stmts", &["Stmt", "Stmts"]),
+        rule("Stmts", &[EPSILON]),
+        rule("Stmt", &["Expr", "';'"]),
+        rule("Atom", &["'num'"]),
+        rule("Atom", &["'('", "Expr", "')'"]),
+        rule("'num'", &[r"\d+"]),
+    ]
+}
+
+fn operator_table() -> OperatorTable {
+    OperatorTable::new("Expr", "Atom")
+        .infix("'=='", 0, Associativity::None)
+        .infix("'+'", 1, Associativity::Left)
+        .infix("'-'", 1, Associativity::Left)
+        .infix("'*'", 2, Associativity::Left)
+        .infix("'^'", 3, Associativity::Right)
+        .prefix("'-'", 4)
+        .postfix("'!'", 5)
+}
+
+/// Statements of `source` parsed with [`operator_grammar`], as s-expressions.
+fn operator_parse(algorithm: ParserAlgorithm, source: &str) -> Result<String, ParseError> {
+    let (grammar, parser) =
+        GrammarParserPratt::from_rules(operator_rules(), algorithm, vec![operator_table()]);
+    let derivation = parser.parse_from_string(&grammar, Arc::new(source.into()))?;
+    let mut actions = SemanticActions::new(|token| token.value.clone())
+        .on_rule(1, |v| {
+            [v[0].as_str(), v[1].as_str()].join(" ").trim().into()
+        })
+        .on_rule(5, |v| v[1].clone());
+    for (i, rule) in grammar.get_rules().iter().enumerate().skip(7) {
+        actions = match rule.right.len() {
+            2 if rule.right[0] == "Expr" => actions.on_rule(i, |v| format!("({} {})", v[1], v[0])),
+            2 => actions.on_rule(i, |v| format!("({} {})", v[0], v[1])),
+            _ => actions.on_rule(i, |v| format!("({} {} {})", v[1], v[0], v[2])),
+        };
+    }
+    Ok(actions.evaluate(&grammar, &derivation))
+}
+
+/// E -> E '+' E | 'num', ambiguous on chained sums
+fn sum_grammar() -> Grammar {
+    Grammar::from_rules(vec![
+        GrammarRule {
+            left: "E".into(),
+            right: vec!["E".into(), "'+'".into(), "E".into()],
+        },
+        GrammarRule {
+            left: "E".into(),
+            right: vec!["'num'".into()],
+        },
+        GrammarRule {
+            left: "'num'".into(),
+            right: vec![r"\d+".into()],
+        },
+    ])
+}
+
+/// S -> 'a' S 'a' | 'b' S 'b' | ε
+fn palindrome_grammar() -> Grammar {
+    Grammar::from_rules(vec![
+        GrammarRule {
+            left: "S".into(),
+            right: vec!["'a'".into(), "S".into(), "'a'".into()],
+        },
+        GrammarRule {
+            left: "S".into(),
+            right: vec!["'b'".into(), "S".into(), "'b'".into()],
+        },
+        GrammarRule {
+            left: "S".into(),
+            right: vec![EPSILON.into()],
+        },
+    ])
+}
+
+fn earley_parse(grammar: &Grammar, source: &str) -> Result<EarleyParse, ParseError> {
+    let parser = GrammarParserEarley::from_grammar(grammar);
+    let tokenizer = Tokenizer::from_string(Arc::new(source.into()), grammar.get_token_types());
+    parser.parse(grammar, tokenizer)
+}
+
 // --- Table test helpers ---
 
 /// Canonicalize state numbers by BFS through shift/goto edges from state 0.
@@ -208,6 +446,88 @@ fn test_slr1_rejects_invalid_input() {
     assert!(result.is_err());
 }
 
+#[test]
+fn test_table_default_reductions() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserSLR1::from_grammar(&grammar);
+    let table = &parser.table;
+    let (state, _, _) = table
+        .entries()
+        .find(|(_, _, a)| matches!(a, ParseTableAction::Reduce(3)))
+        .unwrap();
+    assert_eq!(table.default_reduction(state), Some(3));
+    assert!(matches!(
+        table.get_action(state, "')'").as_ref(),
+        ParseTableAction::Reject
+    ));
+    let id = table.symbol_id("'+'");
+    assert!(matches!(
+        table.action(state, id),
+        ParseTableAction::Reduce(3)
+    ));
+    assert!(matches!(
+        table.action(state, None),
+        ParseTableAction::Reduce(3)
+    ));
+    assert_eq!(table.default_reduction(0), None);
+
+    // The error is found on the same token after the default reductions
+    let result = parser.parse_from_string(&grammar, Arc::new("1 + 2 3".into()));
+    let Err(ParseError::Syntax(err)) = result else {
+        panic!("expected a syntax error");
+    };
+    assert_eq!(err.span, (6, 1).into());
+
+    // An override that adds a conflict to the state removes its default reduction
+    let mut table = parser.table.clone();
+    let overrides = [ParseTableOverride {
+        state,
+        symbol: "'+'",
+        action: ParseTableAction::Shift(0),
+    }];
+    table.apply_conflict_resolutions(overrides.iter());
+    assert_eq!(table.default_reduction(state), None);
+}
+
+#[test]
+fn test_table_eliminate_unit_rules() {
+    let grammar = precedence_grammar();
+    let mut parser = GrammarParserSLR1::from_grammar(&grammar);
+    let source = Arc::new(String::from("1 + 2 * (3 + 4)"));
+    let full = parser.parse_from_string(&grammar, source.clone()).unwrap();
+    assert!(parser.table.eliminate_unit_rules(&grammar) > 0);
+    let reduced = parser.parse_from_string(&grammar, source.clone()).unwrap();
+
+    assert_eq!(leaf_values(&reduced), leaf_values(&full));
+    let count = |derivation: &Derivation, rule: usize| {
+        derivation
+            .pre_order(derivation.root)
+            .filter(|n| !derivation.is_token(*n) && derivation[*n].rule_index == rule)
+            .count()
+    };
+    assert_eq!((count(&full, 2), count(&full, 4)), (2, 4));
+    // `E -> T` is kept, as the state after `T` can also shift '*'
+    assert_eq!((count(&reduced, 2), count(&reduced, 4)), (2, 0));
+    assert_eq!(reduced.graph.node_count() + 4, full.graph.node_count());
+
+    let actions = SemanticActions::new(|token| token.value.parse::<i64>().unwrap_or_default())
+        .on_rule(1, |v| v[0] + v[2])
+        .on_rule(3, |v| v[0] * v[2])
+        .on_rule(6, |v| v[1]);
+    assert_eq!(actions.evaluate(&grammar, &full), 15);
+    assert_eq!(actions.evaluate(&grammar, &reduced), 15);
+    assert!(!actions.acts_on_unit_rules(&grammar));
+    assert!(parser.table.unit_rules_eliminated());
+    let tokenizer = Tokenizer::from_string(source, grammar.get_token_types());
+    assert_eq!(
+        parser.parse_with(&grammar, tokenizer, &actions).unwrap(),
+        15
+    );
+    assert!(parser
+        .parse_from_string(&grammar, Arc::new("1 + * 2".into()))
+        .is_err());
+}
+
 #[test]
 fn test_slr1_rejects_empty_input() {
     let grammar = simple_grammar();
@@ -291,6 +611,20 @@ fn test_lalr1_trivial_grammar() {
         .is_err());
 }
 
+#[test]
+fn test_lalr1_epsilon_rules() {
+    let grammar = right_recursive_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    assert!(!has_conflicts(parser.get_parse_table()));
+    let derivation = parser
+        .parse_from_string(&grammar, Arc::new("1 + 2".into()))
+        .unwrap();
+    assert_eq!(leaf_values(&derivation), vec!["1", "+", "2", "E'"]);
+    assert!(parser
+        .parse_from_string(&grammar, Arc::new("1".into()))
+        .is_ok());
+}
+
 // --- Derivation tree ---
 
 #[test]
@@ -319,6 +653,113 @@ fn test_derivation_addition_tree_shape() {
     assert_eq!(leaf_values(&derivation), vec!["1", "+", "2"]);
 }
 
+#[test]
+fn test_derivation_children_and_parent() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    let source = "1 + 23";
+    let derivation = parser
+        .parse_from_string(&grammar, Arc::new(source.into()))
+        .unwrap();
+
+    let children = derivation.children(derivation.root);
+    let symbols = children
+        .iter()
+        .map(|c| derivation[*c].token.token.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(symbols, vec!["E", "'+'", "T"]);
+    assert!(children
+        .iter()
+        .all(|c| derivation.parent(*c) == Some(derivation.root)));
+    assert_eq!(derivation.parent(derivation.root), None);
+
+    assert_eq!(
+        derivation.rule(&grammar, derivation.root).unwrap().left,
+        "E"
+    );
+    assert!(derivation.rule(&grammar, children[1]).is_none());
+    assert_eq!(derivation.text(source, derivation.root), "1 + 23");
+    assert_eq!(derivation.text(source, children[2]), "23");
+}
+
+#[test]
+fn test_derivation_pre_and_post_order() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    let derivation = parser
+        .parse_from_string(&grammar, Arc::new("1 + 2".into()))
+        .unwrap();
+    let symbols = |nodes: Vec<NodeIndex>| {
+        nodes
+            .into_iter()
+            .map(|n| derivation[n].token.value.clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        symbols(derivation.pre_order(derivation.root).collect()),
+        vec!["E", "E", "T", "1", "+", "T", "2"]
+    );
+    assert_eq!(
+        symbols(derivation.post_order(derivation.root).collect()),
+        vec!["1", "T", "E", "+", "2", "T", "E"]
+    );
+}
+
+#[test]
+fn test_derivation_visitor() {
+    #[derive(Default)]
+    struct Visitor {
+        events: Vec<String>,
+    }
+
+    impl DerivationVisitor for Visitor {
+        fn enter(&mut self, _: &Derivation, _: NodeIndex, rule: &GrammarRule) -> bool {
+            self.events.push(format!("<{}>", rule.left));
+            rule.left != "T"
+        }
+
+        fn exit(&mut self, _: &Derivation, _: NodeIndex, rule: &GrammarRule) {
+            self.events.push(format!("</{}>", rule.left));
+        }
+
+        fn token(&mut self, _: &Derivation, _: NodeIndex, token: &TokenInstance) {
+            self.events.push(token.value.clone());
+        }
+    }
+
+    let grammar = simple_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    let derivation = parser
+        .parse_from_string(&grammar, Arc::new("1 + 2".into()))
+        .unwrap();
+    let mut visitor = Visitor::default();
+    derivation.walk(&grammar, &mut visitor);
+    assert_eq!(visitor.events.join(" "), "<E> <E> <T> </E> + <T> </E>");
+}
+
+#[test]
+fn test_derivation_dispatch() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    let derivation = parser
+        .parse_from_string(&grammar, Arc::new("1 + 2 + 3".into()))
+        .unwrap();
+    let dispatch = DerivationDispatch::<Vec<String>>::new()
+        .on_enter("E", |events, _, _| {
+            events.push(String::from("<E>"));
+            true
+        })
+        .on_exit("E", |events, _, _| events.push(String::from("</E>")))
+        .on_enter("T", |_, derivation, node| {
+            derivation.children(node).len() > 1
+        })
+        .on_token("'+'", |events, _, token| events.push(token.value.clone()));
+    let mut events = vec![];
+    dispatch.walk(&grammar, &derivation, &mut events);
+    assert_eq!(events.join(" "), "<E> <E> <E> </E> + </E> + </E>");
+}
+
 // --- Table structure tests ---
 
 #[test]
@@ -359,3 +800,581 @@ fn test_lalr1_table_snapshot_trivial_grammar() {
     let parser = GrammarParserLALR1::from_grammar(&grammar);
     insta::assert_snapshot!(table_canonical_string(parser.get_parse_table()));
 }
+
+// --- Incremental reparsing ---
+
+#[test]
+fn test_text_edit_apply() {
+    let edit = TextEdit::new(4..5, "42");
+    assert_eq!(edit.apply("1 + 2 + 3"), "1 + 42 + 3");
+    assert_eq!(edit.delta(), 1);
+}
+
+#[test]
+fn test_reparse_replace_token() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    assert_reparse_matches(
+        &grammar,
+        &parser,
+        "1 + 2 + 3 + 4",
+        TextEdit::new(4..5, "42"),
+        &["3", "4"],
+    );
+}
+
+#[test]
+fn test_reparse_insert_and_delete() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserSLR1::from_grammar(&grammar);
+    let source = "1 + 2 + 3 + 4";
+    assert_reparse_matches(
+        &grammar,
+        &parser,
+        source,
+        TextEdit::new(0..0, "7 + "),
+        &["2", "3", "4"],
+    );
+    assert_reparse_matches(
+        &grammar,
+        &parser,
+        source,
+        TextEdit::new(13..13, " + 5"),
+        &["1 + 2"],
+    );
+    assert_reparse_matches(
+        &grammar,
+        &parser,
+        source,
+        TextEdit::new(0..4, ""),
+        &["3", "4"],
+    );
+    assert_reparse_matches(&grammar, &parser, source, TextEdit::new(5..9, ""), &["4"]);
+}
+
+#[test]
+fn test_reparse_merges_adjacent_tokens() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    // `1` is followed by the token looked back from the edit, so no subtree is reused
+    assert_reparse_matches(&grammar, &parser, "1 + 2 + 3", TextEdit::new(5..8, ""), &[]);
+    assert_reparse_matches(
+        &grammar,
+        &parser,
+        "1 + 2 + 3",
+        TextEdit::new(9..9, "9"),
+        &["1"],
+    );
+}
+
+#[test]
+fn test_reparse_whitespace_edit() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    assert_reparse_matches(
+        &grammar,
+        &parser,
+        "1 + 2 + 3",
+        TextEdit::new(5..6, "   "),
+        &["3"],
+    );
+}
+
+#[test]
+fn test_reparse_contextual_keywords() {
+    let grammar = keyword_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    let source = "let a = 1; struct b {} let c = a;";
+    assert_reparse_matches(
+        &grammar,
+        &parser,
+        source,
+        TextEdit::new(31..32, "let"),
+        &["let a = 1;", "struct b {}"],
+    );
+    assert_reparse_matches(
+        &grammar,
+        &parser,
+        source,
+        TextEdit::new(4..5, "struct"),
+        &["1", "struct b {} let c = a;"],
+    );
+    assert_reparse_matches(
+        &grammar,
+        &parser,
+        source,
+        TextEdit::new(18..19, "let"),
+        &["1", "let c = a;"],
+    );
+}
+
+#[test]
+fn test_reparse_rejects_invalid_edit() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    let source = "1 + 2 + 3";
+    let previous = parser
+        .parse_from_string(&grammar, Arc::new(source.into()))
+        .unwrap();
+    let edit = TextEdit::new(4..5, "+");
+    let result = parser.reparse(&grammar, &previous, Arc::new(edit.apply(source)), &edit);
+    assert!(result.is_err());
+}
+
+// --- Contextual keywords ---
+
+#[test]
+fn test_contextual_keywords() {
+    let grammar = keyword_grammar();
+    let source = Arc::new(String::from(
+        "struct struct {} let let = struct; let x = let; x;",
+    ));
+    for algorithm in [ParserAlgorithm::SLR1, ParserAlgorithm::LALR1] {
+        let artifact = ParseTableArtifact::from_grammar(keyword_grammar(), algorithm);
+        let derivation = artifact
+            .parse_from_string(&grammar, source.clone())
+            .unwrap();
+        let tokens = derivation
+            .tokens(derivation.root)
+            .map(|t| t.token.as_str())
+            .collect::<Vec<_>>();
+        let expected = [
+            "'struct'", "'ident'", "'{'", "'}'", "'let'", "'ident'", "'='", "'ident'", "';'",
+            "'let'", "'ident'", "'='", "'ident'", "';'", "'ident'", "';'",
+        ];
+        assert_eq!(tokens, expected);
+    }
+
+    // Outside of an LR parse, keywords are reserved
+    let mut tokenizer = Tokenizer::from_string(source, grammar.get_token_types());
+    assert_eq!(tokenizer.next_token().unwrap().token, "'struct'");
+    assert_eq!(tokenizer.next_token().unwrap().token, "'struct'");
+
+    // A keyword where neither it nor its host is expected is reported as is
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    let result = parser.parse_from_string(&grammar, Arc::new("let x = 1 struct".into()));
+    let Err(ParseError::Syntax(err)) = result else {
+        panic!("expected a syntax error");
+    };
+    assert_eq!(err.span, (10, 6).into());
+}
+
+// --- Parse table artifacts ---
+
+#[test]
+fn test_artifact_round_trip() {
+    let artifact = ParseTableArtifact::from_grammar(simple_grammar(), ParserAlgorithm::LALR1);
+    let mut buffer = vec![];
+    artifact.to_writer(&mut buffer).unwrap();
+    let loaded = ParseTableArtifact::from_reader(buffer.as_slice()).unwrap();
+
+    assert_eq!(loaded.algorithm, ParserAlgorithm::LALR1);
+    assert!(loaded.grammar.get_rules() == artifact.grammar.get_rules());
+    assert_eq!(
+        table_canonical_string(&loaded.table),
+        table_canonical_string(&artifact.table)
+    );
+
+    let source = Arc::new(String::from("1 + 2 + 3"));
+    let expected = artifact
+        .parse_from_string(&artifact.grammar, source.clone())
+        .unwrap();
+    let derivation = loaded.parse_from_string(&loaded.grammar, source).unwrap();
+    assert_eq!(
+        derivation_canonical_string(&derivation),
+        derivation_canonical_string(&expected)
+    );
+}
+
+#[test]
+fn test_artifact_is_deterministic() {
+    let serialize = || {
+        let mut buffer = vec![];
+        ParseTableArtifact::from_grammar(simple_grammar(), ParserAlgorithm::SLR1)
+            .to_writer(&mut buffer)
+            .unwrap();
+        buffer
+    };
+    assert_eq!(serialize(), serialize());
+}
+
+#[test]
+fn test_artifact_rejects_unknown_version() {
+    let artifact = ParseTableArtifact::from_grammar(trivial_grammar(), ParserAlgorithm::SLR1);
+    let mut value = serde_json::to_value(&artifact).unwrap();
+    value["version"] = 99.into();
+    let result = ParseTableArtifact::from_reader(value.to_string().as_bytes());
+    assert!(matches!(
+        result,
+        Err(ArtifactError::Version { found: 99, .. })
+    ));
+
+    value["format"] = "something-else".into();
+    let result = ParseTableArtifact::from_reader(value.to_string().as_bytes());
+    assert!(matches!(result, Err(ArtifactError::Format(_))));
+}
+
+// --- Exporters ---
+
+/// S -> 'a' S | 'a' | ε
+fn ambiguous_grammar() -> Grammar {
+    Grammar::from_rules(vec![
+        GrammarRule {
+            left: "S".into(),
+            right: vec!["'a'".into(), "S".into()],
+        },
+        GrammarRule {
+            left: "S".into(),
+            right: vec!["'a'".into()],
+        },
+        GrammarRule {
+            left: "S".into(),
+            right: vec![EPSILON.into()],
+        },
+    ])
+}
+
+#[test]
+fn test_export_bison_output_snapshot_simple_grammar() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    let export = ParseTableExport::new(&grammar, parser.get_collection(), parser.get_parse_table());
+    insta::assert_snapshot!(export.to_bison_output());
+}
+
+#[test]
+fn test_export_bison_output_reports_conflicts() {
+    let grammar = ambiguous_grammar();
+    let parser = GrammarParserSLR1::from_grammar(&grammar);
+    let export = ParseTableExport::new(&grammar, parser.get_collection(), parser.get_parse_table());
+    let output = export.to_bison_output();
+    assert!(output.contains("conflicts: 1 reduce/reduce"));
+    assert!(output.contains("    $  reduce using rule 3 (S)\n    $  [reduce using rule 2 (S)]"));
+}
+
+#[test]
+fn test_export_dot_snapshot_simple_grammar() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserSLR1::from_grammar(&grammar);
+    let export = ParseTableExport::new(&grammar, parser.get_collection(), parser.get_parse_table());
+    insta::assert_snapshot!(export.to_dot());
+}
+
+#[test]
+fn test_export_html_contains_states() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserSLR1::from_grammar(&grammar);
+    let export = ParseTableExport::new(&grammar, parser.get_collection(), parser.get_parse_table());
+    let html = export.to_html();
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(!html.contains("/*DATA*/"));
+    assert!(html.contains(r#""text":"E: E • '+' T""#));
+    assert_eq!(
+        html.matches(r#""index":"#).count(),
+        parser.get_collection().node_count()
+    );
+}
+
+// --- Parse traces ---
+
+#[test]
+fn test_trace_events_addition() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserSLR1::from_grammar(&grammar);
+    let tokenizer = Tokenizer::from_string(Arc::new("1 + 2".into()), grammar.get_token_types());
+    let trace = parser.record(&grammar, tokenizer);
+
+    let kinds = trace
+        .events()
+        .map(|e| match e.kind {
+            ParseEventKind::Shift(_) => "shift".to_string(),
+            ParseEventKind::Reduce(r) => format!("reduce {r}"),
+            ParseEventKind::Goto(_) => "goto".to_string(),
+            ParseEventKind::Accept => "accept".to_string(),
+            ParseEventKind::Error(_) => "error".to_string(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        vec![
+            "shift", "reduce 3", "goto", "reduce 2", "goto", "shift", "shift", "reduce 3", "goto",
+            "reduce 1", "goto", "accept"
+        ]
+    );
+
+    // The configuration before the second shift holds the partial derivation of `1`
+    let event = trace.event(5).unwrap();
+    assert_eq!(event.stack_string(), "[0, E, 2]");
+    assert_eq!(event.nodes.len(), 1);
+    assert!(event.forest().to_string().contains("`1`"));
+
+    assert!(trace.is_accepted());
+    let derivation = trace.into_derivation().unwrap();
+    assert_eq!(leaf_values(&derivation), vec!["1", "+", "2"]);
+}
+
+#[test]
+fn test_trace_ends_with_error() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    let tokenizer = Tokenizer::from_string(Arc::new("1 + + 2".into()), grammar.get_token_types());
+    let trace = parser.record(&grammar, tokenizer);
+
+    assert!(!trace.is_accepted());
+    let last = trace.event(trace.len() - 1).unwrap();
+    assert!(matches!(last.kind, ParseEventKind::Error(_)));
+    assert_eq!(last.lookahead.value, "+");
+    assert!(trace.event(trace.len()).is_none());
+}
+
+#[test]
+fn test_trace_builder_rows_match_events() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    let mut builder = Builder::new();
+    parser
+        .parse_from_string_trace(&grammar, Arc::new("1 + 2".into()), Some(&mut builder))
+        .unwrap();
+    let tokenizer = Tokenizer::from_string(Arc::new("1 + 2".into()), grammar.get_token_types());
+    let trace = parser.record(&grammar, tokenizer);
+    assert_eq!(builder.count_records(), trace.len() + 1);
+}
+
+#[test]
+fn test_ll1_conflicts_left_recursive_grammar() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserLL1::from_grammar(&grammar);
+    assert!(!parser.is_ll1());
+    assert_eq!(parser.conflicts(), vec![("E", "'num'", &[1, 2][..])]);
+    let tokenizer = Tokenizer::from_string(Arc::new("1 + 2".into()), grammar.get_token_types());
+    let result = parser.parse(&grammar, tokenizer);
+    assert!(matches!(result, Err(ParseError::NotLL1)));
+}
+
+#[test]
+fn test_ll1_parses_right_recursive_grammar() {
+    let grammar = right_recursive_grammar();
+    let parser = GrammarParserLL1::from_grammar(&grammar);
+    assert!(parser.is_ll1());
+    assert_eq!(parser.get_rule_index("E'", EOF), Some(3));
+    let tokenizer = Tokenizer::from_string(Arc::new("1 + 2".into()), grammar.get_token_types());
+    let derivation = parser.parse(&grammar, tokenizer).unwrap();
+    assert_eq!(root_symbol(&derivation, &grammar), "E");
+    assert_eq!(leaf_values(&derivation), vec!["1", "+", "2", "E'"]);
+}
+
+#[test]
+fn test_ll1_rejects_invalid_input() {
+    let grammar = right_recursive_grammar();
+    let parser = GrammarParserLL1::from_grammar(&grammar);
+    let tokenizer = Tokenizer::from_string(Arc::new("1 +".into()), grammar.get_token_types());
+    assert!(parser.parse(&grammar, tokenizer).is_err());
+}
+
+#[test]
+fn test_earley_left_recursion() {
+    let grammar = simple_grammar();
+    let parse = earley_parse(&grammar, "1 + 2 + 3").unwrap();
+    assert!(!parse.is_ambiguous());
+
+    let derivation = &parse.derivation;
+    let lr = GrammarParserSLR1::from_grammar(&grammar)
+        .parse_from_string(&grammar, Arc::new("1 + 2 + 3".into()))
+        .unwrap();
+    let symbols = |derivation: &Derivation| {
+        derivation
+            .pre_order(derivation.root)
+            .map(|n| derivation[n].token.value.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(symbols(derivation), symbols(&lr));
+    assert_eq!(derivation.span(derivation.root), (0, 9).into());
+}
+
+#[test]
+fn test_earley_reports_ambiguity() {
+    let grammar = sum_grammar();
+    let parse = earley_parse(&grammar, "1 + 2 + 3").unwrap();
+    assert_eq!(
+        leaf_values(&parse.derivation),
+        vec!["1", "+", "2", "+", "3"]
+    );
+    assert_eq!(parse.ambiguities.len(), 1);
+    let ambiguity = &parse.ambiguities[0];
+    assert_eq!(ambiguity.non_terminal, "E");
+    assert_eq!(ambiguity.span, (0, 9).into());
+    assert_eq!(ambiguity.rules, vec![1, 1]);
+
+    assert!(!earley_parse(&grammar, "1 + 2").unwrap().is_ambiguous());
+}
+
+#[test]
+fn test_earley_epsilon_rules() {
+    let grammar = palindrome_grammar();
+    for source in ["", "a a", "a b b a", "b a a b"] {
+        let parse = earley_parse(&grammar, source).unwrap();
+        assert!(!parse.is_ambiguous());
+        assert_eq!(root_symbol(&parse.derivation, &grammar), "S");
+    }
+    assert!(earley_parse(&grammar, "a b a").is_err());
+
+    let grammar = right_recursive_grammar();
+    let parse = earley_parse(&grammar, "1 + 2").unwrap();
+    assert_eq!(leaf_values(&parse.derivation), vec!["1", "+", "2", "E'"]);
+}
+
+#[test]
+fn test_earley_rejects_invalid_input() {
+    let grammar = simple_grammar();
+    let Err(ParseError::Syntax(err)) = earley_parse(&grammar, "1 + + 2") else {
+        panic!("expected a syntax error");
+    };
+    assert_eq!(err.span, (4, 1).into());
+    let Err(ParseError::Syntax(err)) = earley_parse(&grammar, "1 +") else {
+        panic!("expected a syntax error");
+    };
+    assert_eq!(err.message, "unexpected end of input");
+}
+
+#[test]
+fn test_semantic_actions_eliminated_unit_rules() {
+    let grammar = precedence_grammar();
+    let mut parser = GrammarParserSLR1::from_grammar(&grammar);
+    parser.table.eliminate_unit_rules(&grammar);
+    // `T -> F` has an action but no nodes left
+    let actions = SemanticActions::new(|token| token.value.parse::<i64>().unwrap_or_default())
+        .on_rule(4, |values| values[0] * 10);
+    let tokenizer = Tokenizer::from_string(Arc::new("1 + 2".into()), grammar.get_token_types());
+    let result = parser.parse_with(&grammar, tokenizer, &actions);
+    assert!(matches!(result, Err(ParseError::EliminatedUnitRules)));
+}
+
+#[test]
+fn test_semantic_actions_per_rule() {
+    let grammar = simple_grammar();
+    let parser = GrammarParserSLR1::from_grammar(&grammar);
+    let actions = SemanticActions::new(|token| token.value.parse::<i64>().unwrap_or_default())
+        .on_rule(1, |values| values[0] + values[2]);
+    let tokenizer =
+        Tokenizer::from_string(Arc::new("1 + 2 + 39".into()), grammar.get_token_types());
+    assert_eq!(
+        parser.parse_with(&grammar, tokenizer, &actions).unwrap(),
+        42
+    );
+
+    let tokenizer = Tokenizer::from_string(Arc::new("1 +".into()), grammar.get_token_types());
+    assert!(parser.parse_with(&grammar, tokenizer, &actions).is_err());
+}
+
+#[test]
+fn test_semantic_actions_per_non_terminal() {
+    let grammar = right_recursive_grammar();
+    let parser = GrammarParserLALR1::from_grammar(&grammar);
+    let actions = SemanticActions::new(|token| token.value.clone())
+        .on_non_terminal("E'", |values| format!("{}{}", values[1], values[2]))
+        .on_non_terminal("E", |values| format!("[{}{}]", values[0], values[1]))
+        .on_rule(3, |_| String::from("."));
+    let tokenizer = Tokenizer::from_string(Arc::new("1 + 2 + 3".into()), grammar.get_token_types());
+    assert_eq!(
+        parser.parse_with(&grammar, tokenizer, &actions).unwrap(),
+        "[123.]"
+    );
+
+    // Without an action, an ε rule has the default value
+    let actions = SemanticActions::new(|token| token.value.clone())
+        .on_rule(1, |values| values.concat())
+        .on_rule(2, |values| format!("{}{}", values[1], values[2]));
+    let derivation = parser
+        .parse_from_string(&grammar, Arc::new("4 + 5".into()))
+        .unwrap();
+    assert_eq!(actions.evaluate(&grammar, &derivation), "45");
+    let tokenizer = Tokenizer::from_string(Arc::new("4 + 5".into()), grammar.get_token_types());
+    assert_eq!(
+        parser.parse_with(&grammar, tokenizer, &actions).unwrap(),
+        "45"
+    );
+}
+
+#[test]
+fn test_pratt_precedence_and_associativity() {
+    for algorithm in [ParserAlgorithm::SLR1, ParserAlgorithm::LALR1] {
+        let parse = |source| operator_parse(algorithm, source).unwrap();
+        assert_eq!(parse("1 + 2 * 3 - 4;"), "(- (+ 1 (* 2 3)) 4)");
+        assert_eq!(parse("2 ^ 3 ^ 4;"), "(^ 2 (^ 3 4))");
+        assert_eq!(parse("- 1 ! * 2;"), "(* (- (! 1)) 2)");
+        assert_eq!(parse("(1 + 2) * -3; 4;"), "(* (+ 1 2) (- 3)) 4");
+        assert_eq!(parse("1 == 2 + 3;"), "(== 1 (+ 2 3))");
+        assert_eq!(parse(""), "");
+    }
+}
+
+#[test]
+fn test_pratt_generated_rules() {
+    let rules = |grammar: &Grammar| {
+        grammar
+            .get_rules()
+            .iter()
+            .map(|r| r.to_string())
+            .collect::<Vec<_>>()
+    };
+    let expected = rules(&operator_grammar());
+    let (grammar, _) = GrammarParserPratt::from_rules(
+        operator_rules(),
+        ParserAlgorithm::LALR1,
+        vec![operator_table()],
+    );
+    assert_eq!(rules(&grammar), expected);
+    // Rules already in the grammar are not added again
+    let mut complete = operator_rules();
+    complete.extend(operator_table().rules());
+    let (grammar, _) =
+        GrammarParserPratt::from_rules(complete, ParserAlgorithm::LALR1, vec![operator_table()]);
+    assert_eq!(rules(&grammar), expected);
+}
+
+#[test]
+fn test_pratt_derivation_nodes() {
+    let grammar = operator_grammar();
+    let parser =
+        GrammarParserPratt::from_grammar(&grammar, ParserAlgorithm::LALR1, vec![operator_table()]);
+    let derivation = parser
+        .parse_from_string(&grammar, Arc::new("1 + (2);".into()))
+        .unwrap();
+    assert_eq!(
+        leaf_values(&derivation),
+        vec!["1", "+", "(", "2", ")", ";", "Stmts"]
+    );
+    let stmt = derivation.children(derivation.root)[0];
+    let sum = derivation.children(stmt)[0];
+    assert_eq!(
+        derivation.rule(&grammar, sum).unwrap().to_string(),
+        "Expr -> Expr '+' Expr"
+    );
+    assert_eq!(derivation.span(sum), (0, 7).into());
+    // A single reduction to `Expr` per operand, whatever the number of precedence levels
+    let expressions = derivation
+        .pre_order(derivation.root)
+        .filter(|n| derivation[*n].token.token == "Expr")
+        .count();
+    assert_eq!(expressions, 4);
+}
+
+#[test]
+fn test_pratt_rejects_invalid_input() {
+    let Err(ParseError::Syntax(err)) = operator_parse(ParserAlgorithm::LALR1, "1 == 2 == 3;")
+    else {
+        panic!("expected a syntax error");
+    };
+    assert_eq!(err.message, "operator '==' is not associative");
+    assert_eq!(err.span, (7, 2).into());
+
+    let Err(ParseError::Syntax(err)) = operator_parse(ParserAlgorithm::LALR1, "1 + ;") else {
+        panic!("expected a syntax error");
+    };
+    assert_eq!(err.message, "unexpected token ';'");
+    let Err(ParseError::Syntax(err)) = operator_parse(ParserAlgorithm::LALR1, "(1 + 2;") else {
+        panic!("expected a syntax error");
+    };
+    assert_eq!(err.span, (6, 1).into());
+    assert!(operator_parse(ParserAlgorithm::LALR1, "1 2;").is_err());
+}
@@ -0,0 +1,156 @@
+use std::fmt::{Display, Formatter};
+
+use itertools::Itertools;
+use petgraph::graph::NodeIndex;
+use petgraph::Graph;
+
+use crate::error::SyntaxError;
+use crate::grammar::{Derivation, DerivationForest, DerivationNode, Grammar};
+use crate::tokenizer::TokenInstance;
+
+#[derive(Debug, Clone)]
+pub enum ParseEventKind {
+    Shift(usize),
+    Reduce(usize),
+    Goto(usize),
+    Accept,
+    Error(SyntaxError),
+}
+
+impl Display for ParseEventKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseEventKind::Shift(s) => write!(f, "s{s}"),
+            ParseEventKind::Reduce(r) => write!(f, "r{r}"),
+            ParseEventKind::Goto(s) => write!(f, "{s}"),
+            ParseEventKind::Accept => write!(f, "acc"),
+            ParseEventKind::Error(_) => Ok(()),
+        }
+    }
+}
+
+impl ParseEventKind {
+    pub fn describe(&self, grammar: &Grammar) -> String {
+        match self {
+            ParseEventKind::Shift(s) => format!("shift, and go to state {s}"),
+            ParseEventKind::Reduce(r) => {
+                format!("reduce using rule {r} ({})", grammar.get_rule(*r))
+            }
+            ParseEventKind::Goto(s) => format!("go to state {s}"),
+            ParseEventKind::Accept => String::from("accept"),
+            ParseEventKind::Error(err) => format!("error: {}", err.message),
+        }
+    }
+}
+
+/// One step of an LR parse: the configuration of the parser before the step and the action
+/// taken from it.
+///
+/// `nodes` are the roots of the partial derivation on the stack. There is one state more than
+/// there are nodes, except right after a reduction, when the goto state is not pushed yet.
+pub struct ParseEvent<'a> {
+    pub step: usize,
+    pub kind: &'a ParseEventKind,
+    pub states: &'a [usize],
+    pub nodes: &'a [NodeIndex],
+    pub lookahead: &'a TokenInstance,
+    pub graph: &'a Graph<DerivationNode, usize>,
+}
+
+impl ParseEvent<'_> {
+    /// Stack with states and symbols interleaved, e.g. `[0, E, 2, +, 3]`.
+    pub fn stack_string(&self) -> String {
+        let states = self.states.iter().map(|s| s.to_string());
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|id| self.graph[*id].token.value.clone());
+        format!(
+            "[{}]",
+            states
+                .interleave(nodes)
+                .intersperse(String::from(", "))
+                .collect::<String>()
+        )
+    }
+
+    pub fn forest(&self) -> DerivationForest<'_> {
+        DerivationForest {
+            graph: self.graph,
+            roots: self.nodes,
+        }
+    }
+
+    fn to_owned(&self) -> ParseTraceStep {
+        ParseTraceStep {
+            kind: self.kind.clone(),
+            states: self.states.to_vec(),
+            nodes: self.nodes.to_vec(),
+            lookahead: self.lookahead.clone(),
+        }
+    }
+}
+
+struct ParseTraceStep {
+    kind: ParseEventKind,
+    states: Vec<usize>,
+    nodes: Vec<NodeIndex>,
+    lookahead: TokenInstance,
+}
+
+/// Recorded parse that can be replayed one event at a time, in either direction.
+///
+/// Derivation nodes are only ever added during a parse, so every partial derivation is a
+/// forest within the final graph.
+#[derive(Default)]
+pub struct ParseTrace {
+    graph: Graph<DerivationNode, usize>,
+    steps: Vec<ParseTraceStep>,
+    root: Option<NodeIndex>,
+}
+
+impl ParseTrace {
+    pub(crate) fn record(&mut self, event: &ParseEvent) {
+        self.steps.push(event.to_owned());
+    }
+
+    pub(crate) fn finish(&mut self, graph: Graph<DerivationNode, usize>, root: Option<NodeIndex>) {
+        self.graph = graph;
+        self.root = root;
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn event(&self, index: usize) -> Option<ParseEvent<'_>> {
+        let step = self.steps.get(index)?;
+        Some(ParseEvent {
+            step: index + 1,
+            kind: &step.kind,
+            states: &step.states,
+            nodes: &step.nodes,
+            lookahead: &step.lookahead,
+            graph: &self.graph,
+        })
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = ParseEvent<'_>> {
+        (0..self.steps.len()).filter_map(|i| self.event(i))
+    }
+
+    pub fn is_accepted(&self) -> bool {
+        self.root.is_some()
+    }
+
+    pub fn into_derivation(self) -> Option<Derivation> {
+        Some(Derivation {
+            graph: self.graph,
+            root: self.root?,
+        })
+    }
+}
@@ -8,14 +8,17 @@ fn test_token_types() -> Vec<TokenType> {
         TokenType {
             name: "num".into(),
             regex: Regex::new(r"^\d+").unwrap(),
+            keywords: Default::default(),
         },
         TokenType {
             name: "+".into(),
             regex: Regex::new(r"^\+").unwrap(),
+            keywords: Default::default(),
         },
         TokenType {
             name: "".into(),
             regex: Regex::new(r"^\s+").unwrap(),
+            keywords: Default::default(),
         },
     ]
 }
@@ -90,10 +93,12 @@ fn test_longest_match() {
         TokenType {
             name: "if".into(),
             regex: Regex::new(r"if").unwrap(),
+            keywords: Default::default(),
         },
         TokenType {
             name: "ident".into(),
             regex: Regex::new(r"[a-z]+").unwrap(),
+            keywords: Default::default(),
         },
     ];
     let input = Arc::new("if".to_string());
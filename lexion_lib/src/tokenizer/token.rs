@@ -1,11 +1,16 @@
+use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter, Result};
 
+use itertools::Itertools;
 use miette::SourceSpan;
 use regex::Regex;
 
 pub struct TokenType {
     pub name: String,
     pub regex: Regex,
+    /// Reserved words among the matches of the regex, with the terminal each one is read as
+    /// instead of `name`.
+    pub keywords: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone)]
@@ -27,7 +32,36 @@ impl Default for TokenInstance {
 
 impl Display for TokenType {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
-        write!(f, "[{}] -> /{}/", self.name, self.regex)
+        write!(f, "[{}] -> /{}/", self.name, self.regex)?;
+        if !self.keywords.is_empty() {
+            write!(f, " keywords {{ {} }}", self.keywords.keys().join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl TokenType {
+    pub fn new(name: &str, regex: Regex) -> Self {
+        Self {
+            name: String::from(name),
+            regex,
+            keywords: BTreeMap::new(),
+        }
+    }
+
+    /// Terminal of a match of the regex: the keyword it is reserved for, if any, or `name`.
+    pub fn name_of(&self, value: &str) -> &str {
+        self.keywords.get(value).unwrap_or(&self.name)
+    }
+
+    /// Terminal of a match of the regex where only the terminals satisfying `expected` can
+    /// follow: a keyword is read as `name` when only `name` is expected.
+    pub fn name_in_context(&self, value: &str, expected: &dyn Fn(&str) -> bool) -> &str {
+        match self.keywords.get(value) {
+            Some(keyword) if !expected(keyword) && expected(&self.name) => &self.name,
+            Some(keyword) => keyword,
+            None => &self.name,
+        }
     }
 }
 
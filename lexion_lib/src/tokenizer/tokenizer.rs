@@ -33,7 +33,7 @@ impl<'a> Tokenizer<'a> {
         })
     }
 
-    pub fn from_string(input: Arc<String>, token_types: &[TokenType]) -> Tokenizer {
+    pub fn from_string(input: Arc<String>, token_types: &[TokenType]) -> Tokenizer<'_> {
         Tokenizer {
             file: "inline",
             string: input,
@@ -56,6 +56,17 @@ impl<'a> Tokenizer<'a> {
     }
 
     pub fn next_token(&mut self) -> Result<TokenInstance> {
+        self.next_token_expecting(&|_| true)
+    }
+
+    /// Next token where only the terminals satisfying `expected` can follow, such as those
+    /// with an action in the current state of an LR parser. Keywords are contextual: where
+    /// the grammar expects their host terminal but not them, they are read as the host, so
+    /// `struct` can name a variable. Where both are expected, the keyword wins.
+    pub fn next_token_expecting(
+        &mut self,
+        expected: &dyn Fn(&str) -> bool,
+    ) -> Result<TokenInstance> {
         let offset = self.cursor_offset();
         if !self.has_next() {
             return Ok(TokenInstance {
@@ -69,16 +80,18 @@ impl<'a> Tokenizer<'a> {
         let token: &TokenType = &self.token_types[i];
         self.cursor += s.len();
         if token.name.is_empty() {
-            return self.next_token();
+            return self.next_token_expecting(expected);
         }
 
         Ok(TokenInstance {
             span: (offset, s.len()).into(),
-            token: token.name.clone(),
+            token: String::from(token.name_in_context(&s, expected)),
             value: s,
         })
     }
 
+    /// Longest match at the cursor and the index of its token type, the first one declared
+    /// among equally long matches. Keywords are looked up by [`TokenType::name_of`].
     pub fn match_next(&self) -> Result<(String, usize)> {
         let substring = &self.string[self.cursor..];
         let mut longest_match: Option<(&str, usize)> = None;
@@ -110,6 +123,10 @@ impl<'a> Tokenizer<'a> {
         Ok((String::from(s), i))
     }
 
+    pub fn seek(&mut self, cursor: usize) {
+        self.cursor = cursor;
+    }
+
     pub fn cursor_offset(&self) -> SourceOffset {
         self.cursor.into()
     }
@@ -0,0 +1,429 @@
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use lexion_lib::error::ParseError;
+use lexion_lib::grammar::{CompareOptions, Grammar, GrammarComparison};
+use lexion_lib::itertools::Itertools;
+use lexion_lib::miette;
+use lexion_lib::parsers::items::{CanonicalCollectionGraph, LR0Item};
+use lexion_lib::parsers::{
+    GrammarParserEarley, GrammarParserLALR1, GrammarParserLL1, GrammarParserLR, GrammarParserLR0,
+    GrammarParserSLR1, ParseEvent, ParseTableAction, ParseTableArtifact, ParseTableExport,
+    ParserAlgorithm,
+};
+use lexion_lib::tabled::builder::Builder;
+use lexion_lib::tabled::settings::Style;
+use lexion_lib::tokenizer::Tokenizer;
+use lexion_parsers::grm::{load_artifact, load_grammar_data};
+
+/// Workbench for inspecting a `.grm` or `.json` grammar: its sets, parse table and conflicts,
+/// and the derivations it produces.
+#[derive(Parser, Debug)]
+#[command(long_about = None)]
+struct Args {
+    grammar: String,
+    #[arg(short, long, value_enum, default_value_t = Algorithm::Slr1)]
+    algorithm: Algorithm,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Algorithm {
+    Lr0,
+    Slr1,
+    Lalr1,
+    Ll1,
+    Earley,
+}
+
+impl Algorithm {
+    fn lr(self) -> Option<ParserAlgorithm> {
+        match self {
+            Algorithm::Lr0 => Some(ParserAlgorithm::LR0),
+            Algorithm::Slr1 => Some(ParserAlgorithm::SLR1),
+            Algorithm::Lalr1 => Some(ParserAlgorithm::LALR1),
+            Algorithm::Ll1 | Algorithm::Earley => None,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(flatten)]
+    Grammar(GrammarCommand),
+    /// Compare the grammar with another version of it: the sentences accepted by only one of
+    /// them, and the changes to their sets and conflicts
+    Diff {
+        other: String,
+        /// Levels above the shortest derivation tree up to which sentences are enumerated
+        #[arg(long, default_value_t = 4)]
+        depth: usize,
+        /// Number of random sentences generated from each grammar
+        #[arg(long, default_value_t = 200)]
+        random: usize,
+    },
+}
+
+/// Commands run on the grammar with the parser of the selected algorithm.
+#[derive(Subcommand, Debug)]
+enum GrammarCommand {
+    /// Print the nullable non-terminals and the FIRST and FOLLOW sets
+    Sets,
+    /// Print the token types with their keywords, and the overlaps between their regexes
+    Tokens,
+    /// Print the parse table
+    Table,
+    /// List the conflicts of the parse table
+    Conflicts,
+    /// Parse an input file and print its derivation tree
+    Parse {
+        input: String,
+        /// Print the configuration of the parser at every step
+        #[arg(long)]
+        trace: bool,
+    },
+    /// Step forward and backward through the parse of an input file
+    Step { input: String },
+    /// Export the parse table
+    Export {
+        #[arg(short, long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ExportFormat {
+    /// Versioned parse table artifact
+    Json,
+    /// Report in the layout of Bison's `.output` file
+    Bison,
+    /// Graphviz graph of the automaton
+    Dot,
+    /// Self-contained interactive page
+    Html,
+}
+
+const STEP_HELP: &str = "\
+commands:
+  n, <enter>   next step
+  p            previous step
+  <number>     go to step
+  f, l         first / last step
+  q            quit";
+
+fn print_sets(grammar: &Grammar) {
+    let set_string = |set: Option<&std::collections::HashSet<String>>| {
+        set.into_iter()
+            .flatten()
+            .sorted()
+            .map(|s| Grammar::stringify(s))
+            .join(" ")
+    };
+    let mut builder = Builder::default();
+    builder.push_record(["Non-terminal", "Nullable", "FIRST", "FOLLOW"]);
+    for non_terminal in grammar.get_non_terminals().iter().sorted() {
+        builder.push_record([
+            non_terminal.clone(),
+            String::from(if grammar.is_nullable(non_terminal) {
+                "yes"
+            } else {
+                ""
+            }),
+            set_string(grammar.first_of(non_terminal)),
+            set_string(grammar.follow_of(non_terminal)),
+        ]);
+    }
+    let mut table = builder.build();
+    table.with(Style::modern());
+    println!("{table}");
+}
+
+fn print_tokens(grammar: &Grammar) {
+    let mut builder = Builder::default();
+    builder.push_record(["Token", "Regex", "Keywords"]);
+    for token_type in grammar.get_token_types().iter() {
+        if token_type.name.is_empty() {
+            continue;
+        }
+        builder.push_record([
+            token_type.name.clone(),
+            token_type.regex.to_string(),
+            token_type
+                .keywords
+                .iter()
+                .map(
+                    |(word, terminal)| match Grammar::stringify(terminal) == *word {
+                        true => word.clone(),
+                        false => format!("{word} ({terminal})"),
+                    },
+                )
+                .join(" "),
+        ]);
+    }
+    let mut table = builder.build();
+    table.with(Style::modern());
+    println!("{table}");
+    let overlaps = grammar.token_overlaps();
+    if overlaps.is_empty() {
+        println!("No overlap between the token types");
+    }
+    for overlap in overlaps {
+        println!("{overlap}");
+    }
+}
+
+fn print_lr_conflicts(artifact: &ParseTableArtifact) {
+    let conflicts = artifact
+        .table
+        .entries()
+        .filter(|(_, _, action)| matches!(action, ParseTableAction::Conflict(_)))
+        .sorted_by_key(|(state, symbol, _)| (*state, *symbol))
+        .collect::<Vec<_>>();
+    for (state, symbol, action) in &conflicts {
+        println!("state {state} on {symbol}: {action}");
+    }
+    println!("{} conflict(s)", conflicts.len());
+}
+
+fn print_ll1_conflicts(grammar: &Grammar, parser: &GrammarParserLL1) {
+    let conflicts = parser.conflicts();
+    for (non_terminal, terminal, rules) in &conflicts {
+        println!("{non_terminal} on {terminal}:");
+        for rule in rules.iter() {
+            println!("  {rule}: {}", grammar.get_rule(*rule));
+        }
+    }
+    println!("{} conflict(s)", conflicts.len());
+}
+
+fn print_event(event: &ParseEvent, total: usize, grammar: &Grammar) {
+    println!("\nStep {}/{total}", event.step);
+    println!("  stack:     {}", event.stack_string());
+    println!(
+        "  lookahead: {} `{}`",
+        event.lookahead.token, event.lookahead.value
+    );
+    println!("  action:    {}", event.kind.describe(grammar));
+    print!("{}", event.forest());
+}
+
+fn step(artifact: &ParseTableArtifact, tokenizer: Tokenizer) -> Result<(), String> {
+    let trace = artifact.record(&artifact.grammar, tokenizer);
+    if trace.is_empty() {
+        return Ok(());
+    }
+    println!("{STEP_HELP}");
+    let mut index = 0;
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        print_event(&trace.event(index).unwrap(), trace.len(), &artifact.grammar);
+        print!("> ");
+        std::io::stdout().flush().map_err(|err| err.to_string())?;
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        let line = line.map_err(|err| err.to_string())?;
+        match line.trim() {
+            "" | "n" => index = (index + 1).min(trace.len() - 1),
+            "p" => index = index.saturating_sub(1),
+            "f" => index = 0,
+            "l" => index = trace.len() - 1,
+            "q" => return Ok(()),
+            command => match command.parse::<usize>() {
+                Ok(step) if (1..=trace.len()).contains(&step) => index = step - 1,
+                _ => println!("{STEP_HELP}"),
+            },
+        }
+    }
+}
+
+/// Calls `f` with the canonical collection of the parser the artifact was built with.
+fn with_collection<R>(
+    artifact: &ParseTableArtifact,
+    f: impl FnOnce(&CanonicalCollectionGraph<LR0Item>) -> R,
+) -> R {
+    let grammar = &artifact.grammar;
+    match artifact.algorithm {
+        ParserAlgorithm::LR0 => f(GrammarParserLR0::from_grammar(grammar).get_collection()),
+        ParserAlgorithm::SLR1 => f(GrammarParserSLR1::from_grammar(grammar).get_collection()),
+        ParserAlgorithm::LALR1 => f(GrammarParserLALR1::from_grammar(grammar).get_collection()),
+    }
+}
+
+fn export(
+    artifact: &ParseTableArtifact,
+    format: ExportFormat,
+    output: Option<&str>,
+) -> Result<(), String> {
+    let contents = match format {
+        ExportFormat::Json => {
+            return match output {
+                Some(path) => artifact.to_file(path),
+                None => artifact.to_writer(std::io::stdout().lock()),
+            }
+            .map_err(|err| err.to_string());
+        }
+        format => with_collection(artifact, |collection| {
+            let export = ParseTableExport::new(&artifact.grammar, collection, &artifact.table);
+            match format {
+                ExportFormat::Bison => export.to_bison_output(),
+                ExportFormat::Dot => export.to_dot(),
+                _ => export.to_html(),
+            }
+        }),
+    };
+    match output {
+        Some(path) => std::fs::write(path, contents).map_err(|err| err.to_string()),
+        None => {
+            print!("{contents}");
+            Ok(())
+        }
+    }
+}
+
+fn read_input<'a>(grammar: &'a Grammar, input: &str) -> Result<Tokenizer<'a>, String> {
+    let source = std::fs::read_to_string(input).map_err(|err| err.to_string())?;
+    Ok(Tokenizer::from_string(
+        Arc::new(source),
+        grammar.get_token_types(),
+    ))
+}
+
+fn report(err: ParseError) -> String {
+    match err {
+        ParseError::Syntax(err) => format!("{:?}", miette::Report::new(err)),
+        err => err.to_string(),
+    }
+}
+
+fn run_ll1(grammar: &Grammar, command: GrammarCommand) -> Result<(), String> {
+    let parser = GrammarParserLL1::from_grammar(grammar);
+    match command {
+        GrammarCommand::Table => println!("{}", parser.to_table()),
+        GrammarCommand::Conflicts => print_ll1_conflicts(grammar, &parser),
+        GrammarCommand::Parse {
+            input,
+            trace: false,
+        } => {
+            let tokenizer = read_input(grammar, &input)?;
+            let derivation = parser.parse(grammar, tokenizer).map_err(report)?;
+            print!("{derivation}");
+        }
+        GrammarCommand::Sets => print_sets(grammar),
+        GrammarCommand::Tokens => print_tokens(grammar),
+        GrammarCommand::Parse { trace: true, .. }
+        | GrammarCommand::Step { .. }
+        | GrammarCommand::Export { .. } => {
+            return Err(String::from("this command requires an LR algorithm"));
+        }
+    }
+    Ok(())
+}
+
+fn run_earley(grammar: &Grammar, command: GrammarCommand) -> Result<(), String> {
+    match command {
+        GrammarCommand::Sets => print_sets(grammar),
+        GrammarCommand::Tokens => print_tokens(grammar),
+        GrammarCommand::Parse {
+            input,
+            trace: false,
+        } => {
+            let tokenizer = read_input(grammar, &input)?;
+            let parse = GrammarParserEarley::from_grammar(grammar)
+                .parse(grammar, tokenizer)
+                .map_err(report)?;
+            print!("{}", parse.derivation);
+            for ambiguity in parse.ambiguities.iter() {
+                println!(
+                    "ambiguous {} at {}+{}:",
+                    ambiguity.non_terminal,
+                    ambiguity.span.offset(),
+                    ambiguity.span.len()
+                );
+                for rule in ambiguity.rules.iter() {
+                    println!("  {rule}: {}", grammar.get_rule(*rule));
+                }
+            }
+        }
+        _ => {
+            return Err(String::from(
+                "this command requires a table-driven algorithm",
+            ))
+        }
+    }
+    Ok(())
+}
+
+fn run_lr(artifact: &ParseTableArtifact, command: GrammarCommand) -> Result<(), String> {
+    let grammar = &artifact.grammar;
+    match command {
+        GrammarCommand::Sets => print_sets(grammar),
+        GrammarCommand::Tokens => print_tokens(grammar),
+        GrammarCommand::Table => println!("{}", artifact.table.to_table()),
+        GrammarCommand::Conflicts => print_lr_conflicts(artifact),
+        GrammarCommand::Parse { input, trace } => {
+            let tokenizer = read_input(grammar, &input)?;
+            let derivation = if trace {
+                let mut builder = Builder::default();
+                let derivation = artifact.parse_trace(grammar, tokenizer, Some(&mut builder));
+                let mut table = builder.build();
+                table.with(Style::modern());
+                println!("{table}");
+                derivation
+            } else {
+                artifact.parse(grammar, tokenizer)
+            }
+            .map_err(report)?;
+            print!("{derivation}");
+        }
+        GrammarCommand::Step { input } => step(artifact, read_input(grammar, &input)?)?,
+        GrammarCommand::Export { format, output } => export(artifact, format, output.as_deref())?,
+    }
+    Ok(())
+}
+
+fn diff(grammar: &str, other: &str, options: CompareOptions) -> Result<(), String> {
+    let left = Grammar::from_data(&load_grammar_data(grammar)?);
+    let right = Grammar::from_data(&load_grammar_data(other)?);
+    let diff = GrammarComparison::new(&left, &right, options).compare();
+    print!("{diff}");
+    if diff.is_equivalent() {
+        Ok(())
+    } else {
+        Err(String::from("the grammars accept different sentences"))
+    }
+}
+
+fn main() -> Result<(), String> {
+    let args = Args::parse();
+    let command = match args.command {
+        Command::Grammar(command) => command,
+        Command::Diff {
+            other,
+            depth,
+            random,
+        } => {
+            let options = CompareOptions {
+                extra_depth: depth,
+                random_sentences: random,
+                algorithm: args.algorithm.lr().unwrap_or(ParserAlgorithm::LALR1),
+                ..Default::default()
+            };
+            return diff(&args.grammar, &other, options);
+        }
+    };
+    match args.algorithm.lr() {
+        Some(algorithm) => run_lr(&load_artifact(&args.grammar, algorithm)?, command),
+        None => {
+            let grammar = Grammar::from_data(&load_grammar_data(&args.grammar)?);
+            match args.algorithm {
+                Algorithm::Earley => run_earley(&grammar, command),
+                _ => run_ll1(&grammar, command),
+            }
+        }
+    }
+}
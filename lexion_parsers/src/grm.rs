@@ -2,6 +2,7 @@ use lexion_lib::grammar::serialize::{
     GrammarData as Grammar, ParseTableOverrideData as ParseTableOverride,
     ReductionData as Reduction, RuleData as Rule,
 };
+use lexion_lib::parsers::{ParseTableArtifact, ParserAlgorithm};
 use lexion_lib::Parser;
 
 #[derive(Parser)]
@@ -19,3 +20,36 @@ impl ParserGRM {
         Self
     }
 }
+
+/// Reads the grammar at `path`, either a `.json` grammar (a full grammar or a bare list of
+/// rules) or a `.grm` file parsed with [`ParserGRM`].
+pub fn load_grammar_data(path: &str) -> Result<Grammar, String> {
+    if path.ends_with(".json") {
+        let contents = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        return serde_json::from_str(&contents).or_else(|err| {
+            serde_json::from_str(&contents)
+                .map(|rules| Grammar {
+                    rules,
+                    overrides: None,
+                })
+                .map_err(|_| err.to_string())
+        });
+    }
+    ParserGRM::new()
+        .parse_from_file_trace(path, None)
+        .map_err(|err| err.to_string())
+}
+
+/// Loads the grammar at `path` and builds its parse table with `algorithm`, applying the
+/// overrides declared in the grammar.
+pub fn load_artifact(path: &str, algorithm: ParserAlgorithm) -> Result<ParseTableArtifact, String> {
+    let data = load_grammar_data(path)?;
+    let mut artifact =
+        ParseTableArtifact::from_grammar(lexion_lib::grammar::Grammar::from_data(&data), algorithm);
+    if let Some(overrides) = &data.overrides {
+        artifact
+            .apply_overrides(overrides)
+            .map_err(|err| err.to_string())?;
+    }
+    Ok(artifact)
+}
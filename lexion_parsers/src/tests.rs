@@ -48,3 +48,99 @@ pub fn test_grm_parser() {
     println!("{table}");
     println!("{}", grammar.to_jsmachine_string());
 }
+
+mod expression_ast {
+    use lexion_lib::Parser;
+
+    #[derive(Parser)]
+    #[grammar(path = "grammars/expression_ast.json", ast)]
+    pub struct ParserExpressionAst;
+}
+
+#[test]
+pub fn test_generated_ast() {
+    use expression_ast::ast::{Expr, Factor, OptCall, Term};
+
+    let source = "1 + f() * (x - 2)";
+    let expr = expression_ast::ParserExpressionAst
+        .parse_from_string(Arc::new(source.into()))
+        .unwrap();
+    let text = |span: lexion_lib::miette::SourceSpan| &source[span.offset()..][..span.len()];
+
+    let Expr::ExprAddOpTerm(sum) = &expr else {
+        panic!("expected a sum, got {expr:?}");
+    };
+    assert_eq!(text(expr.span()), source);
+    assert_eq!(sum.add_op_token().value, "+");
+    assert_eq!(text(sum.expr().span()), "1");
+
+    let Term::TermFactor(product) = sum.term() else {
+        panic!("expected a product, got {:?}", sum.term());
+    };
+    assert_eq!(product.token_2().value, "*");
+    let Term::Factor(call) = product.term() else {
+        panic!("expected a factor, got {:?}", product.term());
+    };
+    let Factor::IdentOptCall(call) = call.factor() else {
+        panic!("expected a call, got {:?}", call.factor());
+    };
+    assert_eq!(call.ident_token().value, "f");
+    assert!(matches!(call.opt_call(), OptCall::Alt1(_)));
+    assert!(matches!(product.factor(), Factor::Expr(_)));
+    assert_eq!(text(product.factor().span()), "(x - 2)");
+}
+
+#[test]
+pub fn test_generated_ast_unexpected_production() {
+    use expression_ast::ast::Term;
+    use expression_ast::ParserExpressionAst;
+
+    let derivation = ParserExpressionAst::PARSER
+        .parse_from_string(ParserExpressionAst::GRAMMAR, Arc::new("1 + 2".into()))
+        .unwrap();
+    let err = Term::from_derivation(&derivation, derivation.root).unwrap_err();
+    assert_eq!(err.non_terminal, "Term");
+    assert_eq!(err.rule_index, 1);
+    assert_eq!(err.production, "Expr -> Expr 'add_op' Term");
+    assert_eq!(
+        err.to_string(),
+        "unexpected production 1 `Expr -> Expr 'add_op' Term` for Term"
+    );
+}
+
+#[test]
+pub fn test_grm_keywords() {
+    let source = r"
+        Stmt -> 'while' 'ident' | 'bool_literal' ;
+        'ident' -> /[a-z]+/ ;
+        'while' -> 'ident' ;
+        'bool_literal' -> 'ident' 'true' 'false' ;
+    ";
+    let data = ParserGRM::new()
+        .parse_from_string(Arc::new(source.into()))
+        .unwrap();
+    let rights = data
+        .rules
+        .iter()
+        .map(|r| r.right.join(" "))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rights[3..],
+        ["'ident'", "'ident' 'true' 'false'"].map(String::from)
+    );
+
+    let grammar = Grammar::from_data(&data);
+    assert_eq!(
+        grammar.keyword_of("'bool_literal'"),
+        Some(("'ident'", vec!["true".into(), "false".into()]))
+    );
+    let parser = GrammarParserSLR1::from_grammar(&grammar);
+    let derivation = parser
+        .parse_from_string(&grammar, Arc::new("while whilex".into()))
+        .unwrap();
+    let tokens = derivation
+        .tokens(derivation.root)
+        .map(|t| t.token.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(tokens, ["'while'", "'ident'"]);
+}